@@ -0,0 +1,24 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    close(open("/access_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644));
+    chmod("/access_test.txt", 0400);
+
+    printf("F_OK: %d\n", access("/access_test.txt", F_OK));
+    printf("R_OK: %d\n", access("/access_test.txt", R_OK));
+    errno = 0;
+    printf("W_OK: %d %d\n", access("/access_test.txt", W_OK), errno == EACCES);
+    errno = 0;
+    printf("X_OK: %d %d\n", access("/access_test.txt", X_OK), errno == EACCES);
+    printf("AT_EACCESS: %d\n", faccessat(AT_FDCWD, "/access_test.txt", R_OK, AT_EACCESS));
+    errno = 0;
+    printf("missing: %d %d\n", access("/access_missing.txt", F_OK), errno == ENOENT);
+
+    unlink("/access_test.txt");
+    return 0;
+}
@@ -0,0 +1,27 @@
+#include <elf.h>
+#include <link.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/auxv.h>
+
+int main()
+{
+    printf("AT_PAGESZ: %lu\n", getauxval(AT_PAGESZ));
+    printf("AT_PHENT: %d\n", getauxval(AT_PHENT) == sizeof(ElfW(Phdr)));
+
+    const ElfW(Phdr) *phdr = (const ElfW(Phdr) *)getauxval(AT_PHDR);
+    unsigned long phnum = getauxval(AT_PHNUM);
+    int loads = 0;
+    for (unsigned long i = 0; i < phnum; i++) {
+        if (phdr[i].p_type == PT_LOAD)
+            loads++;
+    }
+    printf("program headers have a PT_LOAD: %d\n", phnum > 0 && loads > 0);
+    printf("AT_ENTRY: %d\n", getauxval(AT_ENTRY) != 0);
+    printf("AT_BASE: %lu\n", getauxval(AT_BASE));
+
+    const unsigned char *random = (const unsigned char *)getauxval(AT_RANDOM);
+    unsigned char zero[16] = {0};
+    printf("AT_RANDOM: %d\n", random != NULL && memcmp(random, zero, 16) != 0);
+    return 0;
+}
@@ -0,0 +1,34 @@
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main()
+{
+    char *start = (char *)syscall(SYS_brk, 0);
+    printf("initial break is queried: %d\n", start != NULL);
+
+    char *end = (char *)syscall(SYS_brk, start + 64 * 1024);
+    printf("grown by 64KB: %d\n", end == start + 64 * 1024);
+
+    int zeroed = 1;
+    for (char *p = start; p < end; p++) {
+        if (*p != 0) {
+            zeroed = 0;
+        }
+        *p = (char)(uintptr_t)p;
+    }
+    int written = 1;
+    for (char *p = start; p < end; p++) {
+        if (*p != (char)(uintptr_t)p) {
+            written = 0;
+        }
+    }
+    printf("zeroed %d, written %d\n", zeroed, written);
+
+    printf("below the initial break: %d\n", (char *)syscall(SYS_brk, start - 4096) == end);
+    end = (char *)syscall(SYS_brk, start + 4096);
+    printf("shrunk: %d\n", end == start + 4096);
+    printf("query: %d\n", (char *)syscall(SYS_brk, 0) == end);
+    return 0;
+}
@@ -0,0 +1,50 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    char buf[256];
+    chdir("/");
+    mkdir("chdir_dir", 0755);
+    close(open("chdir_dir/file", O_CREAT | O_WRONLY, 0644));
+
+    int ret = chdir("chdir_dir/file");
+    printf("chdir to a file: %d %d\n", ret, errno == ENOTDIR);
+    ret = chdir("missing");
+    printf("chdir to a missing path: %d %d\n", ret, errno == ENOENT);
+
+    pid_t pid = fork();
+    if (pid == 0) {
+        chdir("chdir_dir");
+        getcwd(buf, sizeof(buf));
+        printf("child: %s\n", buf);
+        printf("child opens relative path: %d\n", access("file", F_OK));
+        return 0;
+    }
+    waitpid(pid, NULL, 0);
+    getcwd(buf, sizeof(buf));
+    printf("parent: %s\n", buf);
+
+    int root = open(".", O_RDONLY | O_DIRECTORY);
+    chdir("chdir_dir");
+    pid = fork();
+    if (pid == 0) {
+        getcwd(buf, sizeof(buf));
+        printf("inherited: %s\n", buf);
+        return 0;
+    }
+    waitpid(pid, NULL, 0);
+    int file = open("file", O_RDONLY);
+    ret = fchdir(file);
+    printf("fchdir to a file: %d %d\n", ret, errno == ENOTDIR);
+    fchdir(root);
+    getcwd(buf, sizeof(buf));
+    printf("fchdir back: %s\n", buf);
+    close(file);
+    close(root);
+    return 0;
+}
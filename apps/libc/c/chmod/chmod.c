@@ -0,0 +1,25 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    struct stat st;
+
+    int fd = open("/chmod_test.sh", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    printf("chmod: %d\n", chmod("/chmod_test.sh", 0755));
+    fstat(fd, &st);
+    printf("fstat mode: %o reg=%d\n", st.st_mode & 07777, S_ISREG(st.st_mode));
+
+    printf("fchmod: %d\n", fchmod(fd, S_IFDIR | 0600));
+    fstat(fd, &st);
+    printf("fstat mode: %o reg=%d\n", st.st_mode & 07777, S_ISREG(st.st_mode));
+    close(fd);
+
+    errno = 0;
+    printf("missing: %d %d\n", chmod("/chmod_missing", 0755), errno == ENOENT);
+    unlink("/chmod_test.sh");
+    return 0;
+}
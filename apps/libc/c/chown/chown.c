@@ -0,0 +1,24 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    struct stat st, st_at;
+
+    int fd = open("/chown_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    printf("chown: %d\n", chown("/chown_test.txt", 1000, 1000));
+    fstat(fd, &st);
+    fstatat(AT_FDCWD, "/chown_test.txt", &st_at, 0);
+    printf("fstat: %u:%u\n", st.st_uid, st.st_gid);
+    printf("agree: %d\n", st.st_uid == st_at.st_uid && st.st_gid == st_at.st_gid);
+
+    printf("fchown: %d\n", fchown(fd, -1, 2000));
+    fstatat(AT_FDCWD, "/chown_test.txt", &st_at, 0);
+    printf("fstatat: %u:%u\n", st_at.st_uid, st_at.st_gid);
+
+    close(fd);
+    unlink("/chown_test.txt");
+    return 0;
+}
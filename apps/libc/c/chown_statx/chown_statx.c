@@ -0,0 +1,26 @@
+#include <fcntl.h>
+#include <linux/stat.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static unsigned int statx_uid(const char *path)
+{
+    struct statx stx;
+    syscall(SYS_statx, AT_FDCWD, path, 0, STATX_UID, &stx);
+    return stx.stx_uid;
+}
+
+int main()
+{
+    close(open("/chown_statx.txt", O_CREAT | O_RDWR | O_TRUNC, 0644));
+    printf("chown root: %d uid=%u\n", chown("/chown_statx.txt", 0, 0),
+           statx_uid("/chown_statx.txt"));
+    printf("chown 1000: %d uid=%u\n", chown("/chown_statx.txt", 1000, -1),
+           statx_uid("/chown_statx.txt"));
+    printf("unchanged: %d uid=%u\n", chown("/chown_statx.txt", -1, 1000),
+           statx_uid("/chown_statx.txt"));
+    unlink("/chown_statx.txt");
+    return 0;
+}
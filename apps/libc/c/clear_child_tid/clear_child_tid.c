@@ -0,0 +1,43 @@
+#define _GNU_SOURCE
+#include <linux/futex.h>
+#include <sched.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define STACK_SIZE 65536
+
+static volatile int child_tid = 0;
+static volatile int ran = 0;
+
+static int child(void *arg)
+{
+    usleep(50000);
+    ran = 1;
+    return 0;
+}
+
+int main()
+{
+    int tid = syscall(SYS_set_tid_address, NULL);
+    printf("set_tid_address returns the tid: %d\n", tid == syscall(SYS_gettid));
+
+    char *stack = malloc(STACK_SIZE);
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD |
+                CLONE_SYSVSEM | CLONE_CHILD_SETTID | CLONE_CHILD_CLEARTID;
+    int ret = clone(child, stack + STACK_SIZE, flags, NULL, NULL, NULL, &child_tid);
+    printf("clone: %d\n", ret > 0);
+
+    /* Sleep until the exit of the thread clears its tid and wakes the futex. */
+    int waits = 0;
+    int t;
+    while ((t = child_tid) != 0) {
+        syscall(SYS_futex, &child_tid, FUTEX_WAIT, t, NULL, NULL, 0);
+        waits++;
+    }
+    printf("thread ran before the tid was cleared: %d\n", ran);
+    printf("woken without spinning: %d\n", waits <= 2);
+    free(stack);
+    return 0;
+}
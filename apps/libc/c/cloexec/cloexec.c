@@ -0,0 +1,33 @@
+#define _GNU_SOURCE
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = open("/cloexec_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "inherited", 9);
+    lseek(fd, 0, SEEK_SET);
+    // The helper expects fd 10 to be closed by exec and fd 11 to be inherited.
+    dup3(fd, 10, O_CLOEXEC);
+    dup2(fd, 11);
+    close(fd);
+
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        char *argv[] = {"cloexec_helper_c", NULL};
+        execve("/cloexec_helper_c", argv, NULL);
+        printf("execve failed\n");
+        return 1;
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    printf("helper exited: %d\n", WEXITSTATUS(status));
+
+    close(10);
+    close(11);
+    unlink("/cloexec_test.txt");
+    return 0;
+}
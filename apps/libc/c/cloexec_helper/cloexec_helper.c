@@ -0,0 +1,15 @@
+#include <errno.h>
+#include <stdio.h>
+#include <unistd.h>
+
+// Run by the cloexec test through execve.
+int main()
+{
+    char buf[16] = {0};
+    errno = 0;
+    int n = read(10, buf, sizeof(buf) - 1);
+    printf("close-on-exec fd: %d %d\n", n, errno == EBADF);
+    n = read(11, buf, sizeof(buf) - 1);
+    printf("inherited fd: %d %s\n", n, buf);
+    return 0;
+}
@@ -0,0 +1,49 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+#define CLOSE_RANGE_CLOEXEC (1U << 2)
+
+int main()
+{
+    int fd = open("/close_range_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    // The helper expects fds 20 to 29 to be open, with 20 to 24 close-on-exec.
+    for (int i = 20; i < 30; i++) {
+        dup2(fd, i);
+    }
+    close(fd);
+    printf("cloexec: %ld\n", syscall(SYS_close_range, 20, 24, CLOSE_RANGE_CLOEXEC));
+    printf("flags: %d %d\n", fcntl(20, F_GETFD), fcntl(25, F_GETFD));
+
+    errno = 0;
+    printf("first > last: %ld %d\n", syscall(SYS_close_range, 30, 20, 0), errno == EINVAL);
+
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        char *argv[] = {"close_range_helper_c", NULL};
+        execve("/close_range_helper_c", argv, NULL);
+        printf("execve failed\n");
+        return 1;
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    printf("helper exited: %d\n", WEXITSTATUS(status));
+
+    // The slots of the range that are already closed are skipped.
+    close(27);
+    printf("close: %ld\n", syscall(SYS_close_range, 20, ~0U, 0));
+    int open_fds = 0;
+    for (int i = 20; i < 30; i++) {
+        if (fcntl(i, F_GETFD) >= 0) {
+            open_fds++;
+        }
+    }
+    printf("open after close: %d\n", open_fds);
+    unlink("/close_range_test.txt");
+    return 0;
+}
@@ -0,0 +1,17 @@
+#include <fcntl.h>
+#include <stdio.h>
+
+// Run by the close_range test through execve.
+int main()
+{
+    int survived = 0, closed = 0;
+    for (int i = 20; i < 30; i++) {
+        if (fcntl(i, F_GETFD) >= 0) {
+            survived += i >= 25;
+        } else {
+            closed += i < 25;
+        }
+    }
+    printf("closed by exec: %d, survived: %d\n", closed, survived);
+    return 0;
+}
@@ -0,0 +1,73 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#define BIG_SIZE (2 * 1024 * 1024)
+
+static char big[BIG_SIZE], big_out[BIG_SIZE];
+
+int main()
+{
+    char buf[32] = {0};
+
+    int in = open("/copy_src.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(in, "0123456789abcdefghij", 20);
+    int out = open("/copy_dst.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+
+    loff_t off_in = 5, off_out = 2;
+    printf("copy_file_range: %ld\n", (long)copy_file_range(in, &off_in, out, &off_out, 10, 0));
+    printf("offsets: %ld %ld\n", (long)off_in, (long)off_out);
+    pread(out, buf, sizeof(buf), 0);
+    printf("copied bytes match: %d\n", memcmp(buf, "\0\0" "56789abcde", 12) == 0);
+
+    lseek(in, 15, SEEK_SET);
+    lseek(out, 0, SEEK_SET);
+    printf("file offsets: %ld\n", (long)copy_file_range(in, NULL, out, NULL, 100, 0));
+    memset(buf, 0, sizeof(buf));
+    pread(out, buf, 5, 0);
+    printf("copied tail: %s %ld\n", buf, (long)lseek(in, 0, SEEK_CUR));
+
+    errno = 0;
+    printf("bad flags: %ld %d\n", (long)copy_file_range(in, NULL, out, NULL, 1, 1), errno == EINVAL);
+    close(out);
+    errno = 0;
+    printf("closed fd: %ld %d\n", (long)copy_file_range(in, NULL, out, NULL, 1, 0), errno == EBADF);
+
+    off_in = 0;
+    off_out = 5;
+    errno = 0;
+    printf("overlapping: %ld %d\n", (long)copy_file_range(in, &off_in, in, &off_out, 10, 0),
+           errno == EINVAL);
+    off_in = 0;
+    off_out = 10;
+    printf("same file: %ld\n", (long)copy_file_range(in, &off_in, in, &off_out, 10, 0));
+    memset(buf, 0, sizeof(buf));
+    pread(in, buf, 20, 0);
+    printf("same file contents: %s\n", buf);
+    close(in);
+
+    // A large copy goes through the kernel buffer in chunks, and callers loop.
+    for (int i = 0; i < BIG_SIZE; i++) {
+        big[i] = i % 251;
+    }
+    in = open("/copy_src.txt", O_RDWR | O_TRUNC);
+    write(in, big, BIG_SIZE);
+    lseek(in, 0, SEEK_SET);
+    out = open("/copy_dst.txt", O_RDWR | O_TRUNC);
+    size_t total = 0;
+    ssize_t n;
+    while ((n = copy_file_range(in, NULL, out, NULL, BIG_SIZE, 0)) > 0) {
+        total += n;
+    }
+    pread(out, big_out, BIG_SIZE, 0);
+    printf("large copy: %zu %d\n", total, memcmp(big, big_out, BIG_SIZE) == 0);
+    close(out);
+
+    close(in);
+    unlink("/copy_src.txt");
+    unlink("/copy_dst.txt");
+    return 0;
+}
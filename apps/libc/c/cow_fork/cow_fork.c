@@ -0,0 +1,50 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static char global[8192] = "before fork";
+
+int main()
+{
+    char *heap = mmap(NULL, 3 * 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    strcpy(heap, "before fork");
+    strcpy(heap + 2 * 4096, "untouched");
+    int local = 1;
+
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        strcpy(global, "child");
+        strcpy(heap, "child");
+        local = 2;
+        printf("child sees: %s %s %d %s\n", global, heap, local, heap + 2 * 4096);
+        fflush(stdout);
+        return 0;
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    printf("fork returned the child pid: %d\n", pid > 0);
+    printf("parent after child wrote: %s %s %d\n", global, heap, local);
+
+    strcpy(global, "parent");
+    strcpy(heap, "parent");
+    printf("parent sees: %s %s %s\n", global, heap, heap + 2 * 4096);
+
+    // A page shared by several forks stays intact for the ones that don't write.
+    for (int i = 0; i < 3; i++) {
+        if (fork() == 0) {
+            heap[4096] = 'a' + i;
+            return heap[0] == 'p' && heap[4096] == 'a' + i ? 0 : 1;
+        }
+    }
+    int ok = 1;
+    for (int i = 0; i < 3; i++) {
+        wait(&status);
+        ok &= WIFEXITED(status) && WEXITSTATUS(status) == 0;
+    }
+    printf("children each wrote their own copy: %d, parent unchanged: %d\n", ok, heap[4096] == 0);
+    munmap(heap, 3 * 4096);
+    return 0;
+}
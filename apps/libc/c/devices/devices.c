@@ -0,0 +1,74 @@
+#include <dirent.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <unistd.h>
+
+int main()
+{
+    const char *names[] = {"null", "zero", "random", "urandom"};
+    int found = 0;
+    DIR *dir = opendir("/dev");
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        for (int i = 0; i < 4; i++) {
+            if (strcmp(entry->d_name, names[i]) == 0 && entry->d_type == DT_CHR) {
+                found++;
+            }
+        }
+    }
+    closedir(dir);
+    printf("listed in /dev: %d\n", found);
+
+    for (int i = 0; i < 4; i++) {
+        char path[32];
+        snprintf(path, sizeof(path), "/dev/%s", names[i]);
+        int fd = open(path, O_RDWR);
+        struct stat st;
+        fstat(fd, &st);
+        printf("%s: %d %d:%d %d\n", names[i], S_ISCHR(st.st_mode), major(st.st_rdev),
+               minor(st.st_rdev), isatty(fd));
+        close(fd);
+    }
+
+    int fd = open("/dev/null", O_RDWR);
+    char buf[64];
+    printf("null: %d %d %d\n", (int)write(fd, "discarded", 9), (int)read(fd, buf, sizeof(buf)),
+           (int)lseek(fd, 100, SEEK_SET));
+    struct pollfd pfd = {.fd = fd, .events = POLLIN | POLLOUT};
+    printf("poll null: %d\n", poll(&pfd, 1, 0) == 1 && pfd.revents == (POLLIN | POLLOUT));
+    close(fd);
+
+    fd = open("/dev/zero", O_RDONLY);
+    memset(buf, 'x', sizeof(buf));
+    int n = read(fd, buf, sizeof(buf));
+    int zeros = 1;
+    for (int i = 0; i < n; i++) {
+        zeros &= buf[i] == 0;
+    }
+    printf("zero: %d %d %d\n", n, zeros, (int)lseek(fd, 0, SEEK_CUR));
+    close(fd);
+
+    // Two reads of random bytes hardly ever agree.
+    fd = open("/dev/urandom", O_RDONLY);
+    char other[64];
+    n = read(fd, buf, sizeof(buf));
+    read(fd, other, sizeof(other));
+    printf("urandom: %d %d\n", n, memcmp(buf, other, sizeof(buf)) != 0);
+    close(fd);
+
+    // Redirecting output to /dev/null drops it.
+    int saved = dup(STDOUT_FILENO);
+    fd = open("/dev/null", O_WRONLY);
+    dup2(fd, STDOUT_FILENO);
+    printf("this line is discarded\n");
+    fflush(stdout);
+    dup2(saved, STDOUT_FILENO);
+    close(fd);
+    close(saved);
+    printf("redirect done\n");
+    return 0;
+}
@@ -0,0 +1,56 @@
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static int count_entries(DIR *dir)
+{
+    int count = 0;
+    while (readdir(dir) != NULL)
+        count++;
+    return count;
+}
+
+int main()
+{
+    char buf[256];
+    mkdir("/dir_seek", 0755);
+    for (int i = 0; i < 3; i++) {
+        snprintf(buf, sizeof(buf), "/dir_seek/file%d", i);
+        close(open(buf, O_CREAT | O_WRONLY, 0644));
+    }
+
+    DIR *dir = opendir("/dir_seek");
+    int first = count_entries(dir);
+    rewinddir(dir);
+    printf("same entries after rewinddir: %d\n", count_entries(dir) == first);
+
+    rewinddir(dir);
+    readdir(dir);
+    long pos = telldir(dir);
+    char name[256];
+    strcpy(name, readdir(dir)->d_name);
+    count_entries(dir);
+    seekdir(dir, pos);
+    printf("seekdir returns to the entry: %d\n", strcmp(readdir(dir)->d_name, name) == 0);
+    printf("lseek to 0: %ld\n", (long)lseek(dirfd(dir), 0, SEEK_SET));
+    closedir(dir);
+
+    chdir("/dir_seek");
+    int saved = open(".", O_RDONLY | O_DIRECTORY);
+    chdir("/");
+    printf("fchdir: %d\n", fchdir(saved));
+    getcwd(buf, sizeof(buf));
+    printf("back in: %s\n", buf);
+    close(saved);
+    chdir("/");
+
+    for (int i = 0; i < 3; i++) {
+        snprintf(buf, sizeof(buf), "/dir_seek/file%d", i);
+        unlink(buf);
+    }
+    rmdir("/dir_seek");
+    return 0;
+}
@@ -0,0 +1,87 @@
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <time.h>
+#include <unistd.h>
+
+#define ENTRIES 1000
+#define DIR_PATH "/dircache_test"
+/* Listing and stat-ing the directory twice must fit in this many seconds. */
+#define BUDGET_SECS 10
+
+static long cache_stat(const char *name)
+{
+    char line[64];
+    long value = -1;
+    FILE *stats = fopen("/proc/dircache", "r");
+    if (stats == NULL)
+        return -1;
+    while (fgets(line, sizeof(line), stats) != NULL) {
+        if (strncmp(line, name, strlen(name)) == 0 && line[strlen(name)] == ':')
+            value = atol(line + strlen(name) + 1);
+    }
+    fclose(stats);
+    return value;
+}
+
+/* List the directory like `ls -l`, returning the number of entries stat-ed. */
+static int list_and_stat(void)
+{
+    char path[64];
+    struct stat st;
+    int count = 0;
+    DIR *dir = opendir(DIR_PATH);
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (entry->d_name[0] == '.')
+            continue;
+        snprintf(path, sizeof(path), DIR_PATH "/%s", entry->d_name);
+        if (stat(path, &st) == 0 && S_ISREG(st.st_mode) && st.st_size == 1)
+            count++;
+    }
+    closedir(dir);
+    return count;
+}
+
+int main()
+{
+    char path[64];
+    mkdir(DIR_PATH, 0755);
+    for (int i = 0; i < ENTRIES; i++) {
+        snprintf(path, sizeof(path), DIR_PATH "/file%d", i);
+        int fd = open(path, O_CREAT | O_WRONLY, 0644);
+        write(fd, "x", 1);
+        close(fd);
+    }
+
+    struct timespec start, end;
+    clock_gettime(CLOCK_MONOTONIC, &start);
+    printf("first listing: %d\n", list_and_stat());
+    long hits = cache_stat("Hits");
+    printf("second listing: %d\n", list_and_stat());
+    clock_gettime(CLOCK_MONOTONIC, &end);
+    printf("within budget: %d\n", end.tv_sec - start.tv_sec < BUDGET_SECS);
+    printf("served from cache: %d\n", cache_stat("Hits") - hits >= ENTRIES);
+
+    int fd = open(DIR_PATH "/extra", O_CREAT | O_WRONLY, 0644);
+    write(fd, "y", 1);
+    close(fd);
+    printf("after create: %d\n", list_and_stat());
+    fd = open(DIR_PATH "/extra", O_WRONLY | O_APPEND);
+    write(fd, "y", 1);
+    close(fd);
+    printf("after write: %d\n", list_and_stat());
+    unlink(DIR_PATH "/extra");
+    struct stat st;
+    printf("after unlink: %d %d\n", list_and_stat(), stat(DIR_PATH "/extra", &st));
+
+    for (int i = 0; i < ENTRIES; i++) {
+        snprintf(path, sizeof(path), DIR_PATH "/file%d", i);
+        unlink(path);
+    }
+    printf("rmdir: %d\n", rmdir(DIR_PATH));
+    return 0;
+}
@@ -0,0 +1,43 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    mkdir("/dirfd_dir", 0755);
+    int dir = open("/dirfd_dir", O_RDONLY | O_DIRECTORY);
+
+    printf("mkdirat: %d\n", mkdirat(dir, "sub", 0755));
+    int fd = openat(dir, "sub/../file", O_CREAT | O_WRONLY, 0644);
+    write(fd, "hello", 5);
+    close(fd);
+
+    struct stat st;
+    printf("fstatat: %d size: %ld\n", fstatat(dir, "./file", &st, 0), (long)st.st_size);
+    printf("faccessat: %d\n", faccessat(dir, "file", R_OK, 0));
+    printf("absolute path ignores dirfd: %d\n", faccessat(-1, "/dirfd_dir/file", F_OK, 0));
+    printf("renameat: %d\n", renameat(dir, "file", dir, "sub/renamed"));
+    printf("linkat: %d\n", linkat(dir, "sub/renamed", dir, "link", 0));
+    printf("unlinkat: %d\n", unlinkat(dir, "link", 0));
+
+    char buf[16] = {0};
+    fd = open("/dirfd_dir/sub/renamed", O_RDONLY);
+    read(fd, buf, sizeof(buf) - 1);
+    printf("content: %s\n", buf);
+
+    int ret = mkdirat(fd, "nope", 0755);
+    printf("dirfd of a file: %d %d\n", ret, errno == ENOTDIR);
+    close(fd);
+    ret = openat(fd, "file", O_RDONLY);
+    printf("closed dirfd: %d %d\n", ret, errno == EBADF);
+
+    printf("unlinkat file: %d\n", unlinkat(dir, "sub/renamed", 0));
+    printf("unlinkat dir: %d\n", unlinkat(dir, "sub", AT_REMOVEDIR));
+    close(dir);
+    printf("rmdir: %d\n", rmdir("/dirfd_dir"));
+    return 0;
+}
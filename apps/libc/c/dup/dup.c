@@ -0,0 +1,46 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = open("/dup_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    int fd2 = dup(fd);
+    printf("dup lowest free: %d\n", fd2 == fd + 1);
+    write(fd, "hello", 5);
+    printf("dup shares offset: %ld\n", (long)lseek(fd2, 0, SEEK_CUR));
+
+    printf("dup2 same fd: %d\n", dup2(fd, fd) == fd);
+    int fd3 = dup2(fd, 10);
+    write(fd3, "!", 1);
+    printf("dup2 shares offset: %ld\n", (long)lseek(fd, 0, SEEK_CUR));
+
+    errno = 0;
+    printf("dup3 same fd: %d %d\n", dup3(fd, fd, 0), errno == EINVAL);
+    printf("dup3 cloexec: %d\n", dup3(fd, 11, O_CLOEXEC) == 11 && (fcntl(11, F_GETFD) & FD_CLOEXEC));
+
+    close(fd);
+    close(fd2);
+    close(fd3);
+    close(11);
+    unlink("/dup_test.txt");
+
+    // Redirect stdout to a file like a shell does, then restore it.
+    fflush(stdout);
+    int saved = dup(STDOUT_FILENO);
+    fd = open("/dup_redirect.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    dup2(fd, STDOUT_FILENO);
+    const char *msg = "redirected";
+    write(STDOUT_FILENO, msg, strlen(msg));
+    dup2(saved, STDOUT_FILENO);
+    close(saved);
+    char buf[16] = {0};
+    pread(fd, buf, sizeof(buf) - 1, 0);
+    printf("stdout redirected: %s\n", buf);
+    close(fd);
+    unlink("/dup_redirect.txt");
+    return 0;
+}
@@ -0,0 +1,13 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/auxv.h>
+
+int main(int argc, char *argv[])
+{
+    // Linked against the shared libc, which the interpreter has loaded.
+    printf("hello from a dynamically linked app\n");
+    printf("argv[0]: %s\n", strstr(argv[0], "dynamic_c") ? "dynamic_c" : argv[0]);
+    printf("AT_BASE: %s\n", getauxval(AT_BASE) != 0 ? "nonzero" : "0");
+    printf("AT_PHDR: %s\n", getauxval(AT_PHDR) != 0 ? "nonzero" : "0");
+    return 0;
+}
@@ -0,0 +1,84 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/epoll.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile int handled;
+
+static void handler(int signo)
+{
+    handled = signo;
+}
+
+int main()
+{
+    int fds[2];
+    pipe(fds);
+    int epfd = epoll_create1(EPOLL_CLOEXEC);
+    printf("create: %d\n", epfd >= 0);
+
+    struct epoll_event ev = {.events = EPOLLIN, .data.u64 = 42};
+    printf("add: %d\n", epoll_ctl(epfd, EPOLL_CTL_ADD, fds[0], &ev));
+    int ret = epoll_ctl(epfd, EPOLL_CTL_ADD, fds[0], &ev);
+    printf("add again: %d %d\n", ret, errno == EEXIST);
+    ret = epoll_ctl(epfd, EPOLL_CTL_DEL, fds[1], NULL);
+    printf("del unknown: %d %d\n", ret, errno == ENOENT);
+    ev.events = EPOLLIN | EPOLLET;
+    ret = epoll_ctl(epfd, EPOLL_CTL_MOD, fds[0], &ev);
+    printf("edge triggered: %d %d\n", ret, errno == EINVAL);
+
+    struct epoll_event out[4];
+    printf("empty pipe: %d\n", epoll_wait(epfd, out, 4, 0));
+
+    pid_t pid = fork();
+    if (pid == 0) {
+        usleep(50000);
+        write(fds[1], "x", 1);
+        return 0;
+    }
+    int n = epoll_wait(epfd, out, 4, -1);
+    printf("wakeup: %d %d %d\n", n, out[0].events == EPOLLIN, (int)out[0].data.u64);
+    waitpid(pid, NULL, 0);
+    // Level-triggered: still ready until the data is read.
+    printf("still ready: %d\n", epoll_wait(epfd, out, 4, 0));
+    char c;
+    read(fds[0], &c, 1);
+    printf("drained: %d\n", epoll_wait(epfd, out, 4, 0));
+
+    ev.events = EPOLLOUT;
+    ev.data.u64 = 7;
+    epoll_ctl(epfd, EPOLL_CTL_ADD, fds[1], &ev);
+    n = epoll_wait(epfd, out, 4, 0);
+    printf("writable: %d %d\n", n, (int)out[0].data.u64);
+
+    close(fds[1]);
+    n = epoll_wait(epfd, out, 4, 0);
+    printf("after close: %d hup=%d\n", n, (out[0].events & EPOLLHUP) != 0);
+    ret = epoll_ctl(epfd, EPOLL_CTL_DEL, fds[0], NULL);
+    printf("del: %d\n", ret);
+    close(fds[0]);
+
+    // A signal blocked outside of `epoll_pwait` is delivered while it waits.
+    pipe(fds);
+    ev.events = EPOLLIN;
+    epoll_ctl(epfd, EPOLL_CTL_ADD, fds[0], &ev);
+    signal(SIGUSR1, handler);
+    sigset_t set, empty;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    raise(SIGUSR1);
+    sigemptyset(&empty);
+    errno = 0;
+    n = epoll_pwait(epfd, out, 4, 5000, &empty);
+    sigprocmask(SIG_BLOCK, NULL, &set);
+    printf("sigmask: %d %d %d restored=%d\n", n, errno == EINTR, handled == SIGUSR1,
+           sigismember(&set, SIGUSR1));
+
+    close(fds[0]);
+    close(fds[1]);
+    close(epfd);
+    return 0;
+}
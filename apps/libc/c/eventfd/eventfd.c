@@ -0,0 +1,61 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <sys/eventfd.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    int efd = eventfd(0, 0);
+    uint64_t value;
+
+    // The reader blocks until the child writes to wake it up.
+    pid_t pid = fork();
+    if (pid == 0) {
+        usleep(50000);
+        value = 3;
+        write(efd, &value, sizeof(value));
+        return 0;
+    }
+    int n = read(efd, &value, sizeof(value));
+    printf("blocking read: %d %d\n", n, (int)value);
+    waitpid(pid, NULL, 0);
+
+    struct pollfd pfd = {.fd = efd, .events = POLLIN | POLLOUT};
+    poll(&pfd, 1, 0);
+    printf("poll empty: %d\n", pfd.revents == POLLOUT);
+    value = 2;
+    write(efd, &value, sizeof(value));
+    poll(&pfd, 1, 0);
+    printf("poll nonzero: %d\n", pfd.revents == (POLLIN | POLLOUT));
+    close(efd);
+
+    efd = eventfd(2, EFD_SEMAPHORE | EFD_NONBLOCK);
+    read(efd, &value, sizeof(value));
+    printf("semaphore: %d", (int)value);
+    read(efd, &value, sizeof(value));
+    printf(" %d", (int)value);
+    n = read(efd, &value, sizeof(value));
+    printf(" %d %d\n", n, errno == EAGAIN);
+
+    value = UINT64_MAX - 1;
+    write(efd, &value, sizeof(value));
+    value = 1;
+    n = write(efd, &value, sizeof(value));
+    printf("full: %d %d\n", n, errno == EAGAIN);
+    n = read(efd, &value, 4);
+    printf("short read: %d %d\n", n, errno == EINVAL);
+    close(efd);
+
+    efd = eventfd(0, EFD_CLOEXEC);
+    value = 5;
+    write(efd, &value, sizeof(value));
+    value = 0;
+    read(efd, &value, sizeof(value));
+    printf("round trip: %d cloexec=%d\n", (int)value, fcntl(efd, F_GETFD) == FD_CLOEXEC);
+    close(efd);
+    return 0;
+}
@@ -0,0 +1,46 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    char *argv[] = {"helper", "one", "two words", NULL};
+    char *envp[] = {"GREETING=hello", NULL};
+
+    int ret = execve("/missing_program", argv, envp);
+    printf("missing: %d %d\n", ret, errno == ENOENT);
+    int fd = open("/execve_script", O_CREAT | O_WRONLY | O_TRUNC, 0755);
+    write(fd, "not an elf\n", 11);
+    close(fd);
+    ret = execve("/execve_script", argv, envp);
+    printf("not an elf: %d %d\n", ret, errno == ENOEXEC);
+    unlink("/execve_script");
+    printf("still running\n");
+
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        char pid_env[32];
+        snprintf(pid_env, sizeof(pid_env), "PARENT_PID=%d", getpid());
+        char *envp[] = {"GREETING=hello", pid_env, NULL};
+        execve("/execve_helper_c", argv, envp);
+        printf("execve failed\n");
+        return 1;
+    }
+    waitpid(pid, NULL, 0);
+
+    pid = fork();
+    if (pid == 0) {
+        char *argv[] = {"helloworld_c", NULL};
+        chdir("/");
+        execve("helloworld_c", argv, NULL);
+        printf("execve failed\n");
+        return 1;
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    printf("hello world exited: %d\n", WEXITSTATUS(status));
+    return 0;
+}
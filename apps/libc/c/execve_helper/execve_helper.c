@@ -0,0 +1,14 @@
+#include <stdio.h>
+#include <stdlib.h>
+#include <unistd.h>
+
+// Run by the execve test, which passes it arguments and an environment.
+int main(int argc, char *argv[])
+{
+    printf("argc: %d\n", argc);
+    for (int i = 0; i < argc; i++)
+        printf("argv[%d]: %s\n", i, argv[i]);
+    printf("GREETING: %s\n", getenv("GREETING"));
+    printf("pid kept: %d\n", getpid() == atoi(getenv("PARENT_PID")));
+    return 0;
+}
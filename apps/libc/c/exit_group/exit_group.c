@@ -0,0 +1,46 @@
+#include <pthread.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static void *spin(void *arg)
+{
+    // Keeps making syscalls until the process is terminated.
+    for (;;)
+        sched_yield();
+    return NULL;
+}
+
+static void *sleeper(void *arg)
+{
+    for (;;)
+        usleep(10000);
+    return NULL;
+}
+
+static void *exiter(void *arg)
+{
+    usleep(50000);
+    syscall(SYS_exit_group, 7);
+    return NULL;
+}
+
+int main()
+{
+    pid_t child = fork();
+    if (child == 0) {
+        pthread_t threads[3];
+        pthread_create(&threads[0], NULL, spin, NULL);
+        pthread_create(&threads[1], NULL, sleeper, NULL);
+        pthread_create(&threads[2], NULL, exiter, NULL);
+        for (int i = 0; i < 3; i++)
+            pthread_join(threads[i], NULL);
+        printf("not reached\n");
+        return 0;
+    }
+    int status = 0;
+    pid_t pid = waitpid(child, &status, 0);
+    printf("exit_group: %d exited: %d status: %d\n", pid == child, WIFEXITED(status), WEXITSTATUS(status));
+    return 0;
+}
@@ -0,0 +1,57 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    char buf[16];
+    struct stat st;
+
+    int fd = open("/fallocate_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "0123456789", 10);
+    printf("punch hole: %d\n", fallocate(fd, FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE, 2, 4));
+    memset(buf, 0, sizeof(buf));
+    pread(fd, buf, 10, 0);
+    int ok = memcmp(buf, "01\0\0\0\0" "6789", 10) == 0;
+    fstat(fd, &st);
+    printf("hole reads zeros: %d size=%ld\n", ok, (long)st.st_size);
+
+    printf("extend: %d\n", fallocate(fd, 0, 8, 12));
+    fstat(fd, &st);
+    printf("extended size: %ld\n", (long)st.st_size);
+
+    long blocks = st.st_blocks;
+    printf("keep size: %d\n", fallocate(fd, FALLOC_FL_KEEP_SIZE, 0, 65536));
+    fstat(fd, &st);
+    printf("preallocated: size=%ld blocks grew=%d\n", (long)st.st_size,
+           st.st_blocks > blocks && st.st_blocks >= 128);
+
+    errno = 0;
+    printf("negative offset: %d %d\n", fallocate(fd, 0, -1, 1), errno == EINVAL);
+    errno = 0;
+    printf("punch without keep size: %d %d\n", fallocate(fd, FALLOC_FL_PUNCH_HOLE, 0, 1),
+           errno == EINVAL);
+    close(fd);
+
+    fd = open("/fallocate_test.txt", O_RDONLY);
+    errno = 0;
+    printf("read-only: %d %d\n", fallocate(fd, 0, 0, 1), errno == EBADF);
+    close(fd);
+    fd = open("/", O_RDONLY | O_DIRECTORY);
+    errno = 0;
+    printf("directory: %d %d\n", fallocate(fd, 0, 0, 1), errno == EISDIR);
+    close(fd);
+
+    int fds[2];
+    pipe(fds);
+    errno = 0;
+    printf("pipe: %d %d\n", fallocate(fds[1], 0, 0, 1), errno == ESPIPE);
+    close(fds[0]);
+    close(fds[1]);
+    unlink("/fallocate_test.txt");
+    return 0;
+}
@@ -0,0 +1,51 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = open("/fcntl_test.txt", O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    printf("F_GETFL access mode: %d\n", (fcntl(fd, F_GETFL) & O_ACCMODE) == O_WRONLY);
+
+    int fd2 = fcntl(fd, F_DUPFD, 20);
+    printf("F_DUPFD: %d\n", fd2);
+    int fd3 = fcntl(fd, F_DUPFD_CLOEXEC, 20);
+    printf("F_DUPFD_CLOEXEC: %d %d\n", fd3, fcntl(fd3, F_GETFD) & FD_CLOEXEC);
+    printf("F_GETFD of dup: %d\n", fcntl(fd2, F_GETFD) & FD_CLOEXEC);
+    fcntl(fd2, F_SETFD, FD_CLOEXEC);
+    printf("F_SETFD: %d %d\n", fcntl(fd2, F_GETFD) & FD_CLOEXEC, fcntl(fd, F_GETFD) & FD_CLOEXEC);
+
+    write(fd, "abc", 3);
+    lseek(fd, 0, SEEK_SET);
+    fcntl(fd, F_SETFL, O_APPEND);
+    printf("F_SETFL O_APPEND shared: %d\n", (fcntl(fd2, F_GETFL) & O_APPEND) != 0);
+    write(fd, "def", 3);
+    printf("offset after append: %ld\n", (long)lseek(fd, 0, SEEK_CUR));
+    printf("unsupported command: %d\n", fcntl(fd, 12345));
+
+    fcntl(fd, F_SETFL, O_RDWR);
+    printf("F_SETFL keeps access mode: %d\n", (fcntl(fd, F_GETFL) & O_ACCMODE) == O_WRONLY);
+
+    close(fd);
+    close(fd2);
+    close(fd3);
+    unlink("/fcntl_test.txt");
+
+    // Toggling O_NONBLOCK changes how reads of an empty pipe behave.
+    int fds[2];
+    char c;
+    pipe(fds);
+    fcntl(fds[0], F_SETFL, fcntl(fds[0], F_GETFL) | O_NONBLOCK);
+    errno = 0;
+    int n = read(fds[0], &c, 1);
+    printf("O_NONBLOCK set: %d %d %d\n", (fcntl(fds[0], F_GETFL) & O_NONBLOCK) != 0, n,
+           errno == EAGAIN);
+    fcntl(fds[0], F_SETFL, fcntl(fds[0], F_GETFL) & ~O_NONBLOCK);
+    write(fds[1], "x", 1);
+    n = read(fds[0], &c, 1);
+    printf("O_NONBLOCK cleared: %d %d\n", (fcntl(fds[0], F_GETFL) & O_NONBLOCK) != 0, n);
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
@@ -0,0 +1,41 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/ioctl.h>
+#include <unistd.h>
+
+int main()
+{
+    int fds[2];
+    pipe(fds);
+    int avail = -1;
+    ioctl(fds[0], FIONREAD, &avail);
+    printf("empty pipe: %d\n", avail);
+    write(fds[1], "hello", 5);
+    ioctl(fds[0], FIONREAD, &avail);
+    printf("pipe: %d\n", avail);
+
+    int on = 1;
+    int ret = ioctl(fds[0], FIONBIO, &on);
+    char buf[16];
+    read(fds[0], buf, sizeof(buf));
+    printf("FIONBIO on: %d O_NONBLOCK: %d\n", ret, (fcntl(fds[0], F_GETFL) & O_NONBLOCK) != 0);
+    ret = read(fds[0], buf, sizeof(buf));
+    printf("empty nonblocking read: %d %d\n", ret, errno == EAGAIN);
+    int off = 0;
+    ioctl(fds[0], FIONBIO, &off);
+    printf("FIONBIO off: O_NONBLOCK: %d\n", (fcntl(fds[0], F_GETFL) & O_NONBLOCK) != 0);
+    close(fds[0]);
+    close(fds[1]);
+
+    int fd = open("/fionread_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "0123456789", 10);
+    lseek(fd, 3, SEEK_SET);
+    ioctl(fd, FIONREAD, &avail);
+    printf("file: %d\n", avail);
+    ret = ioctl(fd, FIONREAD, NULL);
+    printf("null argp: %d %d\n", ret, errno == EFAULT);
+    close(fd);
+    unlink("/fionread_test.txt");
+    return 0;
+}
@@ -0,0 +1,78 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/file.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static void on_signal(int signo)
+{
+    (void)signo;
+}
+
+int main()
+{
+    int fd = open("/flock_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    int other = open("/flock_test.txt", O_RDWR);
+    int dup_fd = dup(fd);
+
+    printf("exclusive: %d\n", flock(fd, LOCK_EX));
+    printf("dup shares lock: %d\n", flock(dup_fd, LOCK_EX | LOCK_NB));
+    errno = 0;
+    printf("other open conflicts: %d %d\n", flock(other, LOCK_SH | LOCK_NB), errno == EWOULDBLOCK);
+    printf("downgrade: %d\n", flock(fd, LOCK_SH));
+    printf("shared with other: %d\n", flock(other, LOCK_SH | LOCK_NB));
+    errno = 0;
+    printf("exclusive while shared: %d %d\n", flock(fd, LOCK_EX | LOCK_NB), errno == EWOULDBLOCK);
+    flock(other, LOCK_UN);
+
+    // The child blocks until the parent releases its lock.
+    flock(fd, LOCK_EX);
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        int child_fd = open("/flock_test.txt", O_RDWR);
+        flock(child_fd, LOCK_EX);
+        write(child_fd, "child", 5);
+        flock(child_fd, LOCK_UN);
+        close(child_fd);
+        return 0;
+    }
+    usleep(50000);
+    char buf[8] = {0};
+    printf("child waited: %ld\n", (long)pread(fd, buf, sizeof(buf), 0));
+    flock(fd, LOCK_UN);
+    waitpid(pid, NULL, 0);
+    printf("child wrote: %ld\n", (long)pread(fd, buf, sizeof(buf), 0));
+
+    // Closing every fd of the open file releases its lock.
+    flock(fd, LOCK_EX);
+    close(dup_fd);
+    errno = 0;
+    printf("still locked: %d %d\n", flock(other, LOCK_EX | LOCK_NB), errno == EWOULDBLOCK);
+    close(fd);
+    printf("released by close: %d\n", flock(other, LOCK_EX | LOCK_NB));
+
+    // A signal interrupts waiting for the lock `other` holds.
+    fflush(stdout);
+    pid = fork();
+    if (pid == 0) {
+        struct sigaction sa = {.sa_handler = on_signal};
+        sigaction(SIGUSR1, &sa, NULL);
+        int child_fd = open("/flock_test.txt", O_RDWR);
+        errno = 0;
+        int r = flock(child_fd, LOCK_EX);
+        printf("interrupted: %d %d\n", r, errno == EINTR);
+        return 0;
+    }
+    usleep(50000);
+    kill(pid, SIGUSR1);
+    waitpid(pid, NULL, 0);
+
+    errno = 0;
+    printf("bad op: %d %d\n", flock(other, 0), errno == EINVAL);
+    close(other);
+    unlink("/flock_test.txt");
+    return 0;
+}
@@ -0,0 +1,41 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    struct stat st;
+
+    mkdir("/fstatat_dir", 0755);
+    int fd = open("/fstatat_dir/file.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "hello", 5);
+    close(fd);
+
+    if (stat("/fstatat_dir/file.txt", &st) == 0) {
+        printf("stat absolute: size=%ld\n", (long)st.st_size);
+    }
+    chdir("/fstatat_dir");
+    if (fstatat(AT_FDCWD, "file.txt", &st, 0) == 0) {
+        printf("fstatat cwd: size=%ld\n", (long)st.st_size);
+    }
+    chdir("/");
+    int dirfd = open("/fstatat_dir", O_RDONLY | O_DIRECTORY);
+    if (fstatat(dirfd, "file.txt", &st, 0) == 0) {
+        printf("fstatat dirfd: size=%ld\n", (long)st.st_size);
+    }
+    if (fstatat(dirfd, "", &st, AT_EMPTY_PATH) == 0) {
+        printf("fstatat empty path: dir=%d\n", S_ISDIR(st.st_mode));
+    }
+    close(dirfd);
+
+    errno = 0;
+    printf("missing: %d %d\n", stat("/fstatat_dir/missing", &st), errno == ENOENT);
+    errno = 0;
+    printf("file as directory: %d %d\n", stat("/fstatat_dir/file.txt/x", &st), errno == ENOTDIR);
+
+    unlink("/fstatat_dir/file.txt");
+    rmdir("/fstatat_dir");
+    return 0;
+}
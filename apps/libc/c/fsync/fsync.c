@@ -0,0 +1,39 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+int main()
+{
+    char buf[8] = {0};
+
+    int fd = open("/fsync_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "durable", 7);
+    printf("fsync: %d\n", fsync(fd));
+    printf("fdatasync: %d\n", fdatasync(fd));
+    sync();
+    close(fd);
+
+    fd = open("/fsync_test.txt", O_RDONLY);
+    read(fd, buf, 7);
+    printf("read back: %s\n", buf);
+    printf("fsync read-only: %d\n", fsync(fd));
+    close(fd);
+
+    errno = 0;
+    printf("bad fd: %d %d\n", fsync(fd), errno == EBADF);
+
+    // `sync` writes back what has been written to a shared mapping as well.
+    fd = open("/fsync_test.txt", O_RDWR);
+    char *map = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    memcpy(map, "DURABLE", 7);
+    sync();
+    pread(fd, buf, 7, 0);
+    printf("mapping synced: %s\n", buf);
+    munmap(map, 4096);
+    close(fd);
+    unlink("/fsync_test.txt");
+    return 0;
+}
@@ -0,0 +1,73 @@
+#include <errno.h>
+#include <linux/futex.h>
+#include <pthread.h>
+#include <stdatomic.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <time.h>
+#include <unistd.h>
+
+#define ITERATIONS 100000
+
+static atomic_int lock_word = 0;
+static long counter = 0;
+
+static long futex(atomic_int *uaddr, int op, int val, const struct timespec *timeout)
+{
+    return syscall(SYS_futex, uaddr, op, val, timeout, NULL, 0);
+}
+
+/* 0: unlocked, 1: locked, 2: locked with waiters. */
+static void lock(void)
+{
+    int c = 0;
+    if (atomic_compare_exchange_strong(&lock_word, &c, 1))
+        return;
+    if (c != 2)
+        c = atomic_exchange(&lock_word, 2);
+    while (c != 0) {
+        futex(&lock_word, FUTEX_WAIT_PRIVATE, 2, NULL);
+        c = atomic_exchange(&lock_word, 2);
+    }
+}
+
+static void unlock(void)
+{
+    if (atomic_fetch_sub(&lock_word, 1) != 1) {
+        atomic_store(&lock_word, 0);
+        futex(&lock_word, FUTEX_WAKE_PRIVATE, 1, NULL);
+    }
+}
+
+static void *increment(void *arg)
+{
+    for (int i = 0; i < ITERATIONS; i++) {
+        lock();
+        counter++;
+        unlock();
+    }
+    return NULL;
+}
+
+int main()
+{
+    pthread_t threads[2];
+    for (int i = 0; i < 2; i++) {
+        pthread_create(&threads[i], NULL, increment, NULL);
+    }
+    for (int i = 0; i < 2; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    printf("counter: %ld\n", counter);
+
+    atomic_int word = 1;
+    long ret = futex(&word, FUTEX_WAIT, 0, NULL);
+    printf("wait on a changed word: %ld %d\n", ret, errno == EAGAIN);
+    struct timespec timeout = {0, 10000000};
+    ret = futex(&word, FUTEX_WAIT, 1, &timeout);
+    printf("wait with a timeout: %ld %d\n", ret, errno == ETIMEDOUT);
+    printf("wake without waiters: %ld\n", futex(&word, FUTEX_WAKE, 1, NULL));
+    ret = futex((atomic_int *)((char *)&word + 1), FUTEX_WAKE, 1, NULL);
+    printf("unaligned: %ld %d\n", ret, errno == EINVAL);
+    return 0;
+}
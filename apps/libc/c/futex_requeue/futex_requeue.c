@@ -0,0 +1,99 @@
+#include <errno.h>
+#include <limits.h>
+#include <linux/futex.h>
+#include <pthread.h>
+#include <stdatomic.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define WAITERS 3
+
+static atomic_int mutex = 0;
+static atomic_int cond = 0;
+static int ready = 0;
+static int signaled = 0;
+static int woken = 0;
+
+static long futex(atomic_int *uaddr, int op, int val, long val2, atomic_int *uaddr2, int val3)
+{
+    return syscall(SYS_futex, uaddr, op, val, val2, uaddr2, val3);
+}
+
+/* 0: unlocked, 1: locked, 2: locked with waiters. */
+static void lock(void)
+{
+    int c = 0;
+    if (atomic_compare_exchange_strong(&mutex, &c, 1))
+        return;
+    if (c != 2)
+        c = atomic_exchange(&mutex, 2);
+    while (c != 0) {
+        futex(&mutex, FUTEX_WAIT_PRIVATE, 2, 0, NULL, 0);
+        c = atomic_exchange(&mutex, 2);
+    }
+}
+
+static void unlock(void)
+{
+    if (atomic_fetch_sub(&mutex, 1) != 1) {
+        atomic_store(&mutex, 0);
+        futex(&mutex, FUTEX_WAKE_PRIVATE, 1, 0, NULL, 0);
+    }
+}
+
+static void *wait_signal(void *arg)
+{
+    lock();
+    ready++;
+    while (!signaled) {
+        int seq = atomic_load(&cond);
+        unlock();
+        futex(&cond, FUTEX_WAIT_PRIVATE, seq, 0, NULL, 0);
+        /* Requeued waiters come back through the mutex, so it is contended. */
+        int c = atomic_exchange(&mutex, 2);
+        while (c != 0) {
+            futex(&mutex, FUTEX_WAIT_PRIVATE, 2, 0, NULL, 0);
+            c = atomic_exchange(&mutex, 2);
+        }
+    }
+    woken++;
+    unlock();
+    return NULL;
+}
+
+int main()
+{
+    pthread_t threads[WAITERS];
+    for (int i = 0; i < WAITERS; i++) {
+        pthread_create(&threads[i], NULL, wait_signal, NULL);
+    }
+    for (;;) {
+        lock();
+        int all_ready = ready == WAITERS;
+        unlock();
+        if (all_ready)
+            break;
+        usleep(10000);
+    }
+    usleep(100000);
+
+    lock();
+    long ret = futex(&cond, FUTEX_CMP_REQUEUE_PRIVATE, 1, INT_MAX, &mutex, cond + 1);
+    printf("stale requeue: %ld %d\n", ret, errno == EAGAIN);
+    signaled = 1;
+    int seq = atomic_fetch_add(&cond, 1) + 1;
+    /* Wake one waiter and move the others onto the mutex rather than waking them all. */
+    ret = futex(&cond, FUTEX_CMP_REQUEUE_PRIVATE, 1, INT_MAX, &mutex, seq);
+    printf("woken and requeued: %ld\n", ret);
+    atomic_store(&mutex, 2);
+    unlock();
+
+    for (int i = 0; i < WAITERS; i++) {
+        pthread_join(threads[i], NULL);
+    }
+    printf("woken: %d\n", woken);
+    printf("nothing left to requeue: %ld\n",
+           futex(&cond, FUTEX_REQUEUE_PRIVATE, 1, INT_MAX, &mutex, 0));
+    return 0;
+}
@@ -0,0 +1,27 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main()
+{
+    char buf[256];
+    chdir("/");
+    long len = syscall(SYS_getcwd, buf, sizeof(buf));
+    printf("root: %s %ld\n", buf, len);
+
+    memset(buf, 'x', sizeof(buf));
+    long ret = syscall(SYS_getcwd, buf, 1);
+    printf("too small: %ld %d untouched: %d\n", ret, errno == ERANGE, buf[0] == 'x');
+    ret = syscall(SYS_getcwd, buf, 0);
+    printf("zero size: %ld %d\n", ret, errno == EINVAL);
+    ret = syscall(SYS_getcwd, NULL, sizeof(buf));
+    printf("null buffer: %ld %d\n", ret, errno == EFAULT);
+    ret = syscall(SYS_getcwd, (char *)1, sizeof(buf));
+    printf("bad buffer: %ld %d\n", ret, errno == EFAULT);
+
+    if (getcwd(buf, sizeof(buf)) != NULL)
+        printf("getcwd: %s\n", buf);
+    return 0;
+}
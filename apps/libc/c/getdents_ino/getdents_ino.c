@@ -0,0 +1,66 @@
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+#define NFILES 4
+
+int main()
+{
+    char path[64];
+    mkdir("/ino_dir", 0755);
+    for (int i = 0; i < NFILES; i++) {
+        sprintf(path, "/ino_dir/file%d", i);
+        close(open(path, O_CREAT | O_RDWR, 0644));
+    }
+    symlink("/ino_dir/file0", "/ino_dir/link");
+
+    ino_t inos[NFILES];
+    int count = 0;
+    DIR *dir = opendir("/ino_dir");
+    struct dirent *entry;
+    struct stat st;
+    int match_stat = 1, link_type = 0;
+    while ((entry = readdir(dir)) != NULL) {
+        if (strncmp(entry->d_name, "file", 4) == 0 && count < NFILES) {
+            inos[count++] = entry->d_ino;
+        }
+        sprintf(path, "/ino_dir/%s", entry->d_name);
+        if (entry->d_name[0] != '.' && (lstat(path, &st) != 0 || st.st_ino != entry->d_ino)) {
+            match_stat = 0;
+        }
+        if (strcmp(entry->d_name, "link") == 0) {
+            link_type = entry->d_type == DT_LNK;
+        }
+    }
+    closedir(dir);
+
+    int distinct = count == NFILES;
+    for (int i = 0; i < count; i++) {
+        for (int j = i + 1; j < count; j++) {
+            if (inos[i] == inos[j]) {
+                distinct = 0;
+            }
+        }
+    }
+    printf("getdents64 inodes distinct: %d\n", distinct);
+    printf("getdents64 d_ino matches st_ino: %d\n", match_stat);
+    printf("getdents64 symlink type: %d\n", link_type);
+
+    int fd = open("/ino_dir/file1", O_RDONLY);
+    struct stat fst;
+    fstat(fd, &fst);
+    stat("/ino_dir/file1", &st);
+    printf("fstat st_ino matches stat: %d\n", fst.st_ino == st.st_ino);
+    close(fd);
+    unlink("/ino_dir/link");
+
+    for (int i = 0; i < NFILES; i++) {
+        sprintf(path, "/ino_dir/file%d", i);
+        unlink(path);
+    }
+    rmdir("/ino_dir");
+    return 0;
+}
@@ -0,0 +1,71 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define NFILES 200
+
+struct linux_dirent64 {
+    unsigned long long d_ino;
+    long long d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+int main()
+{
+    char path[64];
+    mkdir("/paging_dir", 0755);
+    for (int i = 0; i < NFILES; i++) {
+        sprintf(path, "/paging_dir/f%d", i);
+        close(open(path, O_CREAT | O_RDWR, 0644));
+    }
+
+    int seen[NFILES] = {0};
+    char buf[256];
+    int fd = open("/paging_dir", O_RDONLY | O_DIRECTORY);
+    for (;;) {
+        long n = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+        if (n <= 0) {
+            break;
+        }
+        for (long off = 0; off < n;) {
+            struct linux_dirent64 *d = (struct linux_dirent64 *)(buf + off);
+            if (d->d_name[0] == 'f') {
+                seen[atoi(d->d_name + 1)]++;
+            }
+            off += d->d_reclen;
+        }
+    }
+
+    lseek(fd, 0, SEEK_SET);
+    int entries = 0;
+    for (;;) {
+        long n = syscall(SYS_getdents64, fd, buf, sizeof(buf));
+        if (n <= 0) {
+            break;
+        }
+        for (long off = 0; off < n;) {
+            struct linux_dirent64 *d = (struct linux_dirent64 *)(buf + off);
+            entries += d->d_name[0] == 'f';
+            off += d->d_reclen;
+        }
+    }
+    close(fd);
+
+    int exactly_once = 1;
+    for (int i = 0; i < NFILES; i++) {
+        if (seen[i] != 1) {
+            exactly_once = 0;
+        }
+        sprintf(path, "/paging_dir/f%d", i);
+        unlink(path);
+    }
+    rmdir("/paging_dir");
+    printf("getdents64 paging exactly once: %d\n", exactly_once);
+    printf("getdents64 after rewind: %d\n", entries);
+    return 0;
+}
@@ -0,0 +1,43 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/random.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static int all_zero(const unsigned char *buf, size_t len)
+{
+    for (size_t i = 0; i < len; i++)
+        if (buf[i])
+            return 0;
+    return 1;
+}
+
+int main()
+{
+    unsigned char a[64], b[64];
+    memset(a, 0, sizeof(a));
+    memset(b, 0, sizeof(b));
+    long ret = syscall(SYS_getrandom, a, sizeof(a), 0);
+    printf("filled: %ld %d\n", ret, !all_zero(a, sizeof(a)));
+    ret = syscall(SYS_getrandom, b, sizeof(b), GRND_NONBLOCK);
+    printf("nonblock: %ld differs: %d\n", ret, memcmp(a, b, sizeof(a)) != 0);
+    ret = syscall(SYS_getrandom, a, 0, 0);
+    printf("zero length: %ld\n", ret);
+
+    static unsigned char big[4096];
+    ret = syscall(SYS_getrandom, big, sizeof(big), GRND_RANDOM);
+    printf("large: %ld %d\n", ret, !all_zero(big + 4032, 64));
+
+    ret = syscall(SYS_getrandom, a, sizeof(a), 0x100);
+    printf("unknown flag: %ld %d\n", ret, errno == EINVAL);
+    ret = syscall(SYS_getrandom, NULL, sizeof(a), 0);
+    printf("bad buffer: %ld %d\n", ret, errno == EFAULT);
+
+    int fd = open("/dev/urandom", O_RDONLY);
+    ret = read(fd, a, sizeof(a));
+    printf("urandom: %ld differs: %d\n", ret, memcmp(a, b, sizeof(a)) != 0);
+    close(fd);
+    return 0;
+}
@@ -0,0 +1,50 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static void print_file(const char *name, const char *path)
+{
+    char buf[32] = {0};
+    int fd = open(path, O_RDONLY);
+    read(fd, buf, sizeof(buf) - 1);
+    close(fd);
+    printf("%s: %s\n", name, buf);
+}
+
+int main()
+{
+    int fd = open("/hardlink_a", O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    write(fd, "original", 8);
+    close(fd);
+
+    printf("link: %d\n", link("/hardlink_a", "/hardlink_b"));
+    struct stat a, b;
+    stat("/hardlink_a", &a);
+    stat("/hardlink_b", &b);
+    printf("nlink: %ld %ld\n", (long)a.st_nlink, (long)b.st_nlink);
+    printf("same inode: %d\n", a.st_ino == b.st_ino);
+
+    fd = open("/hardlink_b", O_WRONLY | O_TRUNC);
+    write(fd, "changed", 7);
+    close(fd);
+    print_file("read through the original", "/hardlink_a");
+
+    int ret = link("/hardlink_a", "/hardlink_b");
+    printf("existing name: %d %d\n", ret, errno == EEXIST);
+    mkdir("/hardlink_dir", 0755);
+    ret = link("/hardlink_dir", "/hardlink_dir2");
+    printf("directory: %d %d\n", ret, errno == EPERM);
+    rmdir("/hardlink_dir");
+
+    printf("unlink the original: %d\n", unlink("/hardlink_a"));
+    printf("original gone: %d\n", access("/hardlink_a", F_OK));
+    print_file("read through the link", "/hardlink_b");
+    stat("/hardlink_b", &b);
+    printf("nlink: %ld\n", (long)b.st_nlink);
+    printf("unlink the link: %d\n", unlink("/hardlink_b"));
+    printf("data gone: %d\n", access("/hardlink_b", F_OK));
+    return 0;
+}
@@ -0,0 +1,19 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+int main()
+{
+    for (int fd = 0; fd < 3; fd++) {
+        printf("isatty(%d): %d\n", fd, isatty(fd));
+    }
+    int fd = open("/isatty_test.txt", O_CREAT | O_RDWR, 0644);
+    if (fd < 0) {
+        printf("open failed!\n");
+        return 1;
+    }
+    printf("isatty(file): %d\n", isatty(fd));
+    close(fd);
+    unlink("/isatty_test.txt");
+    return 0;
+}
@@ -0,0 +1,44 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = open("/lseek_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    if (fd < 0) {
+        printf("open failed!\n");
+        return 1;
+    }
+    write(fd, "head", 4);
+    off_t off = lseek(fd, 8, SEEK_CUR);
+    printf("lseek past EOF: %ld\n", (long)off);
+    write(fd, "tail", 4);
+    printf("lseek end: %ld\n", (long)lseek(fd, 0, SEEK_END));
+
+    char buf[16];
+    lseek(fd, 0, SEEK_SET);
+    int n = read(fd, buf, sizeof(buf));
+    int hole_is_zero = n == 16;
+    for (int i = 4; i < 12; i++) {
+        if (buf[i] != 0) {
+            hole_is_zero = 0;
+        }
+    }
+    printf("hole reads as zeros: %d\n", hole_is_zero);
+    printf("lseek negative: %ld\n", (long)lseek(fd, -100, SEEK_SET));
+    printf("SEEK_DATA: %ld\n", (long)lseek(fd, 5, SEEK_DATA));
+    printf("SEEK_HOLE: %ld\n", (long)lseek(fd, 5, SEEK_HOLE));
+    off = lseek(fd, 16, SEEK_DATA);
+    printf("SEEK_DATA at EOF: %ld %d\n", (long)off, errno == ENXIO);
+    close(fd);
+    unlink("/lseek_test.txt");
+
+    int fds[2];
+    pipe(fds);
+    printf("lseek pipe: %ld\n", (long)lseek(fds[0], 0, SEEK_CUR));
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
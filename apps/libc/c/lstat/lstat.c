@@ -0,0 +1,35 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    struct stat st;
+
+    int fd = open("/lstat_target", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "hello", 5);
+    close(fd);
+    mkdir("/lstat_dir", 0755);
+    symlink("/lstat_target", "/lstat_link");
+    symlink("/lstat_dir", "/lstat_dir_link");
+
+    if (lstat("/lstat_link", &st) == 0) {
+        printf("lstat: lnk=%d size=%ld\n", S_ISLNK(st.st_mode), (long)st.st_size);
+    }
+    if (stat("/lstat_link", &st) == 0) {
+        printf("stat: reg=%d size=%ld\n", S_ISREG(st.st_mode), (long)st.st_size);
+    }
+    if (fstatat(AT_FDCWD, "/lstat_dir_link", &st, AT_SYMLINK_NOFOLLOW) == 0) {
+        printf("fstatat nofollow: lnk=%d\n", S_ISLNK(st.st_mode));
+    }
+    if (fstatat(AT_FDCWD, "/lstat_dir_link", &st, 0) == 0) {
+        printf("fstatat follow: dir=%d\n", S_ISDIR(st.st_mode));
+    }
+
+    unlink("/lstat_link");
+    unlink("/lstat_dir_link");
+    unlink("/lstat_target");
+    rmdir("/lstat_dir");
+    return 0;
+}
@@ -0,0 +1,31 @@
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+
+int main()
+{
+    size_t size = 4 * 4096;
+    char *map = mmap(NULL, size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    memset(map, 0x5a, size);
+
+    printf("willneed: %d\n", madvise(map, size, MADV_WILLNEED));
+    printf("kept after a hint: %d\n", map[0] == 0x5a);
+
+    printf("dontneed: %d\n", madvise(map + 4096, 2 * 4096, MADV_DONTNEED));
+    int zeros = 1;
+    for (size_t i = 4096; i < 3 * 4096; i++) {
+        if (map[i] != 0) {
+            zeros = 0;
+        }
+    }
+    printf("discarded pages read zeros: %d\n", zeros);
+    printf("other pages kept: %d %d\n", map[4095] == 0x5a, map[3 * 4096] == 0x5a);
+    map[4096] = 1;
+    printf("writable again: %d\n", map[4096] == 1);
+
+    int ret = madvise(map + 1, 4096, MADV_DONTNEED);
+    printf("unaligned: %d %d\n", ret, errno == EINVAL);
+    munmap(map, size);
+    return 0;
+}
@@ -0,0 +1,59 @@
+#define _GNU_SOURCE
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = memfd_create("test", MFD_CLOEXEC);
+    printf("cloexec: %d\n", fcntl(fd, F_GETFD) == FD_CLOEXEC);
+
+    printf("write: %d\n", (int)write(fd, "hello memfd", 11));
+    struct stat st;
+    fstat(fd, &st);
+    printf("regular: %d, size: %d\n", S_ISREG(st.st_mode), (int)st.st_size);
+
+    // The memfd has no name in any directory.
+    int named = 0;
+    DIR *dir = opendir("/tmp");
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        named += strncmp(entry->d_name, ".memfd", 6) == 0;
+    }
+    closedir(dir);
+    printf("unnamed: %d\n", named == 0);
+
+    char buf[32] = {0};
+    lseek(fd, 6, SEEK_SET);
+    printf("read: %d %s\n", (int)read(fd, buf, sizeof(buf) - 1), buf);
+
+    ftruncate(fd, 4096);
+    fstat(fd, &st);
+    printf("truncated size: %d\n", (int)st.st_size);
+    char *map = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    printf("mapped: %.5s\n", map);
+    memcpy(map, "HELLO", 5);
+    munmap(map, 4096);
+    memset(buf, 0, sizeof(buf));
+    pread(fd, buf, 11, 0);
+    printf("written through the mapping: %s\n", buf);
+    close(fd);
+
+    char name[300];
+    memset(name, 'a', sizeof(name) - 1);
+    name[sizeof(name) - 1] = '\0';
+    errno = 0;
+    printf("long name: %d %d\n", memfd_create(name, 0), errno == EINVAL);
+    errno = 0;
+    printf("bad flags: %d %d\n", memfd_create("test", 0x100), errno == EINVAL);
+    fd = memfd_create("sealed", MFD_ALLOW_SEALING);
+    errno = 0;
+    printf("add seals: %d %d\n", fcntl(fd, F_ADD_SEALS, F_SEAL_WRITE), errno == EINVAL);
+    close(fd);
+    return 0;
+}
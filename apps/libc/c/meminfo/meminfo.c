@@ -0,0 +1,45 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+
+static long meminfo(const char *field)
+{
+    char line[128];
+    long value = -1;
+    FILE *file = fopen("/proc/meminfo", "r");
+    while (fgets(line, sizeof(line), file) != NULL) {
+        size_t len = strlen(field);
+        if (strncmp(line, field, len) == 0 && line[len] == ':') {
+            sscanf(line + len + 1, "%ld", &value);
+        }
+    }
+    fclose(file);
+    return value;
+}
+
+int main()
+{
+    long total = meminfo("MemTotal");
+    long free = meminfo("MemFree");
+    long available = meminfo("MemAvailable");
+    printf("total >= available >= free > 0: %d\n", total >= available && available >= free && free > 0);
+
+    size_t size = 4 << 20;
+    char *map = mmap(NULL, size, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    memset(map, 1, size);
+    long used = free - meminfo("MemFree");
+    printf("touching 4MB uses it: %d\n", used >= 4096);
+    munmap(map, size);
+
+    char line[128];
+    int processors = 0;
+    FILE *file = fopen("/proc/cpuinfo", "r");
+    while (fgets(line, sizeof(line), file) != NULL) {
+        if (strncmp(line, "processor", 9) == 0) {
+            processors++;
+        }
+    }
+    fclose(file);
+    printf("processors listed: %d\n", processors > 0);
+    return 0;
+}
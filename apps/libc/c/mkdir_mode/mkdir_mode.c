@@ -0,0 +1,22 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+static void check_mode(const char *path, mode_t mode)
+{
+    struct stat st;
+    mkdir(path, mode);
+    int fd = open(path, O_RDONLY | O_DIRECTORY);
+    fstat(fd, &st);
+    close(fd);
+    printf("mkdir %s %o: %o\n", path, mode, st.st_mode & 0777);
+    rmdir(path);
+}
+
+int main()
+{
+    check_mode("/mode_dir_700", 0700);
+    check_mode("/mode_dir_777", 0777);
+    return 0;
+}
@@ -0,0 +1,67 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/sysmacros.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    int r = mkfifo("/mknod_fifo", 0644);
+    struct stat st;
+    stat("/mknod_fifo", &st);
+    printf("mkfifo: %d %d\n", r, S_ISFIFO(st.st_mode));
+    errno = 0;
+    r = mkfifo("/mknod_fifo", 0644);
+    printf("mkfifo existing: %d %d\n", r, errno == EEXIST);
+
+    // Without a reader, a nonblocking writer can't open the FIFO.
+    errno = 0;
+    int fd = open("/mknod_fifo", O_WRONLY | O_NONBLOCK);
+    printf("nonblocking writer alone: %d %d\n", fd, errno == ENXIO);
+    fd = open("/mknod_fifo", O_RDONLY | O_NONBLOCK);
+    char buf[16] = {0};
+    printf("nonblocking reader alone: %d %d\n", fd >= 0, (int)read(fd, buf, sizeof(buf)));
+    close(fd);
+
+    // The reader waits for the child to open the FIFO for writing.
+    pid_t pid = fork();
+    if (pid == 0) {
+        usleep(50000);
+        int wfd = open("/mknod_fifo", O_WRONLY);
+        write(wfd, "through fifo", 12);
+        close(wfd);
+        return 0;
+    }
+    fd = open("/mknod_fifo", O_RDONLY);
+    int n = read(fd, buf, sizeof(buf) - 1);
+    printf("blocking open: %d %s\n", n, buf);
+    printf("writer gone: %d\n", (int)read(fd, buf, sizeof(buf)));
+    close(fd);
+    waitpid(pid, NULL, 0);
+    unlink("/mknod_fifo");
+
+    r = mknod("/mknod_null", S_IFCHR | 0666, makedev(1, 3));
+    stat("/mknod_null", &st);
+    printf("mknod null: %d %d %d %d\n", r, S_ISCHR(st.st_mode), major(st.st_rdev),
+           minor(st.st_rdev));
+    fd = open("/mknod_null", O_RDWR);
+    printf("null: %d %d\n", (int)write(fd, "discarded", 9), (int)read(fd, buf, sizeof(buf)));
+    close(fd);
+    unlink("/mknod_null");
+
+    mknod("/mknod_zero", S_IFCHR | 0666, makedev(1, 5));
+    fd = open("/mknod_zero", O_RDONLY);
+    memset(buf, 'x', sizeof(buf));
+    n = read(fd, buf, sizeof(buf));
+    printf("zero: %d %d\n", n, buf[0] == 0 && buf[sizeof(buf) - 1] == 0);
+    close(fd);
+    unlink("/mknod_zero");
+
+    errno = 0;
+    r = mknod("/mknod_bad", S_IFMT | 0644, 0);
+    printf("bad type: %d %d\n", r, errno == EINVAL);
+    return 0;
+}
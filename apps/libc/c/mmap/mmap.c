@@ -0,0 +1,82 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    char *anon = mmap(NULL, 3 * 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    printf("anonymous zeroed: %d\n", anon != MAP_FAILED && anon[0] == 0 && anon[3 * 4096 - 1] == 0);
+    strcpy(anon + 4096, "anonymous");
+    printf("anonymous write: %s\n", anon + 4096);
+    munmap(anon, 3 * 4096);
+
+    // The second page is first touched after the fork.
+    char *shared_anon = mmap(NULL, 2 * 4096, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    shared_anon[0] = 'p';
+    if (fork() == 0) {
+        shared_anon[4096] = shared_anon[0] == 'p' ? 'c' : '?';
+        return 0;
+    }
+    wait(NULL);
+    printf("shared anonymous across fork: %c\n", shared_anon[4096]);
+    munmap(shared_anon, 2 * 4096);
+
+    int fd = open("/mmap_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    char data[5000];
+    for (int i = 0; i < (int)sizeof(data); i++) {
+        data[i] = 'a' + i % 26;
+    }
+    write(fd, data, sizeof(data));
+
+    char *priv = mmap(NULL, sizeof(data), PROT_READ | PROT_WRITE, MAP_PRIVATE, fd, 0);
+    printf("file contents: %d\n", memcmp(priv, data, sizeof(data)) == 0);
+    printf("past end of file: %d\n", priv[sizeof(data)] == 0 && priv[8191] == 0);
+    priv[0] = '!';
+    char c;
+    pread(fd, &c, 1, 0);
+    printf("private write kept private: %c\n", c);
+    munmap(priv, sizeof(data));
+
+    char *off = mmap(NULL, 4096, PROT_READ, MAP_PRIVATE, fd, 4096);
+    printf("offset mapping: %d\n", memcmp(off, data + 4096, sizeof(data) - 4096) == 0);
+    munmap(off, 4096);
+
+    // The kernel reads the pages of the mapping before user space touches them.
+    char *src = mmap(NULL, sizeof(data), PROT_READ, MAP_PRIVATE, fd, 0);
+    int copy = open("/mmap_copy.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(copy, src, sizeof(data));
+    char back[sizeof(data)];
+    pread(copy, back, sizeof(back), 0);
+    printf("write from untouched mapping: %d %d\n", memcmp(back, data, sizeof(data)) == 0,
+           memcmp(src, data, sizeof(data)) == 0);
+    close(copy);
+    unlink("/mmap_copy.txt");
+    munmap(src, sizeof(data));
+
+    char *shared = mmap(NULL, sizeof(data), PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    memcpy(shared + 4096, "shared", 6);
+    munmap(shared, sizeof(data));
+    char buf[7] = {0};
+    pread(fd, buf, 6, 4096);
+    printf("shared write reaches file: %s\n", buf);
+
+    errno = 0;
+    void *bad = mmap(NULL, 4096, PROT_READ, MAP_PRIVATE, fd, 100);
+    printf("unaligned offset: %d %d\n", bad == MAP_FAILED, errno == EINVAL);
+    errno = 0;
+    bad = mmap(NULL, 4096, PROT_READ, MAP_ANONYMOUS, -1, 0);
+    printf("neither shared nor private: %d %d\n", bad == MAP_FAILED, errno == EINVAL);
+    close(fd);
+
+    fd = open("/mmap_test.txt", O_RDONLY);
+    errno = 0;
+    bad = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    printf("shared writable on read-only fd: %d %d\n", bad == MAP_FAILED, errno == EACCES);
+    close(fd);
+    unlink("/mmap_test.txt");
+    return 0;
+}
@@ -0,0 +1,68 @@
+#include <dirent.h>
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mount.h>
+#include <sys/stat.h>
+#include <sys/vfs.h>
+#include <unistd.h>
+
+#define TMPFS_MAGIC 0x01021994
+
+int main()
+{
+    struct stat st;
+    struct statfs fs;
+    mkdir("/mount_test", 0755);
+    int fd = open("/mount_test/kept.txt", O_CREAT | O_WRONLY, 0644);
+    close(fd);
+
+    printf("mount tmpfs: %d\n", mount("tmpfs", "/mount_test", "tmpfs", 0, NULL));
+    printf("covered file hidden: %d\n", stat("/mount_test/kept.txt", &st) != 0);
+    fd = open("/mount_test/new.txt", O_CREAT | O_WRONLY, 0644);
+    write(fd, "mounted", 7);
+    statfs("/mount_test/new.txt", &fs);
+    printf("tmpfs: %d\n", fs.f_type == TMPFS_MAGIC);
+    DIR *dir = opendir("/mount_test");
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (entry->d_name[0] != '.')
+            printf("entry: %s\n", entry->d_name);
+    }
+    closedir(dir);
+
+    errno = 0;
+    printf("open file: %d %d\n", umount2("/mount_test", 0), errno == EBUSY);
+    close(fd);
+    errno = 0;
+    printf("rmdir mount point: %d %d\n", rmdir("/mount_test"), errno == EBUSY);
+
+    char cwd[64];
+    chdir("/mount_test");
+    printf("cwd: %s\n", getcwd(cwd, sizeof(cwd)));
+    chdir("..");
+    printf("cwd: %s\n", getcwd(cwd, sizeof(cwd)));
+
+    printf("umount: %d\n", umount2("/mount_test", 0));
+    printf("covered file back: %d\n", stat("/mount_test/kept.txt", &st) == 0);
+    printf("mounted file gone: %d\n", stat("/mount_test/new.txt", &st) != 0);
+    errno = 0;
+    printf("not mounted: %d %d\n", umount2("/mount_test", 0), errno == EINVAL);
+    errno = 0;
+    printf("boot mount: %d %d\n", umount2("/tmp", 0), errno == EBUSY);
+
+    errno = 0;
+    printf("not a directory: %d %d\n", mount("tmpfs", "/mount_test/kept.txt", "tmpfs", 0, NULL),
+           errno == ENOTDIR);
+    errno = 0;
+    printf("unknown type: %d %d\n", mount("none", "/mount_test", "nosuchfs", 0, NULL),
+           errno == ENODEV);
+    errno = 0;
+    printf("no device: %d %d\n", mount("/dev/nosuchdisk", "/mount_test", "vfat", 0, NULL),
+           errno == ENOENT);
+
+    unlink("/mount_test/kept.txt");
+    rmdir("/mount_test");
+    return 0;
+}
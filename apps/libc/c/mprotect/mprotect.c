@@ -0,0 +1,84 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+// Whether writing to `p` kills a child.
+static int write_faults(char *p)
+{
+    pid_t pid = fork();
+    if (pid == 0) {
+        *p = 'x';
+        return 0;
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    return !(WIFEXITED(status) && WEXITSTATUS(status) == 0);
+}
+
+// The free memory in kB, from `/proc/meminfo`.
+static long mem_free(void)
+{
+    char line[128];
+    long kb = -1;
+    FILE *meminfo = fopen("/proc/meminfo", "r");
+    while (fgets(line, sizeof(line), meminfo) != NULL) {
+        if (strncmp(line, "MemFree:", 8) == 0) {
+            sscanf(line + 8, "%ld", &kb);
+        }
+    }
+    fclose(meminfo);
+    return kb;
+}
+
+int main()
+{
+    char *p = mmap(NULL, 2 * 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    p[0] = 'a';
+    printf("writable: %d\n", write_faults(p));
+
+    // The second page hasn't been touched yet.
+    int r = mprotect(p, 2 * 4096, PROT_READ);
+    printf("read only: %d %c %d\n", r, p[0], p[4096]);
+    printf("write faults: %d %d\n", write_faults(p), write_faults(p + 4096));
+
+    mprotect(p + 4096, 4096, PROT_READ | PROT_WRITE);
+    p[4096] = 'b';
+    printf("writable again: %c %d\n", p[4096], write_faults(p));
+
+    munmap(p, 2 * 4096);
+    errno = 0;
+    r = mprotect(p, 4096, PROT_READ);
+    printf("unmapped: %d %d\n", r, errno == ENOMEM);
+
+    // Changing the protection of a reserved region doesn't commit its memory.
+    size_t size = 64 << 20;
+    char *reserved = mmap(NULL, size, PROT_NONE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    long before = mem_free();
+    r = mprotect(reserved, size, PROT_READ | PROT_WRITE);
+    long after = mem_free();
+    reserved[size / 2] = 'r';
+    printf("reserved: %d %d %c\n", r, before - after < 4096, reserved[size / 2]);
+    munmap(reserved, size);
+
+    int fd = open("/mprotect_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "file data", 9);
+    close(fd);
+    fd = open("/mprotect_test.txt", O_RDONLY);
+    char *shared = mmap(NULL, 4096, PROT_READ, MAP_SHARED, fd, 0);
+    errno = 0;
+    r = mprotect(shared, 4096, PROT_READ | PROT_WRITE);
+    printf("shared read-only file: %d %d %.9s\n", r, errno == EACCES, shared);
+    char *priv = mmap(NULL, 4096, PROT_READ, MAP_PRIVATE, fd, 0);
+    r = mprotect(priv, 4096, PROT_READ | PROT_WRITE);
+    priv[0] = 'F';
+    printf("private read-only file: %d %.9s\n", r, priv);
+    munmap(shared, 4096);
+    munmap(priv, 4096);
+    close(fd);
+    unlink("/mprotect_test.txt");
+    return 0;
+}
@@ -0,0 +1,40 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+
+int main()
+{
+    char *p = mmap(NULL, 2 * 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    memset(p, 'a', 2 * 4096);
+
+    // Growing without moving fails if the pages after it are taken.
+    char *blocker = mmap(p + 2 * 4096, 4096, PROT_READ, MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED, -1, 0);
+    errno = 0;
+    char *q = mremap(p, 2 * 4096, 4 * 4096, 0);
+    printf("grow blocked: %d %d\n", q == MAP_FAILED, errno == ENOMEM);
+
+    q = mremap(p, 2 * 4096, 4 * 4096, MREMAP_MAYMOVE);
+    int kept = 1, zeroed = 1;
+    for (int i = 0; i < 2 * 4096; i++) {
+        kept &= q[i] == 'a';
+    }
+    for (int i = 2 * 4096; i < 4 * 4096; i++) {
+        zeroed &= q[i] == 0;
+    }
+    printf("grow moved: %d %d %d\n", q != MAP_FAILED && q != p, kept, zeroed);
+    q[4 * 4096 - 1] = 'z';
+    munmap(blocker, 4096);
+
+    char *r = mremap(q, 4 * 4096, 4096, 0);
+    printf("shrink: %d %c\n", r == q, r[0]);
+    r = mremap(r, 4096, 3 * 4096, 0);
+    printf("grow in place: %d %c %d\n", r == q, r[0], r[3 * 4096 - 1]);
+
+    errno = 0;
+    void *bad = mremap(r + 1, 4096, 2 * 4096, MREMAP_MAYMOVE);
+    printf("unaligned: %d %d\n", bad == MAP_FAILED, errno == EINVAL);
+    munmap(r, 3 * 4096);
+    return 0;
+}
@@ -0,0 +1,43 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = open("/msync_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    char data[8192];
+    memset(data, 'a', sizeof(data));
+    write(fd, data, sizeof(data));
+
+    char *shared = mmap(NULL, sizeof(data), PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    memcpy(shared + 4096, "synced", 6);
+    int ret = msync(shared, sizeof(data), MS_SYNC);
+    char buf[7] = {0};
+    pread(fd, buf, 6, 4096);
+    printf("MS_SYNC: %d %s\n", ret, buf);
+
+    memcpy(shared, "async", 5);
+    ret = msync(shared, 4096, MS_ASYNC | MS_INVALIDATE);
+    memset(buf, 0, sizeof(buf));
+    pread(fd, buf, 5, 0);
+    printf("MS_ASYNC: %d %s\n", ret, buf);
+
+    ret = msync(shared + 1, 4096, MS_SYNC);
+    printf("unaligned: %d %d\n", ret, errno == EINVAL);
+    ret = msync(shared, 4096, MS_SYNC | MS_ASYNC);
+    printf("both sync and async: %d %d\n", ret, errno == EINVAL);
+    ret = msync(shared, 4096, 0x100);
+    printf("unknown flag: %d %d\n", ret, errno == EINVAL);
+
+    munmap(shared + 4096, 4096);
+    ret = msync(shared, sizeof(data), MS_SYNC);
+    printf("unmapped page: %d %d\n", ret, errno == ENOMEM);
+
+    munmap(shared, 4096);
+    close(fd);
+    unlink("/msync_test.txt");
+    return 0;
+}
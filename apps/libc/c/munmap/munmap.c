@@ -0,0 +1,41 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    char *p = mmap(NULL, 3 * 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    p[0] = 'a';
+    p[4096] = 'b';
+    p[2 * 4096] = 'c';
+
+    int r = munmap(p + 4096, 100);
+    printf("unmap middle: %d\n", r);
+    printf("first and third: %c %c\n", p[0], p[2 * 4096]);
+
+    // Touching the middle page kills the child.
+    pid_t pid = fork();
+    if (pid == 0) {
+        p[4096] = 'x';
+        return 0;
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    printf("middle faults: %d\n", !(WIFEXITED(status) && WEXITSTATUS(status) == 0));
+
+    // The hole can be mapped again, and comes back zeroed.
+    char *q = mmap(p + 4096, 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS | MAP_FIXED,
+                   -1, 0);
+    printf("remap hole: %d %d\n", q == p + 4096, q[0]);
+
+    errno = 0;
+    r = munmap(p + 1, 4096);
+    printf("unaligned: %d %d\n", r, errno == EINVAL);
+    errno = 0;
+    r = munmap(p, 0);
+    printf("zero length: %d %d\n", r, errno == EINVAL);
+    printf("unmap all: %d\n", munmap(p, 3 * 4096));
+    return 0;
+}
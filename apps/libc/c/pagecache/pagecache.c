@@ -0,0 +1,60 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+#define FILE_SIZE (1024 * 1024)
+#define CHUNK 4096
+
+static long cache_stat(const char *name)
+{
+    char line[64];
+    long value = -1;
+    FILE *stats = fopen("/proc/pagecache", "r");
+    if (stats == NULL)
+        return -1;
+    while (fgets(line, sizeof(line), stats) != NULL) {
+        if (strncmp(line, name, strlen(name)) == 0 && line[strlen(name)] == ':')
+            value = atol(line + strlen(name) + 1);
+    }
+    fclose(stats);
+    return value;
+}
+
+static long read_all(int fd)
+{
+    static char buf[CHUNK];
+    long total = 0;
+    ssize_t n;
+    lseek(fd, 0, SEEK_SET);
+    while ((n = read(fd, buf, sizeof(buf))) > 0)
+        total += n;
+    return total;
+}
+
+int main()
+{
+    static char data[FILE_SIZE];
+    for (int i = 0; i < FILE_SIZE; i++)
+        data[i] = i % 251;
+    int fd = open("/pagecache.bin", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    printf("write: %d\n", write(fd, data, FILE_SIZE) == FILE_SIZE);
+
+    printf("first read: %ld\n", read_all(fd));
+    long hits = cache_stat("Hits"), misses = cache_stat("Misses");
+    printf("second read: %ld\n", read_all(fd));
+    printf("served from cache: %d\n", cache_stat("Hits") - hits >= FILE_SIZE / CHUNK);
+    printf("no misses: %d\n", cache_stat("Misses") == misses);
+
+    char buf[8] = {0};
+    pwrite(fd, "updated", 7, 4096);
+    pread(fd, buf, 7, 4096);
+    printf("read after write: %s\n", buf);
+    ftruncate(fd, 10);
+    printf("size after truncate: %ld\n", read_all(fd));
+    printf("fsync: %d\n", fsync(fd));
+    close(fd);
+    unlink("/pagecache.bin");
+    return 0;
+}
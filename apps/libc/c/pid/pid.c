@@ -0,0 +1,34 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    pid_t parent = getpid();
+    printf("main thread: %d\n", syscall(SYS_gettid) == parent);
+    printf("own group: %d\n", getpgid(0) == getpgid(parent));
+
+    int pipefd[2];
+    pipe(pipefd);
+    pid_t child = fork();
+    if (child == 0) {
+        char ok = getpid() != parent && getppid() == parent && getpgid(0) == getpgid(parent);
+        write(pipefd[1], &ok, 1);
+        read(pipefd[0], &ok, 1);
+        _exit(0);
+    }
+    char ok = 0;
+    read(pipefd[0], &ok, 1);
+    printf("child sees its pid and parent: %d\n", ok);
+    printf("fresh pid: %d\n", child > 0 && child != parent);
+    printf("setpgid: %d\n", setpgid(child, child));
+    printf("child group: %d\n", getpgid(child) == child);
+    write(pipefd[1], &ok, 1);
+    int status;
+    printf("wait group: %d\n", waitpid(-child, &status, 0) == child);
+    printf("no group: %d %d\n", setpgid(0, 99999), errno == EPERM);
+    printf("no process: %d %d\n", getpgid(99999), errno == ESRCH);
+    return 0;
+}
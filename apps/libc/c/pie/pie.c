@@ -0,0 +1,18 @@
+#include <stdio.h>
+#include <sys/auxv.h>
+
+static const char *message = "hello from a PIE";
+static const char **pointer = &message;
+
+int main()
+{
+    // The pointer is only right if its relocation has been applied.
+    printf("%s\n", *pointer);
+    printf("AT_BASE: %s\n", getauxval(AT_BASE) == 0 ? "0" : "nonzero");
+    printf("AT_ENTRY in image: %s\n",
+           getauxval(AT_ENTRY) > (unsigned long)&main - 0x100000 &&
+                   getauxval(AT_ENTRY) < (unsigned long)&main + 0x100000
+               ? "yes"
+               : "no");
+    return 0;
+}
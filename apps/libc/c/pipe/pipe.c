@@ -0,0 +1,70 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    int fds[2];
+    char buf[64];
+    struct stat st;
+
+    printf("pipe2: %d\n", pipe2(fds, O_NONBLOCK | O_CLOEXEC));
+    errno = 0;
+    printf("empty nonblocking read: %ld %d\n", (long)read(fds[0], buf, sizeof(buf)),
+           errno == EAGAIN);
+    fstat(fds[0], &st);
+    printf("fifo: %d\n", S_ISFIFO(st.st_mode));
+    printf("cloexec: %d nonblock: %d\n", fcntl(fds[1], F_GETFD) == FD_CLOEXEC,
+           (fcntl(fds[1], F_GETFL) & O_NONBLOCK) != 0);
+    close(fds[0]);
+    close(fds[1]);
+
+    pipe(fds);
+    pid_t pid = fork();
+    if (pid == 0) {
+        close(fds[0]);
+        const char *msg = "hello from child";
+        write(fds[1], msg, strlen(msg));
+        close(fds[1]);
+        return 0;
+    }
+    close(fds[1]);
+    size_t total = 0;
+    ssize_t n;
+    while ((n = read(fds[0], buf + total, sizeof(buf) - 1 - total)) > 0) {
+        total += n;
+    }
+    buf[total] = '\0';
+    printf("read from child: %s\n", buf);
+    printf("eof: %ld\n", (long)n);
+    close(fds[0]);
+    waitpid(pid, NULL, 0);
+
+    // Writing with no reader left raises `SIGPIPE`, which terminates the writer.
+    pipe(fds);
+    close(fds[0]);
+    pid = fork();
+    if (pid == 0) {
+        write(fds[1], "x", 1);
+        _exit(0);
+    }
+    close(fds[1]);
+    int status;
+    waitpid(pid, &status, 0);
+    printf("sigpipe: %d %d\n", WIFSIGNALED(status), WTERMSIG(status) == SIGPIPE);
+
+    // It fails with `EPIPE` once `SIGPIPE` is ignored.
+    signal(SIGPIPE, SIG_IGN);
+    pipe(fds);
+    close(fds[0]);
+    errno = 0;
+    printf("broken pipe: %ld %d\n", (long)write(fds[1], "x", 1), errno == EPIPE);
+    close(fds[1]);
+    return 0;
+}
@@ -0,0 +1,84 @@
+#define _GNU_SOURCE
+#include <errno.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <sys/wait.h>
+#include <time.h>
+#include <unistd.h>
+
+static volatile int handled;
+
+static void handler(int signo)
+{
+    handled = signo;
+}
+
+int main()
+{
+    int fds[2];
+    pipe(fds);
+    struct pollfd pfds[3] = {
+        {.fd = fds[0], .events = POLLIN},
+        {.fd = fds[1], .events = POLLOUT},
+        {.fd = 100, .events = POLLIN},
+    };
+    struct timespec zero = {0, 0};
+
+    int n = ppoll(pfds, 3, &zero, NULL);
+    printf("empty pipe: %d in=%d out=%d nval=%d\n", n, pfds[0].revents, pfds[1].revents == POLLOUT,
+           pfds[2].revents == POLLNVAL);
+    n = ppoll(pfds, 1, &zero, NULL);
+    printf("timeout: %d\n", n);
+
+    pid_t pid = fork();
+    if (pid == 0) {
+        usleep(50000);
+        write(fds[1], "x", 1);
+        return 0;
+    }
+    n = ppoll(pfds, 1, NULL, NULL);
+    printf("wakeup: %d in=%d\n", n, pfds[0].revents == POLLIN);
+    waitpid(pid, NULL, 0);
+
+    // The raw syscall updates the timeout to the time remaining.
+    struct timespec ts = {5, 0};
+    n = syscall(SYS_ppoll, pfds, 1, &ts, NULL, 8);
+    printf("remaining: %d %d\n", n, ts.tv_sec == 4 || (ts.tv_sec == 5 && ts.tv_nsec == 0));
+
+    char c;
+    read(fds[0], &c, 1);
+    close(fds[1]);
+    n = ppoll(pfds, 1, &zero, NULL);
+    printf("hangup: %d hup=%d\n", n, (pfds[0].revents & POLLHUP) != 0);
+    close(fds[0]);
+
+    // A signal blocked outside of `ppoll` is delivered while it waits.
+    pipe(fds);
+    pfds[0].fd = fds[0];
+    signal(SIGUSR1, handler);
+    sigset_t set, empty;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    raise(SIGUSR1);
+    sigemptyset(&empty);
+    ts.tv_sec = 5;
+    errno = 0;
+    n = ppoll(pfds, 1, &ts, &empty);
+    sigprocmask(SIG_BLOCK, NULL, &set);
+    printf("sigmask: %d %d %d restored=%d\n", n, errno == EINTR, handled == SIGUSR1,
+           sigismember(&set, SIGUSR1));
+    close(fds[0]);
+    close(fds[1]);
+
+    int fd = open("/poll_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    struct pollfd file = {.fd = fd, .events = POLLIN | POLLOUT};
+    n = ppoll(&file, 1, &zero, NULL);
+    printf("regular file: %d %d\n", n, file.revents == (POLLIN | POLLOUT));
+    close(fd);
+    unlink("/poll_test.txt");
+    return 0;
+}
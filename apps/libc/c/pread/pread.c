@@ -0,0 +1,27 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = open("/pread_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "0123456789", 10);
+    lseek(fd, 2, SEEK_SET);
+
+    char buf[8] = {0};
+    pread(fd, buf, 3, 6);
+    printf("pread: %s\n", buf);
+    read(fd, buf, 3);
+    printf("read after pread: %s\n", buf);
+    printf("pread past EOF: %ld\n", (long)pread(fd, buf, 3, 20));
+    pwrite(fd, "ab", 2, 0);
+    read(fd, buf, 3);
+    printf("read after pwrite: %s\n", buf);
+    close(fd);
+
+    fd = open("/pread_test.txt", O_WRONLY);
+    printf("pread on write-only fd: %ld\n", (long)pread(fd, buf, 3, 0));
+    close(fd);
+    unlink("/pread_test.txt");
+    return 0;
+}
@@ -0,0 +1,117 @@
+#include <dirent.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/mman.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static int threads_in_status(void)
+{
+    char line[256];
+    int threads = -1;
+    FILE *status = fopen("/proc/self/status", "r");
+    while (fgets(line, sizeof(line), status) != NULL) {
+        if (strncmp(line, "Threads:", 8) == 0) {
+            threads = atoi(line + 8);
+        }
+    }
+    fclose(status);
+    return threads;
+}
+
+// Whether `/proc/self` leads to the directory of the calling process.
+static int self_is_own(void)
+{
+    char target[16], own[16];
+    ssize_t len = readlink("/proc/self", target, sizeof(target) - 1);
+    target[len < 0 ? 0 : len] = '\0';
+    snprintf(own, sizeof(own), "%d", getpid());
+    return strcmp(target, own) == 0;
+}
+
+static void *wait_for_pipe(void *arg)
+{
+    char c;
+    read(*(int *)arg, &c, 1);
+    return NULL;
+}
+
+int main()
+{
+    char line[256];
+    int pid = -1;
+    FILE *status = fopen("/proc/self/status", "r");
+    while (fgets(line, sizeof(line), status) != NULL) {
+        if (strncmp(line, "Pid:", 4) == 0) {
+            pid = atoi(line + 4);
+        }
+    }
+    fclose(status);
+    printf("status pid matches: %d\n", pid == getpid());
+
+    int fds[2];
+    pipe(fds);
+    int before = threads_in_status();
+    pthread_t thread;
+    pthread_create(&thread, NULL, wait_for_pipe, &fds[0]);
+    int during = threads_in_status();
+    write(fds[1], "x", 1);
+    pthread_join(thread, NULL);
+    printf("threads: %d %d %d\n", before, during, threads_in_status());
+
+    char *map = mmap(NULL, 4096, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0);
+    map[0] = 1;
+    int stack = 0, mapped = 0;
+    FILE *maps = fopen("/proc/self/maps", "r");
+    while (fgets(line, sizeof(line), maps) != NULL) {
+        unsigned long start, end;
+        char perms[5];
+        if (sscanf(line, "%lx-%lx %4s", &start, &end, perms) != 3) {
+            continue;
+        }
+        if (strstr(line, "[stack]") != NULL) {
+            stack = 1;
+        }
+        if (start <= (unsigned long)map && (unsigned long)map < end && strncmp(perms, "rw", 2) == 0) {
+            mapped = 1;
+        }
+    }
+    fclose(maps);
+    printf("maps: stack %d, mmap %d\n", stack, mapped);
+
+    char exe[256];
+    ssize_t len = readlink("/proc/self/exe", exe, sizeof(exe) - 1);
+    exe[len < 0 ? 0 : len] = '\0';
+    printf("exe: %d\n", len > 0 && strstr(exe, "procfs") != NULL);
+
+    char name[16];
+    snprintf(name, sizeof(name), "%d", getpid());
+    int listed = 0;
+    DIR *dir = opendir("/proc");
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (strcmp(entry->d_name, name) == 0) {
+            listed = 1;
+        }
+    }
+    closedir(dir);
+    printf("pid listed in /proc: %d\n", listed);
+
+    // A parent and child resolving `/proc/self` at once each reach their own.
+    int own = 1;
+    fflush(stdout);
+    pid_t child = fork();
+    for (int i = 0; i < 100; i++) {
+        own &= self_is_own() && threads_in_status() == 1;
+    }
+    if (child == 0) {
+        exit(own ? 0 : 1);
+    }
+    int wstatus;
+    waitpid(child, &wstatus, 0);
+    printf("self is own: %d %d\n", own, WEXITSTATUS(wstatus) == 0);
+    return 0;
+}
@@ -0,0 +1,27 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+int main()
+{
+    char buf[256];
+    ssize_t n = readlink("/proc/self/exe", buf, sizeof(buf) - 1);
+    buf[n > 0 ? n : 0] = '\0';
+    printf("readlink /proc/self/exe: %d\n", n > 0 && strstr(buf, "readlink_c") != NULL);
+
+    symlink("/readlink_target", "/readlink_link");
+    n = readlink("/readlink_link", buf, sizeof(buf));
+    printf("readlink symlink: %.*s\n", (int)(n > 0 ? n : 0), buf);
+    n = readlink("/readlink_link", buf, 4);
+    printf("readlink truncated: %ld %.4s\n", (long)n, buf);
+    unlink("/readlink_link");
+
+    close(open("/readlink_regular", O_CREAT | O_RDWR, 0644));
+    errno = 0;
+    printf("readlink regular file: %ld %d\n", (long)readlink("/readlink_regular", buf, sizeof(buf)),
+           errno == EINVAL);
+    unlink("/readlink_regular");
+    return 0;
+}
@@ -0,0 +1,172 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <pthread.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static pid_t parent;
+
+static int lock(int fd, int cmd, short type, off_t start, off_t len)
+{
+    struct flock fl = {.l_type = type, .l_whence = SEEK_SET, .l_start = start, .l_len = len};
+    return fcntl(fd, cmd, &fl);
+}
+
+static void getlk(int fd, off_t start, off_t len)
+{
+    struct flock fl = {.l_type = F_WRLCK, .l_whence = SEEK_SET, .l_start = start, .l_len = len};
+    fcntl(fd, F_GETLK, &fl);
+    if (fl.l_type == F_UNLCK) {
+        printf("F_GETLK [%ld, +%ld): unlocked\n", (long)start, (long)len);
+    } else {
+        printf("F_GETLK [%ld, +%ld): %s at %ld len %ld parent=%d\n", (long)start, (long)len,
+               fl.l_type == F_WRLCK ? "write" : "read", (long)fl.l_start, (long)fl.l_len,
+               fl.l_pid == parent);
+    }
+}
+
+// Run `f` in a child process, which has its own record locks.
+static void in_child(void (*f)(void))
+{
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        f();
+        _exit(0);
+    }
+    waitpid(pid, NULL, 0);
+}
+
+static void check_conflicts(void)
+{
+    int fd = open("/record_lock.txt", O_RDWR);
+    getlk(fd, 0, 100);
+    errno = 0;
+    printf("write over write lock: %d %d\n", lock(fd, F_SETLK, F_WRLCK, 5, 1), errno == EAGAIN);
+    printf("read over read lock: %d\n", lock(fd, F_SETLK, F_RDLCK, 20, 10));
+    errno = 0;
+    printf("write over read lock: %d %d\n", lock(fd, F_SETLK, F_WRLCK, 25, 1), errno == EAGAIN);
+    printf("unlocked range: %d\n", lock(fd, F_SETLK, F_WRLCK, 40, 10));
+}
+
+static void check_split(void)
+{
+    int fd = open("/record_lock.txt", O_RDWR);
+    getlk(fd, 3, 2);
+    getlk(fd, 4, 10);
+}
+
+static void wait_for_lock(void)
+{
+    int fd = open("/record_lock.txt", O_RDWR);
+    printf("F_SETLKW: %d\n", lock(fd, F_SETLKW, F_WRLCK, 0, 1));
+}
+
+static void on_signal(int signo)
+{
+    (void)signo;
+}
+
+static void wait_interrupted(void)
+{
+    struct sigaction sa = {.sa_handler = on_signal};
+    sigaction(SIGUSR1, &sa, NULL);
+    int fd = open("/record_lock.txt", O_RDWR);
+    errno = 0;
+    int r = lock(fd, F_SETLKW, F_WRLCK, 60, 1);
+    printf("F_SETLKW interrupted: %d %d\n", r, errno == EINTR);
+}
+
+static void wait_for_lock_at_60(void)
+{
+    int fd = open("/record_lock.txt", O_RDWR);
+    lock(fd, F_SETLKW, F_WRLCK, 60, 1);
+}
+
+static void check_held(void)
+{
+    int fd = open("/record_lock.txt", O_RDWR);
+    getlk(fd, 0, 1);
+}
+
+static void *exit_early(void *arg)
+{
+    pthread_exit(arg);
+}
+
+int main()
+{
+    parent = getpid();
+    int fd = open("/record_lock.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    char buf[100] = {0};
+    write(fd, buf, sizeof(buf));
+
+    printf("write lock: %d\n", lock(fd, F_SETLK, F_WRLCK, 0, 10));
+    printf("read lock: %d\n", lock(fd, F_SETLK, F_RDLCK, 20, 10));
+    in_child(check_conflicts);
+
+    // Unlocking the middle of a lock splits it.
+    printf("unlock middle: %d\n", lock(fd, F_SETLK, F_UNLCK, 3, 2));
+    in_child(check_split);
+
+    // Closing any fd on the file releases the locks of the process.
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        wait_for_lock();
+        _exit(0);
+    }
+    usleep(50000);
+    int other = open("/record_lock.txt", O_RDONLY);
+    close(other);
+    waitpid(pid, NULL, 0);
+    in_child(check_split);
+
+    // A thread exiting leaves the locks of the process alone.
+    printf("relock: %d\n", lock(fd, F_SETLK, F_WRLCK, 0, 1));
+    pthread_t thread;
+    pthread_create(&thread, NULL, exit_early, NULL);
+    pthread_join(thread, NULL);
+    in_child(check_held);
+    lock(fd, F_SETLK, F_UNLCK, 0, 1);
+
+    // A signal interrupts the wait, and so does being killed.
+    printf("lock for waiters: %d\n", lock(fd, F_SETLK, F_WRLCK, 60, 1));
+    fflush(stdout);
+    pid = fork();
+    if (pid == 0) {
+        wait_interrupted();
+        _exit(0);
+    }
+    usleep(50000);
+    kill(pid, SIGUSR1);
+    waitpid(pid, NULL, 0);
+    fflush(stdout);
+    pid = fork();
+    if (pid == 0) {
+        wait_for_lock_at_60();
+        _exit(0);
+    }
+    usleep(50000);
+    kill(pid, SIGKILL);
+    int status;
+    waitpid(pid, &status, 0);
+    printf("killed while waiting: %d\n", WIFSIGNALED(status) && WTERMSIG(status) == SIGKILL);
+    lock(fd, F_SETLK, F_UNLCK, 60, 1);
+
+    // The locks of a process killed by a signal are released.
+    fflush(stdout);
+    pid = fork();
+    if (pid == 0) {
+        lock(fd, F_SETLK, F_WRLCK, 50, 1);
+        raise(SIGKILL);
+    }
+    waitpid(pid, NULL, 0);
+    printf("after kill: %d\n", lock(fd, F_SETLK, F_WRLCK, 50, 1));
+
+    close(fd);
+    unlink("/record_lock.txt");
+    return 0;
+}
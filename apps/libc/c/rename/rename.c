@@ -0,0 +1,37 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    int fd = open("/rename_src", O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    write(fd, "new", 3);
+    close(fd);
+    fd = open("/rename_dst", O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    write(fd, "old content", 11);
+    close(fd);
+
+    printf("rename over existing: %d\n", renameat(AT_FDCWD, "/rename_src", AT_FDCWD, "/rename_dst"));
+    struct stat st;
+    fd = open("/rename_dst", O_RDONLY);
+    fstat(fd, &st);
+    close(fd);
+    printf("destination replaced: %d %d\n", (int)st.st_size, access("/rename_src", F_OK) != 0);
+
+    mkdir("/rename_parent", 0755);
+    mkdir("/rename_parent/child", 0755);
+    errno = 0;
+    printf("rename into descendant: %d %d\n", rename("/rename_parent", "/rename_parent/child/x"),
+           errno == EINVAL);
+
+    mkdir("/rename_empty", 0755);
+    printf("rename over empty dir: %d\n", rename("/rename_parent", "/rename_empty"));
+    printf("child moved: %d\n", access("/rename_empty/child", F_OK) == 0);
+
+    rmdir("/rename_empty/child");
+    rmdir("/rename_empty");
+    unlink("/rename_dst");
+    return 0;
+}
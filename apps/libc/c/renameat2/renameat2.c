@@ -0,0 +1,66 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define RENAME_NOREPLACE (1 << 0)
+#define RENAME_EXCHANGE (1 << 1)
+
+static int do_renameat2(const char *old_path, const char *new_path, unsigned flags)
+{
+    return syscall(SYS_renameat2, AT_FDCWD, old_path, AT_FDCWD, new_path, flags);
+}
+
+static void create(const char *path, const char *content)
+{
+    int fd = open(path, O_CREAT | O_WRONLY | O_TRUNC, 0644);
+    while (*content) {
+        write(fd, content++, 1);
+    }
+    close(fd);
+}
+
+static char first_byte(const char *path)
+{
+    char c = 0;
+    int fd = open(path, O_RDONLY);
+    read(fd, &c, 1);
+    close(fd);
+    return c;
+}
+
+int main()
+{
+    create("/rename_a", "a");
+    create("/rename_b", "b");
+
+    printf("rename: %d %d\n", do_renameat2("/rename_a", "/rename_c", 0),
+           access("/rename_a", F_OK) != 0 && first_byte("/rename_c") == 'a');
+
+    errno = 0;
+    printf("noreplace existing: %d %d\n",
+           do_renameat2("/rename_c", "/rename_b", RENAME_NOREPLACE), errno == EEXIST);
+
+    // A file that happens to have the name the exchange moves a file aside to is left alone.
+    create("/rename_b.renameat2-exchange.0", "x");
+    printf("exchange: %d %c%c\n", do_renameat2("/rename_b", "/rename_c", RENAME_EXCHANGE),
+           first_byte("/rename_b"), first_byte("/rename_c"));
+    printf("bystander: %c\n", first_byte("/rename_b.renameat2-exchange.0"));
+    unlink("/rename_b.renameat2-exchange.0");
+
+    errno = 0;
+    printf("noreplace|exchange: %d %d\n",
+           do_renameat2("/rename_b", "/rename_c", RENAME_NOREPLACE | RENAME_EXCHANGE),
+           errno == EINVAL);
+
+    mkdir("/rename_dir", 0755);
+    printf("cross directory: %d %c\n", do_renameat2("/rename_b", "/rename_dir/moved", 0),
+           first_byte("/rename_dir/moved"));
+
+    unlink("/rename_c");
+    unlink("/rename_dir/moved");
+    rmdir("/rename_dir");
+    return 0;
+}
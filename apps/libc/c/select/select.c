@@ -0,0 +1,91 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/select.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile int handled;
+
+static void handler(int signo)
+{
+    handled = signo;
+}
+
+int main()
+{
+    int fds[2];
+    pipe(fds);
+    fd_set rfds, wfds;
+
+    FD_ZERO(&rfds);
+    FD_ZERO(&wfds);
+    FD_SET(fds[0], &rfds);
+    FD_SET(fds[1], &wfds);
+    struct timespec ts = {0, 0};
+    int n = pselect(fds[1] + 1, &rfds, &wfds, NULL, &ts, NULL);
+    printf("empty pipe: %d r=%d w=%d\n", n, FD_ISSET(fds[0], &rfds), FD_ISSET(fds[1], &wfds));
+
+    pid_t pid = fork();
+    if (pid == 0) {
+        usleep(50000);
+        write(fds[1], "x", 1);
+        return 0;
+    }
+    FD_ZERO(&rfds);
+    FD_SET(fds[0], &rfds);
+    ts.tv_sec = 5;
+    n = pselect(fds[0] + 1, &rfds, NULL, NULL, &ts, NULL);
+    printf("wakeup: %d r=%d remaining=%d\n", n, FD_ISSET(fds[0], &rfds), ts.tv_sec < 5);
+    waitpid(pid, NULL, 0);
+
+    FD_ZERO(&rfds);
+    FD_ZERO(&wfds);
+    FD_SET(fds[0], &rfds);
+    FD_SET(fds[1], &wfds);
+    ts.tv_sec = 0;
+    n = pselect(fds[1] + 1, &rfds, &wfds, NULL, &ts, NULL);
+    printf("both ready: %d\n", n);
+
+    FD_ZERO(&rfds);
+    FD_SET(100, &rfds);
+    n = pselect(101, &rfds, NULL, NULL, &ts, NULL);
+    printf("closed fd: %d %d\n", n, errno == EBADF);
+
+    // Only the pipe with data is left in the set.
+    int other[2];
+    pipe(other);
+    char c;
+    read(fds[0], &c, 1);
+    write(other[1], "y", 1);
+    FD_ZERO(&rfds);
+    FD_SET(fds[0], &rfds);
+    FD_SET(other[0], &rfds);
+    int max = fds[0] > other[0] ? fds[0] : other[0];
+    n = pselect(max + 1, &rfds, NULL, NULL, &ts, NULL);
+    printf("two pipes: %d %d %d\n", n, FD_ISSET(fds[0], &rfds), FD_ISSET(other[0], &rfds));
+    close(other[0]);
+    close(other[1]);
+
+    // A signal blocked outside of `pselect` is delivered while it waits.
+    signal(SIGUSR1, handler);
+    sigset_t set, empty;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, NULL);
+    raise(SIGUSR1);
+    printf("blocked: %d\n", handled);
+    sigemptyset(&empty);
+    FD_ZERO(&rfds);
+    FD_SET(fds[0], &rfds);
+    ts.tv_sec = 5;
+    errno = 0;
+    n = pselect(fds[0] + 1, &rfds, NULL, NULL, &ts, &empty);
+    sigprocmask(SIG_BLOCK, NULL, &set);
+    printf("sigmask: %d %d %d restored=%d\n", n, errno == EINTR, handled == SIGUSR1,
+           sigismember(&set, SIGUSR1));
+
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
@@ -0,0 +1,63 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/sendfile.h>
+#include <unistd.h>
+
+#define SIZE 10240
+
+static char data[SIZE], out[SIZE];
+
+int main()
+{
+    for (int i = 0; i < SIZE; i++) {
+        data[i] = 'a' + i % 26;
+    }
+    int fd = open("/sendfile_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, data, SIZE);
+    lseek(fd, 0, SEEK_SET);
+
+    int fds[2];
+    pipe(fds);
+    printf("sendfile: %ld\n", (long)sendfile(fds[1], fd, NULL, SIZE));
+    printf("offset advanced: %ld\n", (long)lseek(fd, 0, SEEK_CUR));
+    size_t total = 0;
+    while (total < SIZE) {
+        ssize_t n = read(fds[0], out + total, SIZE - total);
+        if (n <= 0) {
+            break;
+        }
+        total += n;
+    }
+    printf("contents match: %d\n", total == SIZE && memcmp(data, out, SIZE) == 0);
+
+    off_t offset = SIZE - 100;
+    printf("sendfile with offset: %ld\n", (long)sendfile(fds[1], fd, &offset, 1000));
+    printf("offset updated: %ld file offset kept: %ld\n", (long)offset,
+           (long)lseek(fd, 0, SEEK_CUR));
+    ssize_t n = read(fds[0], out, sizeof(out));
+    printf("tail matches: %d\n", n == 100 && memcmp(data + SIZE - 100, out, 100) == 0);
+
+    close(fds[0]);
+    close(fds[1]);
+
+    // File to file, stopping short at the end of the input.
+    int fd2 = open("/sendfile_copy.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    offset = 0;
+    printf("file to file: %ld\n", (long)sendfile(fd2, fd, &offset, SIZE * 2));
+    n = pread(fd2, out, SIZE, 0);
+    printf("copy matches: %d\n", n == SIZE && memcmp(data, out, SIZE) == 0);
+    close(fd2);
+
+    fd2 = open("/sendfile_copy.txt", O_WRONLY | O_APPEND);
+    offset = 0;
+    errno = 0;
+    printf("append: %ld %d\n", (long)sendfile(fd2, fd, &offset, 10), errno == EINVAL);
+    close(fd2);
+    unlink("/sendfile_copy.txt");
+
+    close(fd);
+    unlink("/sendfile_test.txt");
+    return 0;
+}
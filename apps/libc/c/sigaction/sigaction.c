@@ -0,0 +1,49 @@
+#include <errno.h>
+#include <signal.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+/* The `struct sigaction` of the kernel, which differs from that of libc. */
+struct k_sigaction {
+    void (*handler)(int);
+    unsigned long flags;
+#if defined(__x86_64__) || defined(__aarch64__)
+    void (*restorer)(void);
+#endif
+    unsigned long mask;
+};
+
+static void handler(int signo)
+{
+    (void)signo;
+}
+
+static long k_sigaction(int signo, const struct k_sigaction *act, struct k_sigaction *oldact)
+{
+    return syscall(SYS_rt_sigaction, signo, act, oldact, 8);
+}
+
+int main()
+{
+    struct k_sigaction act = {0}, old;
+    act.handler = handler;
+    act.flags = SA_RESTART;
+    act.mask = 1UL << (SIGUSR2 - 1);
+    printf("install: %ld\n", k_sigaction(SIGUSR1, &act, &old));
+    printf("default before: %d\n", old.handler == SIG_DFL);
+
+    struct k_sigaction ignore = {0};
+    ignore.handler = SIG_IGN;
+    k_sigaction(SIGUSR1, &ignore, &old);
+    printf("read back: %d %d %d\n", old.handler == handler, old.flags == SA_RESTART,
+           old.mask == 1UL << (SIGUSR2 - 1));
+    k_sigaction(SIGUSR1, NULL, &old);
+    printf("ignored: %d\n", old.handler == SIG_IGN);
+
+    printf("SIGKILL: %ld %d\n", k_sigaction(SIGKILL, &act, NULL), errno == EINVAL);
+    printf("SIGSTOP query: %ld\n", k_sigaction(SIGSTOP, NULL, &old));
+    printf("bad signal: %ld %d\n", k_sigaction(65, NULL, &old), errno == EINVAL);
+    printf("bad size: %ld %d\n", syscall(SYS_rt_sigaction, SIGUSR1, NULL, &old, 4), errno == EINVAL);
+    return 0;
+}
@@ -0,0 +1,29 @@
+#include <signal.h>
+#include <stdio.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static void handler(int signo)
+{
+    (void)signo;
+}
+
+int main()
+{
+    // The helper expects SIGUSR1 to stay ignored and SIGUSR2 to be reset.
+    signal(SIGUSR1, SIG_IGN);
+    signal(SIGUSR2, handler);
+
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        char *argv[] = {"sigexec_helper_c", NULL};
+        execve("/sigexec_helper_c", argv, NULL);
+        printf("execve failed\n");
+        return 1;
+    }
+    int status;
+    waitpid(pid, &status, 0);
+    printf("helper exited: %d\n", WEXITSTATUS(status));
+    return 0;
+}
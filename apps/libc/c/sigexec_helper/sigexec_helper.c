@@ -0,0 +1,13 @@
+#include <signal.h>
+#include <stdio.h>
+
+// Run by the sigexec test through execve.
+int main()
+{
+    struct sigaction old;
+    sigaction(SIGUSR1, NULL, &old);
+    printf("ignored kept: %d\n", old.sa_handler == SIG_IGN);
+    sigaction(SIGUSR2, NULL, &old);
+    printf("handler reset: %d\n", old.sa_handler == SIG_DFL);
+    return 0;
+}
@@ -0,0 +1,95 @@
+#include <errno.h>
+#include <sched.h>
+#include <signal.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+static volatile int handled;
+static volatile int info_signo;
+
+static void handler(int signo)
+{
+    handled = signo;
+}
+
+static void info_handler(int signo, siginfo_t *info, void *ucontext)
+{
+    (void)ucontext;
+    handled = signo;
+    info_signo = info->si_signo;
+}
+
+int main()
+{
+    struct sigaction act;
+    memset(&act, 0, sizeof(act));
+    act.sa_handler = handler;
+    sigaction(SIGUSR1, &act, NULL);
+    int local = 42;
+    printf("raise: %d\n", raise(SIGUSR1));
+    printf("handled: %d\n", handled == SIGUSR1);
+    printf("resumed: %d\n", local == 42);
+
+    memset(&act, 0, sizeof(act));
+    act.sa_sigaction = info_handler;
+    act.sa_flags = SA_SIGINFO;
+    sigaction(SIGUSR2, &act, NULL);
+    handled = 0;
+    kill(getpid(), SIGUSR2);
+    printf("siginfo: %d %d\n", handled == SIGUSR2, info_signo == SIGUSR2);
+
+    sigset_t set, old;
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    sigprocmask(SIG_BLOCK, &set, &old);
+    handled = 0;
+    raise(SIGUSR1);
+    printf("blocked: %d\n", handled);
+    sigprocmask(SIG_SETMASK, &old, NULL);
+    printf("unblocked: %d\n", handled == SIGUSR1);
+
+    signal(SIGUSR1, SIG_IGN);
+    handled = 0;
+    raise(SIGUSR1);
+    printf("ignored: %d\n", handled);
+
+    pid_t pid = fork();
+    if (pid == 0) {
+        for (;;)
+            sched_yield();
+    }
+    kill(pid, SIGTERM);
+    int status;
+    waitpid(pid, &status, 0);
+    printf("killed: %d %d\n", WIFSIGNALED(status), WTERMSIG(status) == SIGTERM);
+
+    int fds[2];
+    pipe(fds);
+    pid = fork();
+    if (pid == 0) {
+        char c;
+        read(fds[0], &c, 1);
+        _exit(0);
+    }
+    usleep(50000);
+    kill(pid, SIGKILL);
+    waitpid(pid, &status, 0);
+    printf("killed blocked: %d %d\n", WIFSIGNALED(status), WTERMSIG(status) == SIGKILL);
+
+    memset(&act, 0, sizeof(act));
+    act.sa_handler = handler;
+    sigaction(SIGUSR1, &act, NULL);
+    pid = fork();
+    if (pid == 0) {
+        char c;
+        ssize_t n = read(fds[0], &c, 1);
+        _exit(n == -1 && errno == EINTR && handled == SIGUSR1 ? 3 : 4);
+    }
+    usleep(50000);
+    kill(pid, SIGUSR1);
+    waitpid(pid, &status, 0);
+    printf("interrupted: %d\n", WIFEXITED(status) && WEXITSTATUS(status) == 3);
+    return 0;
+}
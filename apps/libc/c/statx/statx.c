@@ -0,0 +1,45 @@
+#include <fcntl.h>
+#include <linux/stat.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static int do_statx(int dirfd, const char *path, struct statx *stx)
+{
+    return syscall(SYS_statx, dirfd, path, 0, STATX_BASIC_STATS, stx);
+}
+
+int main()
+{
+    struct statx stx;
+
+    int fd = open("statx_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "hello", 5);
+    close(fd);
+    if (do_statx(AT_FDCWD, "statx_test.txt", &stx) == 0) {
+        printf("statx relative: size=%llu reg=%d\n", (unsigned long long)stx.stx_size,
+               (stx.stx_mode & S_IFMT) == S_IFREG);
+        printf("statx mask: basic=%d attributes=%llu\n",
+               (stx.stx_mask & STATX_BASIC_STATS) == STATX_BASIC_STATS,
+               (unsigned long long)stx.stx_attributes);
+    }
+
+    mkdir("/statx_dir", 0755);
+    fd = open("/statx_dir/inner.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "hello world", 11);
+    close(fd);
+    int dirfd = open("/statx_dir", O_RDONLY | O_DIRECTORY);
+    if (do_statx(dirfd, "inner.txt", &stx) == 0) {
+        printf("statx dirfd: size=%llu\n", (unsigned long long)stx.stx_size);
+    }
+    if (do_statx(AT_FDCWD, "/statx_dir", &stx) == 0) {
+        printf("statx absolute: dir=%d\n", (stx.stx_mode & S_IFMT) == S_IFDIR);
+    }
+    close(dirfd);
+
+    unlink("statx_test.txt");
+    unlink("/statx_dir/inner.txt");
+    rmdir("/statx_dir");
+    return 0;
+}
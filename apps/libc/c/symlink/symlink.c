@@ -0,0 +1,41 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/stat.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static int do_statx(const char *path, int flags, struct statx *stx)
+{
+    return syscall(SYS_statx, AT_FDCWD, path, flags, STATX_BASIC_STATS, stx);
+}
+
+int main()
+{
+    char buf[64];
+    struct statx stx;
+
+    printf("symlinkat dangling: %d\n", symlinkat("/symlink_target", AT_FDCWD, "/symlink_link"));
+    ssize_t n = readlinkat(AT_FDCWD, "/symlink_link", buf, sizeof(buf));
+    printf("readlinkat: %.*s\n", (int)(n > 0 ? n : 0), buf);
+    errno = 0;
+    printf("symlinkat existing: %d %d\n", symlinkat("/other", AT_FDCWD, "/symlink_link"),
+           errno == EEXIST);
+
+    int fd = open("/symlink_target", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, "hello", 5);
+    close(fd);
+    if (do_statx("/symlink_link", AT_SYMLINK_NOFOLLOW, &stx) == 0) {
+        printf("nofollow: lnk=%d size=%llu\n", (stx.stx_mode & S_IFMT) == S_IFLNK,
+               (unsigned long long)stx.stx_size);
+    }
+    if (do_statx("/symlink_link", 0, &stx) == 0) {
+        printf("follow: reg=%d size=%llu\n", (stx.stx_mode & S_IFMT) == S_IFREG,
+               (unsigned long long)stx.stx_size);
+    }
+
+    unlink("/symlink_link");
+    unlink("/symlink_target");
+    return 0;
+}
@@ -0,0 +1,48 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/ioctl.h>
+#include <termios.h>
+#include <unistd.h>
+
+int main()
+{
+    struct termios saved, t;
+    tcgetattr(STDIN_FILENO, &saved);
+    printf("canonical echo: %d %d\n", !!(saved.c_lflag & ICANON), !!(saved.c_lflag & ECHO));
+
+    t = saved;
+    t.c_lflag &= ~(ICANON | ECHO);
+    int r = tcsetattr(STDIN_FILENO, TCSANOW, &t);
+    tcgetattr(STDOUT_FILENO, &t);
+    printf("raw mode: %d %d %d\n", r, !!(t.c_lflag & ICANON), !!(t.c_lflag & ECHO));
+    tcsetattr(STDIN_FILENO, TCSAFLUSH, &saved);
+
+    struct winsize ws = {.ws_row = 50, .ws_col = 132};
+    ioctl(STDOUT_FILENO, TIOCSWINSZ, &ws);
+    ws.ws_row = ws.ws_col = 0;
+    r = ioctl(STDOUT_FILENO, TIOCGWINSZ, &ws);
+    printf("window size: %d %d %d\n", r, ws.ws_row, ws.ws_col);
+    ws.ws_row = 24;
+    ws.ws_col = 80;
+    ioctl(STDOUT_FILENO, TIOCSWINSZ, &ws);
+
+    printf("foreground: %d\n", tcgetpgrp(STDIN_FILENO) == getpid());
+    errno = 0;
+    r = tcsetpgrp(STDIN_FILENO, -1);
+    printf("bad group: %d %d\n", r, errno == EINVAL);
+    errno = 0;
+    r = ioctl(STDOUT_FILENO, TIOCGWINSZ, (void *)16);
+    printf("bad pointer: %d %d\n", r, errno == EFAULT);
+
+    int fds[2];
+    pipe(fds);
+    errno = 0;
+    r = tcgetattr(fds[0], &t);
+    printf("not a tty: %d %d\n", r, errno == ENOTTY);
+    errno = 0;
+    r = ioctl(fds[0], 0x12345678, 0);
+    printf("unknown request: %d %d\n", r, errno == EINVAL);
+    close(fds[0]);
+    close(fds[1]);
+    return 0;
+}
@@ -0,0 +1,42 @@
+#include <pthread.h>
+#include <stdio.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#define ITERATIONS 100000
+
+static pthread_mutex_t lock = PTHREAD_MUTEX_INITIALIZER;
+static long counter = 0;
+static int tids[2];
+
+static void *increment(void *arg)
+{
+    int index = *(int *)arg;
+    tids[index] = syscall(SYS_gettid);
+    for (int i = 0; i < ITERATIONS; i++) {
+        pthread_mutex_lock(&lock);
+        counter++;
+        pthread_mutex_unlock(&lock);
+    }
+    return (void *)(long)getpid();
+}
+
+int main()
+{
+    pthread_t threads[2];
+    int indices[2] = {0, 1};
+    for (int i = 0; i < 2; i++) {
+        pthread_create(&threads[i], NULL, increment, &indices[i]);
+    }
+    int same_pid = 1;
+    for (int i = 0; i < 2; i++) {
+        void *pid;
+        pthread_join(threads[i], &pid);
+        same_pid &= (long)pid == getpid();
+    }
+    printf("counter: %ld\n", counter);
+    printf("threads share the pid: %d\n", same_pid);
+    printf("threads have their own tids: %d\n",
+           tids[0] != tids[1] && tids[0] != getpid() && tids[1] != getpid());
+    return 0;
+}
@@ -0,0 +1,25 @@
+#include <pthread.h>
+#include <stdio.h>
+
+static __thread int counter = 42;
+static __thread char name[16] = "initial";
+static __thread long zeroed;
+
+static void *thread_main(void *arg)
+{
+    printf("thread: %d %s %ld\n", counter, name, zeroed);
+    counter = 7;
+    return NULL;
+}
+
+int main()
+{
+    printf("main: %d %s %ld\n", counter, name, zeroed);
+    counter++;
+    zeroed = 5;
+    pthread_t thread;
+    pthread_create(&thread, NULL, thread_main, NULL);
+    pthread_join(thread, NULL);
+    printf("main after thread: %d %ld\n", counter, zeroed);
+    return 0;
+}
@@ -0,0 +1,49 @@
+#include <dirent.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <sys/vfs.h>
+#include <unistd.h>
+
+#define TMPFS_MAGIC 0x01021994
+
+int main()
+{
+    struct statfs fs;
+    int ret = statfs("/tmp", &fs);
+    printf("statfs: %d tmpfs: %d\n", ret, fs.f_type == TMPFS_MAGIC);
+    printf("space: %d\n", fs.f_blocks > 0 && fs.f_bfree <= fs.f_blocks);
+
+    int fd = open("/tmp/scratch", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    printf("create: %d\n", fd >= 0);
+    write(fd, "scratch data", 12);
+    fstatfs(fd, &fs);
+    printf("fstatfs tmpfs: %d\n", fs.f_type == TMPFS_MAGIC);
+    struct stat st;
+    fstat(fd, &st);
+    printf("size: %ld mtime set: %d\n", (long)st.st_size, st.st_mtime != 0);
+    ftruncate(fd, 7);
+    char buf[16] = {0};
+    pread(fd, buf, sizeof(buf) - 1, 0);
+    printf("truncated: %s\n", buf);
+    close(fd);
+
+    printf("mkdir: %d\n", mkdir("/tmp/dir", 0755));
+    printf("rename: %d\n", rename("/tmp/scratch", "/tmp/dir/moved"));
+    DIR *dir = opendir("/tmp/dir");
+    struct dirent *entry;
+    while ((entry = readdir(dir)) != NULL) {
+        if (entry->d_name[0] != '.')
+            printf("entry: %s\n", entry->d_name);
+    }
+    closedir(dir);
+    printf("unlink: %d\n", unlink("/tmp/dir/moved"));
+    printf("rmdir: %d\n", rmdir("/tmp/dir"));
+    printf("gone: %d\n", access("/tmp/dir", F_OK) != 0);
+
+    ret = statfs("/", &fs);
+    printf("root statfs: %d not tmpfs: %d namelen: %ld\n", ret, fs.f_type != TMPFS_MAGIC,
+           (long)fs.f_namelen);
+    return 0;
+}
@@ -0,0 +1,53 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <string.h>
+#include <sys/stat.h>
+#include <unistd.h>
+
+int main()
+{
+    char buf[8192];
+    struct stat st;
+    memset(buf, 'a', sizeof(buf));
+
+    int fd = open("/truncate_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644);
+    write(fd, buf, sizeof(buf));
+    fstat(fd, &st);
+    long blocks = st.st_blocks;
+    printf("before: size=%ld\n", (long)st.st_size);
+    ftruncate(fd, 100);
+    fstat(fd, &st);
+    printf("after ftruncate: size=%ld blocks shrunk=%d\n", (long)st.st_size, st.st_blocks < blocks);
+    printf("offset unchanged: %ld\n", (long)lseek(fd, 0, SEEK_CUR));
+
+    truncate("/truncate_test.txt", 200);
+    lseek(fd, 100, SEEK_SET);
+    int zeros = read(fd, buf, sizeof(buf)) == 100;
+    for (int i = 0; i < 100; i++) {
+        if (buf[i] != 0) {
+            zeros = 0;
+        }
+    }
+    printf("extended part reads as zeros: %d\n", zeros);
+    errno = 0;
+    printf("negative length: %d %d\n", ftruncate(fd, -1), errno == EINVAL);
+
+    lseek(fd, 0, SEEK_SET);
+    write(fd, "hello world", 11);
+    ftruncate(fd, 5);
+    memset(buf, 0, sizeof(buf));
+    printf("shrunk content: %ld %s\n", (long)pread(fd, buf, sizeof(buf), 0), buf);
+    close(fd);
+
+    fd = open("/truncate_test.txt", O_RDONLY);
+    errno = 0;
+    printf("ftruncate read-only: %d %d\n", ftruncate(fd, 0), errno == EINVAL);
+    close(fd);
+    mkdir("/truncate_dir", 0755);
+    errno = 0;
+    printf("truncate directory: %d %d\n", truncate("/truncate_dir", 0), errno == EISDIR);
+    rmdir("/truncate_dir");
+    unlink("/truncate_test.txt");
+    return 0;
+}
@@ -0,0 +1,29 @@
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    mode_t old = umask(0077);
+    printf("umask default: %o\n", old);
+    printf("umask previous: %o\n", umask(0077));
+
+    struct stat st;
+    int fd = open("/umask_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0666);
+    fstat(fd, &st);
+    printf("file mode with umask 077: %o\n", st.st_mode & 0777);
+    close(fd);
+    unlink("/umask_test.txt");
+
+    fflush(stdout);
+    pid_t pid = fork();
+    if (pid == 0) {
+        printf("umask inherited by child: %o\n", umask(0));
+        return 0;
+    }
+    waitpid(pid, NULL, 0);
+    umask(old);
+    return 0;
+}
@@ -0,0 +1,28 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+int main()
+{
+    mkdir("/unlink_dir", 0755);
+    close(open("/unlink_dir/file", O_CREAT | O_WRONLY, 0644));
+
+    int ret = unlinkat(AT_FDCWD, "/unlink_dir", AT_REMOVEDIR);
+    printf("non-empty directory: %d %d\n", ret, errno == ENOTEMPTY);
+    ret = unlinkat(AT_FDCWD, "/unlink_dir", 0);
+    printf("directory without AT_REMOVEDIR: %d %d\n", ret, errno == EISDIR);
+    ret = unlinkat(AT_FDCWD, "/unlink_dir/missing", 0);
+    printf("missing: %d %d\n", ret, errno == ENOENT);
+    ret = unlinkat(AT_FDCWD, "/unlink_dir/file", 0x1);
+    printf("bad flags: %d %d\n", ret, errno == EINVAL);
+    ret = unlinkat(AT_FDCWD, "/unlink_dir/file", AT_REMOVEDIR);
+    printf("file with AT_REMOVEDIR: %d %d\n", ret, errno == ENOTDIR);
+
+    printf("file without links: %d\n", unlink("/unlink_dir/file"));
+    printf("gone: %d %d\n", access("/unlink_dir/file", F_OK), errno == ENOENT);
+    printf("empty directory: %d\n", rmdir("/unlink_dir"));
+    return 0;
+}
@@ -0,0 +1,58 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <linux/stat.h>
+#include <stdio.h>
+#include <sys/stat.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+static int do_statx(const char *path, struct statx *stx)
+{
+    return syscall(SYS_statx, AT_FDCWD, path, 0, STATX_BASIC_STATS, stx);
+}
+
+int main()
+{
+    struct statx stx;
+
+    close(open("/utimensat_test.txt", O_CREAT | O_RDWR | O_TRUNC, 0644));
+    struct timespec times[2] = {{.tv_sec = 0, .tv_nsec = UTIME_OMIT},
+                                {.tv_sec = 1000000, .tv_nsec = 123}};
+    printf("utimensat: %d\n", utimensat(AT_FDCWD, "/utimensat_test.txt", times, 0));
+    if (do_statx("/utimensat_test.txt", &stx) == 0) {
+        printf("mtime: %lld %u\n", (long long)stx.stx_mtime.tv_sec, stx.stx_mtime.tv_nsec);
+    }
+
+    times[0].tv_sec = 2000000;
+    times[0].tv_nsec = 0;
+    times[1].tv_nsec = UTIME_OMIT;
+    printf("omit mtime: %d\n", utimensat(AT_FDCWD, "/utimensat_test.txt", times, 0));
+    if (do_statx("/utimensat_test.txt", &stx) == 0) {
+        printf("atime: %lld mtime: %lld\n", (long long)stx.stx_atime.tv_sec,
+               (long long)stx.stx_mtime.tv_sec);
+    }
+
+    printf("now: %d\n", utimensat(AT_FDCWD, "/utimensat_test.txt", NULL, 0));
+    if (do_statx("/utimensat_test.txt", &stx) == 0) {
+        printf("mtime updated: %d\n",
+               stx.stx_mtime.tv_sec != 1000000 || stx.stx_mtime.tv_nsec != 123);
+    }
+
+    times[0].tv_nsec = 1000000000;
+    errno = 0;
+    printf("invalid nsec: %d %d\n", utimensat(AT_FDCWD, "/utimensat_test.txt", times, 0),
+           errno == EINVAL);
+
+    struct stat st;
+    int fd = open("/utimensat_test.txt", O_RDWR);
+    times[0].tv_nsec = UTIME_OMIT;
+    times[1].tv_sec = 3000000;
+    times[1].tv_nsec = 999999999;
+    printf("futimens: %d\n", futimens(fd, times));
+    fstat(fd, &st);
+    printf("fstat mtime: %ld %ld\n", (long)st.st_mtim.tv_sec, (long)st.st_mtim.tv_nsec);
+    close(fd);
+
+    unlink("/utimensat_test.txt");
+    return 0;
+}
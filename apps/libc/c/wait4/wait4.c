@@ -0,0 +1,44 @@
+#include <errno.h>
+#include <stdio.h>
+#include <sys/resource.h>
+#include <sys/wait.h>
+#include <unistd.h>
+
+int main()
+{
+    pid_t child = fork();
+    if (child == 0) {
+        _exit(42);
+    }
+    int status = 0;
+    struct rusage usage;
+    pid_t pid = wait4(child, &status, 0, &usage);
+    printf("reaped: %d exited: %d status: %d\n", pid == child, WIFEXITED(status), WEXITSTATUS(status));
+    printf("rusage: %d\n", usage.ru_utime.tv_usec >= 0 && usage.ru_utime.tv_usec < 1000000);
+    pid = wait4(child, &status, 0, NULL);
+    printf("already reaped: %d %d\n", pid, errno == ECHILD);
+
+    child = fork();
+    if (child == 0) {
+        sleep(1);
+        _exit(3);
+    }
+    pid = waitpid(-1, &status, WNOHANG);
+    printf("WNOHANG: %d\n", pid);
+    pid = waitpid(-1, &status, 0);
+    printf("any child: %d %d\n", pid == child, WEXITSTATUS(status));
+
+    child = fork();
+    if (child == 0) {
+        *(volatile int *)0 = 1;
+        _exit(0);
+    }
+    waitpid(child, &status, 0);
+    printf("signaled: %d signal: %d\n", WIFSIGNALED(status), WTERMSIG(status));
+
+    pid = waitpid(-1, &status, 0);
+    printf("no children: %d %d\n", pid, errno == ECHILD);
+    pid = waitpid(-1, &status, 0x1000);
+    printf("bad options: %d %d\n", pid, errno == EINVAL);
+    return 0;
+}
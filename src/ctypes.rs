@@ -1,5 +1,9 @@
 //! clone 任务时指定的参数。
 
+use core::ffi::{c_long, c_void};
+
+use arceos_posix_api::ctypes::timeval;
+
 use bitflags::*;
 
 bitflags! {
@@ -61,16 +65,233 @@ bitflags! {
 
 }
 
-/// sys_wait4 的返回值
+/// sys_wait4 没有回收到子任务时的原因
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WaitStatus {
-    /// 子任务正常退出
-    Exited,
     /// 子任务正在运行
     Running,
     /// 找不到对应的子任务
     NotExist,
 }
+
+/// sys_wait4 返回的资源使用情况，对应 C 中的 `struct rusage`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RUsage {
+    /// 用户态 CPU 时间
+    pub ru_utime: timeval,
+    /// 内核态 CPU 时间
+    pub ru_stime: timeval,
+    /// 其余统计项，如最大驻留集大小、缺页次数和上下文切换次数，目前均为 0
+    pub ru_others: [c_long; 14],
+}
+/// sys_readv / sys_writev 使用的 I/O 向量，对应 C 中的 `struct iovec`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IoVec {
+    /// 缓冲区起始地址
+    pub iov_base: *mut c_void,
+    /// 缓冲区长度
+    pub iov_len: usize,
+}
+
+/// 单次 sys_readv / sys_writev 最多允许的 I/O 向量个数
+pub const IOV_MAX: usize = 1024;
+
+/// sys_ppoll / sys_poll 使用的文件描述符及事件，对应 C 中的 `struct pollfd`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PollFd {
+    /// 文件描述符，为负数时忽略该项
+    pub fd: i32,
+    /// 关心的事件
+    pub events: i16,
+    /// 实际发生的事件
+    pub revents: i16,
+}
+
+/// sys_epoll_ctl / sys_epoll_pwait 使用的事件，对应 C 中的 `struct epoll_event`
+///
+/// 在 x86_64 上该结构体是紧凑排列的
+#[cfg_attr(target_arch = "x86_64", repr(C, packed))]
+#[cfg_attr(not(target_arch = "x86_64"), repr(C))]
+#[derive(Debug, Clone, Copy)]
+pub struct EpollEvent {
+    /// 关心或实际发生的事件
+    pub events: u32,
+    /// 用户数据，原样返回
+    pub data: u64,
+}
+
+/// fcntl 的 F_GETLK / F_SETLK / F_SETLKW 使用的记录锁，对应 C 中的 `struct flock`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Flock {
+    /// 锁的类型：F_RDLCK、F_WRLCK 或 F_UNLCK
+    pub l_type: i16,
+    /// l_start 的起点：SEEK_SET、SEEK_CUR 或 SEEK_END
+    pub l_whence: i16,
+    /// 锁定区域的起始偏移
+    pub l_start: i64,
+    /// 锁定区域的长度，为 0 表示直到文件末尾
+    pub l_len: i64,
+    /// 持有冲突锁的进程 id，仅由 F_GETLK 返回
+    pub l_pid: i32,
+}
+
+/// sys_statfs / sys_fstatfs 返回的文件系统信息，对应 C 中的 `struct statfs`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatFs {
+    /// 文件系统类型的魔数
+    pub f_type: i64,
+    /// 最优传输块大小
+    pub f_bsize: i64,
+    /// 文件系统中数据块总数
+    pub f_blocks: u64,
+    /// 空闲块数
+    pub f_bfree: u64,
+    /// 非特权用户可用的空闲块数
+    pub f_bavail: u64,
+    /// inode 总数
+    pub f_files: u64,
+    /// 空闲 inode 数
+    pub f_ffree: u64,
+    /// 文件系统 id
+    pub f_fsid: [i32; 2],
+    /// 文件名最大长度
+    pub f_namelen: i64,
+    /// 片段大小
+    pub f_frsize: i64,
+    /// 挂载选项
+    pub f_flags: i64,
+    /// padding
+    pub f_spare: [i64; 4],
+}
+
+/// sys_rt_sigaction 使用的信号处理方式，对应内核中的 `struct sigaction`
+///
+/// 只有 x86_64 和 aarch64 上有 sa_restorer 字段
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigAction {
+    /// 信号处理函数，或 SIG_DFL(0) / SIG_IGN(1)
+    pub handler: usize,
+    /// SA_* 标志
+    pub flags: usize,
+    /// 信号处理函数返回时跳转到的函数，设置了 SA_RESTORER 时有效
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub restorer: usize,
+    /// 执行信号处理函数期间额外屏蔽的信号
+    pub mask: u64,
+}
+
+/// 信号处理函数收到的信号信息，对应 C 中的 `siginfo_t`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigInfo {
+    /// 信号编号
+    pub si_signo: i32,
+    /// 错误码，总是 0
+    pub si_errno: i32,
+    /// 信号的来源，如 SI_USER
+    pub si_code: i32,
+    /// padding
+    pub _pad: i32,
+    /// 与信号来源相关的其余字段，如发送者的进程 id，目前均为 0
+    pub si_fields: [u64; 14],
+}
+
+/// 被信号中断时的用户寄存器，对应 C 中的 `mcontext_t`
+///
+/// gregs 的顺序与 C 中的 REG_R8 等下标相同，不保存浮点寄存器，fpregs 总是 NULL
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MContext {
+    /// r8-r15、rdi、rsi、rbp、rbx、rdx、rax、rcx、rsp、rip、eflags、cs/gs/fs/ss、err、trapno、oldmask、cr2
+    pub gregs: [u64; 23],
+    /// 浮点寄存器的保存位置
+    pub fpregs: usize,
+    /// 保留
+    pub _reserved: [u64; 8],
+}
+
+/// 被信号中断时的用户寄存器，对应 C 中的 `mcontext_t`
+///
+/// 不保存浮点寄存器，fpregs 总是 0
+#[cfg(target_arch = "riscv64")]
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy)]
+pub struct MContext {
+    /// pc 与 x1-x31
+    pub gregs: [usize; 32],
+    /// 浮点寄存器，即 `__riscv_q_ext_state`
+    pub fpregs: [u64; 66],
+}
+
+/// 被信号中断时的用户寄存器，对应 C 中的 `mcontext_t`
+///
+/// 不保存浮点寄存器，reserved 中没有任何扩展记录
+#[cfg(target_arch = "aarch64")]
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy)]
+pub struct MContext {
+    /// 引起异常的地址
+    pub fault_address: u64,
+    /// x0-x30
+    pub regs: [u64; 31],
+    /// 栈指针
+    pub sp: u64,
+    /// 程序计数器
+    pub pc: u64,
+    /// 处理器状态
+    pub pstate: u64,
+    /// padding，使 reserved 按 16 字节对齐
+    pub _pad: u64,
+    /// 浮点寄存器等扩展记录
+    pub reserved: [u64; 512],
+}
+
+/// 被信号中断时的用户寄存器，对应 C 中的 `mcontext_t`
+///
+/// 不保存浮点寄存器，没有任何扩展上下文
+#[cfg(target_arch = "loongarch64")]
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy)]
+pub struct MContext {
+    /// 程序计数器
+    pub pc: usize,
+    /// r0-r31
+    pub gregs: [usize; 32],
+    /// 标志，总是 0
+    pub flags: u32,
+}
+
+/// 传给信号处理函数的用户上下文，对应 C 中的 `ucontext_t`
+///
+/// 从信号处理函数返回时，按其中的 uc_mcontext 和 uc_sigmask 恢复被中断的上下文
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UContext {
+    /// 标志，总是 0
+    pub uc_flags: usize,
+    /// 下一个上下文，总是 NULL
+    pub uc_link: usize,
+    /// 信号栈，即 `stack_t` 的 ss_sp、ss_flags 和 ss_size
+    pub uc_stack: [usize; 3],
+    /// 被中断时的寄存器
+    #[cfg(target_arch = "x86_64")]
+    pub uc_mcontext: MContext,
+    /// 被中断时的信号屏蔽字
+    pub uc_sigmask: u64,
+    /// C 中的 `sigset_t` 占 128 字节
+    pub _sigmask_pad: [u64; 15],
+    /// 被中断时的寄存器
+    #[cfg(not(target_arch = "x86_64"))]
+    pub uc_mcontext: MContext,
+}
+
 #[repr(C)]
 pub struct Tms {
     /// 进程用户态执行时间，单位为us
@@ -96,6 +96,7 @@ extern crate axstd;
 mod ctypes;
 
 mod mm;
+mod signal;
 mod syscall_imp;
 mod task;
 use alloc::{string::ToString, sync::Arc, vec};
@@ -111,6 +112,9 @@ fn main() {
         .unwrap_or_else(|| "Please specify the testcases list by making user_apps")
         .split(',')
         .filter(|&x| !x.is_empty());
+    syscall_imp::init_devices();
+    syscall_imp::init_mounts();
+    syscall_imp::init_tty();
     println!("#### OS COMP TEST GROUP START basic-musl ####");
     for testcase in testcases {
         println!("Testing {}: ", testcase.split('/').next_back().unwrap());
@@ -121,11 +125,19 @@ fn main() {
             axconfig::plat::USER_SPACE_SIZE,
         )
         .expect("Failed to create user address space");
-        let (entry_vaddr, ustack_top) = mm::load_user_app(&mut (args.into()), &mut uspace).unwrap();
+        let (entry_vaddr, ustack_top, heap_bottom, tls) = mm::load_user_app(
+            testcase,
+            &mut (args.into()),
+            &mm::default_envs(),
+            &mut uspace,
+        )
+        .unwrap();
         let user_task = task::spawn_user_task(
+            testcase,
             Arc::new(Mutex::new(uspace)),
             UspaceContext::new(entry_vaddr.into(), ustack_top, 2333),
-            0,
+            heap_bottom.as_usize() as u64,
+            tls,
         );
         let exit_code = user_task.join();
         info!("User task {} exited with code: {:?}", testcase, exit_code);
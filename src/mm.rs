@@ -1,69 +1,138 @@
 use core::str::from_utf8;
 
-use alloc::{collections::vec_deque::VecDeque, string::String, vec};
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    string::String,
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
 
 use axerrno::{AxError, AxResult};
 use axhal::{
-    paging::MappingFlags,
+    paging::{MappingFlags, PageTable},
     trap::{PAGE_FAULT, register_trap_handler},
 };
+use axio::Read;
 
 use axmm::AddrSpace;
+use axsync::Mutex;
 use axtask::TaskExtRef;
-use kernel_elf_parser::{AuxvEntry, ELFParser, app_stack_region};
-use memory_addr::{MemoryAddr, PAGE_SIZE_4K, VirtAddr};
-use xmas_elf::{ElfFile, program::SegmentData};
+use kernel_elf_parser::{AuxvType, ELFParser, app_stack_region};
+use memory_addr::{MemoryAddr, PAGE_SIZE_4K, PhysAddr, VirtAddr, VirtAddrRange};
+use xmas_elf::{
+    ElfFile,
+    dynamic::Tag,
+    header,
+    program::{self, SegmentData},
+};
+
+use crate::task::TaskExt;
+
+/// The type of the relocations that add the load bias to an address.
+#[cfg(target_arch = "x86_64")]
+const R_RELATIVE: u32 = 8;
+#[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+const R_RELATIVE: u32 = 3;
+#[cfg(target_arch = "aarch64")]
+const R_RELATIVE: u32 = 1027;
+
+/// Apply the relative relocations of a position-independent image loaded at
+/// `bias`, which set each address they describe to `bias` plus its addend.
+///
+/// The startup code of a static PIE applies them again, to the same effect.
+fn apply_relative_relocs(elf: &ElfFile, bias: usize, uspace: &mut AddrSpace) -> AxResult {
+    let Some(dynamic) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(program::Type::Dynamic))
+    else {
+        return Ok(());
+    };
+    let Ok(SegmentData::Dynamic64(entries)) = dynamic.get_data(elf) else {
+        return Err(AxError::InvalidData);
+    };
+    let (mut rela, mut rela_size) = (None, 0);
+    for entry in entries {
+        match entry.get_tag() {
+            Ok(Tag::Rela) => rela = entry.get_ptr().ok(),
+            Ok(Tag::RelaSize) => rela_size = entry.get_val().unwrap_or(0),
+            _ => {}
+        }
+    }
+    let Some(rela) = rela else {
+        return Ok(());
+    };
+    // The relocations are found in the file through the segment holding them.
+    let offset = elf
+        .program_iter()
+        .filter(|ph| ph.get_type() == Ok(program::Type::Load))
+        .find(|ph| (ph.virtual_addr()..ph.virtual_addr() + ph.file_size()).contains(&rela))
+        .map(|ph| (rela - ph.virtual_addr() + ph.offset()) as usize)
+        .ok_or(AxError::InvalidData)?;
+    let relocs = elf
+        .input
+        .get(offset..offset + rela_size as usize)
+        .ok_or(AxError::InvalidData)?;
+    // Each is an `Elf64_Rela` of the offset, the info and the addend.
+    for reloc in relocs.chunks_exact(24) {
+        let field = |i: usize| u64::from_le_bytes(reloc[i * 8..i * 8 + 8].try_into().unwrap());
+        if field(1) as u32 == R_RELATIVE {
+            let value = bias.wrapping_add(field(2) as usize);
+            uspace.write(
+                VirtAddr::from(bias.wrapping_add(field(0) as usize)),
+                &value.to_le_bytes(),
+            )?;
+        }
+    }
+    Ok(())
+}
 
-/// Map the elf file to the user address space.
+/// Find the interpreter named by the `PT_INTERP` segment of `elf`, which
+/// dynamically linked apps have, and return its canonical path.
+///
+/// Symbolic links to the interpreter are followed. A missing interpreter fails
+/// with `NotFound`.
+fn find_interp(elf: &ElfFile) -> AxResult<Option<String>> {
+    let Some(interp) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(program::Type::Interp))
+    else {
+        return Ok(None);
+    };
+    let interp = match interp.get_data(elf) {
+        Ok(SegmentData::Undefined(data)) => data,
+        _ => return Err(AxError::InvalidData),
+    };
+    let interp_path = from_utf8(interp).map_err(|_| AxError::InvalidData)?;
+    // remove trailing '\0'
+    let interp_path = interp_path.trim_matches(char::from(0));
+    let real_interp_path = crate::syscall_imp::resolve_symlinks(interp_path, true)
+        .ok()
+        .filter(|path| axfs::api::metadata(path).is_ok_and(|meta| meta.is_file()));
+    if let Some(real_interp_path) = real_interp_path {
+        return Ok(Some(real_interp_path));
+    }
+    if interp_path == "/lib/ld-linux-riscv64-lp64.so.1"
+        || interp_path == "/lib64/ld-linux-loongarch-lp64d.so.1"
+    {
+        // TODO: Use soft link
+        return Ok(Some(String::from("./musl/lib/libc.so")));
+    }
+    error!("The interpreter {interp_path} of the app is missing");
+    Err(AxError::NotFound)
+}
+
+/// Map the segments of the elf file to the user address space.
 ///
 /// # Arguments
-/// - `args`: The arguments of the user app. The first argument is the path of the user app.
 /// - `elf_parser`: The parser of the elf file.
 /// - `uspace`: The address space of the user app.
 ///
 /// # Returns
-/// - The entry point of the user app.
-fn map_elf(
-    args: &mut VecDeque<String>,
-    elf_parser: &ELFParser,
-    uspace: &mut AddrSpace,
-) -> AxResult<(VirtAddr, [AuxvEntry; 17])> {
+/// - The end of the highest segment, where the heap starts.
+fn map_elf(elf_parser: &ELFParser, uspace: &mut AddrSpace) -> AxResult<VirtAddr> {
     let elf = elf_parser.elf();
-    if let Some(interp) = elf
-        .program_iter()
-        .find(|ph| ph.get_type() == Ok(xmas_elf::program::Type::Interp))
-    {
-        let interp = match interp.get_data(elf) {
-            Ok(SegmentData::Undefined(data)) => data,
-            _ => panic!("Invalid data in Interp Elf Program Header"),
-        };
-
-        let interp_path = from_utf8(interp).map_err(|_| AxError::InvalidInput)?;
-        // remove trailing '\0'
-        let mut real_interp_path =
-            axfs::api::canonicalize(interp_path.trim_matches(char::from(0)))?;
-        if real_interp_path == "/lib/ld-linux-riscv64-lp64.so.1"
-            || real_interp_path == "/lib64/ld-linux-loongarch-lp64d.so.1"
-        {
-            // TODO: Use soft link
-            real_interp_path = String::from("./musl/lib/libc.so");
-        }
-
-        let interp_data = axfs::api::read(real_interp_path.as_str())?;
-        let interp_elf = ElfFile::new(&interp_data).map_err(|_| AxError::InvalidData)?;
-        let uspace_base = uspace.base().as_usize();
-
-        let interp_elf_parser = ELFParser::new(
-            &interp_elf,
-            axconfig::plat::USER_INTERP_BASE,
-            Some(uspace_base as isize),
-            uspace_base,
-        )
-        .map_err(|_| AxError::InvalidData)?;
-        // Set the first argument to the path of the user app.
-        args.push_front(real_interp_path);
-        return map_elf(args, &interp_elf_parser, uspace);
-    }
+    let mut image_end = VirtAddr::from(0);
     for segement in elf_parser.ph_load() {
         debug!(
             "Mapping ELF segment: [{:#x?}, {:#x?}) flags: {:#x?}",
@@ -87,32 +156,121 @@ fn map_elf(
             .get(segement.offset..segement.offset + segement.filesz as usize)
             .ok_or(AxError::InvalidData)?;
         uspace.write(segement.vaddr, seg_data)?;
+        image_end = image_end.max(segement.vaddr.align_down_4k() + seg_align_size);
         // TDOO: flush the I-cache
     }
+    if elf.header.pt2.type_().as_type() == header::Type::SharedObject {
+        // A position-independent image is placed at a bias chosen by the parser.
+        let bias = elf_parser
+            .entry()
+            .wrapping_sub(elf.header.pt2.entry_point() as usize);
+        apply_relative_relocs(elf, bias, uspace)?;
+    }
+    Ok(image_end)
+}
 
-    Ok((
-        elf_parser.entry().into(),
-        elf_parser.auxv_vector(PAGE_SIZE_4K),
-    ))
+/// The size of the thread control block at the thread pointer, which the TLS
+/// block follows on the architectures that put TLS above the thread pointer.
+#[cfg(target_arch = "aarch64")]
+const TLS_TCB_SIZE: usize = 16;
+#[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+const TLS_TCB_SIZE: usize = 0;
+
+/// Build the static TLS area of the initial thread from the `PT_TLS` segment
+/// of `elf`, if it has one, and return it with the offset of the thread
+/// pointer in it.
+///
+/// x86_64 puts the TLS block below the thread pointer, which points to a
+/// pointer to itself that is left for the caller to fill. The other
+/// architectures put it above, after the thread control block.
+fn initial_tls(elf: &ElfFile) -> AxResult<Option<(Vec<u8>, usize)>> {
+    let Some(tls) = elf
+        .program_iter()
+        .find(|ph| ph.get_type() == Ok(program::Type::Tls))
+    else {
+        return Ok(None);
+    };
+    let align = tls.align().max(1) as usize;
+    if !align.is_power_of_two() || align > PAGE_SIZE_4K || tls.file_size() > tls.mem_size() {
+        return Err(AxError::InvalidData);
+    }
+    let mem_size = tls.mem_size() as usize;
+    #[cfg(target_arch = "x86_64")]
+    let (block_offset, tp_offset, size) = {
+        let tp_offset = mem_size.next_multiple_of(align);
+        (0, tp_offset, tp_offset + size_of::<usize>())
+    };
+    #[cfg(not(target_arch = "x86_64"))]
+    let (block_offset, tp_offset, size) = {
+        let block_offset = TLS_TCB_SIZE.next_multiple_of(align);
+        (block_offset, 0, block_offset + mem_size)
+    };
+    let image = elf
+        .input
+        .get(tls.offset() as usize..(tls.offset() + tls.file_size()) as usize)
+        .ok_or(AxError::InvalidData)?;
+    // The size keeps the area and the stack below it aligned.
+    let mut area = vec![0; size.next_multiple_of(align.max(16))];
+    area[block_offset..block_offset + image.len()].copy_from_slice(image);
+    Ok(Some((area, tp_offset)))
+}
+
+/// The environment variables of the user apps run at boot.
+pub fn default_envs() -> Vec<String> {
+    vec![
+        "SHLVL=1".into(),
+        "PWD=/".into(),
+        "GCC_EXEC_PREFIX=/riscv64-linux-musl-native/bin/../lib/gcc/".into(),
+        "COLLECT_GCC=./riscv64-linux-musl-native/bin/riscv64-linux-musl-gcc".into(),
+        "COLLECT_LTO_WRAPPER=/riscv64-linux-musl-native/bin/../libexec/gcc/riscv64-linux-musl/11.2.1/lto-wrapper".into(),
+        "COLLECT_GCC_OPTIONS='-march=rv64gc' '-mabi=lp64d' '-march=rv64imafdc' '-dumpdir' 'a.'".into(),
+        "LIBRARY_PATH=/lib/".into(),
+        "LD_LIBRARY_PATH=/lib/".into(),
+        "LD_DEBUG=files".into(),
+    ]
+}
+
+/// Check that the file at `path` looks like an ELF file that can be loaded,
+/// along with its interpreter if it has one, without loading it.
+///
+/// A directory fails with `PermissionDenied`, a file of another format with
+/// `InvalidData` and a missing interpreter with `NotFound`.
+pub fn check_user_app(path: &str) -> AxResult {
+    if axfs::api::metadata(path)?.is_dir() {
+        return Err(AxError::PermissionDenied);
+    }
+    let mut magic = [0; 4];
+    let mut file = axfs::api::File::open(path)?;
+    if file.read(&mut magic)? != magic.len() || magic != *b"\x7fELF" {
+        return Err(AxError::InvalidData);
+    }
+    let file_data = crate::syscall_imp::cached_read_file(path)?;
+    let elf = ElfFile::new(&file_data).map_err(|_| AxError::InvalidData)?;
+    find_interp(&elf)?;
+    Ok(())
 }
 
 /// Load the user app to the user address space.
 ///
 /// # Arguments
-/// - `args`: The arguments of the user app. The first argument is the path of the user app.
+/// - `path`: The path of the user app.
+/// - `args`: The arguments of the user app, the first of which is its name.
+/// - `envs`: The environment variables of the user app.
 /// - `uspace`: The address space of the user app.
 ///
 /// # Returns
-/// - The entry point of the user app.
+/// - The entry point of the user app, or of its interpreter if it has one.
 /// - The stack pointer of the user app.
+/// - The initial program break, i.e. the bottom of the heap.
+/// - The thread pointer of the user app, which points to its static TLS at
+///   the top of the stack, or 0 if it has no `PT_TLS` segment.
 pub fn load_user_app(
+    path: &str,
     args: &mut VecDeque<String>,
+    envs: &[String],
     uspace: &mut AddrSpace,
-) -> AxResult<(VirtAddr, VirtAddr)> {
-    if args.is_empty() {
-        return Err(AxError::InvalidInput);
-    }
-    let file_data = axfs::api::read(args[0].as_str())?;
+) -> AxResult<(VirtAddr, VirtAddr, VirtAddr, usize)> {
+    let file_data = crate::syscall_imp::cached_read_file(path)?;
     let elf = ElfFile::new(&file_data).map_err(|_| AxError::InvalidData)?;
 
     let uspace_base = uspace.base().as_usize();
@@ -124,7 +282,36 @@ pub fn load_user_app(
     )
     .map_err(|_| AxError::InvalidData)?;
 
-    let (entry, mut auxv) = map_elf(args, &elf_parser, uspace)?;
+    let heap_bottom = map_elf(&elf_parser, uspace)?;
+    let mut entry = VirtAddr::from(elf_parser.entry());
+    let mut auxv = elf_parser.auxv_vector(PAGE_SIZE_4K);
+    // A dynamically linked app is started by its interpreter, which is mapped
+    // at `USER_INTERP_BASE` along with it. `AT_BASE` tells the interpreter
+    // where it is, and is 0 for a statically linked app.
+    let mut interp_base = 0;
+    if let Some(interp_path) = find_interp(&elf)? {
+        debug!("Loading interpreter {interp_path} for {path}");
+        let interp_data = crate::syscall_imp::cached_read_file(interp_path.as_str())?;
+        let interp_elf = ElfFile::new(&interp_data).map_err(|_| AxError::InvalidData)?;
+        let interp_elf_parser = ELFParser::new(
+            &interp_elf,
+            axconfig::plat::USER_INTERP_BASE,
+            Some(axconfig::plat::USER_INTERP_BASE as isize),
+            uspace_base,
+        )
+        .map_err(|_| AxError::InvalidData)?;
+        map_elf(&interp_elf_parser, uspace)?;
+        entry = interp_elf_parser.entry().into();
+        interp_base = interp_elf_parser
+            .entry()
+            .wrapping_sub(interp_elf.header.pt2.entry_point() as usize);
+    }
+    if let Some(base) = auxv
+        .iter_mut()
+        .find(|entry| entry.get_type() == AuxvType::BASE)
+    {
+        *base.value_mut_ref() = interp_base;
+    }
     // The user stack is divided into two parts:
     // `ustack_start` -> `ustack_pointer`: It is the stack space that users actually read and write.
     // `ustack_pointer` -> `ustack_end`: It is the space that contains the arguments, environment variables and auxv passed to the app.
@@ -136,25 +323,23 @@ pub fn load_user_app(
         "Mapping user stack: {:#x?} -> {:#x?}",
         ustack_start, ustack_end
     );
-    // FIXME: Add more arguments and environment variables
-    let env = vec![
-        "SHLVL=1".into(),
-        "PWD=/".into(),
-        "GCC_EXEC_PREFIX=/riscv64-linux-musl-native/bin/../lib/gcc/".into(),
-        "COLLECT_GCC=./riscv64-linux-musl-native/bin/riscv64-linux-musl-gcc".into(),
-        "COLLECT_LTO_WRAPPER=/riscv64-linux-musl-native/bin/../libexec/gcc/riscv64-linux-musl/11.2.1/lto-wrapper".into(),
-        "COLLECT_GCC_OPTIONS='-march=rv64gc' '-mabi=lp64d' '-march=rv64imafdc' '-dumpdir' 'a.'".into(),
-        "LIBRARY_PATH=/lib/".into(),
-        "LD_LIBRARY_PATH=/lib/".into(),
-        "LD_DEBUG=files".into(),
-    ];
-
-    let stack_data = app_stack_region(
+    #[allow(unused_mut)]
+    let mut tls = initial_tls(&elf)?;
+    let tls_size = tls.as_ref().map_or(0, |(area, _)| area.len());
+    let tls_start = ustack_end - tls_size;
+    let tp = tls
+        .as_ref()
+        .map_or(0, |(_, tp_offset)| tls_start.as_usize() + tp_offset);
+    #[cfg(target_arch = "x86_64")]
+    if let Some((area, tp_offset)) = &mut tls {
+        area[*tp_offset..*tp_offset + size_of::<usize>()].copy_from_slice(&tp.to_ne_bytes());
+    }
+    let mut stack_data = app_stack_region(
         args.make_contiguous(),
-        &env,
+        envs,
         &mut auxv,
         ustack_start,
-        ustack_size,
+        ustack_size - tls_size,
     );
     uspace.map_alloc(
         ustack_start,
@@ -163,31 +348,805 @@ pub fn load_user_app(
         true,
     )?;
 
-    let user_sp = ustack_end - stack_data.len();
+    if let Some((area, _)) = &tls {
+        uspace.write(tls_start, area)?;
+    }
+
+    let user_sp = tls_start - stack_data.len();
+    // The 16 bytes at `AT_RANDOM` seed the stack protector and the pointer
+    // guard of libc, so they are made random rather than left as they are.
+    if let Some(random) = auxv
+        .iter()
+        .find(|entry| entry.get_type() == AuxvType::RANDOM)
+    {
+        let offset = random.value() - user_sp.as_usize();
+        crate::syscall_imp::fill_random(&mut stack_data[offset..offset + 16]);
+    }
 
     uspace.write(user_sp, stack_data.as_slice())?;
 
-    Ok((entry, user_sp))
+    uspace.map_alloc(
+        VirtAddr::from_usize(SIGNAL_TRAMPOLINE),
+        PAGE_SIZE_4K,
+        MappingFlags::READ | MappingFlags::EXECUTE | MappingFlags::USER,
+        true,
+    )?;
+    uspace.write(VirtAddr::from_usize(SIGNAL_TRAMPOLINE), SIGRETURN_CODE)?;
+
+    Ok((entry, user_sp, heap_bottom, tp))
+}
+
+/// The page right below the user stack, which holds the code that a signal
+/// handler returns to unless it has been installed with `SA_RESTORER`.
+pub const SIGNAL_TRAMPOLINE: usize =
+    axconfig::plat::USER_STACK_TOP - axconfig::plat::USER_STACK_SIZE - PAGE_SIZE_4K;
+
+/// The code in the signal trampoline, which calls `rt_sigreturn`.
+#[cfg(target_arch = "x86_64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0xb8, 0x0f, 0x00, 0x00, 0x00, // mov eax, 15
+    0x0f, 0x05, // syscall
+];
+#[cfg(target_arch = "riscv64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0x93, 0x08, 0xb0, 0x08, // li a7, 139
+    0x73, 0x00, 0x00, 0x00, // ecall
+];
+#[cfg(target_arch = "aarch64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0x68, 0x11, 0x80, 0xd2, // mov x8, #139
+    0x01, 0x00, 0x00, 0xd4, // svc #0
+];
+#[cfg(target_arch = "loongarch64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0x0b, 0x2c, 0x82, 0x03, // ori $a7, $zero, 139
+    0x00, 0x00, 0x2b, 0x00, // syscall 0
+];
+
+/// Get the frame mapped at `page`, or `None` if it is left for a page fault to allocate.
+fn mapped_frame(uspace: &AddrSpace, page: VirtAddr) -> Option<PhysAddr> {
+    uspace
+        .page_table()
+        .query(page)
+        .ok()
+        .filter(|(_, flags, _)| !flags.is_empty())
+        .map(|(paddr, _, _)| paddr)
+}
+
+/// Get the regions mapped in the user address space, each with its flags.
+///
+/// `AddrSpace` doesn't expose its areas, so they are found from the free areas
+/// between them, and split where the flags of the pages allocated so far
+/// change. A region none of whose pages have been allocated has empty flags.
+pub(crate) fn mapped_regions(uspace: &AddrSpace) -> Vec<(VirtAddr, VirtAddr, MappingFlags)> {
+    let limit = VirtAddrRange::new(uspace.base(), uspace.end());
+    let is_free =
+        |addr: VirtAddr, size: usize| uspace.find_free_area(addr, size, limit) == Some(addr);
+    let mut regions = Vec::new();
+    let mut addr = uspace.base();
+    while addr < uspace.end() {
+        if is_free(addr, PAGE_SIZE_4K) {
+            // Bisect for the number of free pages at `addr`.
+            let (mut free, mut not_free) = (1, (uspace.end() - addr) / PAGE_SIZE_4K + 1);
+            while not_free - free > 1 {
+                let pages = (free + not_free) / 2;
+                if is_free(addr, pages * PAGE_SIZE_4K) {
+                    free = pages;
+                } else {
+                    not_free = pages;
+                }
+            }
+            addr += free * PAGE_SIZE_4K;
+            continue;
+        }
+        let end = uspace
+            .find_free_area(addr, PAGE_SIZE_4K, limit)
+            .unwrap_or(uspace.end());
+        let mut start = addr;
+        let mut flags = None;
+        for page in (addr.as_usize()..end.as_usize()).step_by(PAGE_SIZE_4K) {
+            let page = VirtAddr::from(page);
+            let Some(page_flags) = uspace
+                .page_table()
+                .query(page)
+                .ok()
+                .map(|(_, flags, _)| flags)
+                .filter(|flags| !flags.is_empty())
+            else {
+                continue;
+            };
+            if flags.is_some_and(|flags| flags != page_flags) {
+                regions.push((start, page, flags.unwrap()));
+                start = page;
+            }
+            flags = Some(page_flags);
+        }
+        regions.push((start, end, flags.unwrap_or(MappingFlags::empty())));
+        addr = end;
+    }
+    regions
+}
+
+/// A mapping of a file created by `mmap`, or a shared anonymous mapping.
+///
+/// Its pages are allocated lazily like anonymous ones, and filled with the
+/// contents of the file when they are first accessed. A shared anonymous
+/// mapping has no file, so its pages are zero-filled and its changes are not
+/// written anywhere, and it is only recorded so that its frames stay shared
+/// with the processes forked from this one.
+#[derive(Clone)]
+pub(crate) struct FileMapping {
+    pub start: VirtAddr,
+    pub end: VirtAddr,
+    /// The file mapped, or `None` for a shared anonymous mapping.
+    pub file: Option<Arc<arceos_posix_api::File>>,
+    /// The offset in the file that `start` maps.
+    pub offset: u64,
+    /// Whether it is `MAP_SHARED` rather than `MAP_PRIVATE`.
+    pub shared: bool,
+    /// Whether the file is open for writing, without which a shared mapping can't be writable.
+    pub file_writable: bool,
+    /// Whether changes are carried through to the file, i.e. it is shared and has
+    /// been writable.
+    pub write_back: bool,
+}
+
+impl FileMapping {
+    /// The offset in the file that `vaddr` maps.
+    fn file_offset(&self, vaddr: VirtAddr) -> u64 {
+        self.offset + (vaddr - self.start) as u64
+    }
+
+    /// Fill the page at `page`, which has just been allocated, with the contents of the file.
+    fn fill_page(&self, uspace: &mut AddrSpace, page: VirtAddr) -> AxResult {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let mut buf = vec![0u8; PAGE_SIZE_4K];
+        let read = crate::syscall_imp::cached_read_at(
+            file.path(),
+            &file.inner().lock(),
+            self.file_offset(page),
+            &mut buf,
+        )?;
+        // The rest of the page beyond the end of the file stays zeroed.
+        uspace.write(page, &buf[..read])
+    }
+
+    /// Write the pages in `[start, end)` that have been accessed back to the file,
+    /// if changes are carried through to it.
+    ///
+    /// Without dirty tracking, every accessed page is written. The file is not
+    /// extended, so what lies beyond its end is dropped, like Linux does.
+    pub fn sync(&self, uspace: &AddrSpace, start: VirtAddr, end: VirtAddr) -> AxResult {
+        let Some(mapped_file) = self.file.as_ref().filter(|_| self.write_back) else {
+            return Ok(());
+        };
+        let file = mapped_file.inner().lock();
+        let size = file.get_attr()?.size();
+        let mut page = start.max(self.start);
+        let mut result = Ok(());
+        while page < end.min(self.end) && result.is_ok() {
+            let offset = self.file_offset(page);
+            if offset >= size {
+                break;
+            }
+            if let Some(paddr) = mapped_frame(uspace, page) {
+                let len = (size - offset).min(PAGE_SIZE_4K as u64) as usize;
+                let data = unsafe {
+                    core::slice::from_raw_parts(axhal::mem::phys_to_virt(paddr).as_ptr(), len)
+                };
+                result = file.write_at(offset, data).map(|_| ());
+            }
+            page += PAGE_SIZE_4K;
+        }
+        drop(file);
+        crate::syscall_imp::invalidate_cached(mapped_file.path());
+        result
+    }
+}
+
+/// Split the file mapping containing `addr`, if any, into the parts before and after it.
+fn split_file_mappings(mappings: &mut Vec<FileMapping>, addr: VirtAddr) {
+    if let Some(mapping) = mappings
+        .iter_mut()
+        .find(|mapping| mapping.start < addr && addr < mapping.end)
+    {
+        let tail = FileMapping {
+            start: addr,
+            offset: mapping.file_offset(addr),
+            ..mapping.clone()
+        };
+        mapping.end = addr;
+        mappings.push(tail);
+    }
+}
+
+/// Remove `[start, end)` from the file mappings, writing the changes to shared
+/// ones back to their files first.
+///
+/// Mappings partly in the range are split, keeping the parts outside it.
+pub(crate) fn unmap_file_mappings(
+    mappings: &mut Vec<FileMapping>,
+    uspace: &AddrSpace,
+    start: VirtAddr,
+    end: VirtAddr,
+) -> AxResult {
+    split_file_mappings(mappings, start);
+    split_file_mappings(mappings, end);
+    let inside = |mapping: &FileMapping| start <= mapping.start && mapping.end <= end;
+    for mapping in mappings.iter().filter(|mapping| inside(mapping)) {
+        mapping.sync(uspace, start, end)?;
+    }
+    mappings.retain(|mapping| !inside(mapping));
+    Ok(())
+}
+
+/// Write the changes to the shared file mappings in `[start, end)` back to
+/// their files, like `msync` does.
+///
+/// Every page in the range must be mapped, or it fails with `NoMemory` before
+/// anything is written.
+pub(crate) fn sync_file_mappings(
+    mappings: &[FileMapping],
+    uspace: &AddrSpace,
+    start: VirtAddr,
+    end: VirtAddr,
+) -> AxResult {
+    if start < uspace.base() || end > uspace.end() {
+        return Err(AxError::NoMemory);
+    }
+    let limit = VirtAddrRange::new(uspace.base(), uspace.end());
+    let mut page = start;
+    while page < end {
+        if uspace.find_free_area(page, PAGE_SIZE_4K, limit) == Some(page) {
+            return Err(AxError::NoMemory);
+        }
+        page += PAGE_SIZE_4K;
+    }
+    for mapping in mappings
+        .iter()
+        .filter(|mapping| mapping.start < end && start < mapping.end)
+    {
+        mapping.sync(uspace, start, end)?;
+    }
+    Ok(())
+}
+
+/// Allocate the page at `page` if it is left for a page fault to allocate, and
+/// fill it if it belongs to a file mapping.
+///
+/// Returns whether the page is mapped at all.
+fn populate_page(
+    mappings: &[FileMapping],
+    uspace: &mut AddrSpace,
+    page: VirtAddr,
+) -> AxResult<bool> {
+    if mapped_frame(uspace, page).is_some() {
+        return Ok(true);
+    }
+    if !uspace.handle_page_fault(page, MappingFlags::empty()) {
+        return Ok(false);
+    }
+    if let Some(mapping) = mappings
+        .iter()
+        .find(|mapping| (mapping.start..mapping.end).contains(&page))
+    {
+        mapping.fill_page(uspace, page)?;
+    }
+    Ok(true)
+}
+
+/// Check that `[start, start + len)` is mapped in the address space of the task
+/// with `ext`, so that the kernel can access it through raw pointers.
+///
+/// Its pages are populated like user accesses do, filling those of file
+/// mappings from their files, which `AddrSpace::alloc_for_lazy` doesn't do.
+/// The address space is locked before the mappings, like `write_user_bytes`.
+pub(crate) fn check_user_range(ext: &TaskExt, start: VirtAddr, len: usize) -> AxResult {
+    let end = start
+        .as_usize()
+        .checked_add(len)
+        .ok_or(AxError::BadAddress)?;
+    let mut uspace = ext.aspace.lock();
+    let mappings = ext.file_mappings.lock();
+    for page in (start.align_down_4k().as_usize()..end).step_by(PAGE_SIZE_4K) {
+        if !populate_page(&mappings, &mut uspace, page.into())? {
+            return Err(AxError::BadAddress);
+        }
+    }
+    Ok(())
+}
+
+/// Get the flags of the mapping at `page`, or `None` if nothing is mapped there.
+pub(crate) fn mapping_flags(
+    mappings: &[FileMapping],
+    uspace: &mut AddrSpace,
+    page: VirtAddr,
+) -> AxResult<Option<MappingFlags>> {
+    if !populate_page(mappings, uspace, page)? {
+        return Ok(None);
+    }
+    Ok(page_flags(uspace, page))
+}
+
+/// Move the pages in `[from, from + size)` to `to`, which has been mapped for
+/// page faults to allocate, along with the file mappings among them.
+///
+/// Only the pages that have been accessed are copied, and the rest are left
+/// for page faults as they were.
+pub(crate) fn move_pages(
+    mappings: &mut Vec<FileMapping>,
+    uspace: &mut AddrSpace,
+    from: VirtAddr,
+    to: VirtAddr,
+    size: usize,
+) -> AxResult {
+    for offset in (0..size).step_by(PAGE_SIZE_4K) {
+        if let Some(paddr) = mapped_frame(uspace, from + offset) {
+            let data = unsafe {
+                core::slice::from_raw_parts(axhal::mem::phys_to_virt(paddr).as_ptr(), PAGE_SIZE_4K)
+            };
+            if !uspace.handle_page_fault(to + offset, MappingFlags::empty()) {
+                return Err(AxError::BadAddress);
+            }
+            uspace.write(to + offset, data)?;
+        }
+    }
+    split_file_mappings(mappings, from);
+    split_file_mappings(mappings, from + size);
+    for mapping in mappings
+        .iter_mut()
+        .filter(|mapping| from <= mapping.start && mapping.end <= from + size)
+    {
+        mapping.start = to + (mapping.start - from);
+        mapping.end = to + (mapping.end - from);
+    }
+    Ok(())
+}
+
+/// Free the frames backing the pages in `[start, end)`, leaving the pages for
+/// page faults to allocate again.
+///
+/// The changes to shared file mappings are written back to their files first,
+/// so that they are read back, while anonymous pages read zeros afterwards.
+pub(crate) fn discard_pages(
+    mappings: &[FileMapping],
+    uspace: &mut AddrSpace,
+    start: VirtAddr,
+    end: VirtAddr,
+) -> AxResult {
+    for mapping in mappings
+        .iter()
+        .filter(|mapping| mapping.start < end && start < mapping.end)
+    {
+        mapping.sync(uspace, start, end)?;
+    }
+    let mut page = start;
+    while page < end {
+        if let Some(flags) = page_flags(uspace, page) {
+            unmap_pages(uspace, page, PAGE_SIZE_4K)?;
+            uspace.map_alloc(page, PAGE_SIZE_4K, flags, false)?;
+        }
+        page += PAGE_SIZE_4K;
+    }
+    Ok(())
 }
 
+/// Change the protection of the pages in `[start, end)` to `flags`, like
+/// `AddrSpace::protect`.
+///
+/// Every page in the range must be mapped, and a shared mapping of a file that
+/// is not open for writing can't be made writable. The pages that are left for
+/// page faults stay so, and are mapped again with `flags` for the faults to
+/// allocate them with, so that reserving a large region and then changing its
+/// protection doesn't commit its memory. Private pages shared copy-on-write get
+/// frames of their own, since they may become writable.
+pub(crate) fn protect_pages(
+    mappings: &mut Vec<FileMapping>,
+    uspace: &mut AddrSpace,
+    start: VirtAddr,
+    end: VirtAddr,
+    flags: MappingFlags,
+) -> AxResult {
+    let writable = flags.contains(MappingFlags::WRITE);
+    let overlaps = |mapping: &FileMapping| mapping.start < end && start < mapping.end;
+    if writable
+        && mappings
+            .iter()
+            .any(|mapping| overlaps(mapping) && mapping.shared && !mapping.file_writable)
+    {
+        return Err(AxError::PermissionDenied);
+    }
+    if start < uspace.base() || end > uspace.end() {
+        return Err(AxError::NoMemory);
+    }
+    let limit = VirtAddrRange::new(uspace.base(), uspace.end());
+    let mut page = start;
+    while page < end {
+        if uspace.find_free_area(page, PAGE_SIZE_4K, limit) == Some(page) {
+            return Err(AxError::NoMemory);
+        }
+        page += PAGE_SIZE_4K;
+    }
+    // Handle the runs of populated pages and of pages left for page faults in
+    // turn, so that the areas are split no more than needed.
+    let mut run_start = start;
+    while run_start < end {
+        let populated = mapped_frame(uspace, run_start).is_some();
+        let mut run_end = run_start + PAGE_SIZE_4K;
+        while run_end < end && mapped_frame(uspace, run_end).is_some() == populated {
+            run_end += PAGE_SIZE_4K;
+        }
+        if populated {
+            let mut cow = COW.lock();
+            for page in (run_start.as_usize()..run_end.as_usize()).step_by(PAGE_SIZE_4K) {
+                cow.set_flags(uspace, page.into(), flags)?;
+            }
+            drop(cow);
+            uspace.protect(run_start, run_end - run_start, flags)?;
+        } else {
+            uspace.unmap(run_start, run_end - run_start)?;
+            uspace.map_alloc(run_start, run_end - run_start, flags, false)?;
+        }
+        run_start = run_end;
+    }
+    split_file_mappings(mappings, start);
+    split_file_mappings(mappings, end);
+    for mapping in mappings.iter_mut().filter(|mapping| overlaps(mapping)) {
+        mapping.write_back |= mapping.shared && writable;
+    }
+    Ok(())
+}
+
+/// A page that maps a frame which is shared copy-on-write, or has been.
+#[derive(Clone, Copy)]
+struct CowPage {
+    frame: PhysAddr,
+    /// The flags the page is meant to have, which lack `WRITE` in the page
+    /// table while the frame is shared by private pages.
+    flags: MappingFlags,
+    /// Whether it belongs to a `MAP_SHARED` mapping, whose frame stays shared
+    /// when it is written.
+    shared: bool,
+}
+
+/// The frames shared copy-on-write between address spaces by `fork_aspace`.
+///
+/// The allocated areas of `axmm` free the frames of their pages when they are
+/// unmapped, so a frame can't be owned by more than one of them. The frames here
+/// are instead taken out of the areas owning them by `detach_frame`, mapped as
+/// linear mappings, which `axmm` leaves alone, and freed once no page maps them
+/// any more.
+struct CowTable {
+    /// The pages mapping the frames, keyed by the page table root of their
+    /// address space and their address.
+    pages: BTreeMap<(PhysAddr, VirtAddr), CowPage>,
+    /// The number of pages mapping each frame.
+    refs: BTreeMap<PhysAddr, usize>,
+}
+
+static COW: Mutex<CowTable> = Mutex::new(CowTable {
+    pages: BTreeMap::new(),
+    refs: BTreeMap::new(),
+});
+
+fn alloc_frame() -> AxResult<PhysAddr> {
+    let vaddr = axalloc::global_allocator()
+        .alloc_pages(1, PAGE_SIZE_4K)
+        .map_err(|_| AxError::NoMemory)?;
+    Ok(axhal::mem::virt_to_phys(vaddr.into()))
+}
+
+fn dealloc_frame(frame: PhysAddr) {
+    axalloc::global_allocator().dealloc_pages(axhal::mem::phys_to_virt(frame).as_usize(), 1);
+}
+
+/// Take the frame mapped at `page` out of the allocated area owning it, and
+/// unmap the page, without freeing the frame.
+///
+/// An allocated area frees only the frames it finds in the page table when it
+/// is unmapped, so the page is unmapped from the page table first.
+fn detach_frame(uspace: &mut AddrSpace, page: VirtAddr) -> AxResult<PhysAddr> {
+    // Safety: the address space is borrowed mutably, so nothing else uses its
+    // page table, and the page is unmapped from its area right after.
+    let page_table = unsafe { &mut *(uspace.page_table() as *const PageTable as *mut PageTable) };
+    let (frame, _, tlb) = page_table.unmap(page).map_err(|_| AxError::BadAddress)?;
+    tlb.ignore();
+    uspace.unmap(page, PAGE_SIZE_4K)?;
+    Ok(frame)
+}
+
+/// Copy the contents of the frame `from` to the frame `to`.
+fn copy_frame(from: PhysAddr, to: PhysAddr) {
+    unsafe {
+        core::ptr::copy_nonoverlapping(
+            axhal::mem::phys_to_virt(from).as_ptr(),
+            axhal::mem::phys_to_virt(to).as_mut_ptr(),
+            PAGE_SIZE_4K,
+        )
+    };
+}
+
+impl CowTable {
+    /// Map `frame` at `page` in `uspace` with `page_flags` in the page table.
+    fn map(
+        &mut self,
+        uspace: &mut AddrSpace,
+        page: VirtAddr,
+        cow_page: CowPage,
+        page_flags: MappingFlags,
+    ) -> AxResult {
+        uspace.map_linear(page, cow_page.frame, PAGE_SIZE_4K, page_flags)?;
+        self.pages
+            .insert((uspace.page_table_root(), page), cow_page);
+        *self.refs.entry(cow_page.frame).or_default() += 1;
+        Ok(())
+    }
+
+    /// Forget the pages in `[start, end)` of the address space with the page
+    /// table root `root`, which have been unmapped, freeing the frames that no
+    /// page maps any more.
+    fn release(&mut self, root: PhysAddr, start: VirtAddr, end: VirtAddr) {
+        let keys: Vec<_> = self
+            .pages
+            .range((root, start)..(root, end))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in keys {
+            let frame = self.pages.remove(&key).unwrap().frame;
+            let refs = self.refs.get_mut(&frame).unwrap();
+            *refs -= 1;
+            if *refs == 0 {
+                self.refs.remove(&frame);
+                dealloc_frame(frame);
+            }
+        }
+    }
+
+    /// Give the page at `page` a copy of its frame, if the frame is shared by
+    /// other pages and the page is private.
+    ///
+    /// Returns the page, which maps a frame of its own unless it is shared.
+    fn unshare(&mut self, uspace: &mut AddrSpace, page: VirtAddr) -> AxResult<Option<CowPage>> {
+        let Some(&cow_page) = self.pages.get(&(uspace.page_table_root(), page)) else {
+            return Ok(None);
+        };
+        if cow_page.shared || self.refs[&cow_page.frame] == 1 {
+            return Ok(Some(cow_page));
+        }
+        let frame = alloc_frame()?;
+        copy_frame(cow_page.frame, frame);
+        uspace.unmap(page, PAGE_SIZE_4K)?;
+        self.release(uspace.page_table_root(), page, page + PAGE_SIZE_4K);
+        let cow_page = CowPage { frame, ..cow_page };
+        self.map(uspace, page, cow_page, cow_page.flags)?;
+        Ok(Some(cow_page))
+    }
+
+    /// Record that the protection of the page at `page` changes to `flags`,
+    /// giving it a frame of its own first if it is private.
+    fn set_flags(
+        &mut self,
+        uspace: &mut AddrSpace,
+        page: VirtAddr,
+        flags: MappingFlags,
+    ) -> AxResult {
+        if self.unshare(uspace, page)?.is_some() {
+            self.pages
+                .get_mut(&(uspace.page_table_root(), page))
+                .unwrap()
+                .flags = flags;
+        }
+        Ok(())
+    }
+}
+
+/// Get the flags the page at `page` is meant to have, or `None` if it is not
+/// populated.
+fn page_flags(uspace: &AddrSpace, page: VirtAddr) -> Option<MappingFlags> {
+    if let Some(cow_page) = COW.lock().pages.get(&(uspace.page_table_root(), page)) {
+        return Some(cow_page.flags);
+    }
+    uspace
+        .page_table()
+        .query(page)
+        .ok()
+        .map(|(_, flags, _)| flags)
+        .filter(|flags| !flags.is_empty())
+}
+
+/// Unmap `[start, start + size)` from `uspace`, like `AddrSpace::unmap`, freeing
+/// the frames shared copy-on-write that are no longer mapped anywhere.
+pub(crate) fn unmap_pages(uspace: &mut AddrSpace, start: VirtAddr, size: usize) -> AxResult {
+    uspace.unmap(start, size)?;
+    COW.lock()
+        .release(uspace.page_table_root(), start, start + size);
+    Ok(())
+}
+
+/// Unmap all the user areas from `uspace`, like `AddrSpace::unmap_user_areas`,
+/// freeing the frames shared copy-on-write that are no longer mapped anywhere.
+pub(crate) fn unmap_user_pages(uspace: &mut AddrSpace) -> AxResult {
+    uspace.unmap_user_areas()?;
+    COW.lock()
+        .release(uspace.page_table_root(), uspace.base(), uspace.end());
+    Ok(())
+}
+
+/// Copy the mappings of `parent` to `child`, which is empty, sharing the
+/// populated pages copy-on-write.
+///
+/// The populated pages are mapped read-only in both address spaces, unless
+/// they belong to `MAP_SHARED` mappings, and a page is copied when either
+/// writes to it. The pages of `MAP_SHARED` mappings are all populated first, so
+/// that both keep seeing each other's changes to any of them, and the other
+/// pages that are not populated are left for page faults in the child as well.
+/// A frame shared for the first time is taken over from the allocated area
+/// owning it rather than copied.
+pub(crate) fn fork_aspace(
+    mappings: &[FileMapping],
+    parent: &mut AddrSpace,
+    child: &mut AddrSpace,
+) -> AxResult {
+    for mapping in mappings.iter().filter(|mapping| mapping.shared) {
+        for page in (mapping.start.as_usize()..mapping.end.as_usize()).step_by(PAGE_SIZE_4K) {
+            populate_page(mappings, parent, page.into())?;
+        }
+    }
+    for (start, end, _) in mapped_regions(parent) {
+        // Unpopulated regions are given the flags of their first page.
+        populate_page(mappings, parent, start)?;
+        let region_flags = page_flags(parent, start).unwrap_or(MappingFlags::empty());
+        child.map_alloc(start, end - start, region_flags, false)?;
+
+        let mut cow = COW.lock();
+        for page in (start.as_usize()..end.as_usize()).step_by(PAGE_SIZE_4K) {
+            let page = VirtAddr::from(page);
+            let Ok((_, flags, _)) = parent.page_table().query(page) else {
+                continue;
+            };
+            if flags.is_empty() {
+                continue;
+            }
+            let cow_page = match cow.pages.get(&(parent.page_table_root(), page)) {
+                Some(&cow_page) => cow_page,
+                None => {
+                    let cow_page = CowPage {
+                        frame: detach_frame(parent, page)?,
+                        flags,
+                        shared: mappings.iter().any(|mapping| {
+                            mapping.shared && (mapping.start..mapping.end).contains(&page)
+                        }),
+                    };
+                    cow.map(parent, page, cow_page, cow_page.flags)?;
+                    cow_page
+                }
+            };
+            let page_flags = if cow_page.shared {
+                cow_page.flags
+            } else {
+                cow_page.flags - MappingFlags::WRITE
+            };
+            parent.protect(page, PAGE_SIZE_4K, page_flags)?;
+            child.unmap(page, PAGE_SIZE_4K)?;
+            cow.map(child, page, cow_page, page_flags)?;
+        }
+    }
+    axhal::arch::flush_tlb(None);
+    Ok(())
+}
+
+/// Handle a write to the page at `page`, if it is a private page that maps a
+/// frame shared copy-on-write, by giving it a frame of its own and making it
+/// writable.
+///
+/// Returns whether the page is such a page.
+fn handle_cow_fault(uspace: &mut AddrSpace, page: VirtAddr) -> bool {
+    let mut cow = COW.lock();
+    match cow.unshare(uspace, page) {
+        Ok(Some(cow_page)) if cow_page.flags.contains(MappingFlags::WRITE) => {
+            let handled = uspace.protect(page, PAGE_SIZE_4K, cow_page.flags).is_ok();
+            axhal::arch::flush_tlb(Some(page));
+            handled
+        }
+        _ => false,
+    }
+}
+
+/// Write `data` to `start` in `uspace` like a write from user space, which
+/// allocates the pages left for page faults and copies those shared
+/// copy-on-write.
+pub(crate) fn write_pages(
+    mappings: &[FileMapping],
+    uspace: &mut AddrSpace,
+    start: VirtAddr,
+    data: &[u8],
+) -> AxResult {
+    let mut page = start.align_down_4k();
+    while page < start + data.len() {
+        if !populate_page(mappings, uspace, page)? {
+            return Err(AxError::BadAddress);
+        }
+        handle_cow_fault(uspace, page);
+        page += PAGE_SIZE_4K;
+    }
+    uspace.write(start, data)
+}
+
+/// Write `data` to `start` in the address space of the task with `ext`, like
+/// `write_pages`.
+///
+/// The address space is locked before the mappings, in the same order as the
+/// page fault handler, so that it can't deadlock with a fault of another
+/// thread sharing them.
+pub(crate) fn write_user_bytes(ext: &TaskExt, start: VirtAddr, data: &[u8]) -> AxResult {
+    let mut uspace = ext.aspace.lock();
+    write_pages(&ext.file_mappings.lock(), &mut uspace, start, data)
+}
+
+/// Get the physical address that `vaddr` in `uspace` maps to, preparing its
+/// page like a write from user space does, so that the page stays where it is
+/// until it is unmapped.
+pub(crate) fn user_paddr(
+    mappings: &[FileMapping],
+    uspace: &mut AddrSpace,
+    vaddr: VirtAddr,
+) -> AxResult<PhysAddr> {
+    let page = vaddr.align_down_4k();
+    if !populate_page(mappings, uspace, page)? {
+        return Err(AxError::BadAddress);
+    }
+    handle_cow_fault(uspace, page);
+    let frame = mapped_frame(uspace, page).ok_or(AxError::BadAddress)?;
+    Ok(frame + (vaddr - page))
+}
+
+/// Handle a page fault at `vaddr`, from user space or from the kernel accessing
+/// user memory.
+///
+/// The kernel writes to user memory through raw pointers once `check_user_range`
+/// has checked it, which doesn't know about the pages shared copy-on-write, so
+/// its faults on the user addresses of the current task are handled like those
+/// of user space. A user access that can't be handled terminates the task with
+/// `SIGSEGV`, while a kernel access is left to the kernel to panic. The pending
+/// signals are delivered as the task returns to user space from the fault.
 #[register_trap_handler(PAGE_FAULT)]
 fn handle_page_fault(vaddr: VirtAddr, access_flags: MappingFlags, is_user: bool) -> bool {
+    let curr = axtask::current();
+    // Safety: We only check whether the task extended data is null and do not access it.
+    if !is_user && unsafe { curr.task_ext_ptr() }.is_null() {
+        return false;
+    }
+    let mut uspace = curr.task_ext().aspace.lock();
+    if !is_user && !(uspace.base() <= vaddr && vaddr < uspace.end()) {
+        return false;
+    }
+    let page = vaddr.align_down_4k();
+    let newly_mapped = mapped_frame(&uspace, page).is_none();
+    let handled = (access_flags.contains(MappingFlags::WRITE)
+        && handle_cow_fault(&mut uspace, page))
+        || uspace.handle_page_fault(vaddr, access_flags)
+            && (!newly_mapped
+                || curr
+                    .task_ext()
+                    .file_mappings
+                    .lock()
+                    .iter()
+                    .find(|mapping| (mapping.start..mapping.end).contains(&page))
+                    .is_none_or(|mapping| mapping.fill_page(&mut uspace, page).is_ok()));
+    drop(uspace);
+    if !handled && is_user {
+        warn!(
+            "{}: segmentation fault at {:#x}, exit!",
+            curr.id_name(),
+            vaddr
+        );
+        crate::task::exit_on_signal(crate::signal::SIGSEGV);
+    }
     if is_user {
-        if !axtask::current()
-            .task_ext()
-            .aspace
-            .lock()
-            .handle_page_fault(vaddr, access_flags)
-        {
-            warn!(
-                "{}: segmentation fault at {:#x}, exit!",
-                axtask::current().id_name(),
-                vaddr
-            );
-            axtask::exit(-1);
-        }
-        true
-    } else {
-        false
-    }
-}
\ No newline at end of file
+        crate::syscall_imp::exit_if_group_exiting();
+        crate::signal::handle_trap_signals();
+    }
+    handled
+}
@@ -0,0 +1,560 @@
+//! Signals.
+//!
+//! A signal is sent either to a thread or to a process, in which case any of
+//! its threads that doesn't block it may take it. Signals are delivered when a
+//! thread returns to user space from a syscall or a page fault. A thread
+//! blocked in a syscall checks for signals as it waits, and the syscall fails
+//! with `EINTR` once one arrives. The interrupts are handled by axhal without a
+//! hook on their return, so a thread running in user space sees a signal when
+//! it next enters the kernel.
+//!
+//! To run a handler, the interrupted context is saved in a signal frame pushed
+//! on the user stack, and the thread returns into the handler instead, which
+//! returns in turn to code calling `rt_sigreturn` to restore the context.
+
+use core::{
+    mem::offset_of,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use axerrno::{LinuxError, LinuxResult};
+use axhal::{
+    arch::{TrapFrame, UspaceContext},
+    time::monotonic_time,
+};
+use axtask::{TaskExtRef, WaitQueue, current};
+use memory_addr::VirtAddr;
+
+use crate::{
+    ctypes::{MContext, SigAction, SigInfo, UContext},
+    mm::{SIGNAL_TRAMPOLINE, check_user_range},
+    task::{TaskExt, exit_on_signal, read_trapframe_from_kstack, write_trapframe_to_kstack},
+};
+
+/// The number of signals, which are numbered from 1.
+pub const NSIG: usize = 64;
+
+pub const SIGKILL: u32 = 9;
+pub const SIGSEGV: u32 = 11;
+pub const SIGPIPE: u32 = 13;
+pub const SIGCHLD: u32 = 17;
+pub const SIGCONT: u32 = 18;
+pub const SIGSTOP: u32 = 19;
+pub const SIGTSTP: u32 = 20;
+pub const SIGTTIN: u32 = 21;
+pub const SIGTTOU: u32 = 22;
+pub const SIGURG: u32 = 23;
+pub const SIGWINCH: u32 = 28;
+
+const SIG_DFL: usize = 0;
+const SIG_IGN: usize = 1;
+
+const SA_SIGINFO: usize = 0x4;
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const SA_RESTORER: usize = 0x0400_0000;
+const SA_NODEFER: usize = 0x4000_0000;
+const SA_RESETHAND: usize = 0x8000_0000;
+
+/// `si_code` of a signal sent by a process.
+const SI_USER: i32 = 0;
+
+/// How far the instruction pointer of a trap frame in a syscall is from where
+/// the task resumes, since the pc is advanced past the syscall instruction
+/// only after the syscall returns on some architectures.
+#[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+const SYSCALL_IP_SKIP: usize = 4;
+#[cfg(not(any(target_arch = "riscv64", target_arch = "loongarch64")))]
+const SYSCALL_IP_SKIP: usize = 0;
+
+/// The area below the stack pointer that the handler must not overwrite.
+#[cfg(target_arch = "x86_64")]
+const RED_ZONE: usize = 128;
+#[cfg(not(target_arch = "x86_64"))]
+const RED_ZONE: usize = 0;
+
+/// How often a thread blocked in a syscall checks for signals, since the
+/// sender doesn't know what it waits on to wake it up.
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The signals that can be neither blocked nor caught.
+const UNBLOCKABLE: u64 = sig_bit(SIGKILL) | sig_bit(SIGSTOP);
+
+/// The bit of the signal `signo` in a signal set.
+pub const fn sig_bit(signo: u32) -> u64 {
+    1 << (signo - 1)
+}
+
+/// The actions taken on the signals, which are shared by the threads of a process.
+#[derive(Clone)]
+pub struct SignalActions([SigAction; NSIG]);
+
+/// All the signals take the default action.
+impl Default for SignalActions {
+    fn default() -> Self {
+        Self([SigAction::default(); NSIG])
+    }
+}
+
+impl SignalActions {
+    pub fn get(&self, signo: u32) -> SigAction {
+        self.0[signo as usize - 1]
+    }
+
+    /// Set the action taken on `signo`, which must not be `SIGKILL` or `SIGSTOP`.
+    ///
+    /// Like the signals themselves, they can't be blocked while the handler runs.
+    pub fn set(&mut self, signo: u32, mut action: SigAction) {
+        action.mask &= !UNBLOCKABLE;
+        self.0[signo as usize - 1] = action;
+    }
+
+    /// The actions that a new image starts with on exec, when the handlers are
+    /// gone with the old one: the ignored signals stay ignored, and the others
+    /// take the default action.
+    pub fn for_exec(&self) -> Self {
+        Self(self.0.map(|action| SigAction {
+            handler: if action.handler == SIG_IGN {
+                SIG_IGN
+            } else {
+                SIG_DFL
+            },
+            ..Default::default()
+        }))
+    }
+}
+
+/// A set of pending signals, each of which is pending at most once.
+#[derive(Default)]
+pub struct PendingSignals(AtomicU64);
+
+impl PendingSignals {
+    pub fn add(&self, signo: u32) {
+        self.0.fetch_or(sig_bit(signo), Ordering::AcqRel);
+    }
+
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Take the lowest pending signal that is not in `mask`.
+    fn take(&self, mask: u64) -> Option<u32> {
+        loop {
+            let deliverable = self.0.load(Ordering::Acquire) & !mask;
+            if deliverable == 0 {
+                return None;
+            }
+            let signo = deliverable.trailing_zeros() + 1;
+            if self.0.fetch_and(!sig_bit(signo), Ordering::AcqRel) & sig_bit(signo) != 0 {
+                return Some(signo);
+            }
+        }
+    }
+}
+
+/// Set the signals blocked by the current thread, except those that can't be.
+pub fn set_signal_mask(mask: u64) {
+    let curr = current();
+    curr.task_ext()
+        .signal_mask
+        .store(mask & !UNBLOCKABLE, Ordering::Release);
+}
+
+/// Block the signals in `mask` instead until the current thread returns from
+/// the syscall it is in, as the syscalls waiting with a signal mask do.
+///
+/// The signals that `mask` lets through are delivered before the original mask
+/// is restored, and their handlers return to the original mask.
+pub fn set_temporary_signal_mask(mask: u64) {
+    let curr = current();
+    let ext = curr.task_ext();
+    ext.saved_signal_mask
+        .lock()
+        .get_or_insert(ext.signal_mask.load(Ordering::Acquire));
+    set_signal_mask(mask);
+}
+
+/// What is pushed on the user stack to run a signal handler.
+#[repr(C)]
+struct SignalFrame {
+    info: SigInfo,
+    /// The interrupted context, which is where the task resumes.
+    ucontext: UContext,
+}
+
+/// The general registers of a trap frame, in the order of `gregs` in
+/// `mcontext_t`, which can be assigned to as well.
+#[cfg(target_arch = "x86_64")]
+macro_rules! gregs {
+    ($tf:expr) => {
+        [
+            $tf.r8, $tf.r9, $tf.r10, $tf.r11, $tf.r12, $tf.r13, $tf.r14, $tf.r15, $tf.rdi, $tf.rsi,
+            $tf.rbp, $tf.rbx, $tf.rdx, $tf.rax, $tf.rcx, $tf.rsp, $tf.rip,
+        ]
+    };
+}
+#[cfg(target_arch = "riscv64")]
+macro_rules! gregs {
+    ($pc:expr, $r:expr) => {
+        [
+            $pc, $r.ra, $r.sp, $r.gp, $r.tp, $r.t0, $r.t1, $r.t2, $r.s0, $r.s1, $r.a0, $r.a1,
+            $r.a2, $r.a3, $r.a4, $r.a5, $r.a6, $r.a7, $r.s2, $r.s3, $r.s4, $r.s5, $r.s6, $r.s7,
+            $r.s8, $r.s9, $r.s10, $r.s11, $r.t3, $r.t4, $r.t5, $r.t6,
+        ]
+    };
+}
+#[cfg(target_arch = "loongarch64")]
+macro_rules! gregs {
+    ($r:expr) => {
+        [
+            $r.zero, $r.ra, $r.tp, $r.sp, $r.a0, $r.a1, $r.a2, $r.a3, $r.a4, $r.a5, $r.a6, $r.a7,
+            $r.t0, $r.t1, $r.t2, $r.t3, $r.t4, $r.t5, $r.t6, $r.t7, $r.t8, $r.u0, $r.fp, $r.s0,
+            $r.s1, $r.s2, $r.s3, $r.s4, $r.s5, $r.s6, $r.s7, $r.s8,
+        ]
+    };
+}
+
+/// The flags in `rflags` that user space may change.
+#[cfg(target_arch = "x86_64")]
+const USER_RFLAGS: u64 = 0x50dd5;
+/// The condition flags in `pstate`, which are all that user space may change.
+#[cfg(target_arch = "aarch64")]
+const USER_PSTATE: u64 = 0xf000_0000;
+
+/// Save the user registers of `tf` in the layout of `mcontext_t`.
+fn save_mcontext(tf: &TrapFrame, mcontext: &mut MContext) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        mcontext.gregs[..17].copy_from_slice(&gregs!(tf));
+        mcontext.gregs[17] = tf.rflags;
+        mcontext.gregs[18] = tf.cs | tf.ss << 48;
+        mcontext.gregs[19] = tf.error_code;
+        mcontext.gregs[20] = tf.vector;
+    }
+    #[cfg(target_arch = "riscv64")]
+    {
+        mcontext.gregs = gregs!(tf.sepc, tf.regs);
+    }
+    #[cfg(target_arch = "loongarch64")]
+    {
+        mcontext.pc = tf.era;
+        mcontext.gregs = gregs!(tf.regs);
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        mcontext.regs = tf.r;
+        mcontext.sp = tf.usp;
+        mcontext.pc = tf.elr;
+        mcontext.pstate = tf.spsr;
+    }
+}
+
+/// Restore the user registers of `tf` from `mcontext`, keeping the state that
+/// user space may not change: the segments and most of `rflags` on x86_64,
+/// `sstatus` on riscv64, `pstate` but the condition flags on aarch64, and
+/// `prmd` on loongarch64.
+fn restore_mcontext(tf: &mut TrapFrame, mcontext: &MContext) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let regs: [u64; 17] = mcontext.gregs[..17].try_into().unwrap();
+        gregs!(tf) = regs;
+        tf.rflags = (tf.rflags & !USER_RFLAGS) | (mcontext.gregs[17] & USER_RFLAGS);
+    }
+    #[cfg(target_arch = "riscv64")]
+    {
+        gregs!(tf.sepc, tf.regs) = mcontext.gregs;
+    }
+    #[cfg(target_arch = "loongarch64")]
+    {
+        gregs!(tf.regs) = mcontext.gregs;
+        tf.regs.zero = 0;
+        tf.era = mcontext.pc;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        tf.r = mcontext.regs;
+        tf.usp = mcontext.sp;
+        tf.elr = mcontext.pc;
+        tf.spsr = (tf.spsr & !USER_PSTATE) | (mcontext.pstate & USER_PSTATE);
+    }
+}
+
+/// Whether the default action of `signo` is to ignore it. Stopping a process
+/// is not supported, so the signals that would stop it are ignored as well.
+fn ignored_by_default(signo: u32) -> bool {
+    matches!(
+        signo,
+        SIGCHLD | SIGCONT | SIGURG | SIGWINCH | SIGSTOP | SIGTSTP | SIGTTIN | SIGTTOU
+    )
+}
+
+/// Whether taking `signo` with `action` does nothing.
+fn ignored(signo: u32, action: &SigAction) -> bool {
+    action.handler == SIG_IGN || (action.handler == SIG_DFL && ignored_by_default(signo))
+}
+
+/// Whether a signal interrupts the syscall that the current thread is blocked
+/// in, which is one that it doesn't block nor ignore, or its process exiting.
+pub fn signal_pending() -> bool {
+    let curr = current();
+    let ext = curr.task_ext();
+    if ext.thread_group.exit_status().is_some() {
+        return true;
+    }
+    let pending = (ext.pending_signals.get() | ext.thread_group.pending_signals.get())
+        & !ext.signal_mask.load(Ordering::Acquire);
+    if pending == 0 {
+        return false;
+    }
+    let actions = ext.signal_actions.lock();
+    (1..=NSIG as u32)
+        .filter(|&signo| pending & sig_bit(signo) != 0)
+        .any(|signo| !ignored(signo, &actions.get(signo)))
+}
+
+/// Wait on `wait_queue` until `condition` holds, which is checked first, and
+/// return whether it does, which is false if `timeout` expires first.
+///
+/// The wait is interrupted by a signal, in which case it fails with `EINTR`.
+pub fn wait_interruptible(
+    wait_queue: &WaitQueue,
+    timeout: Option<Duration>,
+    condition: impl Fn() -> bool,
+) -> LinuxResult<bool> {
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+    loop {
+        if condition() {
+            return Ok(true);
+        }
+        if signal_pending() {
+            return Err(LinuxError::EINTR);
+        }
+        let wait = match deadline {
+            Some(deadline) => {
+                let now = monotonic_time();
+                if now >= deadline {
+                    return Ok(false);
+                }
+                (deadline - now).min(SIGNAL_CHECK_INTERVAL)
+            }
+            None => SIGNAL_CHECK_INTERVAL,
+        };
+        wait_queue.wait_timeout_until(wait, &condition);
+    }
+}
+
+/// Take the next signal for the current thread to handle, the ones sent to it
+/// first, and the action to take on it.
+fn take_signal(ext: &TaskExt) -> Option<(u32, SigAction)> {
+    let mask = ext.signal_mask.load(Ordering::Acquire);
+    let signo = ext
+        .pending_signals
+        .take(mask)
+        .or_else(|| ext.thread_group.pending_signals.take(mask))?;
+    let mut actions = ext.signal_actions.lock();
+    let action = actions.get(signo);
+    if action.flags & SA_RESETHAND != 0 && action.handler > SIG_IGN {
+        actions.set(signo, SigAction::default());
+    }
+    Some((signo, action))
+}
+
+/// Deliver the pending signals that the current thread doesn't block, as it
+/// returns from a syscall with `ret`, and return what it returns with in the end.
+///
+/// Ignored signals are discarded, and a signal whose default action is to
+/// terminate the process does so. For a signal with a handler, the thread
+/// returns into the handler, with the syscall returning `ret` once the handler
+/// returns. The signal mask that the syscall has replaced is restored then.
+pub fn handle_signals(ret: isize) -> isize {
+    let curr = current();
+    let saved_mask = curr.task_ext().saved_signal_mask.lock().take();
+    while let Some((signo, action)) = take_signal(curr.task_ext()) {
+        match action.handler {
+            _ if ignored(signo, &action) => {}
+            SIG_DFL => exit_on_signal(signo),
+            _ => return enter_handler(signo, &action, Some(ret), saved_mask),
+        }
+    }
+    if let Some(mask) = saved_mask {
+        set_signal_mask(mask);
+    }
+    ret
+}
+
+/// Deliver the pending signals that the current thread doesn't block, as it
+/// returns to user space from a trap other than a syscall, like
+/// `handle_signals` does.
+///
+/// For a signal with a handler, the interrupted instruction is executed again
+/// once the handler returns.
+pub fn handle_trap_signals() {
+    let curr = current();
+    while let Some((signo, action)) = take_signal(curr.task_ext()) {
+        match action.handler {
+            _ if ignored(signo, &action) => {}
+            SIG_DFL => exit_on_signal(signo),
+            _ => {
+                enter_handler(signo, &action, None, None);
+                return;
+            }
+        }
+    }
+}
+
+/// Push a signal frame on the user stack and make the current thread return
+/// into the handler of `signo`, which is its first argument.
+///
+/// With `SA_SIGINFO`, the handler gets the signal information and the user
+/// context as well. It returns to the restorer of the action with
+/// `SA_RESTORER`, and to the signal trampoline otherwise.
+///
+/// `ret` is what the interrupted syscall returns, or `None` if the thread is
+/// not in a syscall but in another trap. The handler returns to the signal
+/// mask `saved_mask` if the syscall has replaced it, and the current one
+/// otherwise.
+fn enter_handler(
+    signo: u32,
+    action: &SigAction,
+    ret: Option<isize>,
+    saved_mask: Option<u64>,
+) -> isize {
+    let curr = current();
+    let ext = curr.task_ext();
+    let kstack_top = curr.get_kernel_stack_top().unwrap();
+    let trap_frame = read_trapframe_from_kstack(kstack_top);
+    let mut context = UspaceContext::from(&trap_frame);
+    let ip_skip = match ret {
+        Some(ret) => {
+            context.set_retval(ret as usize);
+            SYSCALL_IP_SKIP
+        }
+        None => 0,
+    };
+    context.set_ip(context.get_ip() + ip_skip);
+
+    let mask = ext.signal_mask.load(Ordering::Acquire);
+    // Safety: the frame is plain integers, for which all zeros are valid.
+    let mut frame: SignalFrame = unsafe { core::mem::zeroed() };
+    frame.info.si_signo = signo as i32;
+    frame.info.si_code = SI_USER;
+    frame.ucontext.uc_sigmask = saved_mask.unwrap_or(mask);
+    save_mcontext(&context, &mut frame.ucontext.uc_mcontext);
+    let frame_addr = (context.get_sp() - RED_ZONE - size_of::<SignalFrame>()) & !0xf;
+    #[allow(unused_mut)]
+    let mut restorer = SIGNAL_TRAMPOLINE;
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    if action.flags & SA_RESTORER != 0 {
+        restorer = action.restorer;
+    }
+    // The handler is entered as if called, so the return address is pushed on x86_64.
+    #[cfg(target_arch = "x86_64")]
+    let handler_sp = frame_addr - size_of::<usize>();
+    #[cfg(not(target_arch = "x86_64"))]
+    let handler_sp = frame_addr;
+    // SAFETY: the frame is plain data, which is copied byte by byte.
+    let frame_bytes = unsafe {
+        core::slice::from_raw_parts(
+            &frame as *const SignalFrame as *const u8,
+            size_of::<SignalFrame>(),
+        )
+    };
+    let written = {
+        let mut aspace = ext.aspace.lock();
+        let mappings = ext.file_mappings.lock();
+        crate::mm::write_pages(
+            &mappings,
+            &mut aspace,
+            VirtAddr::from(frame_addr),
+            frame_bytes,
+        )
+        .and_then(|_| {
+            if cfg!(target_arch = "x86_64") {
+                crate::mm::write_pages(
+                    &mappings,
+                    &mut aspace,
+                    VirtAddr::from(handler_sp),
+                    &restorer.to_ne_bytes(),
+                )
+            } else {
+                Ok(())
+            }
+        })
+    };
+    if written.is_err() {
+        exit_on_signal(SIGSEGV);
+    }
+
+    let (info_addr, ucontext_addr) = if action.flags & SA_SIGINFO != 0 {
+        (
+            frame_addr + offset_of!(SignalFrame, info),
+            frame_addr + offset_of!(SignalFrame, ucontext),
+        )
+    } else {
+        (0, 0)
+    };
+    let mut handler = UspaceContext::from(&trap_frame);
+    handler.set_ip(action.handler - ip_skip);
+    handler.set_sp(handler_sp);
+    #[cfg(target_arch = "x86_64")]
+    {
+        handler.rdi = signo as u64;
+        handler.rsi = info_addr as u64;
+        handler.rdx = ucontext_addr as u64;
+    }
+    #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+    {
+        handler.regs.a0 = signo as usize;
+        handler.regs.a1 = info_addr;
+        handler.regs.a2 = ucontext_addr;
+        handler.regs.ra = restorer;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        handler.r[0] = signo as u64;
+        handler.r[1] = info_addr as u64;
+        handler.r[2] = ucontext_addr as u64;
+        handler.r[30] = restorer as u64;
+    }
+    write_trapframe_to_kstack(kstack_top, &handler);
+
+    let mut handler_mask = mask | action.mask;
+    if action.flags & SA_NODEFER == 0 {
+        handler_mask |= sig_bit(signo);
+    }
+    set_signal_mask(handler_mask);
+    // The first argument is passed in the register of the return value of a syscall.
+    signo as isize
+}
+
+/// Return from a signal handler, restoring the context and the signal mask in
+/// the user context of the signal frame at the stack pointer, which the
+/// handler may have changed, and return what the interrupted syscall returns.
+///
+/// Only the registers that user space may change are restored, and the task is
+/// terminated by `SIGSEGV` if the frame can't be read.
+pub fn sigreturn() -> isize {
+    let curr = current();
+    let ext = curr.task_ext();
+    let kstack_top = curr.get_kernel_stack_top().unwrap();
+    let trap_frame = read_trapframe_from_kstack(kstack_top);
+    let frame_addr = UspaceContext::from(&trap_frame).get_sp();
+    if check_user_range(ext, VirtAddr::from(frame_addr), size_of::<SignalFrame>()).is_err() {
+        exit_on_signal(SIGSEGV);
+    }
+    // SAFETY: the frame has been checked to be mapped.
+    let frame = unsafe { (frame_addr as *const SignalFrame).read_unaligned() };
+
+    let mut restored = trap_frame;
+    restore_mcontext(&mut restored, &frame.ucontext.uc_mcontext);
+    #[cfg(target_arch = "x86_64")]
+    let ret = restored.rax as isize;
+    #[cfg(not(target_arch = "x86_64"))]
+    let ret = restored.arg0() as isize;
+    let mut context = UspaceContext::from(&restored);
+    context.set_ip(context.get_ip() - SYSCALL_IP_SKIP);
+    write_trapframe_to_kstack(kstack_top, &context);
+
+    set_signal_mask(frame.ucontext.uc_sigmask);
+    ret
+}
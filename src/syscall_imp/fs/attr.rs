@@ -0,0 +1,258 @@
+//! Attributes of files that the filesystems behind `axfs` cannot store.
+//!
+//! They are kept in memory, keyed by the canonical path of the file, and
+//! override what the filesystem reports in `stat` and friends.
+
+use alloc::{collections::btree_map::BTreeMap, format, string::String, vec::Vec};
+use arceos_posix_api::{
+    self as api,
+    ctypes::{stat, timespec},
+};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+
+use super::{mount::mounted_root, procfs::self_link};
+
+/// The file type bits of `st_mode`.
+pub(crate) const S_IFMT: u32 = 0o170000;
+/// The file type of a FIFO.
+pub(crate) const S_IFIFO: u32 = 0o010000;
+/// The file type of a character device.
+pub(crate) const S_IFCHR: u32 = 0o020000;
+/// The file type of a regular file.
+pub(crate) const S_IFREG: u32 = 0o100000;
+
+/// A special file, which the filesystem stores as an empty regular file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SpecialNode {
+    /// A FIFO, i.e. a named pipe
+    Fifo,
+    /// A character device with the given device number
+    CharDevice(u64),
+}
+
+/// Attributes overriding those reported by the filesystem.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileAttr {
+    /// Permission bits, i.e. `st_mode & 0o7777`
+    pub mode: Option<u32>,
+    /// The target, if the file is a symbolic link
+    pub symlink: Option<String>,
+    /// User ID of the owner
+    pub uid: Option<u32>,
+    /// Group ID of the owner
+    pub gid: Option<u32>,
+    /// Time of last access
+    pub atime: Option<timespec>,
+    /// Time of last modification
+    pub mtime: Option<timespec>,
+    /// Time of last status change
+    pub ctime: Option<timespec>,
+    /// Bytes allocated by `fallocate`, which may extend past the end of the file
+    pub allocated: Option<u64>,
+    /// The kind of special file, if the file is a FIFO or a device node
+    pub node: Option<SpecialNode>,
+    /// The canonical path of the file whose data this name shares, if it is a
+    /// hard link, which is stored as an empty regular file
+    pub link: Option<String>,
+}
+
+static FILE_ATTRS: Mutex<BTreeMap<String, FileAttr>> = Mutex::new(BTreeMap::new());
+
+fn attr_key(path: &str) -> String {
+    axfs::api::canonicalize(path).unwrap_or_else(|_| path.into())
+}
+
+/// Update the attributes of the file at `path`.
+pub(crate) fn update_file_attr(path: &str, f: impl FnOnce(&mut FileAttr)) {
+    f(FILE_ATTRS.lock().entry(attr_key(path)).or_default());
+}
+
+/// Forget the attributes of the file at `path`, e.g. when it is removed.
+pub(crate) fn remove_file_attr(path: &str) {
+    FILE_ATTRS.lock().remove(&attr_key(path));
+}
+
+/// Move the attributes of the file at `old_path` to `new_path` after a rename.
+///
+/// If the file is a directory, the attributes of everything inside it are moved too.
+pub(crate) fn rename_file_attr(old_path: &str, new_path: &str) {
+    let (old_key, new_key) = (attr_key(old_path), attr_key(new_path));
+    let mut attrs = FILE_ATTRS.lock();
+    attrs.remove(&new_key);
+    let moved: Vec<_> = attrs
+        .keys()
+        .filter(|key| {
+            key.strip_prefix(old_key.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .cloned()
+        .collect();
+    for key in moved {
+        let attr = attrs.remove(&key).unwrap();
+        attrs.insert(format!("{}{}", new_key, &key[old_key.len()..]), attr);
+    }
+    // The hard links follow the data they share.
+    for link in attrs.values_mut().filter_map(|attr| attr.link.as_mut()) {
+        if let Some(rest) = link
+            .strip_prefix(old_key.as_str())
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        {
+            *link = format!("{new_key}{rest}");
+        }
+    }
+}
+
+/// Get the target of the symbolic link at `path`, or `None` if it is not a symbolic link.
+pub(crate) fn symlink_target(path: &str) -> Option<String> {
+    let key = attr_key(path);
+    self_link(&key).or_else(|| {
+        FILE_ATTRS
+            .lock()
+            .get(&key)
+            .and_then(|attr| attr.symlink.clone())
+    })
+}
+
+/// Get the canonical path of the file whose data the hard link at `path`
+/// shares, or `None` if it is not a hard link.
+pub(crate) fn hard_link_target(path: &str) -> Option<String> {
+    FILE_ATTRS
+        .lock()
+        .get(&attr_key(path))
+        .and_then(|attr| attr.link.clone())
+}
+
+/// Get the hard links sharing the data of the file at `path`, in the order of
+/// their paths.
+pub(crate) fn hard_links_to(path: &str) -> Vec<String> {
+    let key = attr_key(path);
+    FILE_ATTRS
+        .lock()
+        .iter()
+        .filter(|(_, attr)| attr.link.as_ref() == Some(&key))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Get the kind of special file at `path`, or `None` if it is not a special file.
+pub(crate) fn special_node(path: &str) -> Option<SpecialNode> {
+    FILE_ATTRS
+        .lock()
+        .get(&attr_key(path))
+        .and_then(|attr| attr.node)
+}
+
+/// The maximum number of symbolic links followed when resolving a path.
+const MAX_SYMLINK_FOLLOWS: usize = 40;
+
+/// Resolve the symbolic links in `path`, returning the canonical path of the file.
+///
+/// The last component is only resolved if `follow_last` is set, so that the
+/// symbolic link itself can be operated on. The directories with a filesystem
+/// mounted on them are always crossed into the directory where it keeps its
+/// files.
+pub(crate) fn resolve_symlinks(path: &str, follow_last: bool) -> LinuxResult<String> {
+    let mut path = attr_key(path);
+    let mut follows = 0;
+    'resolve: loop {
+        let attrs = FILE_ATTRS.lock();
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        let mut prefix = String::new();
+        for (i, component) in components.iter().enumerate() {
+            let parent_len = prefix.len();
+            prefix.push('/');
+            prefix.push_str(component);
+            if let Some(root) = mounted_root(&prefix) {
+                let rest = components[i + 1..].join("/");
+                drop(attrs);
+                path = attr_key(&format!("{root}/{rest}"));
+                continue 'resolve;
+            }
+            if i + 1 == components.len() && !follow_last {
+                break;
+            }
+            let Some(target) = self_link(&prefix)
+                .or_else(|| attrs.get(&prefix).and_then(|attr| attr.symlink.clone()))
+            else {
+                continue;
+            };
+            follows += 1;
+            if follows > MAX_SYMLINK_FOLLOWS {
+                return Err(LinuxError::ELOOP);
+            }
+            // A relative target is relative to the directory containing the link.
+            let target = if target.starts_with('/') {
+                target
+            } else {
+                format!("{}/{}", &prefix[..parent_len], target)
+            };
+            let rest = components[i + 1..].join("/");
+            let new_path = attr_key(&format!("{target}/{rest}"));
+            drop(attrs);
+            path = new_path;
+            continue 'resolve;
+        }
+        return Ok(path);
+    }
+}
+
+/// Apply the attributes of the file at `path` to `stat`.
+pub(crate) fn apply_file_attr(path: &str, stat: &mut stat) {
+    const S_IFLNK: u32 = 0o120000;
+
+    let key = attr_key(path);
+    let attrs = FILE_ATTRS.lock();
+    // The hard links are the names of the file besides its own.
+    let links = attrs
+        .values()
+        .filter(|attr| attr.link.as_ref() == Some(&key))
+        .count();
+    stat.st_nlink += links as _;
+    if let Some(attr) = attrs.get(&key) {
+        match attr.node {
+            Some(SpecialNode::Fifo) => stat.st_mode = S_IFIFO | (stat.st_mode & !S_IFMT),
+            Some(SpecialNode::CharDevice(dev)) => {
+                stat.st_mode = S_IFCHR | (stat.st_mode & !S_IFMT);
+                stat.st_rdev = dev;
+            }
+            None => {}
+        }
+        if let Some(target) = self_link(&key).or_else(|| attr.symlink.clone()) {
+            stat.st_mode = S_IFLNK | 0o777;
+            stat.st_size = target.len() as _;
+        } else if let Some(mode) = attr.mode {
+            stat.st_mode = (stat.st_mode & S_IFMT) | mode;
+        }
+        if let Some(uid) = attr.uid {
+            stat.st_uid = uid;
+        }
+        if let Some(gid) = attr.gid {
+            stat.st_gid = gid;
+        }
+        if let Some(atime) = attr.atime {
+            stat.st_atime = atime;
+        }
+        if let Some(mtime) = attr.mtime {
+            stat.st_mtime = mtime;
+        }
+        if let Some(ctime) = attr.ctime {
+            stat.st_ctime = ctime;
+        }
+        if let Some(allocated) = attr.allocated {
+            stat.st_blocks = stat.st_blocks.max(allocated.div_ceil(512) as _);
+        }
+    }
+}
+
+/// Get the path of the file or directory referred to by `fd`.
+pub(crate) fn fd_path(fd: i32) -> LinuxResult<String> {
+    let file = api::get_file_like(fd)?.into_any();
+    match file.downcast::<api::File>() {
+        Ok(file) => Ok(file.path().into()),
+        Err(file) => file
+            .downcast::<api::Directory>()
+            .map(|dir| dir.path().into())
+            .map_err(|_| LinuxError::EBADF),
+    }
+}
@@ -0,0 +1,253 @@
+//! The page cache of regular files.
+//!
+//! Reads of regular files are served from the pages of the files kept in
+//! memory, keyed by the path of the file and the index of the page, so that
+//! reading the same data again doesn't go down to the block device. The cache
+//! holds at most [`CAPACITY`] pages, and the least recently used page is evicted
+//! first.
+//!
+//! Writes go through to the filesystem, after which the cached pages of the file
+//! are dropped. Nothing in the cache is ever dirty, so `fsync` has nothing to
+//! write back from it. The files under `/proc` are generated on access, and
+//! those under `/tmp` are in memory already, so they are never cached.
+
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use alloc::{collections::btree_map::BTreeMap, format, string::String, vec, vec::Vec};
+use axerrno::AxResult;
+use axfs::fops::{File, OpenOptions};
+use axsync::Mutex;
+use memory_addr::PAGE_SIZE_4K;
+
+use super::{dircache::invalidate_dir_entry, util::is_beneath};
+
+/// The maximum number of cached pages, which can be set with
+/// `AX_PAGE_CACHE_PAGES` at build time.
+const CAPACITY: usize = match option_env!("AX_PAGE_CACHE_PAGES") {
+    Some(pages) => parse_pages(pages),
+    None => 1024,
+};
+
+const fn parse_pages(pages: &str) -> usize {
+    let bytes = pages.as_bytes();
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(
+            bytes[i].is_ascii_digit(),
+            "AX_PAGE_CACHE_PAGES must be a number"
+        );
+        value = value * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    value
+}
+
+struct CachedPage {
+    /// The contents of the page, which is shorter than a page only if the file
+    /// ends in it.
+    data: Vec<u8>,
+    /// When the page was last used.
+    used: u64,
+}
+
+struct PageCache {
+    pages: BTreeMap<(String, u64), CachedPage>,
+    /// The keys of the pages by the time they were last used, oldest first.
+    lru: BTreeMap<u64, (String, u64)>,
+    clock: u64,
+    /// Counts the invalidations, so that a page read from the filesystem is not
+    /// cached if the file has been written in the meantime.
+    epoch: u64,
+}
+
+impl PageCache {
+    const fn new() -> Self {
+        Self {
+            pages: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            clock: 0,
+            epoch: 0,
+        }
+    }
+
+    /// Look up a page, marking it as the most recently used.
+    fn get(&mut self, key: &(String, u64)) -> Option<Vec<u8>> {
+        self.clock += 1;
+        let page = self.pages.get_mut(key)?;
+        self.lru.remove(&page.used);
+        page.used = self.clock;
+        self.lru.insert(self.clock, key.clone());
+        Some(page.data.clone())
+    }
+
+    fn insert(&mut self, key: (String, u64), data: Vec<u8>) {
+        if CAPACITY == 0 {
+            return;
+        }
+        self.remove(&key);
+        while self.pages.len() >= CAPACITY {
+            let Some((_, oldest)) = self.lru.pop_first() else {
+                break;
+            };
+            self.pages.remove(&oldest);
+        }
+        self.clock += 1;
+        self.lru.insert(self.clock, key.clone());
+        let used = self.clock;
+        self.pages.insert(key, CachedPage { data, used });
+    }
+
+    fn remove(&mut self, key: &(String, u64)) {
+        if let Some(page) = self.pages.remove(key) {
+            self.lru.remove(&page.used);
+        }
+    }
+}
+
+static PAGE_CACHE: Mutex<PageCache> = Mutex::new(PageCache::new());
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+fn cache_key(path: &str) -> String {
+    axfs::api::canonicalize(path).unwrap_or_else(|_| path.into())
+}
+
+/// Read the page `index` of `file` from the filesystem.
+fn read_page(file: &File, index: u64) -> AxResult<Vec<u8>> {
+    let mut data = vec![0u8; PAGE_SIZE_4K];
+    let read = read_full(file, index * PAGE_SIZE_4K as u64, &mut data)?;
+    data.truncate(read);
+    Ok(data)
+}
+
+/// Read from `file` at `offset` until `buf` is full or the end of the file.
+fn read_full(file: &File, offset: u64, buf: &mut [u8]) -> AxResult<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read_at(offset + read as u64, &mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+/// Read from `file`, which is opened at `path`, at `offset` through the page cache.
+///
+/// Unlike `read_at` of the file, `buf` is filled unless the end of the file is
+/// reached. The access mode of the file is not checked for the cached pages.
+pub(crate) fn cached_read_at(
+    path: &str,
+    file: &File,
+    offset: u64,
+    buf: &mut [u8],
+) -> AxResult<usize> {
+    if is_beneath(path, "/proc") || is_beneath(path, "/tmp") {
+        return read_full(file, offset, buf);
+    }
+    let path = cache_key(path);
+    let mut read = 0;
+    while read < buf.len() {
+        let pos = offset + read as u64;
+        let key = (path.clone(), pos / PAGE_SIZE_4K as u64);
+        let (cached, epoch) = {
+            let mut cache = PAGE_CACHE.lock();
+            (cache.get(&key), cache.epoch)
+        };
+        let page = match cached {
+            Some(page) => {
+                HITS.fetch_add(1, Ordering::Relaxed);
+                page
+            }
+            None => {
+                MISSES.fetch_add(1, Ordering::Relaxed);
+                let page = read_page(file, key.1)?;
+                let mut cache = PAGE_CACHE.lock();
+                if cache.epoch == epoch {
+                    cache.insert(key, page.clone());
+                }
+                page
+            }
+        };
+        let start = (pos % PAGE_SIZE_4K as u64) as usize;
+        if start >= page.len() {
+            break;
+        }
+        let len = (page.len() - start).min(buf.len() - read);
+        buf[read..read + len].copy_from_slice(&page[start..start + len]);
+        read += len;
+        if page.len() < PAGE_SIZE_4K {
+            break;
+        }
+    }
+    Ok(read)
+}
+
+/// Read the whole file at `path` through the page cache.
+pub(crate) fn cached_read_file(path: &str) -> AxResult<Vec<u8>> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    let file = File::open(path, &options)?;
+    let mut data = vec![0u8; file.get_attr()?.size() as usize];
+    let read = cached_read_at(path, &file, 0, &mut data)?;
+    data.truncate(read);
+    Ok(data)
+}
+
+/// Drop the cached pages of the file at `path` after it has been written to,
+/// along with the metadata cached for its directory entry.
+pub(crate) fn invalidate_cached(path: &str) {
+    invalidate_dir_entry(path);
+    let path = cache_key(path);
+    let mut cache = PAGE_CACHE.lock();
+    cache.epoch += 1;
+    let keys: Vec<_> = cache
+        .pages
+        .range((path.clone(), 0)..=(path, u64::MAX))
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in keys {
+        cache.remove(&key);
+    }
+}
+
+/// Drop the cached pages of the file at `path`, or of everything beneath it if
+/// it is a directory, after it has been removed or renamed.
+pub(super) fn invalidate_cached_tree(path: &str) {
+    let path = cache_key(path);
+    let mut cache = PAGE_CACHE.lock();
+    cache.epoch += 1;
+    let keys: Vec<_> = cache
+        .pages
+        .keys()
+        .filter(|(file, _)| is_beneath(file, &path))
+        .cloned()
+        .collect();
+    for key in keys {
+        cache.remove(&key);
+    }
+}
+
+/// The number of pages in the page cache.
+pub(super) fn cached_pages() -> usize {
+    PAGE_CACHE.lock().pages.len()
+}
+
+/// Generate `/proc/pagecache`, the statistics of the page cache in the format
+/// of `/proc/meminfo`.
+pub(super) fn cache_stats() -> String {
+    let mut stats = String::new();
+    for (name, value) in [
+        ("Hits", HITS.load(Ordering::Relaxed)),
+        ("Misses", MISSES.load(Ordering::Relaxed)),
+        ("Pages", cached_pages() as u64),
+        ("Capacity", CAPACITY as u64),
+    ] {
+        writeln!(stats, "{:<16}{:>8}", format!("{name}:"), value).unwrap();
+    }
+    stats
+}
@@ -1,11 +1,68 @@
 use core::ffi::{c_char, c_int, c_void};
 
-use alloc::string::ToString;
-use arceos_posix_api::AT_FDCWD;
-use axerrno::{AxError, LinuxError};
+use alloc::{
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+    format,
+    sync::{Arc, Weak},
+};
+use arceos_posix_api::{
+    AT_FDCWD, Directory, FileLike,
+    ctypes::{O_NONBLOCK, timespec},
+};
+use axerrno::{LinuxError, LinuxResult};
+use axstd::io::SeekFrom;
+use axsync::Mutex;
 use axtask::{TaskExtRef, current};
+use num_enum::TryFromPrimitive;
+
+use super::{
+    attr::{
+        S_IFCHR, S_IFIFO, S_IFMT, S_IFREG, SpecialNode, fd_path, hard_link_target, hard_links_to,
+        remove_file_attr, rename_file_attr, special_node, symlink_target, update_file_attr,
+    },
+    cache::invalidate_cached_tree,
+    dircache::{dir_entries, invalidate_dir_of},
+    fd_ops::{file_status_flags, set_file_status_flags},
+    mount::{is_mount_root, visible_path},
+    path::{resolve_at, resolve_name_at},
+    pipe::Pipe,
+    stat::{AT_SYMLINK_NOFOLLOW, lookup, path_inode},
+    tty::{self, Termios, Tty, WinSize},
+};
+use crate::{mm::check_user_range, syscall_body};
+
+/// ioctl request codes
+///
+/// The values are shared by all the architectures we support and are taken
+/// from `include/uapi/asm-generic/ioctls.h`.
+#[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
+#[repr(usize)]
+enum IoctlCmd {
+    /// Get the current serial port settings.
+    Tcgets = 0x5401,
+    /// Set the current serial port settings.
+    Tcsets = 0x5402,
+    /// Allow the output buffer to drain, and set the current serial port settings.
+    Tcsetsw = 0x5403,
+    /// Allow the output buffer to drain, discard pending input, and set the current serial port settings.
+    Tcsetsf = 0x5404,
+    /// Get the foreground process group of the terminal.
+    Tiocgpgrp = 0x540F,
+    /// Set the foreground process group of the terminal.
+    Tiocspgrp = 0x5410,
+    /// Get window size.
+    Tiocgwinsz = 0x5413,
+    /// Set window size.
+    Tiocswinsz = 0x5414,
+    /// Get the number of bytes in the input buffer.
+    Fionread = 0x541B,
+    /// Enable or disable non-blocking mode.
+    Fionbio = 0x5421,
+}
 
-use crate::syscall_body;
+/// The ioctl requests that are not supported and have been warned about,
+/// which are only warned about once.
+static UNSUPPORTED_IOCTLS: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::new());
 
 /// The ioctl() system call manipulates the underlying device parameters
 /// of special files.
@@ -15,54 +72,293 @@ use crate::syscall_body;
 /// * `op` - The request code. It is of type unsigned long in glibc and BSD,
 ///   and of type int in musl and other UNIX systems.
 /// * `argp` - The argument to the request. It is a pointer to a memory location
-pub(crate) fn sys_ioctl(_fd: i32, _op: usize, _argp: *mut c_void) -> i32 {
+///
+/// `FIONREAD` and `FIONBIO` work on any file, while the terminal requests fail
+/// with `ENOTTY` unless `fd` refers to the console. Unsupported requests fail
+/// with `EINVAL`.
+pub(crate) fn sys_ioctl(fd: i32, op: usize, argp: *mut c_void) -> i32 {
     syscall_body!(sys_ioctl, {
-        warn!("Unimplemented syscall: SYS_IOCTL");
+        let file = arceos_posix_api::get_file_like(fd)?;
+        let Ok(cmd) = IoctlCmd::try_from(op) else {
+            if UNSUPPORTED_IOCTLS.lock().insert(op) {
+                warn!("Unsupported ioctl request: {op:#x}");
+            }
+            return Err(LinuxError::EINVAL);
+        };
+        if argp.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        // Every request reads or writes the argument at `argp`.
+        let arg_size = match cmd {
+            IoctlCmd::Tcgets | IoctlCmd::Tcsets | IoctlCmd::Tcsetsw | IoctlCmd::Tcsetsf => {
+                size_of::<Termios>()
+            }
+            IoctlCmd::Tiocgwinsz | IoctlCmd::Tiocswinsz => size_of::<WinSize>(),
+            IoctlCmd::Tiocgpgrp | IoctlCmd::Tiocspgrp | IoctlCmd::Fionread | IoctlCmd::Fionbio => {
+                size_of::<c_int>()
+            }
+        };
+        check_user_range(current().task_ext(), (argp as usize).into(), arg_size)
+            .map_err(|_| LinuxError::EFAULT)?;
+        match cmd {
+            IoctlCmd::Fionbio => {
+                // The same as changing `O_NONBLOCK` with `F_SETFL`.
+                let nonblocking = unsafe { *(argp as *const c_int) } != 0;
+                file.set_nonblocking(nonblocking)?;
+                let flags = file_status_flags(&file) & !O_NONBLOCK;
+                set_file_status_flags(&file, flags | if nonblocking { O_NONBLOCK } else { 0 });
+                return Ok(0);
+            }
+            IoctlCmd::Fionread => {
+                let available = available_bytes(file)?;
+                unsafe { *(argp as *mut c_int) = available.min(c_int::MAX as usize) as c_int };
+                return Ok(0);
+            }
+            _ => {}
+        }
+
+        if !file.into_any().is::<Tty>() {
+            return Err(LinuxError::ENOTTY);
+        }
+        match cmd {
+            IoctlCmd::Tcgets => unsafe { *(argp as *mut Termios) = tty::termios() },
+            IoctlCmd::Tcsets | IoctlCmd::Tcsetsw => unsafe {
+                tty::set_termios(*(argp as *const Termios), false)
+            },
+            IoctlCmd::Tcsetsf => unsafe { tty::set_termios(*(argp as *const Termios), true) },
+            IoctlCmd::Tiocgwinsz => unsafe { *(argp as *mut WinSize) = tty::winsize() },
+            IoctlCmd::Tiocswinsz => unsafe { tty::set_winsize(*(argp as *const WinSize)) },
+            IoctlCmd::Tiocgpgrp => unsafe { *(argp as *mut c_int) = tty::foreground_pgrp() },
+            IoctlCmd::Tiocspgrp => {
+                let pgrp = unsafe { *(argp as *const c_int) };
+                if pgrp <= 0 {
+                    return Err(LinuxError::EINVAL);
+                }
+                tty::set_foreground_pgrp(pgrp);
+            }
+            IoctlCmd::Fionbio | IoctlCmd::Fionread => unreachable!(),
+        }
         Ok(0)
     })
 }
 
+/// Get the number of bytes that can be read from `file` without blocking, for
+/// `FIONREAD`.
+///
+/// It is what is left after the offset of a regular file, and what is buffered
+/// in a pipe or typed on the console. Sockets don't tell how much they have
+/// received, so they report 0 like the other files.
+fn available_bytes(file: Arc<dyn FileLike>) -> LinuxResult<usize> {
+    let file = file.into_any();
+    if let Some(file) = file.downcast_ref::<arceos_posix_api::File>() {
+        let mut file = file.inner().lock();
+        let size = file.get_attr()?.size();
+        let pos = file.seek(SeekFrom::Current(0))?;
+        return Ok(size.saturating_sub(pos) as usize);
+    }
+    if let Some(pipe) = file.downcast_ref::<Pipe>() {
+        return Ok(pipe.available());
+    }
+    if let Some(tty) = file.downcast_ref::<Tty>() {
+        return Ok(tty.available());
+    }
+    Ok(0)
+}
+
+/// Change the working directory of the current task to `path`.
+///
+/// The working directory belongs to the resource namespace of the task rather
+/// than to axfs as a whole, so it only changes for the current task and those
+/// sharing it with `CLONE_FS`. A new task inherits a copy of it, and exec
+/// keeps it.
 pub(crate) fn sys_chdir(path: *const c_char) -> c_int {
-    let path = match arceos_posix_api::char_ptr_to_str(path) {
-        Ok(path) => path,
-        Err(err) => {
-            warn!("Failed to convert path: {err:?}");
-            return -1;
+    syscall_body!(sys_chdir, {
+        let path = resolve_at(AT_FDCWD as i32, path, true)?;
+        if !axfs::api::metadata(&path)?.is_dir() {
+            return Err(LinuxError::ENOTDIR);
         }
-    };
+        axfs::api::set_current_dir(&path)?;
+        Ok(0)
+    })
+}
 
-    axfs::api::set_current_dir(path)
-        .map(|_| 0)
-        .unwrap_or_else(|err| {
-            warn!("Failed to change directory: {err:?}");
-            -1
-        })
+/// Change the working directory of the current task to the directory referred
+/// to by `fd`.
+pub(crate) fn sys_fchdir(fd: c_int) -> c_int {
+    syscall_body!(sys_fchdir, {
+        let file = arceos_posix_api::get_file_like(fd)?;
+        let dir = file
+            .into_any()
+            .downcast::<Directory>()
+            .map_err(|_| LinuxError::ENOTDIR)?;
+        axfs::api::set_current_dir(dir.path())?;
+        Ok(0)
+    })
 }
 
+/// Create a directory at `path` relative to `dirfd`, with the permission bits
+/// in `mode` less those in the umask.
 pub(crate) fn sys_mkdirat(dirfd: i32, path: *const c_char, mode: u32) -> c_int {
-    let path = match arceos_posix_api::char_ptr_to_str(path) {
-        Ok(path) => path,
-        Err(err) => {
-            warn!("Failed to convert path: {err:?}");
-            return -1;
+    syscall_body!(sys_mkdirat, {
+        let path = resolve_at(dirfd, path, false)?;
+        if axfs::api::metadata(&path).is_ok() {
+            return Err(LinuxError::EEXIST);
         }
-    };
+        axfs::api::create_dir(&path)?;
+        invalidate_dir_of(&path);
+        let mode = mode & !current().task_ext().get_umask() & 0o7777;
+        update_file_attr(&path, |attr| attr.mode = Some(mode));
+        Ok(0)
+    })
+}
 
-    if !path.starts_with("/") && dirfd != AT_FDCWD as i32 {
-        warn!("unsupported.");
-        return -1;
-    }
+/// Record the owner and group of the file at `path`.
+///
+/// An ID of `u32::MAX`, i.e. `(uid_t)-1`, leaves the corresponding field unchanged.
+fn chown_path(path: &str, uid: u32, gid: u32) {
+    update_file_attr(path, |attr| {
+        if uid != u32::MAX {
+            attr.uid = Some(uid);
+        }
+        if gid != u32::MAX {
+            attr.gid = Some(gid);
+        }
+    });
+}
 
-    if mode != 0 {
-        info!("directory mode not supported.");
+/// Change the owner and group of the file at `path` relative to `dirfd`.
+///
+/// Symbolic links are followed unless `AT_SYMLINK_NOFOLLOW` is set, and `dirfd`
+/// itself is changed if `path` is empty and `AT_EMPTY_PATH` is set.
+pub(crate) fn sys_fchownat(
+    dirfd: i32,
+    path: *const c_char,
+    uid: u32,
+    gid: u32,
+    flags: u32,
+) -> isize {
+    syscall_body!(sys_fchownat, {
+        const AT_EMPTY_PATH: u32 = 0x1000;
+        if flags & !(AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if arceos_posix_api::char_ptr_to_str(path)?.is_empty() {
+            if flags & AT_EMPTY_PATH == 0 {
+                return Err(LinuxError::ENOENT);
+            }
+            chown_fd(dirfd, uid, gid)?;
+            return Ok(0);
+        }
+        let path = resolve_at(dirfd, path, flags & AT_SYMLINK_NOFOLLOW == 0)?;
+        axfs::api::metadata(&path)?;
+        chown_path(&path, uid, gid);
+        Ok(0)
+    })
+}
+
+/// Record the owner and group of the file referred to by `fd`.
+fn chown_fd(fd: i32, uid: u32, gid: u32) -> LinuxResult {
+    arceos_posix_api::get_file_like(fd)?;
+    // Pipes and sockets have no path, and their owner is never reported.
+    if let Ok(path) = fd_path(fd) {
+        chown_path(&path, uid, gid);
     }
+    Ok(())
+}
 
-    axfs::api::create_dir(path)
-        .map(|_| 0)
-        .unwrap_or_else(|err| {
-            warn!("Failed to create directory {path}: {err:?}");
-            -1
-        })
+/// Change the owner and group of the file referred to by `fd`.
+pub(crate) fn sys_fchown(fd: i32, uid: u32, gid: u32) -> isize {
+    syscall_body!(sys_fchown, {
+        chown_fd(fd, uid, gid)?;
+        Ok(0)
+    })
+}
+
+/// Set the access and modification times of the file at `path` relative to `dirfd`.
+///
+/// `times` points to the new access and modification times, either of which may
+/// have `tv_nsec` set to `UTIME_NOW` or `UTIME_OMIT`. If `times` is null, both
+/// are set to the current time. If `path` is null, the file referred to by
+/// `dirfd` is changed, which is how `futimens` is implemented. Symbolic links
+/// are followed unless `AT_SYMLINK_NOFOLLOW` is set.
+pub(crate) fn sys_utimensat(
+    dirfd: i32,
+    path: *const c_char,
+    times: *const timespec,
+    flags: u32,
+) -> isize {
+    syscall_body!(sys_utimensat, {
+        const UTIME_NOW: i64 = (1 << 30) - 1;
+        const UTIME_OMIT: i64 = (1 << 30) - 2;
+
+        if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = if path.is_null() {
+            fd_path(dirfd)?
+        } else {
+            resolve_at(dirfd, path, flags & AT_SYMLINK_NOFOLLOW == 0)?
+        };
+        axfs::api::metadata(&path)?;
+
+        let now = axhal::time::wall_time();
+        let now = timespec {
+            tv_sec: now.as_secs() as _,
+            tv_nsec: now.subsec_nanos() as _,
+        };
+        let [atime, mtime] = if times.is_null() {
+            [now; 2]
+        } else {
+            unsafe { [*times, *times.add(1)] }
+        };
+        let resolve = |time: timespec| match time.tv_nsec as i64 {
+            UTIME_NOW => Ok(Some(now)),
+            UTIME_OMIT => Ok(None),
+            0..1_000_000_000 => Ok(Some(time)),
+            _ => Err(LinuxError::EINVAL),
+        };
+        let (atime, mtime) = (resolve(atime)?, resolve(mtime)?);
+        if atime.is_none() && mtime.is_none() {
+            return Ok(0);
+        }
+        update_file_attr(&path, |attr| {
+            attr.atime = atime.or(attr.atime);
+            attr.mtime = mtime.or(attr.mtime);
+            attr.ctime = Some(now);
+        });
+        Ok(0)
+    })
+}
+
+/// Change the permission bits of the file at `path` relative to `dirfd`.
+///
+/// Symbolic links are followed unless `AT_SYMLINK_NOFOLLOW` is set, in which
+/// case changing the mode of a link fails with `EOPNOTSUPP` like on Linux.
+pub(crate) fn sys_fchmodat(dirfd: i32, path: *const c_char, mode: u32, flags: u32) -> isize {
+    syscall_body!(sys_fchmodat, {
+        if flags & !AT_SYMLINK_NOFOLLOW != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = resolve_at(dirfd, path, flags & AT_SYMLINK_NOFOLLOW == 0)?;
+        axfs::api::metadata(&path)?;
+        if symlink_target(&path).is_some() {
+            return Err(LinuxError::EOPNOTSUPP);
+        }
+        update_file_attr(&path, |attr| attr.mode = Some(mode & 0o7777));
+        Ok(0)
+    })
+}
+
+/// Change the permission bits of the file referred to by `fd`.
+pub(crate) fn sys_fchmod(fd: i32, mode: u32) -> isize {
+    syscall_body!(sys_fchmod, {
+        arceos_posix_api::get_file_like(fd)?;
+        // Pipes and sockets have no path, and their mode is never reported.
+        if let Ok(path) = fd_path(fd) {
+            update_file_attr(&path, |attr| attr.mode = Some(mode & 0o7777));
+        }
+        Ok(0)
+    })
 }
 
 #[repr(C)]
@@ -95,6 +391,11 @@ impl From<axfs::api::FileType> for FileType {
         match ft {
             ft if ft.is_dir() => FileType::Dir,
             ft if ft.is_file() => FileType::Reg,
+            ft if ft.is_symlink() => FileType::Lnk,
+            ft if ft.is_char_device() => FileType::Chr,
+            ft if ft.is_block_device() => FileType::Blk,
+            ft if ft.is_fifo() => FileType::Fifo,
+            ft if ft.is_socket() => FileType::Socket,
             _ => FileType::Unknown,
         }
     }
@@ -157,156 +458,451 @@ impl<'a> DirBuffer<'a> {
     }
 }
 
-pub(crate) fn sys_getdents64(fd: i32, buf: *mut c_void, len: usize) -> isize {
-    if len < DirEnt::FIXED_SIZE {
-        warn!("Buffer size too small: {len}");
-        return -1;
+/// Positions of the open directories, i.e. how many entries `getdents64` has returned.
+///
+/// The position belongs to the open directory rather than to the fd, so duplicated fds
+/// share it. A weak reference is kept to tell whether the directory is still open, since
+/// the address of a closed directory may be reused by a newly opened one.
+static DIR_POSITIONS: Mutex<BTreeMap<usize, (Weak<Directory>, u64)>> = Mutex::new(BTreeMap::new());
+
+fn dir_position(dir: &Arc<Directory>) -> u64 {
+    match DIR_POSITIONS.lock().get(&(Arc::as_ptr(dir) as usize)) {
+        Some((weak, pos)) if Weak::ptr_eq(weak, &Arc::downgrade(dir)) => *pos,
+        _ => 0,
     }
+}
 
-    let current_task = current();
-    if let Err(e) = current_task
-        .task_ext()
-        .aspace
-        .lock()
-        .alloc_for_lazy((buf as usize).into(), len)
-    {
-        warn!("Memory allocation failed: {:?}", e);
-        return -1;
-    }
+fn set_dir_position(dir: &Arc<Directory>, pos: u64) {
+    let mut positions = DIR_POSITIONS.lock();
+    positions.retain(|_, (weak, _)| weak.strong_count() > 0);
+    positions.insert(Arc::as_ptr(dir) as usize, (Arc::downgrade(dir), pos));
+}
 
-    let path = match arceos_posix_api::Directory::from_fd(fd).map(|dir| dir.path().to_string()) {
-        Ok(path) => path,
-        Err(err) => {
-            warn!("Invalid directory descriptor: {:?}", err);
-            return -1;
-        }
+/// Move the position of an open directory for `lseek`.
+///
+/// Positions count entries, so the `d_off` of an entry returned by `getdents64`
+/// can be used to continue right after it. Seeking to 0 rewinds the directory.
+pub(super) fn seek_dir(dir: &Arc<Directory>, pos: SeekFrom) -> LinuxResult<u64> {
+    let (base, offset) = match pos {
+        SeekFrom::Start(offset) => (0, offset as i64),
+        SeekFrom::Current(offset) => (dir_position(dir), offset),
+        SeekFrom::End(_) => return Err(LinuxError::EINVAL),
     };
+    let pos = (base as i64)
+        .checked_add(offset)
+        .filter(|pos| *pos >= 0)
+        .ok_or(LinuxError::EINVAL)? as u64;
+    set_dir_position(dir, pos);
+    Ok(pos)
+}
 
-    let mut buffer =
-        unsafe { DirBuffer::new(core::slice::from_raw_parts_mut(buf as *mut u8, len)) };
-
-    let (initial_offset, count) = unsafe {
-        let mut buf_offset = 0;
-        let mut count = 0;
-        while buf_offset + DirEnt::FIXED_SIZE <= len {
-            let dir_ent = *(buf.add(buf_offset) as *const DirEnt);
-            if dir_ent.d_reclen == 0 {
+/// Read the entries of the directory referred to by `fd` into `buf`.
+///
+/// Each call continues from where the previous one stopped, and `d_off` of every
+/// entry is the directory position right after it.
+///
+/// # Returns
+/// The number of bytes written, or 0 at the end of the directory.
+pub(crate) fn sys_getdents64(fd: i32, buf: *mut c_void, len: usize) -> isize {
+    syscall_body!(sys_getdents64, {
+        let dir = Directory::from_fd(fd)?;
+        check_user_range(current().task_ext(), (buf as usize).into(), len)
+            .map_err(|_| LinuxError::EFAULT)?;
+
+        let mut buffer =
+            unsafe { DirBuffer::new(core::slice::from_raw_parts_mut(buf as *mut u8, len)) };
+        let path = dir.path();
+        let start = dir_position(&dir);
+        let mut pos = start;
+        let mut buffer_full = false;
+        for entry in dir_entries(path)?.iter().skip(start as usize) {
+            let mut name = entry.name.clone();
+            name.push('\0');
+            let name_bytes = name.as_bytes();
+            // Keep every entry aligned for the next one.
+            let entry_size = (DirEnt::FIXED_SIZE + name_bytes.len())
+                .next_multiple_of(core::mem::align_of::<DirEnt>());
+
+            let entry_path = format!("{}/{}", path.trim_end_matches('/'), entry.name);
+            // Symbolic links and special files are stored as regular files by the filesystem.
+            let file_type = if symlink_target(&entry_path).is_some() {
+                FileType::Lnk
+            } else {
+                match special_node(&entry_path) {
+                    Some(SpecialNode::Fifo) => FileType::Fifo,
+                    Some(SpecialNode::CharDevice(_)) => FileType::Chr,
+                    None => FileType::from(entry.file_type),
+                }
+            };
+            let dirent = DirEnt::new(
+                path_inode(&entry_path),
+                (pos + 1) as i64,
+                entry_size,
+                file_type,
+            );
+            if buffer.write_entry(dirent, name_bytes).is_err() {
+                buffer_full = true;
                 break;
             }
-
-            buf_offset += dir_ent.d_reclen as usize;
-            assert_eq!(dir_ent.d_off, buf_offset as i64);
-            count += 1;
+            pos += 1;
         }
-        (buf_offset as i64, count)
-    };
 
-    axfs::api::read_dir(&path)
-        .map(|entries| {
-            let mut total_size = initial_offset as usize;
-            let mut current_offset = initial_offset;
+        if buffer_full && buffer.offset == 0 {
+            // Not even a single entry fits in the buffer.
+            return Err(LinuxError::EINVAL);
+        }
+        set_dir_position(&dir, pos);
+        Ok(buffer.offset as isize)
+    })
+}
 
-            for entry in entries.flatten().skip(count) {
-                let mut name = entry.file_name();
-                name.push('\0');
-                let name_bytes = name.as_bytes();
+/// Create a hard link at `new_path` to the file at `old_path`.
+///
+/// The filesystems behind `axfs` cannot represent hard links, so the new name
+/// is an empty regular file, and the file whose data it shares is recorded in
+/// its attributes. Symbolic links are followed for `old_path` only if
+/// `AT_SYMLINK_FOLLOW` is set, and directories can't be linked.
+pub(crate) fn sys_linkat(
+    old_dirfd: i32,
+    old_path: *const u8,
+    new_dirfd: i32,
+    new_path: *const u8,
+    flags: i32,
+) -> i32 {
+    const AT_SYMLINK_FOLLOW: i32 = 0x400;
 
-                let entry_size = DirEnt::FIXED_SIZE + name_bytes.len();
-                current_offset += entry_size as i64;
+    syscall_body!(sys_linkat, {
+        if flags & !AT_SYMLINK_FOLLOW != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let old_path = resolve_at(old_dirfd, old_path as _, flags & AT_SYMLINK_FOLLOW != 0)?;
+        let new_path = resolve_name_at(new_dirfd, new_path as _)?;
+        if lookup(&old_path)?.is_dir() {
+            return Err(LinuxError::EPERM);
+        }
+        if axfs::api::metadata(&new_path).is_ok() {
+            return Err(LinuxError::EEXIST);
+        }
+        axfs::api::write(&new_path, "")?;
+        invalidate_dir_of(&new_path);
+        update_file_attr(&new_path, |attr| attr.link = Some(old_path));
+        Ok(0)
+    })
+}
 
-                let dirent = DirEnt::new(
-                    1,
-                    current_offset,
-                    entry_size,
-                    FileType::from(entry.file_type()),
-                );
+/// Remove the name `path` of a regular file, whose data goes away with its
+/// last name.
+///
+/// If the data is kept at `path` and has hard links, it moves to the first of
+/// them instead.
+pub(super) fn unlink_file(path: &str) -> LinuxResult {
+    if hard_link_target(path).is_some() {
+        axfs::api::remove_file(path)?;
+        invalidate_dir_of(path);
+        return Ok(());
+    }
+    match hard_links_to(path).first() {
+        Some(link) => {
+            axfs::api::remove_file(link)?;
+            remove_file_attr(link);
+            rename(path, link)?;
+        }
+        None => axfs::api::remove_file(path)?,
+    }
+    invalidate_cached_tree(path);
+    invalidate_dir_of(path);
+    Ok(())
+}
 
-                if buffer.write_entry(dirent, name_bytes).is_err() {
-                    break;
-                }
+/// Remove the link at `path` relative to `dir_fd`, or the empty directory
+/// there if `flags` is `AT_REMOVEDIR`.
+///
+/// Removing a directory without `AT_REMOVEDIR` fails with `EISDIR`, and
+/// removing anything but an empty directory with it fails with `ENOTDIR` or
+/// `ENOTEMPTY`.
+pub fn sys_unlinkat(dir_fd: isize, path: *const u8, flags: usize) -> isize {
+    const AT_REMOVEDIR: usize = 0x200;
 
-                total_size += entry_size;
+    syscall_body!(sys_unlinkat, {
+        if flags & !AT_REMOVEDIR != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = resolve_name_at(dir_fd as i32, path as _)?;
+        let metadata = axfs::api::metadata(&path)?;
+        if flags & AT_REMOVEDIR != 0 {
+            if !metadata.is_dir() {
+                return Err(LinuxError::ENOTDIR);
             }
-
-            if total_size > 0 && buffer.can_fit_entry(DirEnt::FIXED_SIZE) {
-                let terminal = DirEnt::new(1, current_offset, 0, FileType::Reg);
-                let _ = buffer.write_entry(terminal, &[]);
+            if is_mount_root(&path) {
+                return Err(LinuxError::EBUSY);
+            }
+            if axfs::api::read_dir(&path)?.flatten().next().is_some() {
+                return Err(LinuxError::ENOTEMPTY);
             }
-            total_size as isize
-        })
-        .unwrap_or(LinuxError::ENOENT as isize)
+            axfs::api::remove_dir(&path)?;
+            invalidate_dir_of(&path);
+        } else {
+            if metadata.is_dir() {
+                return Err(LinuxError::EISDIR);
+            }
+            debug!("unlink file: {:?}", path);
+            unlink_file(&path)?;
+        }
+        remove_file_attr(&path);
+        Ok(0)
+    })
 }
 
-/// create a link from new_path to old_path
-/// old_path: old file path
-/// new_path: new file path
-/// flags: link flags
-/// return value: return 0 when success, else return -1.
-pub(crate) fn sys_linkat(
+bitflags::bitflags! {
+    /// Flags for `sys_renameat2`
+    #[derive(Debug, Clone, Copy)]
+    struct RenameFlags: u32 {
+        /// Don't overwrite the new path, fail with `EEXIST` if it exists.
+        const RENAME_NOREPLACE = 1 << 0;
+        /// Atomically exchange the old path and the new path.
+        const RENAME_EXCHANGE = 1 << 1;
+    }
+}
+
+/// Serializes the renames, so that an exchange is observed as atomic.
+static RENAME_LOCK: Mutex<()> = Mutex::new(());
+
+/// Rename a file, and its attributes along with it.
+fn rename(old_path: &str, new_path: &str) -> LinuxResult {
+    axfs::api::rename(old_path, new_path)?;
+    rename_file_attr(old_path, new_path);
+    invalidate_cached_tree(old_path);
+    invalidate_cached_tree(new_path);
+    invalidate_dir_of(old_path);
+    invalidate_dir_of(new_path);
+    Ok(())
+}
+
+/// Undo the renames of a failed exchange, which are pairs of the current path
+/// and the original path, as far as possible.
+fn rename_back(renames: &[(&str, &str)]) {
+    for (path, orig_path) in renames {
+        if let Err(err) = rename(path, orig_path) {
+            warn!("Failed to rename {path} back to {orig_path}: {err:?}");
+        }
+    }
+}
+
+/// Rename a file, moving it between directories if required.
+///
+/// # Arguments
+/// * `old_dirfd` - The directory that a relative `old_path` is resolved against
+/// * `old_path` - The current path of the file
+/// * `new_dirfd` - The directory that a relative `new_path` is resolved against
+/// * `new_path` - The new path of the file
+/// * `flags` - `RENAME_NOREPLACE` or `RENAME_EXCHANGE`
+pub(crate) fn sys_renameat2(
     old_dirfd: i32,
     old_path: *const u8,
     new_dirfd: i32,
     new_path: *const u8,
-    flags: i32,
-) -> i32 {
-    if flags != 0 {
-        warn!("Unsupported flags: {flags}");
-    }
+    flags: u32,
+) -> isize {
+    syscall_body!(sys_renameat2, {
+        let flags = RenameFlags::from_bits(flags).ok_or(LinuxError::EINVAL)?;
+        if flags.contains(RenameFlags::RENAME_NOREPLACE | RenameFlags::RENAME_EXCHANGE) {
+            return Err(LinuxError::EINVAL);
+        }
+        let old_abs = resolve_name_at(old_dirfd, old_path as _)?;
+        let new_abs = resolve_name_at(new_dirfd, new_path as _)?;
+        debug!("sys_renameat2 <= {old_abs:?} -> {new_abs:?}, flags: {flags:?}");
+
+        let _guard = RENAME_LOCK.lock();
+        let old_metadata = axfs::api::metadata(&old_abs)?;
+        let new_metadata = axfs::api::metadata(&new_abs).ok();
+        if old_abs == new_abs {
+            return Ok(0);
+        }
+        if is_mount_root(&old_abs) || is_mount_root(&new_abs) {
+            return Err(LinuxError::EBUSY);
+        }
+        // A directory cannot become a subdirectory of itself, and neither can it
+        // be exchanged with one of its subdirectories.
+        let is_descendant =
+            |path: &str, dir: &str| path.starts_with(&format!("{}/", dir.trim_end_matches('/')));
+        if is_descendant(&new_abs, &old_abs)
+            || (flags.contains(RenameFlags::RENAME_EXCHANGE) && is_descendant(&old_abs, &new_abs))
+        {
+            return Err(LinuxError::EINVAL);
+        }
 
-    // handle old path
-    arceos_posix_api::handle_file_path(old_dirfd as isize, Some(old_path), false)
-        .inspect_err(|err| warn!("Failed to convert new path: {err:?}"))
-        .and_then(|old_path| {
-            //handle new path
-            arceos_posix_api::handle_file_path(new_dirfd as isize, Some(new_path), false)
-                .inspect_err(|err| warn!("Failed to convert new path: {err:?}"))
-                .map(|new_path| (old_path, new_path))
-        })
-        .and_then(|(old_path, new_path)| {
-            arceos_posix_api::HARDLINK_MANAGER
-                .create_link(&new_path, &old_path)
-                .inspect_err(|err| warn!("Failed to create link: {err:?}"))
-                .map_err(Into::into)
-        })
-        .map(|_| 0)
-        .unwrap_or(-1)
-}
-
-/// remove link of specific file (can be used to delete file)
-/// dir_fd: the directory of link to be removed
-/// path: the name of link to be removed
-/// flags: can be 0 or AT_REMOVEDIR
-/// return 0 when success, else return -1
-pub fn sys_unlinkat(dir_fd: isize, path: *const u8, flags: usize) -> isize {
-    const AT_REMOVEDIR: usize = 0x200;
+        if flags.contains(RenameFlags::RENAME_EXCHANGE) {
+            if new_metadata.is_none() {
+                return Err(LinuxError::ENOENT);
+            }
+            // Move the old file aside under a name that is not taken, so that
+            // the new one can take its place.
+            let tmp_path = (0..)
+                .map(|i| format!("{old_abs}.renameat2-exchange.{i}"))
+                .find(|path| axfs::api::metadata(path).is_err())
+                .unwrap();
+            rename(&old_abs, &tmp_path)?;
+            if let Err(err) = rename(&new_abs, &old_abs) {
+                rename_back(&[(tmp_path.as_str(), old_abs.as_str())]);
+                return Err(err);
+            }
+            if let Err(err) = rename(&tmp_path, &new_abs) {
+                rename_back(&[
+                    (old_abs.as_str(), new_abs.as_str()),
+                    (tmp_path.as_str(), old_abs.as_str()),
+                ]);
+                return Err(err);
+            }
+            return Ok(0);
+        }
 
-    arceos_posix_api::handle_file_path(dir_fd, Some(path), false)
-        .inspect_err(|e| warn!("unlinkat error: {:?}", e))
-        .and_then(|path| {
-            if flags == AT_REMOVEDIR {
-                axfs::api::remove_dir(path.as_str())
-                    .inspect_err(|e| warn!("unlinkat error: {:?}", e))
-                    .map(|_| 0)
-            } else {
-                axfs::api::metadata(path.as_str()).and_then(|metadata| {
-                    if metadata.is_dir() {
-                        Err(AxError::IsADirectory)
-                    } else {
-                        debug!("unlink file: {:?}", path);
-                        arceos_posix_api::HARDLINK_MANAGER
-                            .remove_link(&path)
-                            .ok_or_else(|| {
-                                debug!("unlink file error");
-                                AxError::NotFound
-                            })
-                            .map(|_| 0)
+        if let Some(new_metadata) = new_metadata {
+            if flags.contains(RenameFlags::RENAME_NOREPLACE) {
+                return Err(LinuxError::EEXIST);
+            }
+            // Replace the existing file, which must be of the same kind.
+            match (old_metadata.is_dir(), new_metadata.is_dir()) {
+                (true, false) => return Err(LinuxError::ENOTDIR),
+                (false, true) => return Err(LinuxError::EISDIR),
+                (true, true) => {
+                    if axfs::api::read_dir(&new_abs)?.flatten().next().is_some() {
+                        return Err(LinuxError::ENOTEMPTY);
                     }
-                })
+                    axfs::api::remove_dir(&new_abs)?;
+                }
+                (false, false) => unlink_file(&new_abs)?,
             }
-        })
-        .unwrap_or(-1)
+            remove_file_attr(&new_abs);
+        }
+        rename(&old_abs, &new_abs)?;
+        Ok(0)
+    })
 }
 
-pub(crate) fn sys_getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
-    arceos_posix_api::sys_getcwd(buf, size)
+/// Rename a file, replacing the new path if it exists.
+///
+/// The same as `sys_renameat2` with no flags.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_renameat(
+    old_dirfd: i32,
+    old_path: *const u8,
+    new_dirfd: i32,
+    new_path: *const u8,
+) -> isize {
+    sys_renameat2(old_dirfd, old_path, new_dirfd, new_path, 0)
+}
+
+/// Create a regular file, a FIFO or a character device at `path`, as given by
+/// the file type in `mode`.
+///
+/// `dev` is the device number of a character device. Like symbolic links,
+/// special files are stored as empty regular files and their kind is recorded
+/// in the file attributes.
+pub(crate) fn sys_mknodat(dirfd: i32, path: *const c_char, mode: u32, dev: u64) -> c_int {
+    syscall_body!(sys_mknodat, {
+        if mode & !(S_IFMT | 0o7777) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let node = match mode & S_IFMT {
+            0 | S_IFREG => None,
+            S_IFIFO => Some(SpecialNode::Fifo),
+            S_IFCHR => Some(SpecialNode::CharDevice(dev)),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let path = resolve_at(dirfd, path, false)?;
+        if axfs::api::metadata(&path).is_ok() {
+            return Err(LinuxError::EEXIST);
+        }
+        axfs::api::write(&path, "")?;
+        invalidate_dir_of(&path);
+        let mode = mode & !current().task_ext().get_umask() & 0o7777;
+        update_file_attr(&path, |attr| {
+            attr.mode = Some(mode);
+            attr.node = node;
+        });
+        Ok(0)
+    })
+}
+
+/// Create a symbolic link at `link_path` which contains the string `target`.
+///
+/// The target is not resolved, so dangling links are allowed. The filesystems
+/// behind `axfs` cannot represent symbolic links, so a regular file holding the
+/// target is created and the link is recorded in the file attributes.
+pub(crate) fn sys_symlinkat(target: *const c_char, new_dirfd: i32, link_path: *const u8) -> isize {
+    syscall_body!(sys_symlinkat, {
+        let target = arceos_posix_api::char_ptr_to_str(target)?;
+        if target.is_empty() {
+            return Err(LinuxError::ENOENT);
+        }
+        let link_path = resolve_at(new_dirfd, link_path as _, false)?;
+        if axfs::api::metadata(&link_path).is_ok() {
+            return Err(LinuxError::EEXIST);
+        }
+        axfs::api::write(&link_path, target)?;
+        invalidate_dir_of(&link_path);
+        update_file_attr(&link_path, |attr| attr.symlink = Some(target.into()));
+        Ok(0)
+    })
+}
+
+/// Read the target of a symbolic link into `buf`.
+///
+/// The target is truncated if `buf` is too small, and no trailing NUL is written.
+///
+/// # Returns
+/// The number of bytes placed in `buf`.
+pub(crate) fn sys_readlinkat(
+    dirfd: i32,
+    path: *const c_char,
+    buf: *mut u8,
+    bufsiz: isize,
+) -> isize {
+    syscall_body!(sys_readlinkat, {
+        if bufsiz <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let raw_path = arceos_posix_api::char_ptr_to_str(path)?;
+        let target = if raw_path == "/proc/self/exe" {
+            current().task_ext().exe_path.lock().clone()
+        } else {
+            let path = resolve_at(dirfd, path, false)?;
+            axfs::api::metadata(&path)?;
+            symlink_target(&path).ok_or(LinuxError::EINVAL)?
+        };
+
+        let len = core::cmp::min(target.len(), bufsiz as usize);
+        check_user_range(current().task_ext(), (buf as usize).into(), len)
+            .map_err(|_| LinuxError::EFAULT)?;
+        unsafe { core::ptr::copy_nonoverlapping(target.as_ptr(), buf, len) };
+        Ok(len as isize)
+    })
+}
+
+/// Copy the absolute path of the working directory of the current task, with
+/// a terminating NUL, into `buf` of `size` bytes, and return its length
+/// including the NUL.
+///
+/// Nothing is written if the path doesn't fit, which fails with `ERANGE`.
+pub(crate) fn sys_getcwd(buf: *mut c_char, size: usize) -> isize {
+    syscall_body!(sys_getcwd, {
+        if size == 0 && !buf.is_null() {
+            return Err(LinuxError::EINVAL);
+        }
+        let cwd = visible_path(&axfs::api::current_dir()?);
+        let len = cwd.len() + 1;
+        if size < len {
+            return Err(LinuxError::ERANGE);
+        }
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        check_user_range(current().task_ext(), (buf as usize).into(), len)
+            .map_err(|_| LinuxError::EFAULT)?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(cwd.as_ptr(), buf as *mut u8, cwd.len());
+            *buf.add(cwd.len()) = 0;
+        }
+        Ok(len as isize)
+    })
 }
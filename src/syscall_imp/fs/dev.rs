@@ -0,0 +1,268 @@
+//! Character devices, which are opened through device nodes.
+//!
+//! The nodes of the devices are created in `/dev` at boot, and more can be
+//! created by `mknod`.
+
+use alloc::{format, sync::Arc};
+use arceos_posix_api::{self as api, FileLike};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+use memory_addr::VirtAddr;
+
+use super::attr::{S_IFCHR, SpecialNode, update_file_attr};
+use crate::{mm::write_user_bytes, syscall_body};
+
+/// Combine the major and minor numbers into a device number like `makedev()`.
+const fn make_dev(major: u64, minor: u64) -> u64 {
+    ((major & !0xfff) << 32) | ((major & 0xfff) << 8) | ((minor & !0xff) << 12) | (minor & 0xff)
+}
+
+/// A character device that can be opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Device {
+    /// Discards writes and reads nothing.
+    Null,
+    /// Discards writes and reads zeros.
+    Zero,
+    /// Discards writes and reads pseudorandom bytes, never blocking.
+    Random,
+    /// The same as `Random`.
+    Urandom,
+}
+
+/// The devices with their names in `/dev` and their device numbers, which are those of Linux.
+const DEVICES: &[(&str, u64, Device)] = &[
+    ("null", make_dev(1, 3), Device::Null),
+    ("zero", make_dev(1, 5), Device::Zero),
+    ("random", make_dev(1, 8), Device::Random),
+    ("urandom", make_dev(1, 9), Device::Urandom),
+];
+
+impl Device {
+    /// Get the device with the device number `rdev`.
+    pub fn from_rdev(rdev: u64) -> Option<Self> {
+        DEVICES
+            .iter()
+            .find(|(_, dev, _)| *dev == rdev)
+            .map(|(_, _, device)| *device)
+    }
+
+    fn rdev(self) -> u64 {
+        DEVICES
+            .iter()
+            .find(|(_, _, device)| *device == self)
+            .map(|(_, dev, _)| *dev)
+            .unwrap()
+    }
+}
+
+/// Create the nodes of the devices in `/dev`, and seed the random generator.
+///
+/// A file that is already there, e.g. one provided by the filesystem, becomes
+/// the node, so that every device behaves the same.
+pub(crate) fn init_devices() {
+    seed_random();
+    if axfs::api::metadata("/dev").is_err() {
+        if let Err(err) = axfs::api::create_dir("/dev") {
+            warn!("Failed to create /dev: {err:?}");
+            return;
+        }
+        update_file_attr("/dev", |attr| attr.mode = Some(0o755));
+    }
+    for &(name, rdev, _) in DEVICES {
+        let path = format!("/dev/{name}");
+        if axfs::api::metadata(&path).is_err() {
+            if let Err(err) = axfs::api::write(&path, "") {
+                warn!("Failed to create {path}: {err:?}");
+                continue;
+            }
+        }
+        update_file_attr(&path, |attr| {
+            attr.mode = Some(0o666);
+            attr.node = Some(SpecialNode::CharDevice(rdev));
+        });
+    }
+}
+
+/// A ChaCha20 keystream used as a random number generator.
+///
+/// The key is replaced after every request by the next block of the stream, so
+/// the bytes already handed out can't be recovered from the state.
+struct ChaCha20 {
+    key: [u32; 8],
+    counter: u64,
+}
+
+impl ChaCha20 {
+    /// Seed the generator from the timers, whose low bits differ from boot to
+    /// boot. The cycle counter is read again last, after a varying delay.
+    fn from_timers() -> Self {
+        let seeds = [
+            axhal::time::current_ticks(),
+            axhal::time::monotonic_time_nanos(),
+            axhal::time::wall_time_nanos(),
+            axhal::time::current_ticks(),
+        ];
+        let mut key = [0; 8];
+        for (i, seed) in seeds.into_iter().enumerate() {
+            key[i * 2] = seed as u32;
+            key[i * 2 + 1] = (seed >> 32) as u32;
+        }
+        let mut generator = Self { key, counter: 0 };
+        generator.rekey();
+        generator
+    }
+
+    fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(16);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(12);
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(8);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(7);
+    }
+
+    /// Generate the next 64 bytes of the keystream.
+    fn block(&mut self) -> [u8; 64] {
+        let mut input = [0; 16];
+        input[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+        input[4..12].copy_from_slice(&self.key);
+        input[12] = self.counter as u32;
+        input[13] = (self.counter >> 32) as u32;
+        self.counter = self.counter.wrapping_add(1);
+
+        let mut state = input;
+        for _ in 0..10 {
+            Self::quarter_round(&mut state, 0, 4, 8, 12);
+            Self::quarter_round(&mut state, 1, 5, 9, 13);
+            Self::quarter_round(&mut state, 2, 6, 10, 14);
+            Self::quarter_round(&mut state, 3, 7, 11, 15);
+            Self::quarter_round(&mut state, 0, 5, 10, 15);
+            Self::quarter_round(&mut state, 1, 6, 11, 12);
+            Self::quarter_round(&mut state, 2, 7, 8, 13);
+            Self::quarter_round(&mut state, 3, 4, 9, 14);
+        }
+        let mut block = [0; 64];
+        for (i, word) in state.iter().enumerate() {
+            let word = word.wrapping_add(input[i]);
+            block[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        block
+    }
+
+    /// Replace the key by the next block of the keystream.
+    fn rekey(&mut self) {
+        let block = self.block();
+        for (i, word) in self.key.iter_mut().enumerate() {
+            *word = u32::from_le_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+        self.counter = 0;
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(64) {
+            chunk.copy_from_slice(&self.block()[..chunk.len()]);
+        }
+        self.rekey();
+    }
+}
+
+/// The generator behind `/dev/random`, `/dev/urandom` and `getrandom`, which
+/// is seeded by `seed_random` at boot.
+static RANDOM: Mutex<Option<ChaCha20>> = Mutex::new(None);
+
+/// Seed the generator, unless it has been seeded already.
+fn seed_random() {
+    RANDOM.lock().get_or_insert_with(ChaCha20::from_timers);
+}
+
+/// Fill `buf` with random bytes, the same as reading `/dev/urandom`.
+pub(crate) fn fill_random(buf: &mut [u8]) {
+    RANDOM
+        .lock()
+        .get_or_insert_with(ChaCha20::from_timers)
+        .fill(buf);
+}
+
+/// The flags of `getrandom`.
+const GRND_NONBLOCK: u32 = 1;
+const GRND_RANDOM: u32 = 2;
+const GRND_INSECURE: u32 = 4;
+
+/// Fill `buf` with `len` random bytes from the generator of `/dev/urandom`,
+/// and return how many have been written.
+///
+/// The generator never blocks, so `GRND_NONBLOCK` and `GRND_RANDOM` make no
+/// difference. A fault after some bytes have been written ends the call early
+/// rather than failing it.
+pub(crate) fn sys_getrandom(buf: *mut u8, len: usize, flags: u32) -> isize {
+    syscall_body!(sys_getrandom, {
+        if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0
+            || flags & (GRND_RANDOM | GRND_INSECURE) == GRND_RANDOM | GRND_INSECURE
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let task_ext = curr.task_ext();
+        let mut written = 0;
+        let mut chunk = [0; 256];
+        while written < len {
+            let chunk = &mut chunk[..(len - written).min(256)];
+            fill_random(chunk);
+            let start = VirtAddr::from(buf as usize + written);
+            if write_user_bytes(task_ext, start, chunk).is_err() {
+                if written == 0 {
+                    return Err(LinuxError::EFAULT);
+                }
+                break;
+            }
+            written += chunk.len();
+        }
+        Ok(written as isize)
+    })
+}
+
+impl FileLike for Device {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        match self {
+            Device::Null => return Ok(0),
+            Device::Zero => buf.fill(0),
+            Device::Random | Device::Urandom => fill_random(buf),
+        }
+        Ok(buf.len())
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> LinuxResult<api::ctypes::stat> {
+        Ok(api::ctypes::stat {
+            st_ino: self.rdev(),
+            st_nlink: 1,
+            st_mode: S_IFCHR | 0o666,
+            st_rdev: self.rdev(),
+            st_blksize: 4096,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: true,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
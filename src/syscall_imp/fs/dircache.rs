@@ -0,0 +1,169 @@
+//! The cache of directory entries.
+//!
+//! The entries of a directory are cached the first time it is listed, so that
+//! listing it again doesn't scan it anew, and the metadata of each entry is
+//! cached the first time it is looked up, so that stat-ing every entry of a
+//! large directory doesn't search it for each of them. The attributes emulated
+//! on top of the filesystems are not cached, but applied afresh.
+//!
+//! The entries of a directory are dropped whenever an entry is created in it,
+//! removed from it or renamed, and the metadata of an entry whenever the file
+//! is written to. Like the page cache, `/proc` is never cached.
+
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use alloc::{collections::btree_map::BTreeMap, format, string::String, sync::Arc, vec::Vec};
+use axerrno::LinuxResult;
+use axfs::api::FileType;
+use axsync::Mutex;
+
+use super::{stat::lookup, util::is_beneath};
+
+/// The metadata of a file that the filesystem reports.
+#[derive(Clone, Copy)]
+pub(super) struct EntryMetadata {
+    pub file_type: FileType,
+    /// The permission bits.
+    pub perm: u16,
+    pub size: u64,
+}
+
+impl From<axfs::api::Metadata> for EntryMetadata {
+    fn from(metadata: axfs::api::Metadata) -> Self {
+        Self {
+            file_type: metadata.file_type(),
+            perm: metadata.permissions().bits(),
+            size: metadata.len(),
+        }
+    }
+}
+
+/// An entry of a cached directory.
+pub(super) struct DirEntry {
+    pub name: String,
+    pub file_type: FileType,
+    /// The metadata of the file, once it has been looked up.
+    metadata: Mutex<Option<EntryMetadata>>,
+}
+
+struct DirCache {
+    /// The entries of the directories, keyed by their paths, in the order the
+    /// filesystem lists them.
+    dirs: BTreeMap<String, Arc<Vec<DirEntry>>>,
+    /// Counts the invalidations, so that the entries read from the filesystem
+    /// are not cached if the directory has changed in the meantime.
+    epoch: u64,
+}
+
+static DIR_CACHE: Mutex<DirCache> = Mutex::new(DirCache {
+    dirs: BTreeMap::new(),
+    epoch: 0,
+});
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+fn cache_key(path: &str) -> String {
+    axfs::api::canonicalize(path).unwrap_or_else(|_| path.into())
+}
+
+/// Split a canonical path into the path of its directory and its name.
+fn split_parent(path: &str) -> Option<(&str, &str)> {
+    let (parent, name) = path.rsplit_once('/')?;
+    Some((if parent.is_empty() { "/" } else { parent }, name))
+}
+
+/// Get the entries of the directory at `path`, listing it if it is not cached.
+pub(super) fn dir_entries(path: &str) -> LinuxResult<Arc<Vec<DirEntry>>> {
+    let path = cache_key(path);
+    let epoch = {
+        let cache = DIR_CACHE.lock();
+        if let Some(entries) = cache.dirs.get(&path) {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            return Ok(entries.clone());
+        }
+        cache.epoch
+    };
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    let entries: Arc<Vec<_>> = Arc::new(
+        axfs::api::read_dir(&path)?
+            .flatten()
+            .map(|entry| DirEntry {
+                name: entry.file_name(),
+                file_type: entry.file_type(),
+                metadata: Mutex::new(None),
+            })
+            .collect(),
+    );
+    let mut cache = DIR_CACHE.lock();
+    if cache.epoch == epoch && !is_beneath(&path, "/proc") {
+        cache.dirs.insert(path, entries.clone());
+    }
+    Ok(entries)
+}
+
+/// Get the metadata of the file at `path`, from the cache if its directory has
+/// been listed, and from the filesystem otherwise.
+pub(super) fn entry_metadata(path: &str) -> LinuxResult<EntryMetadata> {
+    let key = cache_key(path);
+    let cached = split_parent(&key).and_then(|(parent, name)| {
+        let entries = DIR_CACHE.lock().dirs.get(parent).cloned()?;
+        let index = entries.iter().position(|entry| entry.name == name)?;
+        Some((entries, index))
+    });
+    let Some((entries, index)) = cached else {
+        return Ok(lookup(path)?.into());
+    };
+    // An invalidation waits for the lookup, so it can't be missed.
+    let mut cached = entries[index].metadata.lock();
+    if let Some(metadata) = *cached {
+        HITS.fetch_add(1, Ordering::Relaxed);
+        return Ok(metadata);
+    }
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    let metadata = EntryMetadata::from(lookup(path)?);
+    *cached = Some(metadata);
+    Ok(metadata)
+}
+
+/// Drop the cached entries of the directory containing `path` after an entry
+/// has been created at `path`, or removed or renamed from it. If `path` is a
+/// directory, the entries of everything beneath it are dropped too.
+pub(super) fn invalidate_dir_of(path: &str) {
+    let key = cache_key(path);
+    let mut cache = DIR_CACHE.lock();
+    cache.epoch += 1;
+    if let Some((parent, _)) = split_parent(&key) {
+        cache.dirs.remove(parent);
+    }
+    cache.dirs.retain(|dir, _| !is_beneath(dir, &key));
+}
+
+/// Drop the cached metadata of the file at `path` after it has been written to.
+pub(super) fn invalidate_dir_entry(path: &str) {
+    let key = cache_key(path);
+    let Some((parent, name)) = split_parent(&key) else {
+        return;
+    };
+    if let Some(entries) = DIR_CACHE.lock().dirs.get(parent) {
+        for entry in entries.iter().filter(|entry| entry.name == name) {
+            *entry.metadata.lock() = None;
+        }
+    }
+}
+
+/// Generate `/proc/dircache`, the statistics of the directory entry cache in
+/// the format of `/proc/meminfo`.
+pub(super) fn dir_cache_stats() -> String {
+    let mut stats = String::new();
+    for (name, value) in [
+        ("Hits", HITS.load(Ordering::Relaxed)),
+        ("Misses", MISSES.load(Ordering::Relaxed)),
+        ("Directories", DIR_CACHE.lock().dirs.len() as u64),
+    ] {
+        writeln!(stats, "{:<16}{:>8}", format!("{name}:"), value).unwrap();
+    }
+    stats
+}
@@ -0,0 +1,294 @@
+use core::{ffi::c_int, time::Duration};
+
+use alloc::{
+    collections::btree_map::{BTreeMap, Entry},
+    sync::{Arc, Weak},
+};
+use arceos_posix_api::{self as api, FileLike};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+
+use super::{
+    fd_ops::O_CLOEXEC,
+    poll::{POLLERR, POLLHUP, POLLIN, POLLOUT, apply_sigmask, fd_events, wait_ready},
+};
+use crate::{ctypes::EpollEvent, mm::check_user_range, syscall_body};
+
+const EPOLL_CTL_ADD: i32 = 1;
+const EPOLL_CTL_DEL: i32 = 2;
+const EPOLL_CTL_MOD: i32 = 3;
+
+const EPOLLIN: u32 = POLLIN as u32;
+const EPOLLOUT: u32 = POLLOUT as u32;
+const EPOLLERR: u32 = POLLERR as u32;
+const EPOLLHUP: u32 = POLLHUP as u32;
+const EPOLLRDHUP: u32 = 0x2000;
+const EPOLLONESHOT: u32 = 1 << 30;
+const EPOLLET: u32 = 1 << 31;
+
+/// The flag of `epoll_create1`, which is the same as `O_CLOEXEC`.
+const EPOLL_CLOEXEC: u32 = O_CLOEXEC;
+
+/// An fd registered in an epoll instance.
+struct Interest {
+    /// The file the fd referred to when it was registered.
+    file: Weak<dyn FileLike>,
+    events: u32,
+    data: u64,
+}
+
+impl Interest {
+    /// Whether `fd` still refers to the file it was registered with.
+    ///
+    /// Closing the fd makes this false, after which the fd is no longer in the
+    /// interest list, even if the fd is reused for another file.
+    fn is_alive(&self, fd: c_int) -> bool {
+        api::get_file_like(fd)
+            .is_ok_and(|file| Arc::as_ptr(&file) as *const () == self.file.as_ptr() as *const ())
+    }
+}
+
+/// An epoll instance, which is held in the fd table.
+///
+/// Only level-triggered notification is supported.
+pub(crate) struct EpollInstance {
+    interests: Mutex<BTreeMap<c_int, Interest>>,
+}
+
+impl EpollInstance {
+    fn new() -> Self {
+        Self {
+            interests: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Drop the interests whose fds have been closed.
+    fn remove_closed(interests: &mut BTreeMap<c_int, Interest>) {
+        interests.retain(|&fd, interest| interest.is_alive(fd));
+    }
+
+    fn ctl(&self, op: i32, fd: c_int, file: &Arc<dyn FileLike>, event: EpollEvent) -> LinuxResult {
+        let mut interests = self.interests.lock();
+        Self::remove_closed(&mut interests);
+        match (op, interests.entry(fd)) {
+            (EPOLL_CTL_ADD, Entry::Vacant(entry)) => {
+                entry.insert(Interest {
+                    file: Arc::downgrade(file),
+                    events: event.events,
+                    data: event.data,
+                });
+            }
+            (EPOLL_CTL_ADD, Entry::Occupied(_)) => return Err(LinuxError::EEXIST),
+            (EPOLL_CTL_MOD, Entry::Occupied(mut entry)) => {
+                let interest = entry.get_mut();
+                interest.events = event.events;
+                interest.data = event.data;
+            }
+            (EPOLL_CTL_DEL, Entry::Occupied(entry)) => {
+                entry.remove();
+            }
+            (EPOLL_CTL_MOD | EPOLL_CTL_DEL, Entry::Vacant(_)) => return Err(LinuxError::ENOENT),
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(())
+    }
+
+    /// Store the ready events in `events`, returning how many there are.
+    ///
+    /// An interest with `EPOLLONESHOT` is disabled once it has been reported.
+    fn collect_ready(&self, events: &mut [EpollEvent]) -> LinuxResult<usize> {
+        let mut interests = self.interests.lock();
+        Self::remove_closed(&mut interests);
+        let mut count = 0;
+        for (&fd, interest) in interests.iter_mut() {
+            if count == events.len() {
+                break;
+            }
+            let ready = ready_events(fd)? & (interest.events | EPOLLERR | EPOLLHUP);
+            if ready == 0 {
+                continue;
+            }
+            events[count] = EpollEvent {
+                events: ready,
+                data: interest.data,
+            };
+            count += 1;
+            if interest.events & EPOLLONESHOT != 0 {
+                // Only `EPOLL_CTL_MOD` can enable it again.
+                interest.events = 0;
+            }
+        }
+        Ok(count)
+    }
+}
+
+impl FileLike for EpollInstance {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
+    fn stat(&self) -> LinuxResult<api::ctypes::stat> {
+        Ok(api::ctypes::stat {
+            st_ino: self as *const Self as u64,
+            st_nlink: 1,
+            st_mode: 0o600,
+            st_uid: 1000,
+            st_gid: 1000,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    /// An epoll instance is readable when some of its fds are ready.
+    fn poll(&self) -> LinuxResult<PollState> {
+        let interests = self.interests.lock();
+        let mut readable = false;
+        for (&fd, interest) in interests.iter() {
+            if interest.is_alive(fd)
+                && ready_events(fd)? & (interest.events | EPOLLERR | EPOLLHUP) != 0
+            {
+                readable = true;
+                break;
+            }
+        }
+        Ok(PollState {
+            readable,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+        Ok(())
+    }
+}
+
+/// Get the events currently pending on `fd`, as in `events` of `epoll_event`.
+fn ready_events(fd: c_int) -> LinuxResult<u32> {
+    let mut events = fd_events(fd)? as u16 as u32;
+    // A hangup of a pipe means the writing side is closed.
+    if events & EPOLLHUP != 0 {
+        events |= EPOLLRDHUP;
+    }
+    Ok(events)
+}
+
+fn epoll_instance(epfd: c_int) -> LinuxResult<Arc<EpollInstance>> {
+    api::get_file_like(epfd)?
+        .into_any()
+        .downcast::<EpollInstance>()
+        .map_err(|_| LinuxError::EINVAL)
+}
+
+/// Create an epoll instance and return an fd referring to it.
+///
+/// `flags` may only contain `EPOLL_CLOEXEC`.
+pub(crate) fn sys_epoll_create1(flags: u32) -> c_int {
+    syscall_body!(sys_epoll_create1, {
+        if flags & !EPOLL_CLOEXEC != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let fd = api::add_file_like(Arc::new(EpollInstance::new()))?;
+        current()
+            .task_ext()
+            .set_close_on_exec(fd, flags & EPOLL_CLOEXEC != 0);
+        Ok(fd)
+    })
+}
+
+/// The older form of `epoll_create1`, where `size` is ignored but must be positive.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_epoll_create(size: c_int) -> c_int {
+    if size <= 0 {
+        return -LinuxError::EINVAL.code();
+    }
+    sys_epoll_create1(0)
+}
+
+/// Add, modify or remove the interest in `fd` of the epoll instance `epfd`.
+///
+/// Edge-triggered notification with `EPOLLET` is not supported yet, and is
+/// rejected with `EINVAL`. So are nested epoll instances.
+pub(crate) fn sys_epoll_ctl(epfd: c_int, op: i32, fd: c_int, event: *const EpollEvent) -> c_int {
+    syscall_body!(sys_epoll_ctl, {
+        let epoll = epoll_instance(epfd)?;
+        let file = api::get_file_like(fd)?;
+        if fd == epfd || file.clone().into_any().is::<EpollInstance>() {
+            return Err(LinuxError::EINVAL);
+        }
+        // Like Linux, regular files and directories, which are always ready, can't be watched.
+        let any = file.clone().into_any();
+        if any.is::<api::File>() || any.is::<api::Directory>() {
+            return Err(LinuxError::EPERM);
+        }
+        let event = if op == EPOLL_CTL_DEL {
+            EpollEvent { events: 0, data: 0 }
+        } else {
+            check_user_range(
+                current().task_ext(),
+                (event as usize).into(),
+                size_of::<EpollEvent>(),
+            )
+            .map_err(|_| LinuxError::EFAULT)?;
+            let event = unsafe { event.read() };
+            if event.events & EPOLLET != 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            event
+        };
+        epoll.ctl(op, fd, &file, event)?;
+        Ok(0)
+    })
+}
+
+/// Wait for some of the fds of the epoll instance `epfd` to become ready, storing
+/// at most `maxevents` of them in `events`.
+///
+/// The timeout is in milliseconds, where a negative one waits forever. Unless
+/// `sigmask` is null, its signals are blocked instead while waiting.
+pub(crate) fn sys_epoll_pwait(
+    epfd: c_int,
+    events: *mut EpollEvent,
+    maxevents: c_int,
+    timeout: c_int,
+    sigmask: *const u64,
+    sigsetsize: usize,
+) -> c_int {
+    syscall_body!(sys_epoll_pwait, {
+        let maxevents = usize::try_from(maxevents)
+            .ok()
+            .filter(|&maxevents| maxevents > 0)
+            .ok_or(LinuxError::EINVAL)?;
+        let epoll = epoll_instance(epfd)?;
+        check_user_range(
+            current().task_ext(),
+            (events as usize).into(),
+            maxevents * size_of::<EpollEvent>(),
+        )
+        .map_err(|_| LinuxError::EFAULT)?;
+        let events = unsafe { core::slice::from_raw_parts_mut(events, maxevents) };
+        let timeout = u64::try_from(timeout).ok().map(Duration::from_millis);
+        apply_sigmask(sigmask, sigsetsize)?;
+        let count = wait_ready(timeout, || epoll.collect_ready(events))?;
+        Ok(count as c_int)
+    })
+}
+
+/// Like `epoll_pwait` without a signal mask.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_epoll_wait(
+    epfd: c_int,
+    events: *mut EpollEvent,
+    maxevents: c_int,
+    timeout: c_int,
+) -> c_int {
+    sys_epoll_pwait(epfd, events, maxevents, timeout, core::ptr::null(), 0)
+}
@@ -0,0 +1,150 @@
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::sync::Arc;
+use arceos_posix_api::{self as api, FileLike};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, WaitQueue, current};
+
+use super::{
+    fd_ops::{O_CLOEXEC, set_file_status_flags},
+    poll::notify_pollers,
+};
+use crate::syscall_body;
+
+/// Read the counter by decrementing it by 1 rather than clearing it.
+const EFD_SEMAPHORE: u32 = 1;
+const EFD_CLOEXEC: u32 = O_CLOEXEC;
+const EFD_NONBLOCK: u32 = api::ctypes::O_NONBLOCK;
+
+/// The largest value the counter can hold.
+const MAX_COUNT: u64 = u64::MAX - 1;
+
+/// An eventfd, which is a 64-bit counter used to notify events.
+struct EventFd {
+    count: Mutex<u64>,
+    semaphore: bool,
+    nonblocking: AtomicBool,
+    /// Readers waiting for the counter to be nonzero and writers waiting for space.
+    wait_queue: WaitQueue,
+}
+
+impl EventFd {
+    fn notify(&self) {
+        self.wait_queue.notify_all(false);
+        notify_pollers();
+    }
+}
+
+impl FileLike for EventFd {
+    /// Read the counter into the first 8 bytes of `buf` once it is nonzero.
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.len() < size_of::<u64>() {
+            return Err(LinuxError::EINVAL);
+        }
+        loop {
+            let mut count = self.count.lock();
+            if *count > 0 {
+                let value = if self.semaphore { 1 } else { *count };
+                *count -= value;
+                drop(count);
+                buf[..size_of::<u64>()].copy_from_slice(&value.to_ne_bytes());
+                self.notify();
+                return Ok(size_of::<u64>());
+            }
+            if self.nonblocking.load(Ordering::Relaxed) {
+                return Err(LinuxError::EAGAIN);
+            }
+            drop(count);
+            self.wait_queue.wait_until(|| *self.count.lock() > 0);
+        }
+    }
+
+    /// Add the value in the first 8 bytes of `buf` to the counter, waiting until
+    /// it fits.
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        let value = buf
+            .get(..size_of::<u64>())
+            .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+            .ok_or(LinuxError::EINVAL)?;
+        if value == u64::MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        loop {
+            let mut count = self.count.lock();
+            if MAX_COUNT - *count >= value {
+                *count += value;
+                drop(count);
+                self.notify();
+                return Ok(size_of::<u64>());
+            }
+            if self.nonblocking.load(Ordering::Relaxed) {
+                return Err(LinuxError::EAGAIN);
+            }
+            drop(count);
+            self.wait_queue
+                .wait_until(|| MAX_COUNT - *self.count.lock() >= value);
+        }
+    }
+
+    fn stat(&self) -> LinuxResult<api::ctypes::stat> {
+        Ok(api::ctypes::stat {
+            st_ino: self as *const Self as u64,
+            st_nlink: 1,
+            st_mode: 0o600,
+            st_uid: 1000,
+            st_gid: 1000,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let count = *self.count.lock();
+        Ok(PollState {
+            readable: count > 0,
+            writable: count < MAX_COUNT,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Create an eventfd with the counter set to `initval`, and return an fd referring to it.
+///
+/// `flags` may contain `EFD_SEMAPHORE`, `EFD_CLOEXEC` and `EFD_NONBLOCK`.
+pub(crate) fn sys_eventfd2(initval: u32, flags: u32) -> c_int {
+    syscall_body!(sys_eventfd2, {
+        if flags & !(EFD_SEMAPHORE | EFD_CLOEXEC | EFD_NONBLOCK) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let eventfd: Arc<dyn FileLike> = Arc::new(EventFd {
+            count: Mutex::new(initval as u64),
+            semaphore: flags & EFD_SEMAPHORE != 0,
+            nonblocking: AtomicBool::new(flags & EFD_NONBLOCK != 0),
+            wait_queue: WaitQueue::new(),
+        });
+        set_file_status_flags(&eventfd, api::ctypes::O_RDWR | (flags & EFD_NONBLOCK));
+        let fd = api::add_file_like(eventfd)?;
+        current()
+            .task_ext()
+            .set_close_on_exec(fd, flags & EFD_CLOEXEC != 0);
+        Ok(fd)
+    })
+}
+
+/// The older form of `eventfd2` without flags.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_eventfd(initval: u32) -> c_int {
+    sys_eventfd2(initval, 0)
+}
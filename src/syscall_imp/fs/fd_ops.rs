@@ -1,15 +1,239 @@
 use core::ffi::c_int;
 
-use arceos_posix_api as api;
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use arceos_posix_api::{self as api, FD_TABLE, FileLike};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+
+use num_enum::TryFromPrimitive;
+
+use super::{
+    attr::fd_path,
+    flock::{fcntl_getlk, fcntl_setlk, release_record_locks},
+};
+use crate::syscall_body;
+
+/// The close-on-exec flag of `open`, `dup3` and friends.
+pub(crate) const O_CLOEXEC: u32 = 0o2000000;
+/// The close-on-exec flag of `F_GETFD` and `F_SETFD`.
+const FD_CLOEXEC: usize = 1;
+/// Mask for the access mode in the open flags.
+pub(crate) const O_ACCMODE: u32 = 0o3;
+
+/// The status flags of the open files, e.g. the access mode, `O_APPEND` and `O_NONBLOCK`.
+///
+/// Like the file offset, they belong to the open file rather than to the fd, so
+/// duplicated fds share them. A weak reference is kept to tell whether the file is
+/// still open, since the address of a closed file may be reused by a newly opened one.
+static FILE_STATUS_FLAGS: Mutex<BTreeMap<usize, (Weak<dyn FileLike>, u32)>> =
+    Mutex::new(BTreeMap::new());
+
+/// Get the status flags of an open file.
+///
+/// Files that were not opened by `sys_openat` (e.g. stdio) are readable and writable.
+pub(crate) fn file_status_flags(file: &Arc<dyn FileLike>) -> u32 {
+    match FILE_STATUS_FLAGS
+        .lock()
+        .get(&(Arc::as_ptr(file) as *const () as usize))
+    {
+        Some((weak, flags)) if weak.strong_count() > 0 => *flags,
+        _ => api::ctypes::O_RDWR,
+    }
+}
+
+/// Set the status flags of an open file.
+pub(crate) fn set_file_status_flags(file: &Arc<dyn FileLike>, flags: u32) {
+    let mut status_flags = FILE_STATUS_FLAGS.lock();
+    status_flags.retain(|_, (weak, _)| weak.strong_count() > 0);
+    status_flags.insert(
+        Arc::as_ptr(file) as *const () as usize,
+        (Arc::downgrade(file), flags),
+    );
+}
+
+/// Add `file` to the fd table at the lowest free fd that is not less than `min_fd`.
+fn add_file_like_from(file: Arc<dyn FileLike>, min_fd: usize) -> LinuxResult<c_int> {
+    let mut table = FD_TABLE.write();
+    let fd = (min_fd..table.capacity())
+        .find(|fd| table.get(*fd).is_none())
+        .ok_or(LinuxError::EMFILE)?;
+    table.add_at(fd, file).map_err(|_| LinuxError::EMFILE)?;
+    Ok(fd as c_int)
+}
 
 pub(crate) fn sys_dup(old_fd: c_int) -> c_int {
     api::sys_dup(old_fd)
 }
 
-pub(crate) fn sys_dup3(old_fd: c_int, new_fd: c_int) -> c_int {
-    api::sys_dup2(old_fd, new_fd)
+/// Duplicate `old_fd` onto `new_fd`, closing `new_fd` first if it is open.
+///
+/// Nothing is done if `old_fd` equals `new_fd`, except checking that it is valid.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
+    if old_fd != new_fd && api::get_file_like(old_fd).is_ok() {
+        release_locks_on_close(new_fd);
+    }
+    let fd = api::sys_dup2(old_fd, new_fd);
+    if fd >= 0 && old_fd != new_fd {
+        current().task_ext().set_close_on_exec(new_fd, false);
+    }
+    fd
+}
+
+/// The same as `dup2`, except that the close-on-exec flag of `new_fd` can be set
+/// with `O_CLOEXEC` in `flags`, and `old_fd` equal to `new_fd` is an error.
+pub(crate) fn sys_dup3(old_fd: c_int, new_fd: c_int, flags: c_int) -> c_int {
+    syscall_body!(sys_dup3, {
+        let flags = flags as u32;
+        if old_fd == new_fd || flags & !O_CLOEXEC != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        api::get_file_like(old_fd)?;
+        release_locks_on_close(new_fd);
+        let fd = api::sys_dup2(old_fd, new_fd);
+        if fd < 0 {
+            return Err(LinuxError::try_from(-fd).unwrap_or(LinuxError::EBADF));
+        }
+        current()
+            .task_ext()
+            .set_close_on_exec(new_fd, flags & O_CLOEXEC != 0);
+        Ok(fd)
+    })
+}
+
+/// Release the record locks of the current process on the file referred to by
+/// `fd` before it is closed, as closing any fd referring to the file does.
+fn release_locks_on_close(fd: c_int) {
+    if let Ok(path) = fd_path(fd) {
+        release_record_locks(current().task_ext().proc_id, Some(&path));
+    }
 }
 
 pub(crate) fn sys_close(fd: c_int) -> c_int {
-    api::sys_close(fd)
+    release_locks_on_close(fd);
+    let ret = api::sys_close(fd);
+    if ret == 0 {
+        current().task_ext().set_close_on_exec(fd, false);
+    }
+    ret
+}
+
+/// Unshare the fd table before closing, which is always unshared here.
+const CLOSE_RANGE_UNSHARE: u32 = 1 << 1;
+/// Set the close-on-exec flag of the fds instead of closing them.
+const CLOSE_RANGE_CLOEXEC: u32 = 1 << 2;
+
+/// Close the fds from `first` to `last` inclusive, or set their close-on-exec
+/// flag with `CLOSE_RANGE_CLOEXEC` in `flags`.
+///
+/// The fds in the range that are not open are skipped.
+pub(crate) fn sys_close_range(first: u32, last: u32, flags: u32) -> c_int {
+    syscall_body!(sys_close_range, {
+        if flags & !(CLOSE_RANGE_UNSHARE | CLOSE_RANGE_CLOEXEC) != 0 || first > last {
+            return Err(LinuxError::EINVAL);
+        }
+        let fds: Vec<c_int> = {
+            let table = FD_TABLE.read();
+            table
+                .ids()
+                .filter(|fd| (first as usize..=last as usize).contains(fd))
+                .map(|fd| fd as c_int)
+                .collect()
+        };
+        for fd in fds {
+            if flags & CLOSE_RANGE_CLOEXEC != 0 {
+                current().task_ext().set_close_on_exec(fd, true);
+            } else {
+                sys_close(fd);
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// fcntl commands
+///
+/// See <https://man7.org/linux/man-pages/man2/fcntl.2.html>
+#[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
+#[repr(i32)]
+enum FcntlCmd {
+    /// Duplicate the fd using the lowest fd greater than or equal to the argument.
+    DupFd = 0,
+    /// Get the fd flags.
+    GetFd = 1,
+    /// Set the fd flags.
+    SetFd = 2,
+    /// Get the file status flags.
+    GetFl = 3,
+    /// Set the file status flags.
+    SetFl = 4,
+    /// Get the first record lock that would conflict with the given one.
+    GetLk = 5,
+    /// Set or remove a record lock, failing if another process holds a conflicting one.
+    SetLk = 6,
+    /// The same as `F_SETLK`, but wait for the conflicting locks to be released.
+    SetLkw = 7,
+    /// The same as `F_DUPFD`, but also set the close-on-exec flag of the new fd.
+    DupFdCloexec = 1030,
+    /// Add seals to a memfd, which is not supported.
+    AddSeals = 1033,
+    /// Get the seals of a memfd.
+    GetSeals = 1034,
+}
+
+/// Manipulate the file descriptor `fd`.
+///
+/// # Arguments
+/// * `fd` - The file descriptor
+/// * `cmd` - The operation to perform, see [`FcntlCmd`]
+/// * `arg` - The argument of the operation, if any
+pub(crate) fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
+    syscall_body!(sys_fcntl, {
+        let file = api::get_file_like(fd)?;
+        let curr = current();
+        let Ok(cmd) = FcntlCmd::try_from(cmd) else {
+            warn!("Unsupported fcntl command: {cmd}");
+            return Err(LinuxError::EINVAL);
+        };
+        match cmd {
+            FcntlCmd::DupFd | FcntlCmd::DupFdCloexec => {
+                let new_fd = add_file_like_from(file, arg)?;
+                curr.task_ext()
+                    .set_close_on_exec(new_fd, cmd == FcntlCmd::DupFdCloexec);
+                Ok(new_fd)
+            }
+            FcntlCmd::GetFd => Ok(if curr.task_ext().is_close_on_exec(fd) {
+                FD_CLOEXEC as c_int
+            } else {
+                0
+            }),
+            FcntlCmd::SetFd => {
+                curr.task_ext().set_close_on_exec(fd, arg & FD_CLOEXEC != 0);
+                Ok(0)
+            }
+            FcntlCmd::GetLk => fcntl_getlk(fd, arg as _),
+            FcntlCmd::SetLk | FcntlCmd::SetLkw => {
+                fcntl_setlk(fd, &file, arg as _, cmd == FcntlCmd::SetLkw)
+            }
+            FcntlCmd::GetFl => Ok(file_status_flags(&file) as c_int),
+            FcntlCmd::SetFl => {
+                // Only these flags can be changed, the others are ignored.
+                let changeable = api::ctypes::O_APPEND | api::ctypes::O_NONBLOCK;
+                let new_flags = arg as u32 & changeable;
+                let flags = file_status_flags(&file);
+                if (flags ^ new_flags) & api::ctypes::O_NONBLOCK != 0 {
+                    file.set_nonblocking(new_flags & api::ctypes::O_NONBLOCK != 0)?;
+                }
+                set_file_status_flags(&file, (flags & !changeable) | new_flags);
+                Ok(0)
+            }
+            // No file supports seals.
+            FcntlCmd::AddSeals | FcntlCmd::GetSeals => Err(LinuxError::EINVAL),
+        }
+    })
 }
@@ -0,0 +1,371 @@
+use core::ffi::c_int;
+
+use alloc::{
+    collections::btree_map::BTreeMap,
+    string::String,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use arceos_posix_api::{self as api, FileLike};
+use axerrno::{LinuxError, LinuxResult};
+use axstd::io::SeekFrom;
+use axsync::Mutex;
+use axtask::{TaskExtRef, WaitQueue, current};
+
+use super::{
+    attr::{fd_path, resolve_symlinks},
+    fd_ops::{O_ACCMODE, file_status_flags},
+};
+use crate::{ctypes::Flock, mm::check_user_range, signal::wait_interruptible, syscall_body};
+
+const LOCK_SH: i32 = 1;
+const LOCK_EX: i32 = 2;
+const LOCK_NB: i32 = 4;
+const LOCK_UN: i32 = 8;
+
+/// A lock held by an open file.
+struct Holder {
+    /// The open file, which all fds duplicated from it share.
+    file: Weak<dyn FileLike>,
+    exclusive: bool,
+}
+
+impl Holder {
+    fn is(&self, file: &Arc<dyn FileLike>) -> bool {
+        self.file.as_ptr() as *const () == Arc::as_ptr(file) as *const ()
+    }
+}
+
+/// The locks held on each file, keyed by its canonical path.
+///
+/// A lock is released when its open file is dropped, i.e. when the last fd
+/// referring to it is closed, including when the task exits.
+static FILE_LOCKS: Mutex<BTreeMap<String, Vec<Holder>>> = Mutex::new(BTreeMap::new());
+
+/// Tasks waiting for a lock, woken up when one is released with `LOCK_UN`.
+///
+/// Locks released by closing the file notify nobody, which the waiters notice
+/// as they check for signals every now and then.
+static FLOCK_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// Remove the lock of `file` on the file at `key`, returning whether it held one.
+fn unlock(key: &str, file: &Arc<dyn FileLike>) -> bool {
+    let mut locks = FILE_LOCKS.lock();
+    let Some(holders) = locks.get_mut(key) else {
+        return false;
+    };
+    let len = holders.len();
+    holders.retain(|holder| !holder.is(file) && holder.file.strong_count() > 0);
+    let unlocked = holders.len() < len;
+    if holders.is_empty() {
+        locks.remove(key);
+    }
+    unlocked
+}
+
+/// Take the lock on the file at `key` for `file` if no other open file holds a
+/// conflicting one.
+fn try_lock(key: &str, file: &Arc<dyn FileLike>, exclusive: bool) -> bool {
+    let mut locks = FILE_LOCKS.lock();
+    let holders = locks.entry(key.into()).or_default();
+    holders.retain(|holder| holder.file.strong_count() > 0);
+    if holders
+        .iter()
+        .any(|holder| !holder.is(file) && (exclusive || holder.exclusive))
+    {
+        return false;
+    }
+    holders.retain(|holder| !holder.is(file));
+    holders.push(Holder {
+        file: Arc::downgrade(file),
+        exclusive,
+    });
+    true
+}
+
+/// Apply or remove an advisory lock on the file referred to by `fd`.
+///
+/// `op` is one of `LOCK_SH`, `LOCK_EX` and `LOCK_UN`, optionally with `LOCK_NB`
+/// to fail with `EWOULDBLOCK` instead of waiting for a conflicting lock. Locks
+/// belong to the open file, so they are shared by duplicated fds, but those of
+/// independently opened fds conflict. Converting a lock is not atomic: the old
+/// one is released before the new one is taken, like Linux does. Waiting for a
+/// lock is interrupted by a signal, which fails with `EINTR`.
+pub(crate) fn sys_flock(fd: c_int, op: i32) -> c_int {
+    syscall_body!(sys_flock, {
+        let file = api::get_file_like(fd)?;
+        let exclusive = match op & !LOCK_NB {
+            LOCK_SH => false,
+            LOCK_EX => true,
+            LOCK_UN => {
+                if let Ok(path) = fd_path(fd) {
+                    if unlock(&resolve_symlinks(&path, true)?, &file) {
+                        FLOCK_WAIT_QUEUE.notify_all(false);
+                    }
+                }
+                return Ok(0);
+            }
+            _ => return Err(LinuxError::EINVAL),
+        };
+        // Other kinds of files can't be opened again, so nothing could conflict.
+        let Ok(path) = fd_path(fd) else {
+            return Ok(0);
+        };
+        let key = resolve_symlinks(&path, true)?;
+        if unlock(&key, &file) {
+            FLOCK_WAIT_QUEUE.notify_all(false);
+        }
+        let locked = if op & LOCK_NB != 0 {
+            try_lock(&key, &file, exclusive)
+        } else {
+            wait_interruptible(&FLOCK_WAIT_QUEUE, None, || try_lock(&key, &file, exclusive))?
+        };
+        if !locked {
+            // `EWOULDBLOCK` is the same as `EAGAIN`.
+            return Err(LinuxError::EAGAIN);
+        }
+        Ok(0)
+    })
+}
+
+const F_RDLCK: i16 = 0;
+const F_WRLCK: i16 = 1;
+const F_UNLCK: i16 = 2;
+
+/// A POSIX record lock on a range of a file, held by a process.
+#[derive(Clone, Copy)]
+struct RecordLock {
+    pid: usize,
+    start: u64,
+    /// The end of the range, exclusive, where `u64::MAX` stands for the end of the file however it grows.
+    end: u64,
+    write: bool,
+}
+
+impl RecordLock {
+    fn overlaps(&self, start: u64, end: u64) -> bool {
+        self.start < end && start < self.end
+    }
+
+    fn conflicts(&self, other: &RecordLock) -> bool {
+        self.pid != other.pid
+            && self.overlaps(other.start, other.end)
+            && (self.write || other.write)
+    }
+}
+
+/// The record locks on each file, keyed by its canonical path.
+static RECORD_LOCKS: Mutex<BTreeMap<String, Vec<RecordLock>>> = Mutex::new(BTreeMap::new());
+
+/// Tasks waiting in `F_SETLKW`, woken up whenever record locks are released.
+static RECORD_LOCK_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// Remove the locks of `pid` on `[start, end)`, splitting those partly in the range.
+fn remove_range(locks: &mut Vec<RecordLock>, pid: usize, start: u64, end: u64) {
+    let mut kept = Vec::with_capacity(locks.len());
+    for lock in locks.drain(..) {
+        if lock.pid != pid || !lock.overlaps(start, end) {
+            kept.push(lock);
+            continue;
+        }
+        if lock.start < start {
+            kept.push(RecordLock { end: start, ..lock });
+        }
+        if end < lock.end {
+            kept.push(RecordLock { start: end, ..lock });
+        }
+    }
+    *locks = kept;
+}
+
+/// Merge the locks of `pid` of the same type which overlap or are adjacent.
+fn merge_locks(locks: &mut Vec<RecordLock>, pid: usize) {
+    let (mut own, others): (Vec<_>, Vec<_>) = locks.drain(..).partition(|lock| lock.pid == pid);
+    own.sort_by_key(|lock| lock.start);
+    let mut merged: Vec<RecordLock> = Vec::with_capacity(own.len());
+    for lock in own {
+        match merged.last_mut() {
+            Some(last) if last.write == lock.write && lock.start <= last.end => {
+                last.end = last.end.max(lock.end);
+            }
+            _ => merged.push(lock),
+        }
+    }
+    *locks = others;
+    locks.extend(merged);
+}
+
+/// Set the lock described by `new`, or remove the locks of its process in its
+/// range if `unlock` is set, unless another process holds a conflicting lock.
+fn try_set_record_lock(key: &str, new: RecordLock, unlock: bool) -> bool {
+    let mut all_locks = RECORD_LOCKS.lock();
+    let locks = all_locks.entry(key.into()).or_default();
+    if !unlock && locks.iter().any(|lock| lock.conflicts(&new)) {
+        return false;
+    }
+    remove_range(locks, new.pid, new.start, new.end);
+    if !unlock {
+        locks.push(new);
+        merge_locks(locks, new.pid);
+    }
+    if locks.is_empty() {
+        all_locks.remove(key);
+    }
+    true
+}
+
+/// Release all the record locks of `pid` on the file at `path`, or on every file
+/// if `path` is `None`.
+///
+/// This happens when the process closes any fd referring to the file, or exits.
+pub(crate) fn release_record_locks(pid: usize, path: Option<&str>) {
+    let key = path.map(|path| resolve_symlinks(path, true).unwrap_or_else(|_| path.into()));
+    let mut all_locks = RECORD_LOCKS.lock();
+    let mut released = false;
+    all_locks.retain(|file, locks| {
+        if key.as_ref().is_none_or(|key| key == file) {
+            let len = locks.len();
+            locks.retain(|lock| lock.pid != pid);
+            released |= locks.len() < len;
+        }
+        !locks.is_empty()
+    });
+    drop(all_locks);
+    if released {
+        RECORD_LOCK_WAIT_QUEUE.notify_all(false);
+    }
+}
+
+/// Get the range `[start, end)` described by `flock`, relative to the file referred to by `fd`.
+fn flock_range(fd: c_int, flock: &Flock) -> LinuxResult<(u64, u64)> {
+    const SEEK_SET: i16 = 0;
+    const SEEK_CUR: i16 = 1;
+    const SEEK_END: i16 = 2;
+
+    let file = api::get_file_like(fd)?
+        .into_any()
+        .downcast::<api::File>()
+        .map_err(|_| LinuxError::EINVAL)?;
+    let base = match flock.l_whence {
+        SEEK_SET => 0,
+        SEEK_CUR => file.inner().lock().seek(SeekFrom::Current(0))? as i64,
+        SEEK_END => file.inner().lock().get_attr()?.size() as i64,
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let start = base
+        .checked_add(flock.l_start)
+        .ok_or(LinuxError::EOVERFLOW)?;
+    // A negative length locks the bytes before `start`.
+    let (start, end) = match flock.l_len {
+        0 => (start, None),
+        len if len > 0 => (
+            start,
+            Some(start.checked_add(len).ok_or(LinuxError::EOVERFLOW)?),
+        ),
+        len => (
+            start.checked_add(len).ok_or(LinuxError::EINVAL)?,
+            Some(start),
+        ),
+    };
+    if start < 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok((start as u64, end.map_or(u64::MAX, |end| end as u64)))
+}
+
+fn read_flock(flock: *const Flock) -> LinuxResult<Flock> {
+    check_user_range(
+        current().task_ext(),
+        (flock as usize).into(),
+        size_of::<Flock>(),
+    )
+    .map_err(|_| LinuxError::EFAULT)?;
+    Ok(unsafe { flock.read() })
+}
+
+/// Handle `F_GETLK` of `fcntl`: replace the lock described by `flock` with the
+/// first lock of another process that conflicts with it, or set its type to
+/// `F_UNLCK` if there is none.
+pub(super) fn fcntl_getlk(fd: c_int, flock: *mut Flock) -> LinuxResult<c_int> {
+    let mut request = read_flock(flock)?;
+    let write = match request.l_type {
+        F_RDLCK => false,
+        F_WRLCK => true,
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let (start, end) = flock_range(fd, &request)?;
+    let key = resolve_symlinks(&fd_path(fd)?, true)?;
+    let wanted = RecordLock {
+        pid: current().task_ext().proc_id,
+        start,
+        end,
+        write,
+    };
+    let conflict = RECORD_LOCKS
+        .lock()
+        .get(&key)
+        .and_then(|locks| locks.iter().find(|lock| lock.conflicts(&wanted)).copied());
+    match conflict {
+        Some(lock) => {
+            request.l_type = if lock.write { F_WRLCK } else { F_RDLCK };
+            request.l_whence = 0;
+            request.l_start = lock.start as i64;
+            request.l_len = if lock.end == u64::MAX {
+                0
+            } else {
+                (lock.end - lock.start) as i64
+            };
+            request.l_pid = lock.pid as i32;
+        }
+        None => request.l_type = F_UNLCK,
+    }
+    unsafe { flock.write(request) };
+    Ok(0)
+}
+
+/// Handle `F_SETLK` and, if `wait` is set, `F_SETLKW` of `fcntl`: set or remove
+/// the lock described by `flock`.
+///
+/// Locks of the same process replace each other in the ranges where they
+/// overlap. Waiting in `F_SETLKW` is interrupted by a signal, which fails with
+/// `EINTR`. Deadlocks between processes waiting for each other there are not
+/// detected, so they wait until interrupted instead of failing with `EDEADLK`.
+pub(super) fn fcntl_setlk(
+    fd: c_int,
+    file: &Arc<dyn FileLike>,
+    flock: *const Flock,
+    wait: bool,
+) -> LinuxResult<c_int> {
+    let request = read_flock(flock)?;
+    let access = file_status_flags(file) & O_ACCMODE;
+    let write = match request.l_type {
+        F_RDLCK if access == api::ctypes::O_WRONLY => return Err(LinuxError::EBADF),
+        F_WRLCK if access == api::ctypes::O_RDONLY => return Err(LinuxError::EBADF),
+        F_RDLCK | F_UNLCK => false,
+        F_WRLCK => true,
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let (start, end) = flock_range(fd, &request)?;
+    let key = resolve_symlinks(&fd_path(fd)?, true)?;
+    let lock = RecordLock {
+        pid: current().task_ext().proc_id,
+        start,
+        end,
+        write,
+    };
+    let unlock = request.l_type == F_UNLCK;
+    let set = if wait {
+        wait_interruptible(&RECORD_LOCK_WAIT_QUEUE, None, || {
+            try_set_record_lock(&key, lock, unlock)
+        })?
+    } else {
+        try_set_record_lock(&key, lock, unlock)
+    };
+    if !set {
+        return Err(LinuxError::EAGAIN);
+    }
+    if unlock {
+        RECORD_LOCK_WAIT_QUEUE.notify_all(false);
+    }
+    Ok(0)
+}
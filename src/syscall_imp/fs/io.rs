@@ -1,19 +1,796 @@
 use core::ffi::{c_char, c_void};
 
-use arceos_posix_api::{self as api, ctypes::mode_t};
+use alloc::{sync::Arc, vec, vec::Vec};
+use arceos_posix_api::{self as api, FD_TABLE, ctypes::mode_t};
+use axerrno::{AxError, AxResult, LinuxError, LinuxResult};
+use axfs::fops::OpenOptions;
+use axstd::io::SeekFrom;
+use axtask::{TaskExtRef, current};
 
+use super::{
+    attr::{SpecialNode, special_node, symlink_target, update_file_attr},
+    cache::{cached_read_at, invalidate_cached},
+    ctl::seek_dir,
+    dev::Device,
+    dircache::invalidate_dir_of,
+    fd_ops::{O_ACCMODE, O_CLOEXEC, file_status_flags, set_file_status_flags},
+    path::resolve_at,
+    pipe::open_fifo,
+    stat::lookup,
+};
+use crate::{
+    ctypes::{IOV_MAX, IoVec},
+    mm::check_user_range,
+    syscall_body,
+    task::processes,
+};
+
+/// Read from `fd` at its file offset, which is advanced by the bytes read.
+///
+/// Regular files are read through the page cache.
 pub(crate) fn sys_read(fd: i32, buf: *mut c_void, count: usize) -> isize {
-    api::sys_read(fd, buf, count)
+    syscall_body!(sys_read, {
+        let file = api::get_file_like(fd)?;
+        check_user_range(current().task_ext(), (buf as usize).into(), count)
+            .map_err(|_| LinuxError::EFAULT)?;
+        let buf = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, count) };
+        let write_only = file_status_flags(&file) & O_ACCMODE == api::ctypes::O_WRONLY;
+        let Ok(file) = file.clone().into_any().downcast::<api::File>() else {
+            return Ok(file.read(buf)? as isize);
+        };
+        if write_only {
+            return Err(LinuxError::EBADF);
+        }
+        let mut inner = file.inner().lock();
+        let pos = inner.seek(SeekFrom::Current(0))?;
+        let read = cached_read_at(file.path(), &inner, pos, buf).map_err(access_error)?;
+        inner.seek(SeekFrom::Start(pos + read as u64))?;
+        Ok(read as isize)
+    })
 }
 
 pub(crate) fn sys_write(fd: i32, buf: *const c_void, count: usize) -> isize {
-    api::sys_write(fd, buf, count)
+    if let Err(err) = prepare_write(fd) {
+        return -err.code() as isize;
+    }
+    let written = api::sys_write(fd, buf, count);
+    invalidate_written(fd);
+    written
+}
+
+/// Copy the iovec array from user space and check that every buffer in it is accessible.
+///
+/// Zero-length entries are dropped, so the returned vectors can be used for I/O directly.
+fn user_iovecs(iov: *const IoVec, iocnt: i32) -> LinuxResult<Vec<IoVec>> {
+    if iocnt < 0 || iocnt as usize > IOV_MAX {
+        return Err(LinuxError::EINVAL);
+    }
+    if iocnt == 0 {
+        return Ok(Vec::new());
+    }
+    if iov.is_null() {
+        return Err(LinuxError::EFAULT);
+    }
+    let curr = current();
+    let iov_size = iocnt as usize * core::mem::size_of::<IoVec>();
+    check_user_range(curr.task_ext(), (iov as usize).into(), iov_size)
+        .map_err(|_| LinuxError::EFAULT)?;
+    let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
+    let mut total: usize = 0;
+    let mut result = Vec::with_capacity(iovs.len());
+    for iov in iovs.iter().filter(|iov| iov.iov_len != 0) {
+        total = total
+            .checked_add(iov.iov_len)
+            .filter(|total| *total <= isize::MAX as usize)
+            .ok_or(LinuxError::EINVAL)?;
+        if iov.iov_base.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        check_user_range(curr.task_ext(), (iov.iov_base as usize).into(), iov.iov_len)
+            .map_err(|_| LinuxError::EFAULT)?;
+        result.push(*iov);
+    }
+    Ok(result)
+}
+
+/// Read data from `fd` into multiple buffers.
+///
+/// The buffers are filled in order, and a short read stops the transfer.
+///
+/// # Returns
+/// The total number of bytes read.
+pub(crate) fn sys_readv(fd: i32, iov: *const IoVec, iocnt: i32) -> isize {
+    syscall_body!(sys_readv, {
+        let file = api::get_file_like(fd)?;
+        let iovs = user_iovecs(iov, iocnt)?;
+        let mut total = 0;
+        for iov in iovs {
+            let buf =
+                unsafe { core::slice::from_raw_parts_mut(iov.iov_base as *mut u8, iov.iov_len) };
+            match file.read(buf) {
+                Ok(read) => {
+                    total += read;
+                    if read < buf.len() {
+                        break;
+                    }
+                }
+                Err(_) if total > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(total as isize)
+    })
+}
+
+/// Write data to `fd` from multiple buffers.
+///
+/// All the buffers are checked before anything is written, and a short write
+/// stops the transfer.
+///
+/// # Returns
+/// The total number of bytes written.
+pub(crate) fn sys_writev(fd: i32, iov: *const IoVec, iocnt: i32) -> isize {
+    syscall_body!(sys_writev, {
+        let file = api::get_file_like(fd)?;
+        let iovs = user_iovecs(iov, iocnt)?;
+        prepare_write(fd)?;
+        let mut total = 0;
+        for iov in iovs {
+            let buf =
+                unsafe { core::slice::from_raw_parts(iov.iov_base as *const u8, iov.iov_len) };
+            match file.write(buf) {
+                Ok(written) => {
+                    total += written;
+                    if written < buf.len() {
+                        break;
+                    }
+                }
+                Err(_) if total > 0 => break,
+                Err(err) => return Err(err),
+            }
+        }
+        invalidate_written(fd);
+        Ok(total as isize)
+    })
+}
+
+/// Drop the cached pages of the regular file behind `fd`, which has been written to.
+fn invalidate_written(fd: i32) {
+    if let Ok(file) = positional_file(fd) {
+        invalidate_cached(file.path());
+    }
+}
+
+/// Prepare the offset of a regular file for a write.
+///
+/// With `O_APPEND`, the offset is moved to the end of the file. Otherwise,
+/// `lseek` may have moved the offset past the end of the file, and the
+/// following write must leave the skipped bytes reading as zeros. Not every
+/// filesystem guarantees that, so the gap is zero-filled explicitly.
+fn prepare_write(fd: i32) -> LinuxResult {
+    let file = api::get_file_like(fd)?;
+    let append = file_status_flags(&file) & api::ctypes::O_APPEND != 0;
+    let Ok(file) = file.into_any().downcast::<api::File>() else {
+        return Ok(());
+    };
+    let mut file = file.inner().lock();
+    if append {
+        file.seek(SeekFrom::End(0))?;
+        return Ok(());
+    }
+    let pos = file.seek(SeekFrom::Current(0))?;
+    fill_hole_until(&mut file, pos).map_err(Into::into)
+}
+
+/// Zero-fill the file from its end up to `pos`, if `pos` is past the end.
+fn fill_hole_until(file: &mut axfs::fops::File, pos: u64) -> AxResult {
+    let size = file.get_attr()?.size();
+    write_zeros(file, size, pos)
+}
+
+/// Overwrite the bytes of the file in `start..end` with zeros.
+fn write_zeros(file: &mut axfs::fops::File, start: u64, end: u64) -> AxResult {
+    let zeros = [0u8; 512];
+    let mut offset = start;
+    while offset < end {
+        let len = core::cmp::min(zeros.len() as u64, end - offset) as usize;
+        offset += file.write_at(offset, &zeros[..len])? as u64;
+    }
+    Ok(())
+}
+
+/// Get the regular file behind `fd` for positional I/O.
+///
+/// Pipes, sockets and terminals are not seekable, so `ESPIPE` is returned for them.
+fn positional_file(fd: i32) -> LinuxResult<Arc<api::File>> {
+    api::get_file_like(fd)?
+        .into_any()
+        .downcast::<api::File>()
+        .map_err(|_| LinuxError::ESPIPE)
+}
+
+/// A file opened without the required access mode reports `PermissionDenied`,
+/// which is `EBADF` for read and write.
+fn access_error(err: AxError) -> LinuxError {
+    match err {
+        AxError::PermissionDenied => LinuxError::EBADF,
+        err => err.into(),
+    }
+}
+
+/// Read from a file descriptor at a given offset, without changing the file offset.
+pub(crate) fn sys_pread64(fd: i32, buf: *mut c_void, count: usize, offset: isize) -> isize {
+    syscall_body!(sys_pread64, {
+        if offset < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let file = positional_file(fd)?;
+        check_user_range(current().task_ext(), (buf as usize).into(), count)
+            .map_err(|_| LinuxError::EFAULT)?;
+        let buf = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, count) };
+        if file_status_flags(&(file.clone() as Arc<dyn api::FileLike>)) & O_ACCMODE
+            == api::ctypes::O_WRONLY
+        {
+            return Err(LinuxError::EBADF);
+        }
+        let read = cached_read_at(file.path(), &file.inner().lock(), offset as u64, buf)
+            .map_err(access_error)?;
+        Ok(read as isize)
+    })
+}
+
+/// Write to a file descriptor at a given offset, without changing the file offset.
+pub(crate) fn sys_pwrite64(fd: i32, buf: *const c_void, count: usize, offset: isize) -> isize {
+    syscall_body!(sys_pwrite64, {
+        if offset < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let file = positional_file(fd)?;
+        check_user_range(current().task_ext(), (buf as usize).into(), count)
+            .map_err(|_| LinuxError::EFAULT)?;
+        let buf = unsafe { core::slice::from_raw_parts(buf as *const u8, count) };
+        let mut file = file.inner().lock();
+        fill_hole_until(&mut file, offset as u64).map_err(access_error)?;
+        let written = file.write_at(offset as u64, buf).map_err(access_error);
+        drop(file);
+        invalidate_written(fd);
+        Ok(written? as isize)
+    })
+}
+
+/// Read from `fd` for a copy between fds, at `offset` if given or at the file offset otherwise.
+fn read_for_copy(fd: i32, offset: Option<u64>, buf: &mut [u8]) -> LinuxResult<usize> {
+    match offset {
+        Some(offset) => Ok(positional_file(fd)?
+            .inner()
+            .lock()
+            .read_at(offset, buf)
+            .map_err(access_error)?),
+        None => api::get_file_like(fd)?.read(buf),
+    }
+}
+
+/// Write to `fd` for a copy between fds, at `offset` if given or at the file offset otherwise.
+fn write_for_copy(fd: i32, offset: Option<u64>, buf: &[u8]) -> LinuxResult<usize> {
+    match offset {
+        Some(offset) => {
+            let file = positional_file(fd)?;
+            let mut file = file.inner().lock();
+            fill_hole_until(&mut file, offset).map_err(access_error)?;
+            let written = file.write_at(offset, buf).map_err(access_error);
+            drop(file);
+            invalidate_written(fd);
+            Ok(written?)
+        }
+        None => {
+            prepare_write(fd)?;
+            let written = api::get_file_like(fd)?.write(buf);
+            invalidate_written(fd);
+            written
+        }
+    }
+}
+
+/// Copy up to `count` bytes from `in_fd` to `out_fd` inside the kernel.
+///
+/// A given offset is used and advanced instead of the file offset of the
+/// corresponding fd. The copy stops early at the end of the input or when the
+/// output is full, and an error is only reported if nothing was copied.
+fn copy_between_fds(
+    in_fd: i32,
+    mut in_offset: Option<&mut u64>,
+    out_fd: i32,
+    mut out_offset: Option<&mut u64>,
+    count: usize,
+) -> LinuxResult<usize> {
+    let mut buf = vec![0u8; 4096];
+    let mut copied = 0;
+    while copied < count {
+        let len = buf.len().min(count - copied);
+        let read = match read_for_copy(in_fd, in_offset.as_deref().copied(), &mut buf[..len]) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if copied == 0 => return Err(err),
+            Err(_) => break,
+        };
+        let written = match write_for_copy(out_fd, out_offset.as_deref().copied(), &buf[..read]) {
+            Ok(written) => written,
+            Err(err) if copied == 0 => return Err(err),
+            Err(_) => break,
+        };
+        if let Some(offset) = in_offset.as_deref_mut() {
+            *offset += written as u64;
+        }
+        if let Some(offset) = out_offset.as_deref_mut() {
+            *offset += written as u64;
+        }
+        copied += written;
+        if read < len || written < read {
+            break;
+        }
+    }
+    Ok(copied)
+}
+
+/// Read an offset for `sendfile` or `copy_file_range` from user memory, if given.
+fn read_user_offset(offset: *const i64) -> LinuxResult<Option<u64>> {
+    if offset.is_null() {
+        return Ok(None);
+    }
+    check_user_range(
+        current().task_ext(),
+        (offset as usize).into(),
+        size_of::<i64>(),
+    )
+    .map_err(|_| LinuxError::EFAULT)?;
+    let offset = unsafe { offset.read() };
+    u64::try_from(offset)
+        .map(Some)
+        .map_err(|_| LinuxError::EINVAL)
 }
 
-pub(crate) fn sys_writev(fd: i32, iov: *const api::ctypes::iovec, iocnt: i32) -> isize {
-    unsafe { api::sys_writev(fd, iov, iocnt) }
+/// Copy up to `count` bytes from `in_fd` to `out_fd`.
+///
+/// If `offset` is null, `in_fd` is read from its file offset, which is advanced.
+/// Otherwise it is read from `*offset`, which is updated to follow the last byte
+/// read, and the file offset of `in_fd` is not changed.
+///
+/// Like Linux, `out_fd` must not be opened with `O_APPEND`.
+pub(crate) fn sys_sendfile(out_fd: i32, in_fd: i32, offset: *mut i64, count: usize) -> isize {
+    syscall_body!(sys_sendfile, {
+        let (file_in, file_out) = (api::get_file_like(in_fd)?, api::get_file_like(out_fd)?);
+        if file_status_flags(&file_in) & O_ACCMODE == api::ctypes::O_WRONLY
+            || file_status_flags(&file_out) & O_ACCMODE == api::ctypes::O_RDONLY
+        {
+            return Err(LinuxError::EBADF);
+        }
+        if file_status_flags(&file_out) & api::ctypes::O_APPEND != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let mut in_offset = read_user_offset(offset)?;
+        let copied = copy_between_fds(in_fd, in_offset.as_mut(), out_fd, None, count)?;
+        if let Some(in_offset) = in_offset {
+            unsafe { offset.write(in_offset as i64) };
+        }
+        Ok(copied as isize)
+    })
 }
 
+/// Copy up to `len` bytes between two regular files.
+///
+/// `off_in` and `off_out` work like the offset of `sendfile`: if one is null,
+/// the file offset of the corresponding fd is used and advanced instead.
+/// Within the same file, the two ranges must not overlap.
+pub(crate) fn sys_copy_file_range(
+    fd_in: i32,
+    off_in: *mut i64,
+    fd_out: i32,
+    off_out: *mut i64,
+    len: usize,
+    flags: u32,
+) -> isize {
+    syscall_body!(sys_copy_file_range, {
+        let (file_in, file_out) = (api::get_file_like(fd_in)?, api::get_file_like(fd_out)?);
+        if flags != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if file_status_flags(&file_in) & O_ACCMODE == api::ctypes::O_WRONLY
+            || file_status_flags(&file_out) & O_ACCMODE == api::ctypes::O_RDONLY
+            || file_status_flags(&file_out) & api::ctypes::O_APPEND != 0
+        {
+            return Err(LinuxError::EBADF);
+        }
+        // Both ends must be regular files. All of them live in the same `axfs`
+        // namespace, so copying across filesystems is never a problem.
+        let (Ok(file_in), Ok(file_out)) = (
+            file_in.into_any().downcast::<api::File>(),
+            file_out.into_any().downcast::<api::File>(),
+        ) else {
+            return Err(LinuxError::EINVAL);
+        };
+
+        let mut in_offset = read_user_offset(off_in)?;
+        let mut out_offset = read_user_offset(off_out)?;
+        if file_in.path() == file_out.path() {
+            // The ranges can't overlap within the same file, where the input range
+            // ends at the end of the file.
+            let (pos_in, size) = {
+                let mut file = file_in.inner().lock();
+                let pos = match in_offset {
+                    Some(offset) => offset,
+                    None => file.seek(SeekFrom::Current(0))?,
+                };
+                (pos, file.get_attr()?.size())
+            };
+            let pos_out = match out_offset {
+                Some(offset) => offset,
+                None => file_out.inner().lock().seek(SeekFrom::Current(0))?,
+            };
+            let count = (len as u64).min(size.saturating_sub(pos_in));
+            if count > 0 && pos_in < pos_out + count && pos_out < pos_in + count {
+                return Err(LinuxError::EINVAL);
+            }
+        }
+        let copied = copy_between_fds(fd_in, in_offset.as_mut(), fd_out, out_offset.as_mut(), len)?;
+        if let Some(in_offset) = in_offset {
+            unsafe { off_in.write(in_offset as i64) };
+        }
+        if let Some(out_offset) = out_offset {
+            unsafe { off_out.write(out_offset as i64) };
+        }
+        Ok(copied as isize)
+    })
+}
+
+/// Set the size of `file` to `length`, zero-filling the extended part.
+///
+/// The file offset is not changed.
+fn truncate_file(file: &mut axfs::fops::File, length: u64) -> AxResult {
+    if length > file.get_attr()?.size() {
+        fill_hole_until(file, length)
+    } else {
+        file.truncate(length)
+    }
+}
+
+/// Release the space allocated by `fallocate` past `length` bytes of the file at
+/// `path`, as truncating it does.
+fn release_allocation(path: &str, length: u64) {
+    update_file_attr(path, |attr| {
+        attr.allocated = attr.allocated.map(|allocated| allocated.min(length));
+    });
+}
+
+/// Truncate or extend the file referred to by `fd` to `length` bytes.
+pub(crate) fn sys_ftruncate(fd: i32, length: isize) -> isize {
+    syscall_body!(sys_ftruncate, {
+        if length < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let file = api::get_file_like(fd)?;
+        // The file must be a regular file open for writing.
+        if file_status_flags(&file) & O_ACCMODE == api::ctypes::O_RDONLY {
+            return Err(LinuxError::EINVAL);
+        }
+        let file = file
+            .into_any()
+            .downcast::<api::File>()
+            .map_err(|_| LinuxError::EINVAL)?;
+        let truncated = truncate_file(&mut file.inner().lock(), length as u64);
+        invalidate_cached(file.path());
+        truncated?;
+        release_allocation(file.path(), length as u64);
+        Ok(0)
+    })
+}
+
+/// Truncate or extend the file at `path` to `length` bytes.
+pub(crate) fn sys_truncate(path: *const c_char, length: isize) -> isize {
+    syscall_body!(sys_truncate, {
+        if length < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = resolve_at(api::AT_FDCWD as i32, path, true)?;
+        if lookup(&path)?.is_dir() {
+            return Err(LinuxError::EISDIR);
+        }
+        let mut options = OpenOptions::new();
+        options.write(true);
+        let mut file = axfs::fops::File::open(&path, &options)?;
+        let truncated = truncate_file(&mut file, length as u64);
+        invalidate_cached(&path);
+        truncated?;
+        release_allocation(&path, length as u64);
+        Ok(0)
+    })
+}
+
+/// Manipulate the space allocated for the file referred to by `fd`.
+///
+/// With a `mode` of 0 the file is extended to at least `offset + len` bytes, and
+/// `FALLOC_FL_KEEP_SIZE` alone allocates the range without changing the size.
+/// The filesystems have no notion of preallocation, so the allocation is only
+/// recorded to be reflected in `st_blocks`. `FALLOC_FL_PUNCH_HOLE`, which must
+/// come with `FALLOC_FL_KEEP_SIZE`, zeroes the range without changing the size.
+pub(crate) fn sys_fallocate(fd: i32, mode: u32, offset: isize, len: isize) -> isize {
+    syscall_body!(sys_fallocate, {
+        const FALLOC_FL_KEEP_SIZE: u32 = 0x01;
+        const FALLOC_FL_PUNCH_HOLE: u32 = 0x02;
+
+        if offset < 0 || len <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let file = api::get_file_like(fd)?;
+        if file.clone().into_any().is::<api::Directory>() {
+            return Err(LinuxError::EISDIR);
+        }
+        if file_status_flags(&file) & O_ACCMODE == api::ctypes::O_RDONLY {
+            return Err(LinuxError::EBADF);
+        }
+        let file = file
+            .into_any()
+            .downcast::<api::File>()
+            .map_err(|_| LinuxError::ESPIPE)?;
+        let (start, end) = (offset as u64, offset as u64 + len as u64);
+        let punch_hole = mode == FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE;
+        let written = {
+            let mut inner = file.inner().lock();
+            match mode {
+                0 => fill_hole_until(&mut inner, end),
+                FALLOC_FL_KEEP_SIZE => Ok(()),
+                _ if punch_hole => {
+                    let size = inner.get_attr()?.size();
+                    write_zeros(&mut inner, start, end.min(size))
+                }
+                _ => return Err(LinuxError::EINVAL),
+            }
+        };
+        invalidate_cached(file.path());
+        written.map_err(access_error)?;
+        if punch_hole {
+            return Ok(0);
+        }
+        update_file_attr(file.path(), |attr| {
+            attr.allocated = Some(attr.allocated.unwrap_or(0).max(end));
+        });
+        Ok(0)
+    })
+}
+
+/// Flush the dirty data of a regular file to the backing device.
+///
+/// Other kinds of files have nothing to flush, and neither has the page cache,
+/// which writes go through.
+fn flush_file(file: Arc<dyn api::FileLike>) -> LinuxResult {
+    let Ok(file) = file.into_any().downcast::<api::File>() else {
+        return Ok(());
+    };
+    match file.inner().lock().flush() {
+        // Nothing can be dirty in a file that is not open for writing.
+        Ok(()) | Err(AxError::PermissionDenied) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Synchronize the state of the file referred to by `fd` with the storage device.
+pub(crate) fn sys_fsync(fd: i32) -> isize {
+    syscall_body!(sys_fsync, {
+        flush_file(api::get_file_like(fd)?)?;
+        Ok(0)
+    })
+}
+
+/// The same as `fsync`. The filesystems do not distinguish data from metadata when flushing.
+pub(crate) fn sys_fdatasync(fd: i32) -> isize {
+    syscall_body!(sys_fdatasync, {
+        flush_file(api::get_file_like(fd)?)?;
+        Ok(0)
+    })
+}
+
+/// Commit the buffered data of all the open files to the storage device.
+///
+/// The changes to the shared file mappings of every process are written back
+/// to their files, and every file that any process has open or mapped is
+/// flushed, which makes the filesystem write what it keeps of the file. The
+/// page cache holds nothing dirty, and `axfs` writes through to the block
+/// device below the filesystems, so nothing else is buffered.
+pub(crate) fn sys_sync() -> isize {
+    syscall_body!(sys_sync, {
+        for process in processes() {
+            let ext = process.task_ext();
+            let mappings = ext.file_mappings.lock().clone();
+            {
+                let aspace = ext.aspace.lock();
+                for mapping in &mappings {
+                    if let Err(err) = mapping.sync(&aspace, mapping.start, mapping.end) {
+                        warn!("sync: failed to write back a mapping: {err:?}");
+                    }
+                }
+            }
+            let files: Vec<Arc<dyn api::FileLike>> = {
+                let table = FD_TABLE.deref_from(&ext.ns).read();
+                table
+                    .ids()
+                    .filter_map(|fd| table.get(fd).cloned())
+                    .collect()
+            };
+            let mapped = mappings
+                .into_iter()
+                .filter_map(|mapping| mapping.file)
+                .map(|file| file as Arc<dyn api::FileLike>);
+            for file in files.into_iter().chain(mapped) {
+                if let Err(err) = flush_file(file) {
+                    warn!("sync: failed to flush file: {err:?}");
+                }
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// Reposition the offset of the open file associated with `fd`.
+///
+/// # Arguments
+/// * `fd` - The file descriptor
+/// * `offset` - The offset relative to the position specified by `whence`
+/// * `whence` - One of `SEEK_SET`, `SEEK_CUR`, `SEEK_END`, `SEEK_DATA` and `SEEK_HOLE`
+///
+/// # Returns
+/// The resulting offset measured from the beginning of the file.
+///
+/// Holes are not tracked, so `SEEK_DATA` and `SEEK_HOLE` treat the whole file as
+/// data, followed by the implicit hole at its end.
+pub(crate) fn sys_lseek(fd: i32, offset: isize, whence: i32) -> isize {
+    const SEEK_SET: i32 = 0;
+    const SEEK_CUR: i32 = 1;
+    const SEEK_END: i32 = 2;
+    const SEEK_DATA: i32 = 3;
+    const SEEK_HOLE: i32 = 4;
+
+    syscall_body!(sys_lseek, {
+        let file = api::get_file_like(fd)?.into_any();
+        // Devices have no position, which always stays at 0.
+        if file.is::<Device>() {
+            return Ok(0);
+        }
+        let file = match file.downcast::<api::Directory>() {
+            Ok(dir) => {
+                let pos = match whence {
+                    SEEK_SET => {
+                        SeekFrom::Start(u64::try_from(offset).map_err(|_| LinuxError::EINVAL)?)
+                    }
+                    SEEK_CUR => SeekFrom::Current(offset as i64),
+                    SEEK_END => SeekFrom::End(offset as i64),
+                    _ => return Err(LinuxError::EINVAL),
+                };
+                return Ok(seek_dir(&dir, pos)? as isize);
+            }
+            Err(file) => file
+                .downcast::<api::File>()
+                .map_err(|_| LinuxError::ESPIPE)?,
+        };
+        let mut file = file.inner().lock();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => file.seek(SeekFrom::Current(0))?,
+            SEEK_END => file.get_attr()?.size(),
+            SEEK_DATA | SEEK_HOLE => {
+                let size = file.get_attr()?.size();
+                let offset = u64::try_from(offset)
+                    .ok()
+                    .filter(|&offset| offset < size)
+                    .ok_or(LinuxError::ENXIO)?;
+                let target = if whence == SEEK_DATA { offset } else { size };
+                return Ok(file.seek(SeekFrom::Start(target))? as isize);
+            }
+            _ => return Err(LinuxError::EINVAL),
+        };
+        let new_offset = (base as i64)
+            .checked_add(offset as i64)
+            .filter(|off| *off >= 0)
+            .ok_or(LinuxError::EINVAL)?;
+        Ok(file.seek(SeekFrom::Start(new_offset as u64))? as isize)
+    })
+}
+
+/// Open or create a file relative to a directory file descriptor.
+///
+/// # Arguments
+/// * `dirfd` - The directory that a relative `path` is resolved against, or `AT_FDCWD`
+/// * `path` - The path of the file
+/// * `flags` - The access mode (`O_RDONLY`, `O_WRONLY` or `O_RDWR`) and the creation
+///   and status flags, e.g. `O_CREAT`, `O_EXCL`, `O_TRUNC`, `O_APPEND` and `O_DIRECTORY`
+/// * `modes` - The mode of the file if it is created
 pub(crate) fn sys_openat(dirfd: i32, path: *const c_char, flags: i32, modes: mode_t) -> isize {
-    api::sys_openat(dirfd, path, flags, modes) as isize
+    syscall_body!(sys_openat, {
+        let flags = flags as u32;
+        let nofollow = flags & api::ctypes::O_NOFOLLOW != 0;
+        let path = resolve_at(dirfd, path, !nofollow)?;
+        debug!("sys_openat <= {dirfd} {path:?} {flags:#o} {modes:#o}");
+        if nofollow && symlink_target(&path).is_some() {
+            return Err(LinuxError::ELOOP);
+        }
+
+        let access = flags & O_ACCMODE;
+        let readable = access == api::ctypes::O_RDONLY || access == api::ctypes::O_RDWR;
+        let writable = access == api::ctypes::O_WRONLY || access == api::ctypes::O_RDWR;
+        let create = flags & api::ctypes::O_CREAT != 0;
+        let exclusive = flags & api::ctypes::O_EXCL != 0;
+        let directory = flags & api::ctypes::O_DIRECTORY != 0;
+
+        let fd = match axfs::api::metadata(path.as_str()) {
+            Ok(_) if create && exclusive => return Err(LinuxError::EEXIST),
+            Ok(metadata) if metadata.is_dir() => {
+                if writable {
+                    return Err(LinuxError::EISDIR);
+                }
+                let mut options = OpenOptions::new();
+                options.read(true);
+                let dir = axfs::fops::Directory::open_dir(path.as_str(), &options)?;
+                api::add_file_like(Arc::new(api::Directory::new(dir, path.clone())))?
+            }
+            Ok(_) if directory => return Err(LinuxError::ENOTDIR),
+            Ok(_) => match special_node(&path) {
+                Some(node) => api::add_file_like(open_node(&path, node, flags)?)?,
+                None => open_file(path.as_str(), flags, readable, writable, false)?,
+            },
+            Err(AxError::NotFound) if create && !directory => {
+                let fd = open_file(path.as_str(), flags, readable, writable, true)?;
+                let mode = modes & !current().task_ext().get_umask() & 0o7777;
+                update_file_attr(path.as_str(), |attr| attr.mode = Some(mode));
+                fd
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let file = api::get_file_like(fd)?;
+        if flags & api::ctypes::O_NONBLOCK != 0 {
+            file.set_nonblocking(true)?;
+        }
+        let status_flags = flags & (O_ACCMODE | api::ctypes::O_APPEND | api::ctypes::O_NONBLOCK);
+        set_file_status_flags(&file, status_flags);
+        current()
+            .task_ext()
+            .set_close_on_exec(fd, flags & O_CLOEXEC != 0);
+        Ok(fd as isize)
+    })
+}
+
+/// Open a regular file with the given flags and add it to the fd table.
+fn open_file(
+    path: &str,
+    flags: u32,
+    readable: bool,
+    writable: bool,
+    create: bool,
+) -> LinuxResult<i32> {
+    let mut options = OpenOptions::new();
+    options.read(readable);
+    options.write(writable);
+    // `O_APPEND` is handled by `prepare_write`, so that `F_SETFL` can change it.
+    options.truncate(writable && flags & api::ctypes::O_TRUNC != 0);
+    options.create(create);
+    let file = axfs::fops::File::open(path, &options)?;
+    if create {
+        invalidate_dir_of(path);
+    }
+    if writable && flags & api::ctypes::O_TRUNC != 0 {
+        invalidate_cached(path);
+    }
+    api::add_file_like(Arc::new(api::File::new(file, path.into())))
+}
+
+/// Open a FIFO or a character device.
+fn open_node(path: &str, node: SpecialNode, flags: u32) -> LinuxResult<Arc<dyn api::FileLike>> {
+    match node {
+        SpecialNode::Fifo => Ok(open_fifo(
+            path,
+            flags & O_ACCMODE,
+            flags & api::ctypes::O_NONBLOCK != 0,
+        )?),
+        SpecialNode::CharDevice(rdev) => {
+            Ok(Arc::new(Device::from_rdev(rdev).ok_or(LinuxError::ENXIO)?))
+        }
+    }
 }
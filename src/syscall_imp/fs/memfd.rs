@@ -0,0 +1,70 @@
+//! Anonymous files created by `memfd_create`.
+//!
+//! Like on Linux, where a memfd is a file of an internal tmpfs, a memfd is a
+//! file of the in-memory filesystem on `/tmp`, which is removed as soon as it
+//! is opened. So it can be read, written, truncated and mapped like any other
+//! file, and its memory is freed once no fd refers to it any more.
+
+use core::{
+    ffi::{c_char, c_int},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::{format, sync::Arc};
+use arceos_posix_api::{self as api, FileLike};
+use axerrno::LinuxError;
+use axfs::fops::OpenOptions;
+use axtask::{TaskExtRef, current};
+
+use super::{dircache::invalidate_dir_of, fd_ops::set_file_status_flags};
+use crate::syscall_body;
+
+/// Set the close-on-exec flag of the new fd.
+const MFD_CLOEXEC: u32 = 1;
+/// Allow sealing, which is not supported, so that seals can't be added anyway.
+const MFD_ALLOW_SEALING: u32 = 2;
+
+/// The longest name, excluding the terminating NUL.
+const MFD_NAME_MAX: usize = 249;
+
+/// The prefix of the paths that the files of the memfds have until they are
+/// removed, which is on the in-memory filesystem.
+const MEMFD_PREFIX: &str = "/tmp/.memfd-";
+
+static NEXT_MEMFD_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Create an anonymous file and return an fd referring to it.
+///
+/// The file is empty, and open for reading and writing. `name` is only used
+/// for debugging, and can't be longer than 249 bytes. `flags` may contain
+/// `MFD_CLOEXEC` and `MFD_ALLOW_SEALING`.
+pub(crate) fn sys_memfd_create(name: *const c_char, flags: u32) -> c_int {
+    syscall_body!(sys_memfd_create, {
+        if flags & !(MFD_CLOEXEC | MFD_ALLOW_SEALING) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let name = api::char_ptr_to_str(name)?;
+        if name.len() > MFD_NAME_MAX {
+            return Err(LinuxError::EINVAL);
+        }
+        let id = NEXT_MEMFD_ID.fetch_add(1, Ordering::Relaxed);
+        let path = format!("{MEMFD_PREFIX}{id}");
+        debug!("memfd:{name} is backed by {path}");
+
+        let mut options = OpenOptions::new();
+        options.read(true);
+        options.write(true);
+        options.create_new(true);
+        let file = axfs::fops::File::open(&path, &options)?;
+        // The open file keeps the data, which is freed when it is closed.
+        axfs::api::remove_file(&path)?;
+        invalidate_dir_of(&path);
+        let file: Arc<dyn FileLike> = Arc::new(api::File::new(file, path));
+        set_file_status_flags(&file, api::ctypes::O_RDWR);
+        let fd = api::add_file_like(file)?;
+        current()
+            .task_ext()
+            .set_close_on_exec(fd, flags & MFD_CLOEXEC != 0);
+        Ok(fd)
+    })
+}
@@ -1,11 +1,38 @@
+mod attr;
+mod cache;
 mod ctl;
+mod dev;
+mod dircache;
+mod epoll;
+mod eventfd;
 mod fd_ops;
+mod flock;
 mod io;
+mod memfd;
+mod mount;
+mod path;
 mod pipe;
+mod poll;
+mod procfs;
 mod stat;
+mod tty;
+mod umask;
+mod util;
 
+pub(crate) use self::attr::resolve_symlinks;
+pub(crate) use self::cache::{cached_read_at, cached_read_file, invalidate_cached};
 pub(crate) use self::ctl::*;
+pub(crate) use self::dev::{fill_random, init_devices, sys_getrandom};
+pub(crate) use self::epoll::*;
+pub(crate) use self::eventfd::*;
 pub(crate) use self::fd_ops::*;
+pub(crate) use self::flock::*;
 pub(crate) use self::io::*;
+pub(crate) use self::memfd::*;
+pub(crate) use self::mount::*;
+pub(crate) use self::path::*;
 pub(crate) use self::pipe::*;
+pub(crate) use self::poll::*;
 pub(crate) use self::stat::*;
+pub(crate) use self::tty::init_tty;
+pub(crate) use self::umask::*;
@@ -0,0 +1,251 @@
+//! Mounting filesystems.
+//!
+//! `axfs` can't attach another filesystem once the root is set up, so the
+//! memory filesystems mounted later keep their files in a directory of their
+//! own in the `tmpfs` on `/tmp`, which is the `ramfs` that `axfs` mounts there
+//! at boot. Path resolution goes through the mount table, so that the paths
+//! beneath a mount point lead to that directory instead, and the files of the
+//! directory mounted on are hidden until it is unmounted.
+
+use core::ffi::c_char;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use arceos_posix_api::{self as api, AT_FDCWD, FD_TABLE};
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::TaskExtRef;
+
+use super::{
+    attr::update_file_attr,
+    dircache::invalidate_dir_of,
+    path::resolve_at,
+    stat::lookup,
+    util::{is_beneath, remove_tree},
+};
+use crate::{syscall_body, task::processes};
+
+/// Filesystem types that can be mounted without a device, with the magic
+/// numbers that `statfs` reports for them.
+const MEMORY_FS_TYPES: &[(&str, i64)] = &[("tmpfs", 0x01021994), ("ramfs", 0x858458f6)];
+/// Filesystem types that live on a block device.
+const BLOCK_FS_TYPES: &[&str] = &["vfat", "ext4"];
+
+/// Force unmounting even if the filesystem is busy.
+const MNT_FORCE: u32 = 1;
+/// Detach the filesystem lazily, even if it is busy.
+const MNT_DETACH: u32 = 2;
+/// Fail with `EAGAIN` the first time, which is only used to expire idle mounts.
+const MNT_EXPIRE: u32 = 4;
+/// Don't follow a symbolic link at the target.
+const UMOUNT_NOFOLLOW: u32 = 8;
+
+/// The directory in the `tmpfs` on `/tmp` that holds the directories where the
+/// memory filesystems mounted after boot keep their files.
+const MOUNT_STORE: &str = "/tmp/.mounts";
+
+struct MountPoint {
+    /// The canonical path of the directory mounted on.
+    target: String,
+    /// The canonical path of the directory where the files of the filesystem
+    /// are kept, which is `target` itself for the filesystems mounted at boot.
+    root: String,
+    /// The magic number of the type of the filesystem.
+    magic: i64,
+    /// Whether it has been marked as expired by `MNT_EXPIRE`.
+    expired: bool,
+}
+
+static MOUNTS: Mutex<Vec<MountPoint>> = Mutex::new(Vec::new());
+
+/// The number of the next directory made in `MOUNT_STORE`.
+static NEXT_MOUNT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Record the `tmpfs` on `/tmp`, the in-memory filesystem that `axfs` mounts
+/// there at boot, so that apps have somewhere to put their scratch files even
+/// if the root filesystem is read-only. Its files live in kernel memory, which
+/// is freed when they are removed.
+pub(crate) fn init_mounts() {
+    if axfs::api::metadata("/tmp").is_err() {
+        warn!("No tmpfs is mounted on /tmp");
+        return;
+    }
+    update_file_attr("/tmp", |attr| attr.mode = Some(0o1777));
+    MOUNTS.lock().push(MountPoint {
+        target: "/tmp".into(),
+        root: "/tmp".into(),
+        magic: MEMORY_FS_TYPES[0].1,
+        expired: false,
+    });
+}
+
+/// Get the magic number of the type of the filesystem holding the file at the
+/// canonical `path`, or `None` if it is the root filesystem.
+pub(super) fn mounted_fs_magic(path: &str) -> Option<i64> {
+    MOUNTS
+        .lock()
+        .iter()
+        .filter(|mount| is_beneath(path, &mount.root))
+        .max_by_key(|mount| mount.root.len())
+        .map(|mount| mount.magic)
+}
+
+/// Get the directory where the files of the filesystem mounted on `path` are
+/// kept, if one is mounted there after boot.
+pub(super) fn mounted_root(path: &str) -> Option<String> {
+    MOUNTS
+        .lock()
+        .iter()
+        .find(|mount| mount.target == path && mount.root != mount.target)
+        .map(|mount| mount.root.clone())
+}
+
+/// Whether the canonical `path` is the root of a mounted filesystem, which
+/// can't be removed or renamed.
+pub(super) fn is_mount_root(path: &str) -> bool {
+    path == "/" || MOUNTS.lock().iter().any(|mount| mount.root == path)
+}
+
+/// Get the path that the file at the canonical `path` is seen at, going back
+/// from the directories where the mounted filesystems keep their files to the
+/// directories they are mounted on.
+pub(super) fn visible_path(path: &str) -> String {
+    let mut path = String::from(path);
+    let mounts = MOUNTS.lock();
+    while let Some(mount) = mounts
+        .iter()
+        .filter(|mount| mount.root != mount.target && is_beneath(&path, &mount.root))
+        .max_by_key(|mount| mount.root.len())
+    {
+        path = format!("{}{}", mount.target, &path[mount.root.len()..]);
+    }
+    path
+}
+
+/// Whether something beneath `root` is in use, i.e. is open in any process,
+/// is the working directory of the current task, or has a filesystem mounted
+/// on it.
+fn is_busy(root: &str) -> bool {
+    let open = processes().into_iter().any(|process| {
+        let table = FD_TABLE.deref_from(&process.task_ext().ns).read();
+        table
+            .ids()
+            .filter_map(|fd| table.get(fd).cloned())
+            .any(|file| {
+                let file = file.into_any();
+                let path = match file.downcast::<api::File>() {
+                    Ok(file) => String::from(file.path()),
+                    Err(file) => match file.downcast::<api::Directory>() {
+                        Ok(dir) => dir.path().into(),
+                        Err(_) => return false,
+                    },
+                };
+                is_beneath(&path, root)
+            })
+    });
+    open || axfs::api::current_dir().is_ok_and(|cwd| is_beneath(&cwd, root))
+        || MOUNTS
+            .lock()
+            .iter()
+            .any(|mount| mount.target != root && is_beneath(&mount.target, root))
+}
+
+/// Mount the filesystem `source` of type `fstype` on the directory `target`.
+///
+/// Only memory filesystems, i.e. `tmpfs` and `ramfs`, can be mounted, for which
+/// `source`, `flags` and `data` are ignored. They can't be mounted on `/` or
+/// `/tmp`, where they would hold their own files, nor on a directory that
+/// already has one mounted on it. The filesystems on block devices are
+/// recognized, but `axfs` only has the block device of the root filesystem,
+/// so their `source` is never a block device that can be mounted.
+pub(crate) fn sys_mount(
+    source: *const c_char,
+    target: *const c_char,
+    fstype: *const c_char,
+    _flags: usize,
+    _data: *const u8,
+) -> isize {
+    syscall_body!(sys_mount, {
+        let target = resolve_at(AT_FDCWD as i32, target, true)?;
+        if !lookup(&target)?.is_dir() {
+            return Err(LinuxError::ENOTDIR);
+        }
+        let fstype = api::char_ptr_to_str(fstype)?;
+        if BLOCK_FS_TYPES.contains(&fstype) {
+            lookup(&resolve_at(AT_FDCWD as i32, source, true)?)?;
+            return Err(LinuxError::ENOTBLK);
+        }
+        let &(_, magic) = MEMORY_FS_TYPES
+            .iter()
+            .find(|(name, _)| *name == fstype)
+            .ok_or(LinuxError::ENODEV)?;
+        if is_beneath(MOUNT_STORE, &target) || is_mount_root(&target) {
+            return Err(LinuxError::EBUSY);
+        }
+
+        if axfs::api::metadata(MOUNT_STORE).is_err() {
+            axfs::api::create_dir(MOUNT_STORE)?;
+            invalidate_dir_of(MOUNT_STORE);
+        }
+        let root = format!(
+            "{MOUNT_STORE}/{}",
+            NEXT_MOUNT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        axfs::api::create_dir(&root)?;
+        invalidate_dir_of(&root);
+        if fstype == "tmpfs" {
+            update_file_attr(&root, |attr| attr.mode = Some(0o1777));
+        }
+        invalidate_dir_of(&target);
+        MOUNTS.lock().push(MountPoint {
+            target,
+            root,
+            magic,
+            expired: false,
+        });
+        Ok(0)
+    })
+}
+
+/// Unmount the filesystem mounted on `target`, removing its files.
+///
+/// It fails with `EBUSY` if something beneath it is in use, unless it is
+/// detached lazily with `MNT_DETACH`, which leaves its files in memory for
+/// what still uses them. The filesystems mounted at boot can't be
+/// unmounted, which fails with `EBUSY`, and `target` fails with `EINVAL` if
+/// nothing is mounted on it.
+pub(crate) fn sys_umount2(target: *const c_char, flags: u32) -> isize {
+    syscall_body!(sys_umount2, {
+        if flags & !(MNT_FORCE | MNT_DETACH | MNT_EXPIRE | UMOUNT_NOFOLLOW) != 0
+            || (flags & MNT_EXPIRE != 0 && flags & (MNT_FORCE | MNT_DETACH) != 0)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let root = resolve_at(AT_FDCWD as i32, target, flags & UMOUNT_NOFOLLOW == 0)?;
+        let busy = is_busy(&root);
+        let mut mounts = MOUNTS.lock();
+        let index = mounts
+            .iter()
+            .position(|mount| mount.root == root)
+            .ok_or(LinuxError::EINVAL)?;
+        let mount = &mut mounts[index];
+        if mount.root == mount.target {
+            return Err(LinuxError::EBUSY);
+        }
+        if flags & MNT_EXPIRE != 0 && !mount.expired {
+            mount.expired = true;
+            return Err(LinuxError::EAGAIN);
+        }
+        if busy && flags & MNT_DETACH == 0 {
+            return Err(LinuxError::EBUSY);
+        }
+        let mount = mounts.remove(index);
+        drop(mounts);
+        invalidate_dir_of(&mount.target);
+        if !busy {
+            remove_tree(&mount.root)?;
+        }
+        Ok(0)
+    })
+}
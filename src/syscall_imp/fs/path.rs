@@ -0,0 +1,72 @@
+//! Resolution of the paths given to the `*at` syscalls.
+
+use core::ffi::c_char;
+
+use alloc::{format, string::String};
+use arceos_posix_api::{self as api, AT_FDCWD};
+use axerrno::{LinuxError, LinuxResult};
+
+use super::{
+    attr::{hard_link_target, resolve_symlinks},
+    mount::visible_path,
+    procfs::refresh_proc,
+};
+
+/// Get the path of the directory that relative paths are resolved against:
+/// the directory referred to by `dirfd`, or the working directory if `dirfd`
+/// is `AT_FDCWD`.
+///
+/// It is the path the directory is seen at, so that ".." leads out of a
+/// mounted filesystem to the directory containing its mount point.
+fn dir_path(dirfd: i32) -> LinuxResult<String> {
+    if dirfd == AT_FDCWD as i32 {
+        return Ok(visible_path(&axfs::api::current_dir()?));
+    }
+    api::get_file_like(dirfd)?
+        .into_any()
+        .downcast::<api::Directory>()
+        .map(|dir| visible_path(dir.path()))
+        .map_err(|_| LinuxError::ENOTDIR)
+}
+
+/// Resolve `path` relative to `dirfd` into the canonical absolute path of the
+/// name it refers to, without resolving a symbolic link or a hard link as the
+/// last component.
+///
+/// `dirfd` is ignored if `path` is absolute. Otherwise it must be `AT_FDCWD`
+/// or refer to a directory: a closed fd fails with `EBADF`, and any other file
+/// with `ENOTDIR`. "." and ".." are removed. Files under `/proc` are generated
+/// before they are looked up.
+pub(crate) fn resolve_name_at(dirfd: i32, path: *const c_char) -> LinuxResult<String> {
+    let path = api::char_ptr_to_str(path)?;
+    if path.is_empty() {
+        return Err(LinuxError::ENOENT);
+    }
+    let path = if path.starts_with('/') {
+        path.into()
+    } else {
+        format!("{}/{path}", dir_path(dirfd)?.trim_end_matches('/'))
+    };
+    refresh_proc(&path);
+    resolve_symlinks(&path, false)
+}
+
+/// Resolve `path` relative to `dirfd` into the canonical absolute path of the
+/// file, like `resolve_name_at`.
+///
+/// The last component is resolved if it is a symbolic link and
+/// `follow_symlinks` is set, and then if it is a hard link, so that the path
+/// is where the data of the file is.
+pub(crate) fn resolve_at(
+    dirfd: i32,
+    path: *const c_char,
+    follow_symlinks: bool,
+) -> LinuxResult<String> {
+    let path = resolve_name_at(dirfd, path)?;
+    let path = if follow_symlinks {
+        resolve_symlinks(&path, true)?
+    } else {
+        path
+    };
+    Ok(hard_link_target(&path).unwrap_or(path))
+}
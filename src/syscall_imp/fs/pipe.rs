@@ -1,8 +1,346 @@
-use core::ffi::c_int;
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
 
-use arceos_posix_api as api;
+use alloc::{
+    collections::{btree_map::BTreeMap, vec_deque::VecDeque},
+    string::String,
+    sync::{Arc, Weak},
+};
+use arceos_posix_api::{self as api, FileLike};
+use axerrno::{LinuxError, LinuxResult};
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, WaitQueue, current};
 
-pub(crate) fn sys_pipe2(fds: *mut i32) -> c_int {
-    let fds_slice: &mut [c_int] = unsafe { core::slice::from_raw_parts_mut(fds, 2) };
-    api::sys_pipe(fds_slice)
+use super::{
+    fd_ops::{O_CLOEXEC, set_file_status_flags},
+    poll::notify_pollers,
+};
+use crate::{
+    mm::check_user_range,
+    signal::{SIGPIPE, wait_interruptible},
+    syscall_body,
+};
+
+/// The capacity of a pipe, which is the default of Linux.
+const PIPE_CAPACITY: usize = 65536;
+/// Writes of at most this many bytes are atomic, i.e. not interleaved with other writes.
+const PIPE_BUF: usize = 4096;
+
+/// The state shared by the ends of a pipe.
+struct PipeShared {
+    buffer: Mutex<VecDeque<u8>>,
+    /// Readers waiting for data, writers waiting for space and FIFOs waiting to be opened.
+    wait_queue: WaitQueue,
+    /// The number of open ends that can be read from.
+    readers: AtomicUsize,
+    /// The number of open ends that can be written to.
+    writers: AtomicUsize,
+    /// How many times the pipe has been opened for reading, so that a FIFO being
+    /// opened for writing notices a reader even if it has gone again.
+    read_opens: AtomicUsize,
+    /// How many times the pipe has been opened for writing.
+    write_opens: AtomicUsize,
+}
+
+impl PipeShared {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            buffer: Mutex::new(VecDeque::with_capacity(PIPE_CAPACITY)),
+            wait_queue: WaitQueue::new(),
+            readers: AtomicUsize::new(0),
+            writers: AtomicUsize::new(0),
+            read_opens: AtomicUsize::new(0),
+            write_opens: AtomicUsize::new(0),
+        })
+    }
+}
+
+/// One end of a pipe.
+///
+/// Each end is a single open file, so all the fds referring to it, whether
+/// duplicated or inherited by `clone`, share it. The end is closed when the last
+/// of them is closed. A FIFO opened for both reading and writing is both ends
+/// at once.
+pub(crate) struct Pipe {
+    readable: bool,
+    writable: bool,
+    nonblocking: AtomicBool,
+    shared: Arc<PipeShared>,
+}
+
+impl Pipe {
+    /// Create a pipe, returning the read end and the write end.
+    fn new() -> (Arc<Pipe>, Arc<Pipe>) {
+        let shared = PipeShared::new();
+        (
+            Self::open(shared.clone(), true, false),
+            Self::open(shared, false, true),
+        )
+    }
+
+    /// Open an end of the pipe `shared`.
+    fn open(shared: Arc<PipeShared>, readable: bool, writable: bool) -> Arc<Pipe> {
+        if readable {
+            shared.readers.fetch_add(1, Ordering::AcqRel);
+            shared.read_opens.fetch_add(1, Ordering::AcqRel);
+        }
+        if writable {
+            shared.writers.fetch_add(1, Ordering::AcqRel);
+            shared.write_opens.fetch_add(1, Ordering::AcqRel);
+        }
+        let end = Arc::new(Pipe {
+            readable,
+            writable,
+            nonblocking: AtomicBool::new(false),
+            shared,
+        });
+        // Wake up the FIFOs waiting to be opened.
+        end.notify();
+        end
+    }
+
+    fn read_closed(&self) -> bool {
+        self.shared.readers.load(Ordering::Acquire) == 0
+    }
+
+    fn write_closed(&self) -> bool {
+        self.shared.writers.load(Ordering::Acquire) == 0
+    }
+
+    /// Whether this is the read end of the pipe.
+    pub(super) fn is_read_end(&self) -> bool {
+        self.readable
+    }
+
+    /// The number of bytes that can be read from the read end without blocking.
+    pub(super) fn available(&self) -> usize {
+        if self.readable {
+            self.shared.buffer.lock().len()
+        } else {
+            0
+        }
+    }
+
+    /// Whether the other end of the pipe has been closed.
+    pub(super) fn peer_closed(&self) -> bool {
+        (self.readable && self.write_closed()) || (self.writable && self.read_closed())
+    }
+
+    /// Wake up the tasks waiting for the pipe to change state.
+    fn notify(&self) {
+        self.shared.wait_queue.notify_all(false);
+        notify_pollers();
+    }
+}
+
+impl FileLike for Pipe {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if !self.readable {
+            return Err(LinuxError::EBADF);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let mut buffer = self.shared.buffer.lock();
+            if !buffer.is_empty() {
+                let len = buf.len().min(buffer.len());
+                for (dst, src) in buf.iter_mut().zip(buffer.drain(..len)) {
+                    *dst = src;
+                }
+                drop(buffer);
+                self.notify();
+                return Ok(len);
+            }
+            // Nothing more can be read once every writer is gone.
+            if self.write_closed() {
+                return Ok(0);
+            }
+            if self.nonblocking.load(Ordering::Relaxed) {
+                return Err(LinuxError::EAGAIN);
+            }
+            drop(buffer);
+            wait_interruptible(&self.shared.wait_queue, None, || {
+                !self.shared.buffer.lock().is_empty() || self.write_closed()
+            })?;
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        if !self.writable {
+            return Err(LinuxError::EBADF);
+        }
+        let mut written = 0;
+        while written < buf.len() {
+            if self.read_closed() {
+                // The writer is told by `SIGPIPE` as well, which terminates it by default.
+                current().task_ext().pending_signals.add(SIGPIPE);
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    Err(LinuxError::EPIPE)
+                };
+            }
+            let mut buffer = self.shared.buffer.lock();
+            let space = PIPE_CAPACITY - buffer.len();
+            // A small write must not be split, so it waits until it fits as a whole.
+            let needed = if buf.len() <= PIPE_BUF { buf.len() } else { 1 };
+            if space >= needed {
+                let len = space.min(buf.len() - written);
+                buffer.extend(&buf[written..written + len]);
+                written += len;
+                drop(buffer);
+                self.notify();
+                continue;
+            }
+            if self.nonblocking.load(Ordering::Relaxed) {
+                return if written > 0 {
+                    Ok(written)
+                } else {
+                    Err(LinuxError::EAGAIN)
+                };
+            }
+            drop(buffer);
+            // A write interrupted by a signal returns what has been written so far.
+            if let Err(err) = wait_interruptible(&self.shared.wait_queue, None, || {
+                PIPE_CAPACITY - self.shared.buffer.lock().len() >= needed || self.read_closed()
+            }) {
+                return if written > 0 { Ok(written) } else { Err(err) };
+            }
+        }
+        Ok(written)
+    }
+
+    fn stat(&self) -> LinuxResult<api::ctypes::stat> {
+        const S_IFIFO: u32 = 0o010000;
+        Ok(api::ctypes::stat {
+            st_ino: Arc::as_ptr(&self.shared) as u64,
+            st_nlink: 1,
+            st_mode: S_IFIFO | 0o600,
+            st_uid: 1000,
+            st_gid: 1000,
+            st_blksize: PIPE_BUF as _,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    /// Closing the other end is reported by `peer_closed` as a hangup or an
+    /// error, like Linux does, rather than making the pipe readable or writable.
+    fn poll(&self) -> LinuxResult<PollState> {
+        let buffer = self.shared.buffer.lock();
+        Ok(PollState {
+            readable: self.readable && !buffer.is_empty(),
+            writable: self.writable && buffer.len() < PIPE_CAPACITY,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        if self.readable {
+            self.shared.readers.fetch_sub(1, Ordering::AcqRel);
+        }
+        if self.writable {
+            self.shared.writers.fetch_sub(1, Ordering::AcqRel);
+        }
+        // Wake up the other end so that it sees the end of file or the broken pipe.
+        self.notify();
+    }
+}
+
+/// The pipes of the FIFOs that are open, keyed by the canonical paths of the FIFOs.
+static FIFOS: Mutex<BTreeMap<String, Weak<PipeShared>>> = Mutex::new(BTreeMap::new());
+
+/// Open the FIFO at `path` with the access mode `access`.
+///
+/// The pipe behind the FIFO is created when it is first opened, and is gone
+/// with its data once every end is closed. Opening it only for reading waits
+/// until it is opened for writing, and vice versa. With `nonblocking` set,
+/// opening it for reading succeeds at once, and opening it for writing fails
+/// with `ENXIO` if it is not open for reading.
+pub(super) fn open_fifo(path: &str, access: u32, nonblocking: bool) -> LinuxResult<Arc<Pipe>> {
+    let (readable, writable) = match access {
+        api::ctypes::O_RDONLY => (true, false),
+        api::ctypes::O_WRONLY => (false, true),
+        api::ctypes::O_RDWR => (true, true),
+        _ => return Err(LinuxError::EINVAL),
+    };
+    let shared = {
+        let mut fifos = FIFOS.lock();
+        fifos.retain(|_, shared| shared.strong_count() > 0);
+        match fifos.get(path).and_then(Weak::upgrade) {
+            Some(shared) => shared,
+            None => {
+                let shared = PipeShared::new();
+                fifos.insert(path.into(), Arc::downgrade(&shared));
+                shared
+            }
+        }
+    };
+    if nonblocking && !readable && shared.readers.load(Ordering::Acquire) == 0 {
+        return Err(LinuxError::ENXIO);
+    }
+
+    let (peers, peer_opens) = if readable {
+        (&shared.writers, &shared.write_opens)
+    } else {
+        (&shared.readers, &shared.read_opens)
+    };
+    let opens = peer_opens.load(Ordering::Acquire);
+    let end = Pipe::open(shared.clone(), readable, writable);
+    if !nonblocking && !(readable && writable) {
+        wait_interruptible(&shared.wait_queue, None, || {
+            peers.load(Ordering::Acquire) > 0 || peer_opens.load(Ordering::Acquire) != opens
+        })?;
+    }
+    Ok(end)
+}
+
+/// Add an end of a pipe to the fd table with the flags given to `pipe2`.
+fn add_pipe_end(end: Arc<Pipe>, access: u32, flags: u32) -> LinuxResult<c_int> {
+    end.set_nonblocking(flags & api::ctypes::O_NONBLOCK != 0)?;
+    let end: Arc<dyn FileLike> = end;
+    set_file_status_flags(&end, access | (flags & api::ctypes::O_NONBLOCK));
+    let fd = api::add_file_like(end)?;
+    current()
+        .task_ext()
+        .set_close_on_exec(fd, flags & O_CLOEXEC != 0);
+    Ok(fd)
+}
+
+/// Create a pipe, storing the fds of its read end and write end in `fds`.
+///
+/// `flags` may contain `O_NONBLOCK` and `O_CLOEXEC`, which apply to both ends.
+pub(crate) fn sys_pipe2(fds: *mut c_int, flags: u32) -> c_int {
+    syscall_body!(sys_pipe2, {
+        if flags & !(api::ctypes::O_NONBLOCK | O_CLOEXEC) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        check_user_range(
+            current().task_ext(),
+            (fds as usize).into(),
+            size_of::<[c_int; 2]>(),
+        )
+        .map_err(|_| LinuxError::EFAULT)?;
+
+        let (read_end, write_end) = Pipe::new();
+        let read_fd = add_pipe_end(read_end, api::ctypes::O_RDONLY, flags)?;
+        let write_fd = add_pipe_end(write_end, api::ctypes::O_WRONLY, flags).inspect_err(|_| {
+            api::sys_close(read_fd);
+        })?;
+        unsafe { fds.cast::<[c_int; 2]>().write([read_fd, write_fd]) };
+        Ok(0)
+    })
 }
@@ -0,0 +1,390 @@
+use core::time::Duration;
+
+use alloc::{vec, vec::Vec};
+#[cfg(target_arch = "x86_64")]
+use arceos_posix_api::ctypes::timeval;
+use arceos_posix_api::{self as api, ctypes::timespec};
+use axerrno::{LinuxError, LinuxResult};
+use axhal::time::monotonic_time;
+use axtask::{TaskExtRef, WaitQueue, current};
+
+use super::pipe::Pipe;
+use crate::{
+    ctypes::PollFd,
+    mm::check_user_range,
+    signal::{set_temporary_signal_mask, signal_pending},
+    syscall_body,
+};
+
+pub(super) const POLLIN: i16 = 0x001;
+pub(super) const POLLOUT: i16 = 0x004;
+pub(super) const POLLERR: i16 = 0x008;
+pub(super) const POLLHUP: i16 = 0x010;
+const POLLNVAL: i16 = 0x020;
+
+/// Tasks waiting in `poll` or `select`, woken up whenever a pipe changes state.
+static POLL_WAIT_QUEUE: WaitQueue = WaitQueue::new();
+
+/// Files which do not notify `POLL_WAIT_QUEUE`, like the console, are checked
+/// again after this interval.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Wake up the tasks waiting in `poll` or `select` to check their fds again.
+pub(super) fn notify_pollers() {
+    POLL_WAIT_QUEUE.notify_all(false);
+}
+
+/// Get the events currently pending on `fd`, as in `revents` of `poll`.
+pub(super) fn fd_events(fd: i32) -> LinuxResult<i16> {
+    let file = api::get_file_like(fd)?;
+    let state = file.poll()?;
+    let mut events = 0;
+    if state.readable {
+        events |= POLLIN;
+    }
+    if state.writable {
+        events |= POLLOUT;
+    }
+    if let Ok(pipe) = file.into_any().downcast::<Pipe>() {
+        if pipe.peer_closed() {
+            events |= if pipe.is_read_end() { POLLHUP } else { POLLERR };
+        }
+    }
+    Ok(events)
+}
+
+/// Wait until `check` reports something ready or `timeout` expires.
+///
+/// `check` is called at least once, and again whenever something may have
+/// changed. A `timeout` of `None` waits forever, and a signal interrupts the
+/// wait with `EINTR`.
+pub(super) fn wait_ready(
+    timeout: Option<Duration>,
+    mut check: impl FnMut() -> LinuxResult<usize>,
+) -> LinuxResult<usize> {
+    let deadline = timeout.map(|timeout| monotonic_time() + timeout);
+    loop {
+        let ready = check()?;
+        if ready > 0 {
+            return Ok(ready);
+        }
+        if signal_pending() {
+            return Err(LinuxError::EINTR);
+        }
+        let wait = match deadline {
+            Some(deadline) => {
+                let now = monotonic_time();
+                if now >= deadline {
+                    return Ok(0);
+                }
+                (deadline - now).min(POLL_INTERVAL)
+            }
+            None => POLL_INTERVAL,
+        };
+        POLL_WAIT_QUEUE.wait_timeout(wait);
+    }
+}
+
+/// Block the signals in the set at `sigmask` of `sigsetsize` bytes instead while
+/// the syscall waits, unless `sigmask` is null.
+pub(super) fn apply_sigmask(sigmask: *const u64, sigsetsize: usize) -> LinuxResult {
+    if sigmask.is_null() {
+        return Ok(());
+    }
+    if sigsetsize != size_of::<u64>() {
+        return Err(LinuxError::EINVAL);
+    }
+    check_user_range(
+        current().task_ext(),
+        (sigmask as usize).into(),
+        size_of::<u64>(),
+    )
+    .map_err(|_| LinuxError::EFAULT)?;
+    set_temporary_signal_mask(unsafe { sigmask.read() });
+    Ok(())
+}
+
+/// Read a timeout from user memory, or `None` if `timeout` is null.
+pub(crate) fn user_timeout(timeout: *const timespec) -> LinuxResult<Option<Duration>> {
+    if timeout.is_null() {
+        return Ok(None);
+    }
+    check_user_range(
+        current().task_ext(),
+        (timeout as usize).into(),
+        size_of::<timespec>(),
+    )
+    .map_err(|_| LinuxError::EFAULT)?;
+    let timeout = unsafe { timeout.read() };
+    if timeout.tv_sec < 0 || !(0..1_000_000_000).contains(&timeout.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Some(Duration::new(
+        timeout.tv_sec as u64,
+        timeout.tv_nsec as u32,
+    )))
+}
+
+/// Update the user `timeout` of `duration` to the time remaining after `elapsed`.
+fn write_remaining(timeout: *mut timespec, duration: Duration, elapsed: Duration) {
+    let remaining = duration.saturating_sub(elapsed);
+    unsafe {
+        timeout.write(timespec {
+            tv_sec: remaining.as_secs() as _,
+            tv_nsec: remaining.subsec_nanos() as _,
+        })
+    };
+}
+
+/// Poll `nfds` fds in `fds` for the events they request.
+///
+/// `revents` is written back for every entry, and the number of entries with
+/// nonzero `revents` is returned.
+fn do_poll(fds: *mut PollFd, nfds: usize, timeout: Option<Duration>) -> LinuxResult<isize> {
+    if nfds > api::FD_TABLE.read().capacity() {
+        return Err(LinuxError::EINVAL);
+    }
+    check_user_range(
+        current().task_ext(),
+        (fds as usize).into(),
+        nfds * size_of::<PollFd>(),
+    )
+    .map_err(|_| LinuxError::EFAULT)?;
+    let fds = unsafe { core::slice::from_raw_parts_mut(fds, nfds) };
+
+    let ready = wait_ready(timeout, || {
+        let mut ready = 0;
+        for pollfd in fds.iter_mut() {
+            pollfd.revents = if pollfd.fd < 0 {
+                0
+            } else {
+                match fd_events(pollfd.fd) {
+                    // Errors and hangups are reported even if not requested.
+                    Ok(events) => events & (pollfd.events | POLLERR | POLLHUP),
+                    Err(_) => POLLNVAL,
+                }
+            };
+            if pollfd.revents != 0 {
+                ready += 1;
+            }
+        }
+        Ok(ready)
+    })?;
+    Ok(ready as isize)
+}
+
+/// Wait for one of a set of fds to become ready for I/O.
+///
+/// A null `timeout` waits forever, and a zero one returns immediately. Otherwise
+/// it is updated to the time remaining, like Linux does. Unless `sigmask` is
+/// null, its signals are blocked instead while waiting.
+pub(crate) fn sys_ppoll(
+    fds: *mut PollFd,
+    nfds: usize,
+    timeout: *mut timespec,
+    sigmask: *const u64,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_ppoll, {
+        let duration = user_timeout(timeout)?;
+        apply_sigmask(sigmask, sigsetsize)?;
+        let start = monotonic_time();
+        let ready = do_poll(fds, nfds, duration)?;
+        if let Some(duration) = duration {
+            write_remaining(timeout, duration, monotonic_time() - start);
+        }
+        Ok(ready)
+    })
+}
+
+/// Like `ppoll`, but with the timeout in milliseconds, where a negative one waits forever.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_poll(fds: *mut PollFd, nfds: usize, timeout: i32) -> isize {
+    syscall_body!(sys_poll, {
+        let timeout = u64::try_from(timeout).ok().map(Duration::from_millis);
+        do_poll(fds, nfds, timeout)
+    })
+}
+
+/// Bits in one word of an `fd_set`.
+const FD_SET_WORD_BITS: usize = usize::BITS as usize;
+
+/// An `fd_set` of `select`, copied in from user memory.
+struct FdSet {
+    user: *mut usize,
+    words: Vec<usize>,
+}
+
+impl FdSet {
+    /// Copy in the first `nfds` bits of the set at `user`, or `None` if it is null.
+    fn read(user: *mut usize, nfds: usize) -> LinuxResult<Option<Self>> {
+        if user.is_null() {
+            return Ok(None);
+        }
+        let len = nfds.div_ceil(FD_SET_WORD_BITS);
+        check_user_range(
+            current().task_ext(),
+            (user as usize).into(),
+            len * size_of::<usize>(),
+        )
+        .map_err(|_| LinuxError::EFAULT)?;
+        let mut words = unsafe { core::slice::from_raw_parts(user, len) }.to_vec();
+        // Bits beyond `nfds` in the last word are ignored, and cleared on return.
+        if nfds % FD_SET_WORD_BITS != 0 {
+            words[len - 1] &= (1 << (nfds % FD_SET_WORD_BITS)) - 1;
+        }
+        Ok(Some(Self { user, words }))
+    }
+
+    fn contains(&self, fd: usize) -> bool {
+        self.words[fd / FD_SET_WORD_BITS] & (1 << (fd % FD_SET_WORD_BITS)) != 0
+    }
+
+    /// Copy out the fds in this set for which `ready` holds, returning how many there are.
+    fn write_ready(&self, ready: impl Fn(usize) -> bool) -> usize {
+        let mut count = 0;
+        for (i, &word) in self.words.iter().enumerate() {
+            let mut out = 0;
+            for bit in 0..FD_SET_WORD_BITS {
+                if word & (1 << bit) != 0 && ready(i * FD_SET_WORD_BITS + bit) {
+                    out |= 1 << bit;
+                    count += 1;
+                }
+            }
+            unsafe { self.user.add(i).write(out) };
+        }
+        count
+    }
+}
+
+/// Wait for some of the fds in the three sets of `select` to become ready for
+/// reading, writing, or to have an exceptional condition.
+///
+/// On return each set only keeps the ready fds, and the total number of them is
+/// returned along with the time elapsed.
+fn do_select(
+    nfds: i32,
+    readfds: *mut usize,
+    writefds: *mut usize,
+    exceptfds: *mut usize,
+    timeout: Option<Duration>,
+) -> LinuxResult<(usize, Duration)> {
+    // Events which make an fd ready for each set, like Linux. There is no
+    // exceptional condition, like out-of-band data, to be reported yet.
+    const SET_EVENTS: [i16; 3] = [POLLIN | POLLHUP | POLLERR, POLLOUT | POLLERR, 0];
+
+    let nfds = usize::try_from(nfds).map_err(|_| LinuxError::EINVAL)?;
+    let nfds = nfds.min(api::FD_TABLE.read().capacity());
+    let sets = [
+        FdSet::read(readfds, nfds)?,
+        FdSet::read(writefds, nfds)?,
+        FdSet::read(exceptfds, nfds)?,
+    ];
+
+    let start = monotonic_time();
+    let mut events = vec![0; nfds];
+    wait_ready(timeout, || {
+        let mut ready = 0;
+        for (fd, events) in events.iter_mut().enumerate() {
+            let mut selected = false;
+            let mut wanted = 0;
+            for (set, set_events) in sets.iter().zip(SET_EVENTS) {
+                if set.as_ref().is_some_and(|set| set.contains(fd)) {
+                    selected = true;
+                    wanted |= set_events;
+                }
+            }
+            if !selected {
+                continue;
+            }
+            *events = fd_events(fd as i32).map_err(|_| LinuxError::EBADF)? & wanted;
+            if *events != 0 {
+                ready += 1;
+            }
+        }
+        Ok(ready)
+    })?;
+
+    let mut count = 0;
+    for (set, set_events) in sets.iter().zip(SET_EVENTS) {
+        if let Some(set) = set {
+            count += set.write_ready(|fd| events[fd] & set_events != 0);
+        }
+    }
+    Ok((count, monotonic_time() - start))
+}
+
+/// Wait for some of the fds in the three sets to become ready, as in `do_select`.
+///
+/// Unless `timeout` is null, it is updated to the time remaining. Unless
+/// `sigmask` is null, it points to the address and the size of a signal set,
+/// whose signals are blocked instead while waiting.
+pub(crate) fn sys_pselect6(
+    nfds: i32,
+    readfds: *mut usize,
+    writefds: *mut usize,
+    exceptfds: *mut usize,
+    timeout: *mut timespec,
+    sigmask: *const [usize; 2],
+) -> isize {
+    syscall_body!(sys_pselect6, {
+        let duration = user_timeout(timeout)?;
+        if !sigmask.is_null() {
+            check_user_range(
+                current().task_ext(),
+                (sigmask as usize).into(),
+                size_of::<[usize; 2]>(),
+            )
+            .map_err(|_| LinuxError::EFAULT)?;
+            let [set, size] = unsafe { sigmask.read() };
+            apply_sigmask(set as *const u64, size)?;
+        }
+        let (count, elapsed) = do_select(nfds, readfds, writefds, exceptfds, duration)?;
+        if let Some(duration) = duration {
+            write_remaining(timeout, duration, elapsed);
+        }
+        Ok(count as isize)
+    })
+}
+
+/// Like `pselect6` without a signal mask, but with the timeout as a `timeval`.
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn sys_select(
+    nfds: i32,
+    readfds: *mut usize,
+    writefds: *mut usize,
+    exceptfds: *mut usize,
+    timeout: *mut timeval,
+) -> isize {
+    syscall_body!(sys_select, {
+        let duration = if timeout.is_null() {
+            None
+        } else {
+            check_user_range(
+                current().task_ext(),
+                (timeout as usize).into(),
+                size_of::<timeval>(),
+            )
+            .map_err(|_| LinuxError::EFAULT)?;
+            let timeout = unsafe { timeout.read() };
+            if timeout.tv_sec < 0 || !(0..1_000_000).contains(&timeout.tv_usec) {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(Duration::new(
+                timeout.tv_sec as u64,
+                timeout.tv_usec as u32 * 1000,
+            ))
+        };
+        let (count, elapsed) = do_select(nfds, readfds, writefds, exceptfds, duration)?;
+        if let Some(duration) = duration {
+            let remaining = duration.saturating_sub(elapsed);
+            unsafe {
+                timeout.write(timeval {
+                    tv_sec: remaining.as_secs() as _,
+                    tv_usec: remaining.subsec_micros() as _,
+                })
+            };
+        }
+        Ok(count as isize)
+    })
+}
@@ -0,0 +1,306 @@
+//! The `/proc` filesystem.
+//!
+//! It is emulated with ordinary files: the files under `/proc` are generated
+//! whenever a path beneath it is accessed, so that they are read, listed and
+//! stat-ed like any other file. `/proc/self` is a symbolic link whose target
+//! is not stored but given by `self_link` for the process resolving it, so
+//! that processes accessing it at once each reach their own directory.
+
+use core::fmt::Write;
+
+use alloc::{
+    collections::btree_set::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use axerrno::LinuxResult;
+use axhal::paging::MappingFlags;
+use axsync::Mutex;
+use axtask::{AxTaskRef, TaskExtRef, current};
+use memory_addr::{PAGE_SIZE_4K, VirtAddr};
+
+use super::{
+    attr::update_file_attr,
+    cache::{cache_stats, cached_pages},
+    dircache::dir_cache_stats,
+    util::{entry_names, is_beneath, remove_tree},
+};
+use crate::{mm::mapped_regions, task::processes};
+
+const PROC: &str = "/proc";
+
+/// Serializes the generation of the files, which are rewritten as a whole.
+static PROC_LOCK: Mutex<()> = Mutex::new(());
+
+fn create_dir(path: &str) -> LinuxResult {
+    if axfs::api::metadata(path).is_err() {
+        axfs::api::create_dir(path)?;
+        update_file_attr(path, |attr| attr.mode = Some(0o555));
+    }
+    Ok(())
+}
+
+fn write_file(path: &str, contents: &str) -> LinuxResult {
+    axfs::api::write(path, contents)?;
+    update_file_attr(path, |attr| attr.mode = Some(0o444));
+    Ok(())
+}
+
+fn write_symlink(path: &str, target: &str) -> LinuxResult {
+    axfs::api::write(path, target)?;
+    update_file_attr(path, |attr| attr.symlink = Some(target.into()));
+    Ok(())
+}
+
+/// Bring `/proc` up to date for an access to `path`.
+///
+/// The directories of the processes are kept in line with the processes alive,
+/// and the files of the process that `path` leads into are generated afresh.
+pub(super) fn refresh_proc(path: &str) {
+    if !is_beneath(path, PROC) {
+        return;
+    }
+    if let Err(err) = refresh(&path[PROC.len()..]) {
+        warn!("Failed to generate {PROC}: {err:?}");
+    }
+}
+
+fn refresh(rest: &str) -> LinuxResult {
+    let _guard = PROC_LOCK.lock();
+    create_dir(PROC)?;
+    let processes = processes();
+    let pids: BTreeSet<String> = processes
+        .iter()
+        .map(|task| task.task_ext().proc_id.to_string())
+        .collect();
+    for name in entry_names(PROC)? {
+        if name.bytes().all(|byte| byte.is_ascii_digit()) && !pids.contains(&name) {
+            remove_tree(&format!("{PROC}/{name}"))?;
+        }
+    }
+    for pid in &pids {
+        create_dir(&format!("{PROC}/{pid}"))?;
+    }
+    let self_pid = current().task_ext().proc_id;
+    // The stored target is only there to make it a symbolic link.
+    if axfs::api::metadata(&format!("{PROC}/self")).is_err() {
+        write_symlink(&format!("{PROC}/self"), &self_pid.to_string())?;
+    }
+    write_file(&format!("{PROC}/meminfo"), &meminfo())?;
+    write_file(&format!("{PROC}/cpuinfo"), &cpuinfo())?;
+    write_file(&format!("{PROC}/pagecache"), &cache_stats())?;
+    write_file(&format!("{PROC}/dircache"), &dir_cache_stats())?;
+
+    let first = rest.trim_start_matches('/').split('/').next().unwrap_or("");
+    let pid = if first == "self" {
+        Some(self_pid)
+    } else {
+        first.parse().ok()
+    };
+    if let Some(task) =
+        pid.and_then(|pid| processes.iter().find(|task| task.task_ext().proc_id == pid))
+    {
+        let dir = format!("{PROC}/{}", task.task_ext().proc_id);
+        write_symlink(&format!("{dir}/exe"), &task.task_ext().exe_path.lock())?;
+        write_file(&format!("{dir}/maps"), &maps(task))?;
+        write_file(&format!("{dir}/status"), &status(task))?;
+    }
+    Ok(())
+}
+
+/// Get the target of `/proc/self` for the current process if `path` is its
+/// canonical path, or `None` otherwise.
+pub(super) fn self_link(path: &str) -> Option<String> {
+    (path == "/proc/self").then(|| current().task_ext().proc_id.to_string())
+}
+
+/// Generate `/proc/meminfo`, which describes the usage of memory.
+///
+/// The free memory is what the frame allocator has left, and what the byte
+/// allocator has left is available in addition.
+fn meminfo() -> String {
+    let allocator = axalloc::global_allocator();
+    let free = allocator.available_pages() * PAGE_SIZE_4K;
+    let total = allocator.used_pages() * PAGE_SIZE_4K + free;
+    let available = free + allocator.available_bytes();
+    let mut meminfo = String::new();
+    for (name, bytes) in [
+        ("MemTotal", total),
+        ("MemFree", free),
+        ("MemAvailable", available),
+        ("Buffers", 0),
+        ("Cached", cached_pages() * PAGE_SIZE_4K),
+        ("SwapCached", 0),
+        ("Shmem", 0),
+        ("SwapTotal", 0),
+        ("SwapFree", 0),
+    ] {
+        writeln!(meminfo, "{:<16}{:>8} kB", format!("{name}:"), bytes / 1024).unwrap();
+    }
+    meminfo
+}
+
+/// Generate `/proc/cpuinfo`, which has a paragraph for each CPU.
+fn cpuinfo() -> String {
+    let mut cpuinfo = String::new();
+    for cpu in 0..axconfig::SMP {
+        writeln!(cpuinfo, "processor\t: {cpu}").unwrap();
+        #[cfg(target_arch = "riscv64")]
+        {
+            writeln!(cpuinfo, "hart\t\t: {cpu}").unwrap();
+            writeln!(cpuinfo, "isa\t\t: rv64imafdc").unwrap();
+            writeln!(cpuinfo, "mmu\t\t: sv39").unwrap();
+        }
+        #[cfg(target_arch = "x86_64")]
+        {
+            let (vendor, model) = x86_cpu_names();
+            writeln!(cpuinfo, "vendor_id\t: {vendor}").unwrap();
+            writeln!(cpuinfo, "model name\t: {model}").unwrap();
+        }
+        #[cfg(target_arch = "loongarch64")]
+        writeln!(cpuinfo, "model name\t\t: Loongson-3A5000").unwrap();
+        #[cfg(target_arch = "aarch64")]
+        writeln!(cpuinfo, "CPU architecture: 8").unwrap();
+        writeln!(cpuinfo).unwrap();
+    }
+    cpuinfo
+}
+
+/// Get the vendor and the brand string of the CPU from `cpuid`.
+#[cfg(target_arch = "x86_64")]
+fn x86_cpu_names() -> (String, String) {
+    use core::arch::x86_64::__cpuid;
+
+    let to_string = |regs: &[u32]| {
+        let bytes: Vec<u8> = regs.iter().flat_map(|reg| reg.to_le_bytes()).collect();
+        String::from_utf8_lossy(&bytes)
+            .trim_matches(|c: char| c == '\0' || c == ' ')
+            .to_string()
+    };
+    let leaf = unsafe { __cpuid(0) };
+    let vendor = to_string(&[leaf.ebx, leaf.edx, leaf.ecx]);
+    let model = if unsafe { __cpuid(0x8000_0000) }.eax >= 0x8000_0004 {
+        let regs: Vec<u32> = (0x8000_0002..=0x8000_0004)
+            .flat_map(|leaf| {
+                let leaf = unsafe { __cpuid(leaf) };
+                [leaf.eax, leaf.ebx, leaf.ecx, leaf.edx]
+            })
+            .collect();
+        to_string(&regs)
+    } else {
+        String::new()
+    };
+    (vendor, model)
+}
+
+/// Generate `/proc/<pid>/maps`, which lists the mapped regions.
+///
+/// The address space is locked while it is looked at, so the regions are a
+/// consistent snapshot.
+fn maps(task: &AxTaskRef) -> String {
+    let ext = task.task_ext();
+    let aspace = ext.aspace.lock();
+    let mappings = ext.file_mappings.lock().clone();
+    let heap =
+        VirtAddr::from(ext.get_heap_bottom() as usize)..VirtAddr::from(ext.get_heap_top() as usize);
+    let stack_top = VirtAddr::from(axconfig::plat::USER_STACK_TOP);
+
+    let mut maps = String::new();
+    for (start, end, flags) in mapped_regions(&aspace) {
+        // Split the region where file mappings start and end, so that each part
+        // is backed by a single file.
+        let mut cuts: Vec<VirtAddr> = mappings
+            .iter()
+            .flat_map(|mapping| [mapping.start, mapping.end])
+            .filter(|&addr| start < addr && addr < end)
+            .chain([end])
+            .collect();
+        cuts.sort();
+        cuts.dedup();
+        let mut from = start;
+        for to in cuts {
+            let mapping = mappings
+                .iter()
+                .find(|mapping| mapping.start <= from && from < mapping.end);
+            let (offset, name) = match mapping {
+                Some(mapping) => (
+                    mapping.offset + (from - mapping.start) as u64,
+                    // Linux backs shared anonymous mappings with an unlinked
+                    // file that it names after `/dev/zero`.
+                    mapping
+                        .file
+                        .as_ref()
+                        .map_or("/dev/zero (deleted)", |file| file.path()),
+                ),
+                None if from < stack_top && stack_top <= to => (0, "[stack]"),
+                None if from < heap.end && heap.start < to => (0, "[heap]"),
+                None => (0, ""),
+            };
+            let perms = [
+                if flags.contains(MappingFlags::READ) {
+                    'r'
+                } else {
+                    '-'
+                },
+                if flags.contains(MappingFlags::WRITE) {
+                    'w'
+                } else {
+                    '-'
+                },
+                if flags.contains(MappingFlags::EXECUTE) {
+                    'x'
+                } else {
+                    '-'
+                },
+                if mapping.is_some_and(|mapping| mapping.shared) {
+                    's'
+                } else {
+                    'p'
+                },
+            ];
+            let line = format!(
+                "{:08x}-{:08x} {} {:08x} 00:00 0 ",
+                from,
+                to,
+                perms.iter().collect::<String>(),
+                offset
+            );
+            if name.is_empty() {
+                writeln!(maps, "{}", line.trim_end()).unwrap();
+            } else {
+                writeln!(maps, "{line:<73}{name}").unwrap();
+            }
+            from = to;
+        }
+    }
+    maps
+}
+
+/// Generate `/proc/<pid>/status`, which describes the process.
+fn status(task: &AxTaskRef) -> String {
+    let ext = task.task_ext();
+    let exe_path = ext.exe_path.lock().clone();
+    let name: String = exe_path
+        .rsplit('/')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .take(15)
+        .collect();
+    let state = match task.state() {
+        axtask::TaskState::Blocked => "S (sleeping)",
+        _ => "R (running)",
+    };
+    let vm_size: usize = mapped_regions(&ext.aspace.lock())
+        .iter()
+        .map(|(start, end, _)| *end - *start)
+        .sum();
+    format!(
+        "Name:\t{name}\nState:\t{state}\nPid:\t{}\nPPid:\t{}\nVmSize:\t{:>8} kB\nThreads:\t{}\n",
+        ext.proc_id,
+        ext.get_parent(),
+        vm_size / 1024,
+        ext.thread_group.live_threads()
+    )
+}
@@ -1,8 +1,17 @@
 use core::ffi::c_void;
 
-use axerrno::LinuxError;
+use arceos_posix_api::AT_FDCWD;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+use memory_addr::PAGE_SIZE_4K;
 
-use crate::syscall_body;
+use super::{
+    attr::{apply_file_attr, fd_path},
+    dircache::entry_metadata,
+    mount::mounted_fs_magic,
+    path::resolve_at,
+};
+use crate::{ctypes::StatFs, mm::check_user_range, syscall_body};
 
 #[derive(Debug, Clone, Copy, Default)]
 #[repr(C)]
@@ -70,22 +79,172 @@ impl From<arceos_posix_api::ctypes::stat> for Kstat {
     }
 }
 
-pub(crate) fn sys_fstat(fd: i32, kstatbuf: *mut c_void) -> i32 {
-    let kstatbuf = kstatbuf as *mut Kstat;
-    let mut statbuf = arceos_posix_api::ctypes::stat::default();
-
-    if unsafe {
-        arceos_posix_api::sys_fstat(fd, &mut statbuf as *mut arceos_posix_api::ctypes::stat)
-    } < 0
-    {
-        return -1;
+/// Get the status of the file referred to by `fd`.
+pub(crate) fn stat_fd(fd: i32) -> LinuxResult<arceos_posix_api::ctypes::stat> {
+    let mut status = arceos_posix_api::ctypes::stat::default();
+    let res = unsafe { arceos_posix_api::sys_fstat(fd, &mut status as *mut _) };
+    if res < 0 {
+        return Err(LinuxError::try_from(-res).unwrap());
+    }
+    if let Ok(path) = fd_path(fd) {
+        // Agree with `stat_path` and `getdents64`.
+        status.st_ino = path_inode(&path);
+        apply_file_attr(&path, &mut status);
     }
+    Ok(status)
+}
 
-    unsafe {
-        let kstat = Kstat::from(statbuf);
-        kstatbuf.write(kstat);
+/// Do not follow the symbolic link named by the last component of the path.
+pub(crate) const AT_SYMLINK_NOFOLLOW: u32 = 0x100;
+/// Operate on `dirfd` itself if the path is empty.
+const AT_EMPTY_PATH: u32 = 0x1000;
+
+/// Get the status of the file at `pathname` relative to `dirfd`.
+///
+/// `flags` may contain `AT_SYMLINK_NOFOLLOW` and `AT_EMPTY_PATH`.
+fn stat_at(
+    dirfd: i32,
+    pathname: *const u8,
+    flags: u32,
+) -> LinuxResult<arceos_posix_api::ctypes::stat> {
+    let path = arceos_posix_api::char_ptr_to_str(pathname as *const _)?;
+    if path.is_empty() {
+        if flags & AT_EMPTY_PATH == 0 {
+            return Err(LinuxError::ENOENT);
+        }
+        return stat_fd(dirfd);
     }
-    0
+    let follow = flags & AT_SYMLINK_NOFOLLOW == 0;
+    stat_path(&resolve_at(dirfd, pathname as _, follow)?)
+}
+
+pub(crate) fn sys_fstat(fd: i32, kstatbuf: *mut c_void) -> i32 {
+    syscall_body!(sys_fstat, {
+        let kstat = Kstat::from(stat_fd(fd)?);
+        unsafe { (kstatbuf as *mut Kstat).write(kstat) };
+        Ok(0)
+    })
+}
+
+/// Get the status of the file at `pathname` relative to `dirfd`.
+///
+/// The symbolic link itself is reported if `AT_SYMLINK_NOFOLLOW` is set.
+pub(crate) fn sys_fstatat(
+    dirfd: i32,
+    pathname: *const u8,
+    kstatbuf: *mut c_void,
+    flags: u32,
+) -> i32 {
+    syscall_body!(sys_fstatat, {
+        const AT_NO_AUTOMOUNT: u32 = 0x800;
+        if flags & !(AT_SYMLINK_NOFOLLOW | AT_NO_AUTOMOUNT | AT_EMPTY_PATH) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let kstat = Kstat::from(stat_at(dirfd, pathname, flags)?);
+        unsafe { (kstatbuf as *mut Kstat).write(kstat) };
+        Ok(0)
+    })
+}
+
+/// Check whether the calling process can access the file at `pathname`.
+///
+/// `mode` is either `F_OK` or a mask of `R_OK`, `W_OK` and `X_OK`, which are
+/// checked against the permission bits of the file. This also implements
+/// `faccessat2`, whose `flags` may contain `AT_EACCESS`, `AT_SYMLINK_NOFOLLOW`
+/// and `AT_EMPTY_PATH`.
+pub(crate) fn sys_faccessat(dirfd: i32, pathname: *const u8, mode: u32, flags: u32) -> i32 {
+    syscall_body!(sys_faccessat, {
+        const AT_EACCESS: u32 = 0x200;
+        const R_OK: u32 = 4;
+        const W_OK: u32 = 2;
+        const X_OK: u32 = 1;
+        if mode & !(R_OK | W_OK | X_OK) != 0
+            || flags & !(AT_EACCESS | AT_SYMLINK_NOFOLLOW | AT_EMPTY_PATH) != 0
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let status = stat_at(dirfd, pathname, flags)?;
+        // Every task runs as the single user owning every file, so the real and
+        // effective IDs are the same, `AT_EACCESS` makes no difference, and the
+        // owner bits apply.
+        let permitted = (status.st_mode >> 6) & 0o7;
+        if mode & !permitted != 0 {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(0)
+    })
+}
+
+/// Describe the filesystem holding the file at `path`.
+///
+/// The memory filesystems keep their files in kernel memory, so their size and
+/// free space are those of the frame allocator, and they have no limit on the
+/// number of files. `axfs` does not report the geometry or usage of the root
+/// filesystem, so only its type and the maximum length of its names are
+/// reported for it. The counts that aren't known are zero.
+fn path_statfs(path: &str) -> StatFs {
+    /// `EXT4_SUPER_MAGIC`
+    #[cfg(feature = "lwext4_rs")]
+    const ROOT_FS_MAGIC: i64 = 0xef53;
+    /// `MSDOS_SUPER_MAGIC`, which is also reported for FAT32
+    #[cfg(not(feature = "lwext4_rs"))]
+    const ROOT_FS_MAGIC: i64 = 0x4d44;
+    const BLOCK_SIZE: i64 = PAGE_SIZE_4K as i64;
+    const NAME_MAX: i64 = 255;
+
+    let statfs = StatFs {
+        f_type: ROOT_FS_MAGIC,
+        f_bsize: BLOCK_SIZE,
+        f_namelen: NAME_MAX,
+        f_frsize: BLOCK_SIZE,
+        ..Default::default()
+    };
+    let Some(f_type) = mounted_fs_magic(path) else {
+        return statfs;
+    };
+    let allocator = axalloc::global_allocator();
+    let free = allocator.available_pages() as u64;
+    StatFs {
+        f_type,
+        f_blocks: allocator.used_pages() as u64 + free,
+        f_bfree: free,
+        f_bavail: free,
+        ..statfs
+    }
+}
+
+/// Write `statfs` to the user buffer `buf`, checking that it is mapped first.
+fn write_statfs(buf: *mut StatFs, statfs: StatFs) -> LinuxResult {
+    check_user_range(
+        current().task_ext(),
+        (buf as usize).into(),
+        size_of::<StatFs>(),
+    )
+    .map_err(|_| LinuxError::EFAULT)?;
+    unsafe { buf.write(statfs) };
+    Ok(())
+}
+
+/// Get information about the filesystem holding the file at `path`.
+pub(crate) fn sys_statfs(path: *const u8, buf: *mut StatFs) -> i32 {
+    syscall_body!(sys_statfs, {
+        let path = resolve_at(AT_FDCWD as i32, path as _, true)?;
+        axfs::api::metadata(&path)?;
+        write_statfs(buf, path_statfs(&path))?;
+        Ok(0)
+    })
+}
+
+/// Get information about the filesystem holding the file referred to by `fd`.
+pub(crate) fn sys_fstatfs(fd: i32, buf: *mut StatFs) -> i32 {
+    syscall_body!(sys_fstatfs, {
+        arceos_posix_api::get_file_like(fd)?;
+        // Pipes, sockets and devices are not in any mounted filesystem, which
+        // is described as the root filesystem.
+        let path = fd_path(fd).unwrap_or_default();
+        write_statfs(buf, path_statfs(&path))?;
+        Ok(0)
+    })
 }
 
 #[repr(C)]
@@ -147,11 +306,180 @@ pub struct StatX {
     pub stx_dio_offset_align: u32,
 }
 
+/// Synthesize a stable inode number for the file at `path`.
+///
+/// The filesystems behind `axfs` do not expose inode numbers, so the FNV-1a hash of
+/// the canonical path is used instead. It is stable across calls and distinct for
+/// different files in practice, which is what `ls -i` and `find` rely on.
+pub(crate) fn path_inode(path: &str) -> u64 {
+    let path = axfs::api::canonicalize(path).unwrap_or_else(|_| path.into());
+    let hash = path.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3)
+    });
+    // Inode 0 marks a deleted entry for some programs.
+    hash.max(1)
+}
+
+/// Get the metadata of the file at `path`.
+///
+/// `axfs` reports a missing file when a component before the last one is not a
+/// directory, so the ancestors are checked to fail with `ENOTDIR` in that case.
+pub(crate) fn lookup(path: &str) -> LinuxResult<axfs::api::Metadata> {
+    axfs::api::metadata(path).map_err(|err| {
+        let not_dir = path.match_indices('/').skip(1).any(|(end, _)| {
+            axfs::api::metadata(&path[..end]).is_ok_and(|metadata| !metadata.is_dir())
+        });
+        if not_dir {
+            LinuxError::ENOTDIR
+        } else {
+            err.into()
+        }
+    })
+}
+
+/// Get the status of the file at `path`, which has already been resolved.
+///
+/// The result is in the same form as `arceos_posix_api::sys_fstat` produces, so it can be
+/// converted the same way. The metadata is taken from the directory entry cache
+/// if the directory of the file has been listed.
+pub(crate) fn stat_path(path: &str) -> LinuxResult<arceos_posix_api::ctypes::stat> {
+    let metadata = entry_metadata(path)?;
+    let ty = metadata.file_type as u8;
+    let perm = metadata.perm as u32;
+    let size = metadata.size;
+    let mut status = arceos_posix_api::ctypes::stat {
+        st_ino: path_inode(path),
+        st_nlink: 1,
+        st_mode: ((ty as u32) << 12) | perm,
+        st_uid: 1000,
+        st_gid: 1000,
+        st_size: size as _,
+        st_blocks: size.div_ceil(512) as _,
+        st_blksize: 512,
+        ..Default::default()
+    };
+    apply_file_attr(path, &mut status);
+    Ok(status)
+}
+
+bitflags::bitflags! {
+    /// Mask of the fields requested from and reported by `statx`.
+    ///
+    /// See <https://man7.org/linux/man-pages/man2/statx.2.html>
+    #[derive(Debug, Clone, Copy)]
+    pub struct StatXMask: u32 {
+        /// Want/got `stx_mode & S_IFMT`
+        const STATX_TYPE = 0x0001;
+        /// Want/got `stx_mode & ~S_IFMT`
+        const STATX_MODE = 0x0002;
+        /// Want/got `stx_nlink`
+        const STATX_NLINK = 0x0004;
+        /// Want/got `stx_uid`
+        const STATX_UID = 0x0008;
+        /// Want/got `stx_gid`
+        const STATX_GID = 0x0010;
+        /// Want/got `stx_atime`
+        const STATX_ATIME = 0x0020;
+        /// Want/got `stx_mtime`
+        const STATX_MTIME = 0x0040;
+        /// Want/got `stx_ctime`
+        const STATX_CTIME = 0x0080;
+        /// Want/got `stx_ino`
+        const STATX_INO = 0x0100;
+        /// Want/got `stx_size`
+        const STATX_SIZE = 0x0200;
+        /// Want/got `stx_blocks`
+        const STATX_BLOCKS = 0x0400;
+        /// Want/got `stx_btime`
+        const STATX_BTIME = 0x0800;
+    }
+}
+
+impl StatXMask {
+    /// The fields we are able to report.
+    ///
+    /// `STATX_BTIME` is not included since none of the backing filesystems
+    /// report the creation time of a file.
+    const SUPPORTED: Self = Self::STATX_TYPE
+        .union(Self::STATX_MODE)
+        .union(Self::STATX_NLINK)
+        .union(Self::STATX_UID)
+        .union(Self::STATX_GID)
+        .union(Self::STATX_ATIME)
+        .union(Self::STATX_MTIME)
+        .union(Self::STATX_CTIME)
+        .union(Self::STATX_INO)
+        .union(Self::STATX_SIZE)
+        .union(Self::STATX_BLOCKS);
+}
+
+/// Split a device number into its major and minor parts like `major()` and `minor()`.
+fn split_dev(dev: u64) -> (u32, u32) {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    (major as u32, minor as u32)
+}
+
+impl StatX {
+    /// Fill the fields selected by `mask` from a `stat` struct.
+    ///
+    /// Fields that are not requested are left zeroed, and `stx_mask` is set to
+    /// the fields that were actually filled.
+    fn fill_from_stat(&mut self, status: &arceos_posix_api::ctypes::stat, mask: StatXMask) {
+        const S_IFMT: u32 = 0o170000;
+
+        let mask = mask & StatXMask::SUPPORTED;
+        self.stx_mask = mask.bits();
+        self.stx_blksize = status.st_blksize as u32;
+        // None of the `STATX_ATTR_*` attributes are supported, so `stx_attributes`
+        // stays zero and no bit of it is meaningful.
+        self.stx_attributes_mask = 0;
+        (self.stx_dev_major, self.stx_dev_minor) = split_dev(status.st_dev);
+        (self.stx_rdev_major, self.stx_rdev_minor) = split_dev(status.st_rdev);
+        if mask.contains(StatXMask::STATX_TYPE) {
+            self.stx_mode |= (status.st_mode & S_IFMT) as u16;
+        }
+        if mask.contains(StatXMask::STATX_MODE) {
+            self.stx_mode |= (status.st_mode & !S_IFMT) as u16;
+        }
+        if mask.contains(StatXMask::STATX_NLINK) {
+            self.stx_nlink = status.st_nlink;
+        }
+        if mask.contains(StatXMask::STATX_UID) {
+            self.stx_uid = status.st_uid;
+        }
+        if mask.contains(StatXMask::STATX_GID) {
+            self.stx_gid = status.st_gid;
+        }
+        if mask.contains(StatXMask::STATX_ATIME) {
+            self.stx_atime.tv_sec = status.st_atime.tv_sec;
+            self.stx_atime.tv_nsec = status.st_atime.tv_nsec as u32;
+        }
+        if mask.contains(StatXMask::STATX_MTIME) {
+            self.stx_mtime.tv_sec = status.st_mtime.tv_sec;
+            self.stx_mtime.tv_nsec = status.st_mtime.tv_nsec as u32;
+        }
+        if mask.contains(StatXMask::STATX_CTIME) {
+            self.stx_ctime.tv_sec = status.st_ctime.tv_sec;
+            self.stx_ctime.tv_nsec = status.st_ctime.tv_nsec as u32;
+        }
+        if mask.contains(StatXMask::STATX_INO) {
+            self.stx_ino = status.st_ino;
+        }
+        if mask.contains(StatXMask::STATX_SIZE) {
+            self.stx_size = status.st_size as u64;
+        }
+        if mask.contains(StatXMask::STATX_BLOCKS) {
+            self.stx_blocks = status.st_blocks as u64;
+        }
+    }
+}
+
 pub(crate) fn sys_statx(
     dirfd: i32,
     pathname: *const u8,
     flags: u32,
-    _mask: u32,
+    mask: u32,
     statxbuf: *mut c_void,
 ) -> i32 {
     // `statx()` uses pathname, dirfd, and flags to identify the target
@@ -182,39 +510,10 @@ pub(crate) fn sys_statx(
     //        file descriptor dirfd.
 
     syscall_body!(sys_statx, {
-        let path = arceos_posix_api::char_ptr_to_str(pathname as *const _)?;
-
-        const AT_EMPTY_PATH: u32 = 0x1000;
-        if path.is_empty() {
-            if flags & AT_EMPTY_PATH == 0 {
-                return Err(LinuxError::EINVAL);
-            }
-            // Alloc a new space for stat struct
-            let mut status = arceos_posix_api::ctypes::stat::default();
-            let res = unsafe { arceos_posix_api::sys_fstat(dirfd, &mut status as *mut _) };
-            if res < 0 {
-                return Err(LinuxError::try_from(-res).unwrap());
-            }
-            let statx = unsafe { &mut *(statxbuf as *mut StatX) };
-            statx.stx_blksize = status.st_blksize as u32;
-            statx.stx_attributes = status.st_mode as u64;
-            statx.stx_nlink = status.st_nlink;
-            statx.stx_uid = status.st_uid;
-            statx.stx_gid = status.st_gid;
-            statx.stx_mode = status.st_mode as u16;
-            statx.stx_ino = status.st_ino;
-            statx.stx_size = status.st_size as u64;
-            statx.stx_blocks = status.st_blocks as u64;
-            statx.stx_attributes_mask = 0x7FF;
-            statx.stx_atime.tv_sec = status.st_atime.tv_sec;
-            statx.stx_atime.tv_nsec = status.st_atime.tv_nsec as u32;
-            statx.stx_ctime.tv_sec = status.st_ctime.tv_sec;
-            statx.stx_ctime.tv_nsec = status.st_ctime.tv_nsec as u32;
-            statx.stx_mtime.tv_sec = status.st_mtime.tv_sec;
-            statx.stx_mtime.tv_nsec = status.st_mtime.tv_nsec as u32;
-            Ok(0)
-        } else {
-            Err(LinuxError::ENOSYS)
-        }
+        let status = stat_at(dirfd, pathname, flags)?;
+        let mut statx = StatX::default();
+        statx.fill_from_stat(&status, StatXMask::from_bits_truncate(mask));
+        unsafe { (statxbuf as *mut StatX).write(statx) };
+        Ok(0)
     })
 }
@@ -0,0 +1,290 @@
+//! The console, which the stdio fds refer to as a terminal.
+//!
+//! Input from the console goes through a line discipline, which follows the
+//! settings in `termios` that matter the most: canonical mode, echoing, and
+//! mapping carriage returns to newlines.
+
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use arceos_posix_api::{self as api, FD_TABLE, FileLike};
+use axerrno::LinuxResult;
+use axio::PollState;
+use axsync::Mutex;
+use axtask::{TaskExtRef, current};
+
+/// Map carriage returns in input to newlines.
+const ICRNL: u32 = 0o400;
+/// Canonical mode, where input is read line by line and can be edited.
+const ICANON: u32 = 0o2;
+/// Echo input.
+const ECHO: u32 = 0o10;
+/// Make the erase character erase the previous character on the screen.
+const ECHOE: u32 = 0o20;
+/// Echo newlines even without `ECHO`.
+const ECHONL: u32 = 0o100;
+
+/// Index of the erase character in `c_cc`.
+const VERASE: usize = 2;
+/// Index of the kill character, which erases the line, in `c_cc`.
+const VKILL: usize = 3;
+/// Index of the end-of-file character in `c_cc`.
+const VEOF: usize = 4;
+
+/// Number of control characters in the kernel `termios` struct.
+const NCCS: usize = 19;
+
+/// Terminal attributes, the kernel version of `struct termios`.
+///
+/// See <https://man7.org/linux/man-pages/man3/termios.3.html>
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Termios {
+    /// Input modes
+    pub c_iflag: u32,
+    /// Output modes
+    pub c_oflag: u32,
+    /// Control modes
+    pub c_cflag: u32,
+    /// Local modes
+    pub c_lflag: u32,
+    /// Line discipline
+    pub c_line: u8,
+    /// Special characters
+    pub c_cc: [u8; NCCS],
+}
+
+impl Default for Termios {
+    fn default() -> Self {
+        // The same defaults as a freshly opened Linux console:
+        // ICRNL | IXON, OPOST | ONLCR, B38400 | CS8 | CREAD | HUPCL,
+        // ISIG | ICANON | ECHO | ECHOE | ECHOK | ECHOCTL | ECHOKE | IEXTEN.
+        let mut c_cc = [0; NCCS];
+        c_cc[..7].copy_from_slice(&[3, 28, 127, 21, 4, 0, 1]);
+        Self {
+            c_iflag: 0o2400,
+            c_oflag: 0o5,
+            c_cflag: 0o2277,
+            c_lflag: 0o105073,
+            c_line: 0,
+            c_cc,
+        }
+    }
+}
+
+/// Terminal window size.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct WinSize {
+    /// Rows, in characters
+    pub ws_row: u16,
+    /// Columns, in characters
+    pub ws_col: u16,
+    /// Horizontal size, in pixels (unused)
+    pub ws_xpixel: u16,
+    /// Vertical size, in pixels (unused)
+    pub ws_ypixel: u16,
+}
+
+impl Default for WinSize {
+    fn default() -> Self {
+        Self {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+/// All the stdio fds share the same console, so they share the same settings.
+static CONSOLE_TERMIOS: Mutex<Option<Termios>> = Mutex::new(None);
+static CONSOLE_WINSIZE: Mutex<Option<WinSize>> = Mutex::new(None);
+/// The foreground process group of the console, or 0 if it hasn't been set,
+/// in which case it is the process asking for it.
+static FOREGROUND_PGRP: AtomicI32 = AtomicI32::new(0);
+static LINE_DISCIPLINE: Mutex<LineDiscipline> = Mutex::new(LineDiscipline::new());
+
+pub(super) fn termios() -> Termios {
+    CONSOLE_TERMIOS.lock().unwrap_or_default()
+}
+
+/// Change the settings of the console, discarding the pending input if `flush` is set.
+pub(super) fn set_termios(termios: Termios, flush: bool) {
+    *CONSOLE_TERMIOS.lock() = Some(termios);
+    let mut line = LINE_DISCIPLINE.lock();
+    if flush {
+        *line = LineDiscipline::new();
+    } else if termios.c_lflag & ICANON == 0 {
+        // Whatever has been typed becomes readable at once.
+        line.ready = line.input.len();
+    }
+}
+
+pub(super) fn winsize() -> WinSize {
+    CONSOLE_WINSIZE.lock().unwrap_or_default()
+}
+
+pub(super) fn set_winsize(winsize: WinSize) {
+    *CONSOLE_WINSIZE.lock() = Some(winsize);
+}
+
+pub(super) fn foreground_pgrp() -> i32 {
+    match FOREGROUND_PGRP.load(Ordering::Acquire) {
+        0 => current().task_ext().proc_id as i32,
+        pgrp => pgrp,
+    }
+}
+
+pub(super) fn set_foreground_pgrp(pgrp: i32) {
+    FOREGROUND_PGRP.store(pgrp, Ordering::Release);
+}
+
+/// The input typed on the console that hasn't been read yet.
+struct LineDiscipline {
+    input: VecDeque<u8>,
+    /// How many bytes at the front of `input` can be read, which in canonical
+    /// mode are the complete lines.
+    ready: usize,
+    /// Whether the end-of-file character was typed at the start of a line.
+    eof: bool,
+}
+
+impl LineDiscipline {
+    const fn new() -> Self {
+        Self {
+            input: VecDeque::new(),
+            ready: 0,
+            eof: false,
+        }
+    }
+
+    /// Take in a byte typed on the console, adding what to echo to `echo`.
+    fn receive(&mut self, mut byte: u8, termios: &Termios, echo: &mut Vec<u8>) {
+        let echoing = termios.c_lflag & ECHO != 0;
+        if termios.c_iflag & ICRNL != 0 && byte == b'\r' {
+            byte = b'\n';
+        }
+        if termios.c_lflag & ICANON == 0 {
+            self.input.push_back(byte);
+            self.ready = self.input.len();
+            if echoing {
+                echo.push(byte);
+            }
+            return;
+        }
+
+        let editing = self.input.len() > self.ready;
+        let erase_echo = echoing && termios.c_lflag & ECHOE != 0;
+        if byte == termios.c_cc[VERASE] || byte == b'\x08' {
+            if editing {
+                self.input.pop_back();
+                if erase_echo {
+                    echo.extend_from_slice(b"\x08 \x08");
+                }
+            }
+        } else if byte == termios.c_cc[VKILL] {
+            for _ in self.ready..self.input.len() {
+                if erase_echo {
+                    echo.extend_from_slice(b"\x08 \x08");
+                }
+            }
+            self.input.truncate(self.ready);
+        } else if byte == termios.c_cc[VEOF] {
+            // It ends the line without being part of it, and marks the end of
+            // file on an empty line.
+            self.eof = !editing;
+            self.ready = self.input.len();
+        } else {
+            self.input.push_back(byte);
+            if byte == b'\n' {
+                self.ready = self.input.len();
+            }
+            if echoing || (byte == b'\n' && termios.c_lflag & ECHONL != 0) {
+                echo.push(byte);
+            }
+        }
+    }
+
+    /// Read what is readable into `buf`, or return `None` if nothing is.
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.ready == 0 {
+            // The end of file is reported once, by a read returning 0.
+            return core::mem::take(&mut self.eof).then_some(0);
+        }
+        let len = buf.len().min(self.ready);
+        for (dst, src) in buf.iter_mut().zip(self.input.drain(..len)) {
+            *dst = src;
+        }
+        self.ready -= len;
+        Some(len)
+    }
+}
+
+/// A stdio fd referring to the console, which wraps the file it was before.
+pub(crate) struct Tty {
+    inner: Arc<dyn FileLike>,
+}
+
+impl Tty {
+    /// The number of bytes typed on the console that can be read without
+    /// blocking, which in canonical mode are those of the complete lines.
+    pub(super) fn available(&self) -> usize {
+        LINE_DISCIPLINE.lock().ready
+    }
+}
+
+impl FileLike for Tty {
+    fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut raw = [0u8; 64];
+        loop {
+            if let Some(len) = LINE_DISCIPLINE.lock().read(buf) {
+                return Ok(len);
+            }
+            let len = self.inner.read(&mut raw)?;
+            let termios = termios();
+            let mut echo = Vec::new();
+            let mut line = LINE_DISCIPLINE.lock();
+            for &byte in &raw[..len] {
+                line.receive(byte, &termios, &mut echo);
+            }
+            drop(line);
+            axhal::console::write_bytes(&echo);
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
+        self.inner.write(buf)
+    }
+
+    fn stat(&self) -> LinuxResult<api::ctypes::stat> {
+        self.inner.stat()
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        let mut state = self.inner.poll()?;
+        state.readable |= LINE_DISCIPLINE.lock().ready > 0;
+        Ok(state)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.inner.set_nonblocking(nonblocking)
+    }
+}
+
+/// Make the stdio fds of the kernel, which the first process inherits, refer to the console as a terminal.
+pub(crate) fn init_tty() {
+    let mut table = FD_TABLE.write();
+    for fd in 0..3 {
+        if let Some(inner) = table.remove(fd) {
+            let _ = table.add_at(fd, Arc::new(Tty { inner }));
+        }
+    }
+}
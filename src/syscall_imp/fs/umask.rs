@@ -0,0 +1,19 @@
+use axtask::{TaskExtRef, current};
+
+use crate::syscall_body;
+
+/// Set the file mode creation mask of the calling process.
+///
+/// The mask is inherited by children created with `clone`, and is kept across
+/// `execve` like Linux does.
+///
+/// # Returns
+/// The previous value of the mask.
+pub(crate) fn sys_umask(mask: u32) -> u32 {
+    syscall_body!(sys_umask, {
+        let curr = current();
+        let old_mask = curr.task_ext().get_umask();
+        curr.task_ext().set_umask(mask & 0o777);
+        Ok(old_mask)
+    })
+}
@@ -0,0 +1,35 @@
+//! Helpers for working with the paths and trees of files.
+
+use alloc::{collections::btree_set::BTreeSet, format, string::String};
+use axerrno::LinuxResult;
+
+use super::{attr::remove_file_attr, ctl::unlink_file, dircache::invalidate_dir_of, stat::lookup};
+
+/// Get the names of the entries of the directory at `dir`.
+pub(super) fn entry_names(dir: &str) -> LinuxResult<BTreeSet<String>> {
+    Ok(axfs::api::read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.file_name())
+        .collect())
+}
+
+/// Remove `path` and everything beneath it.
+pub(super) fn remove_tree(path: &str) -> LinuxResult {
+    if lookup(path)?.is_dir() {
+        for name in entry_names(path)? {
+            remove_tree(&format!("{path}/{name}"))?;
+        }
+        axfs::api::remove_dir(path)?;
+        invalidate_dir_of(path);
+    } else {
+        unlink_file(path)?;
+    }
+    remove_file_attr(path);
+    Ok(())
+}
+
+/// Whether the canonical `path` is `dir` or beneath it.
+pub(super) fn is_beneath(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || dir == "/")
+}
@@ -1,18 +1,50 @@
+use axhal::paging::MappingFlags;
 use axtask::{TaskExtRef, current};
+use memory_addr::{VirtAddr, VirtAddrRange};
 
-use crate::syscall_body;
-
-const MAX_HEAP_SIZE: usize = 0x20000;
+use crate::{mm::unmap_pages, syscall_body};
 
+/// Set the program break, i.e. the end of the heap, to `addr`, and return the
+/// new program break.
+///
+/// The heap is the region from the initial program break, which is the end of
+/// the loaded image, to the program break. Its pages are allocated lazily and
+/// zero-filled by page faults. If `addr` is 0 or below the initial program
+/// break, or the heap can't grow that far because something else is mapped in
+/// the way, the program break is left unchanged and returned.
 pub fn sys_brk(addr: usize) -> isize {
     syscall_body!(sys_brk, {
         let current_task = current();
-        let mut return_val: isize = current_task.task_ext().get_heap_top() as isize;
-        let heap_bottom = current_task.task_ext().get_heap_bottom() as usize;
-        if addr != 0 && addr >= heap_bottom && addr <= heap_bottom + MAX_HEAP_SIZE {
-            current_task.task_ext().set_heap_top(addr as u64);
-            return_val = addr as isize;
+        let task_ext = current_task.task_ext();
+        let heap_bottom = task_ext.get_heap_bottom() as usize;
+        let heap_top = task_ext.get_heap_top() as usize;
+        if addr < heap_bottom {
+            return Ok(heap_top as isize);
+        }
+
+        // The pages of the heap end at the program break rounded up.
+        let mapped_end = VirtAddr::from(memory_addr::align_up_4k(heap_top));
+        let new_end = VirtAddr::from(memory_addr::align_up_4k(addr));
+        let mut aspace = task_ext.aspace.lock();
+        if new_end > mapped_end {
+            let limit = VirtAddrRange::new(aspace.base(), aspace.end());
+            let size = new_end - mapped_end;
+            if new_end > aspace.end()
+                || aspace.find_free_area(mapped_end, size, limit) != Some(mapped_end)
+            {
+                return Ok(heap_top as isize);
+            }
+            aspace.map_alloc(
+                mapped_end,
+                size,
+                MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER,
+                false,
+            )?;
+        } else if new_end < mapped_end {
+            unmap_pages(&mut aspace, new_end, mapped_end - new_end)?;
+            axhal::arch::flush_tlb(None);
         }
-        Ok(return_val)
+        task_ext.set_heap_top(addr as u64);
+        Ok(addr as isize)
     })
 }
@@ -1,10 +1,16 @@
-use alloc::vec;
 use axerrno::LinuxError;
 use axhal::paging::MappingFlags;
 use axtask::{TaskExtRef, current};
-use memory_addr::{VirtAddr, VirtAddrRange};
+use memory_addr::{PAGE_SIZE_4K, VirtAddr, VirtAddrRange};
 
-use crate::syscall_body;
+use crate::{
+    mm::{
+        FileMapping, discard_pages, mapping_flags, move_pages, protect_pages, sync_file_mappings,
+        unmap_file_mappings, unmap_pages,
+    },
+    syscall_body,
+    syscall_imp::fs::{O_ACCMODE, file_status_flags},
+};
 
 bitflags::bitflags! {
     /// permissions for sys_mmap
@@ -58,8 +64,20 @@ bitflags::bitflags! {
     }
 }
 
+/// Map `length` bytes of memory into the address space, returning the address of the mapping.
+///
+/// Anonymous mappings are zero-filled and file mappings are filled from `fd`
+/// starting at `offset`, in both cases page by page when first accessed. The
+/// changes to a `MAP_SHARED` file mapping are written back to the file by
+/// `msync` or when it is unmapped, while those to a `MAP_PRIVATE` one stay
+/// private. The pages of a `MAP_SHARED` mapping stay shared with the processes
+/// forked afterwards, while other processes mapping the same file only see the
+/// changes once they are written back.
+///
+/// `addr` is a hint for where to place the mapping, unless `MAP_FIXED` is given,
+/// in which case the mapping replaces whatever is mapped there.
 pub(crate) fn sys_mmap(
-    mut addr: *mut usize,
+    addr: *mut usize,
     length: usize,
     prot: i32,
     flags: i32,
@@ -69,83 +87,311 @@ pub(crate) fn sys_mmap(
     syscall_body!(sys_mmap, {
         let curr = current();
         let curr_ext = curr.task_ext();
-        let mut aspace = curr_ext.aspace.lock();
         let permission_flags = MmapProt::from_bits_truncate(prot);
-        // TODO: check illegal flags for mmap
-        // An example is the flags contained none of MAP_PRIVATE, MAP_SHARED, or MAP_SHARED_VALIDATE.
         let map_flags = MmapFlags::from_bits_truncate(flags);
-        let mut aligned_length = length;
-
-        if addr.is_null() {
-            aligned_length = memory_addr::align_up_4k(aligned_length);
-        } else {
-            let start = addr as usize;
-            let mut end = start + aligned_length;
-            addr = memory_addr::align_down_4k(start) as *mut usize;
-            end = memory_addr::align_up_4k(end);
-            aligned_length = end - start;
-        }
-
-        let start_addr = if map_flags.contains(MmapFlags::MAP_FIXED) {
-            VirtAddr::from(addr as usize)
-        } else {
-            aspace
-                .find_free_area(
-                    VirtAddr::from(addr as usize),
-                    aligned_length,
-                    VirtAddrRange::new(aspace.base(), aspace.end()),
-                )
-                .or(aspace.find_free_area(
-                    aspace.base(),
-                    aligned_length,
-                    VirtAddrRange::new(aspace.base(), aspace.end()),
-                ))
-                .ok_or(LinuxError::ENOMEM)?
+        // Exactly one of them must be given, where both stand for `MAP_SHARED_VALIDATE`.
+        let shared = match flags & 0b11 {
+            1 | 3 => true,
+            2 => false,
+            _ => return Err(LinuxError::EINVAL),
         };
+        if length == 0 || offset < 0 || offset as usize % PAGE_SIZE_4K != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let aligned_length = memory_addr::align_up_4k(length);
 
-        let populate = if fd == -1 {
-            false
+        // A shared anonymous mapping can always be made writable.
+        let mut file_writable = true;
+        let file = if map_flags.contains(MmapFlags::MAP_ANONYMOUS) {
+            None
         } else {
-            !map_flags.contains(MmapFlags::MAP_ANONYMOUS)
-        };
-
-        aspace.map_alloc(
-            start_addr,
-            aligned_length,
-            permission_flags.into(),
-            populate,
-        )?;
-
-        if populate {
             let file = arceos_posix_api::get_file_like(fd)?;
-            let file_size = file.stat()?.st_size as usize;
+            let access = file_status_flags(&file) & O_ACCMODE;
             let file = file
                 .into_any()
                 .downcast::<arceos_posix_api::File>()
-                .map_err(|_| LinuxError::EBADF)?;
-            let file = file.inner().lock();
-            if offset < 0 || offset as usize >= file_size {
+                .map_err(|_| LinuxError::ENODEV)?;
+            if access == arceos_posix_api::ctypes::O_WRONLY
+                || (shared
+                    && permission_flags.contains(MmapProt::PROT_WRITE)
+                    && access != arceos_posix_api::ctypes::O_RDWR)
+            {
+                return Err(LinuxError::EACCES);
+            }
+            file_writable = access == arceos_posix_api::ctypes::O_RDWR;
+            Some(file)
+        };
+
+        let mut aspace = curr_ext.aspace.lock();
+        let start_addr = if map_flags.contains(MmapFlags::MAP_FIXED) {
+            if addr as usize % PAGE_SIZE_4K != 0 {
                 return Err(LinuxError::EINVAL);
             }
-            let offset = offset as usize;
-            let length = core::cmp::min(length, file_size - offset);
-            let mut buf = vec![0u8; length];
-            file.read_at(offset as u64, &mut buf)?;
-            aspace.write(start_addr, &buf)?;
+            let start_addr = VirtAddr::from(addr as usize);
+            unmap_file_mappings(
+                &mut curr_ext.file_mappings.lock(),
+                &aspace,
+                start_addr,
+                start_addr + aligned_length,
+            )?;
+            unmap_pages(&mut aspace, start_addr, aligned_length)?;
+            start_addr
+        } else {
+            let hint = VirtAddr::from(memory_addr::align_down_4k(addr as usize));
+            let limit = VirtAddrRange::new(aspace.base(), aspace.end());
+            aspace
+                .find_free_area(hint, aligned_length, limit)
+                .or(aspace.find_free_area(aspace.base(), aligned_length, limit))
+                .ok_or(LinuxError::ENOMEM)?
+        };
+
+        aspace.map_alloc(start_addr, aligned_length, permission_flags.into(), false)?;
+        if file.is_some() || shared {
+            curr_ext.file_mappings.lock().push(FileMapping {
+                start: start_addr,
+                end: start_addr + aligned_length,
+                file,
+                offset: offset as u64,
+                shared,
+                file_writable,
+                write_back: shared && permission_flags.contains(MmapProt::PROT_WRITE),
+            });
         }
         Ok(start_addr.as_usize())
     })
 }
 
+/// Unmap the pages in `[addr, addr + length)`, where `length` is rounded up to
+/// a multiple of the page size.
+///
+/// The range may cover parts of mappings, which are split, and the frames
+/// backing the pages are freed. It's not an error if nothing is mapped there.
 pub(crate) fn sys_munmap(addr: *mut usize, mut length: usize) -> i32 {
     syscall_body!(sys_munmap, {
+        if addr as usize % PAGE_SIZE_4K != 0 || length == 0 {
+            return Err(LinuxError::EINVAL);
+        }
         let curr = current();
         let curr_ext = curr.task_ext();
         let mut aspace = curr_ext.aspace.lock();
         length = memory_addr::align_up_4k(length);
         let start_addr = VirtAddr::from(addr as usize);
-        aspace.unmap(start_addr, length)?;
+        unmap_file_mappings(
+            &mut curr_ext.file_mappings.lock(),
+            &aspace,
+            start_addr,
+            start_addr + length,
+        )?;
+        unmap_pages(&mut aspace, start_addr, length)?;
         axhal::arch::flush_tlb(None);
         Ok(0)
     })
 }
+
+/// Change the protection of the pages in `[addr, addr + length)` to `prot`.
+///
+/// Every page in the range must be mapped. Making a shared mapping writable
+/// requires its file to be open for writing, like `mmap` does.
+pub(crate) fn sys_mprotect(addr: *mut usize, length: usize, prot: i32) -> i32 {
+    syscall_body!(sys_mprotect, {
+        if addr as usize % PAGE_SIZE_4K != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let permission_flags = MmapProt::from_bits(prot).ok_or(LinuxError::EINVAL)?;
+        let length = memory_addr::align_up_4k(length);
+        if length == 0 {
+            return Ok(0);
+        }
+        let curr = current();
+        let curr_ext = curr.task_ext();
+        let mut aspace = curr_ext.aspace.lock();
+        let start_addr = VirtAddr::from(addr as usize);
+        let flags = permission_flags.into();
+        protect_pages(
+            &mut curr_ext.file_mappings.lock(),
+            &mut aspace,
+            start_addr,
+            start_addr + length,
+            flags,
+        )?;
+        axhal::arch::flush_tlb(None);
+        Ok(0)
+    })
+}
+
+/// No special treatment.
+const MADV_NORMAL: i32 = 0;
+/// Expect page references in random order.
+const MADV_RANDOM: i32 = 1;
+/// Expect page references in sequential order.
+const MADV_SEQUENTIAL: i32 = 2;
+/// Expect access in the near future.
+const MADV_WILLNEED: i32 = 3;
+/// Don't expect access in the near future, so the pages can be freed.
+const MADV_DONTNEED: i32 = 4;
+/// The pages can be freed when memory is needed.
+const MADV_FREE: i32 = 8;
+
+/// Give advice about the use of the pages in `[addr, addr + length)`.
+///
+/// `MADV_DONTNEED` and `MADV_FREE` free the frames backing the pages, so that
+/// anonymous pages read zeros and file mappings read the file again when they
+/// are next accessed. The other advice is only a hint and is ignored.
+pub(crate) fn sys_madvise(addr: *mut usize, length: usize, advice: i32) -> i32 {
+    syscall_body!(sys_madvise, {
+        if addr as usize % PAGE_SIZE_4K != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        match advice {
+            MADV_NORMAL | MADV_RANDOM | MADV_SEQUENTIAL | MADV_WILLNEED => Ok(0),
+            MADV_DONTNEED | MADV_FREE => {
+                let curr = current();
+                let curr_ext = curr.task_ext();
+                let mut aspace = curr_ext.aspace.lock();
+                let start_addr = VirtAddr::from(addr as usize);
+                discard_pages(
+                    &curr_ext.file_mappings.lock(),
+                    &mut aspace,
+                    start_addr,
+                    start_addr + memory_addr::align_up_4k(length),
+                )?;
+                axhal::arch::flush_tlb(None);
+                Ok(0)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
+
+/// Return once the writeback has been started.
+const MS_ASYNC: i32 = 1;
+/// Invalidate the other mappings of the same file.
+const MS_INVALIDATE: i32 = 2;
+/// Return once the writeback has completed.
+const MS_SYNC: i32 = 4;
+
+/// Write the changes to the shared file mappings in `[addr, addr + length)`
+/// back to their files.
+///
+/// Every page in the range must be mapped. The writeback is always done before
+/// returning, for `MS_ASYNC` as well as `MS_SYNC`. Pages can't be locked and
+/// are not shared with other processes, so `MS_INVALIDATE` has nothing more to
+/// do and never fails with `EBUSY`.
+pub(crate) fn sys_msync(addr: *mut usize, length: usize, flags: i32) -> i32 {
+    syscall_body!(sys_msync, {
+        if addr as usize % PAGE_SIZE_4K != 0
+            || flags & !(MS_ASYNC | MS_INVALIDATE | MS_SYNC) != 0
+            || flags & (MS_ASYNC | MS_SYNC) == MS_ASYNC | MS_SYNC
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let length = memory_addr::align_up_4k(length);
+        if length == 0 {
+            return Ok(0);
+        }
+        let curr = current();
+        let curr_ext = curr.task_ext();
+        let start_addr = VirtAddr::from(addr as usize);
+        let aspace = curr_ext.aspace.lock();
+        sync_file_mappings(
+            &curr_ext.file_mappings.lock(),
+            &aspace,
+            start_addr,
+            start_addr + length,
+        )?;
+        Ok(0)
+    })
+}
+
+/// Let the mapping move to another address if it can't be resized in place.
+const MREMAP_MAYMOVE: u32 = 1;
+/// Move the mapping to `new_addr`, replacing whatever is mapped there.
+const MREMAP_FIXED: u32 = 2;
+
+/// Resize the mapping at `old_addr` from `old_size` bytes to `new_size` bytes,
+/// possibly moving it, and return its new address.
+///
+/// A mapping grows in place if the pages after it are free. Otherwise it is
+/// moved if `MREMAP_MAYMOVE` is given, keeping the contents that fit. Growing
+/// a file mapping maps more of the file.
+pub(crate) fn sys_mremap(
+    old_addr: *mut usize,
+    old_size: usize,
+    new_size: usize,
+    flags: u32,
+    new_addr: *mut usize,
+) -> usize {
+    syscall_body!(sys_mremap, {
+        if old_addr as usize % PAGE_SIZE_4K != 0
+            || flags & !(MREMAP_MAYMOVE | MREMAP_FIXED) != 0
+            || (flags & MREMAP_FIXED != 0 && flags & MREMAP_MAYMOVE == 0)
+        {
+            return Err(LinuxError::EINVAL);
+        }
+        let old_size = memory_addr::align_up_4k(old_size);
+        let new_size = memory_addr::align_up_4k(new_size);
+        // Duplicating a shared mapping with an `old_size` of 0 isn't supported.
+        if old_size == 0 || new_size == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let curr_ext = curr.task_ext();
+        let mut aspace = curr_ext.aspace.lock();
+        let mut mappings = curr_ext.file_mappings.lock();
+        let old_start = VirtAddr::from(old_addr as usize);
+        let old_end = old_start + old_size;
+        let flags_of_mapping =
+            mapping_flags(&mappings, &mut aspace, old_start)?.ok_or(LinuxError::EFAULT)?;
+
+        if new_size <= old_size && flags & MREMAP_FIXED == 0 {
+            unmap_file_mappings(&mut mappings, &aspace, old_start + new_size, old_end)?;
+            unmap_pages(&mut aspace, old_start + new_size, old_size - new_size)?;
+            axhal::arch::flush_tlb(None);
+            return Ok(old_start.as_usize());
+        }
+
+        let limit = VirtAddrRange::new(aspace.base(), aspace.end());
+        if flags & MREMAP_FIXED == 0
+            && aspace.find_free_area(old_end, new_size - old_size, limit) == Some(old_end)
+        {
+            aspace.map_alloc(old_end, new_size - old_size, flags_of_mapping, false)?;
+            if let Some(mapping) = mappings.iter_mut().find(|mapping| mapping.end == old_end) {
+                mapping.end = old_start + new_size;
+            }
+            return Ok(old_start.as_usize());
+        }
+        if flags & MREMAP_MAYMOVE == 0 {
+            return Err(LinuxError::ENOMEM);
+        }
+
+        let new_start = if flags & MREMAP_FIXED != 0 {
+            let new_start = VirtAddr::from(new_addr as usize);
+            if new_addr as usize % PAGE_SIZE_4K != 0
+                || (new_start < old_end && old_start < new_start + new_size)
+            {
+                return Err(LinuxError::EINVAL);
+            }
+            unmap_file_mappings(&mut mappings, &aspace, new_start, new_start + new_size)?;
+            unmap_pages(&mut aspace, new_start, new_size)?;
+            new_start
+        } else {
+            aspace
+                .find_free_area(aspace.base(), new_size, limit)
+                .ok_or(LinuxError::ENOMEM)?
+        };
+        if new_size < old_size {
+            unmap_file_mappings(&mut mappings, &aspace, old_start + new_size, old_end)?;
+        }
+        aspace.map_alloc(new_start, new_size, flags_of_mapping, false)?;
+        move_pages(
+            &mut mappings,
+            &mut aspace,
+            old_start,
+            new_start,
+            old_size.min(new_size),
+        )?;
+        unmap_pages(&mut aspace, old_start, old_size)?;
+        axhal::arch::flush_tlb(None);
+        Ok(new_start.as_usize())
+    })
+}
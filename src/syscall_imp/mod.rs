@@ -16,6 +16,12 @@ use self::mm::*;
 use self::task::*;
 use self::utils::*;
 
+pub(crate) use self::fs::{
+    cached_read_at, cached_read_file, fill_random, init_devices, init_mounts, init_tty,
+    invalidate_cached, resolve_symlinks,
+};
+pub(crate) use self::task::{exit_if_group_exiting, exit_thread};
+
 /// Macro to generate syscall body
 ///
 /// It will receive a function which return Result<_, LinuxError> and convert it to
@@ -42,9 +48,22 @@ macro_rules! syscall_body {
 fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
     info!("Syscall {:?}", Sysno::from(syscall_num as u32));
     time_stat_from_user_to_kernel();
+    exit_if_group_exiting();
     let ans = match Sysno::from(syscall_num as u32) {
         Sysno::read => sys_read(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         Sysno::write => sys_write(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::pread64 => sys_pread64(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::pwrite64 => sys_pwrite64(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
         Sysno::mmap => sys_mmap(
             tf.arg0() as _,
             tf.arg1() as _,
@@ -54,16 +73,23 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg5() as _,
         ) as _,
         Sysno::ioctl => sys_ioctl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::readv => sys_readv(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         Sysno::writev => sys_writev(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         Sysno::sched_yield => sys_sched_yield() as isize,
         Sysno::nanosleep => sys_nanosleep(tf.arg0() as _, tf.arg1() as _) as _,
         Sysno::getpid => sys_getpid() as isize,
         Sysno::getppid => sys_getppid() as isize,
+        Sysno::gettid => sys_gettid() as isize,
+        Sysno::getpgid => sys_getpgid(tf.arg0() as _) as isize,
+        Sysno::setpgid => sys_setpgid(tf.arg0() as _, tf.arg1() as _) as isize,
         Sysno::exit => sys_exit(tf.arg0() as _),
         Sysno::gettimeofday => sys_get_time_of_day(tf.arg0() as _) as _,
         Sysno::getcwd => sys_getcwd(tf.arg0() as _, tf.arg1() as _) as _,
         Sysno::dup => sys_dup(tf.arg0() as _) as _,
-        Sysno::dup3 => sys_dup3(tf.arg0() as _, tf.arg1() as _) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::dup2 => sys_dup2(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::fcntl => sys_fcntl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::dup3 => sys_dup3(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
         Sysno::clone => sys_clone(
             tf.arg0() as _,
             tf.arg1() as _,
@@ -71,11 +97,46 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg3() as _,
             tf.arg4() as _,
         ) as _,
-        Sysno::wait4 => sys_wait4(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
-        Sysno::pipe2 => sys_pipe2(tf.arg0() as _) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::fork => sys_clone(crate::signal::SIGCHLD as _, 0, 0, 0, 0) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::vfork => sys_clone(
+            (crate::ctypes::CloneFlags::CLONE_VM | crate::ctypes::CloneFlags::CLONE_VFORK).bits()
+                as usize
+                | crate::signal::SIGCHLD as usize,
+            0,
+            0,
+            0,
+            0,
+        ) as _,
+        Sysno::wait4 => sys_wait4(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ) as _,
+        Sysno::pipe2 => sys_pipe2(tf.arg0() as _, tf.arg1() as _) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::pipe => sys_pipe2(tf.arg0() as _, 0) as _,
         Sysno::close => sys_close(tf.arg0() as _) as _,
+        Sysno::close_range => sys_close_range(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
         Sysno::chdir => sys_chdir(tf.arg0() as _) as _,
+        Sysno::fchdir => sys_fchdir(tf.arg0() as _) as _,
+        Sysno::umask => sys_umask(tf.arg0() as _) as _,
         Sysno::mkdirat => sys_mkdirat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::mknodat => sys_mknodat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::mknod => sys_mknodat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+        ) as _,
         Sysno::execve => sys_execve(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
         Sysno::openat => sys_openat(
             tf.arg0() as _,
@@ -83,6 +144,94 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg2() as _,
             tf.arg3() as _,
         ) as _,
+        Sysno::lseek => sys_lseek(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::truncate => sys_truncate(tf.arg0() as _, tf.arg1() as _),
+        Sysno::ftruncate => sys_ftruncate(tf.arg0() as _, tf.arg1() as _),
+        Sysno::epoll_create1 => sys_epoll_create1(tf.arg0() as _) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::epoll_create => sys_epoll_create(tf.arg0() as _) as _,
+        Sysno::epoll_ctl => sys_epoll_ctl(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ) as _,
+        Sysno::epoll_pwait => sys_epoll_pwait(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::epoll_wait => sys_epoll_wait(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ) as _,
+        Sysno::eventfd2 => sys_eventfd2(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::memfd_create => sys_memfd_create(tf.arg0() as _, tf.arg1() as _) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::eventfd => sys_eventfd(tf.arg0() as _) as _,
+        Sysno::mount => sys_mount(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::umount2 => sys_umount2(tf.arg0() as _, tf.arg1() as _),
+        Sysno::flock => sys_flock(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::ppoll => sys_ppoll(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::poll => sys_poll(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        Sysno::pselect6 => sys_pselect6(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::select => sys_select(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::sendfile => sys_sendfile(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::copy_file_range => sys_copy_file_range(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ),
+        Sysno::fallocate => sys_fallocate(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::sync => sys_sync(),
+        Sysno::fsync => sys_fsync(tf.arg0() as _),
+        Sysno::fdatasync => sys_fdatasync(tf.arg0() as _),
         Sysno::getdents64 => sys_getdents64(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         Sysno::linkat => sys_linkat(
             tf.arg0() as _,
@@ -92,8 +241,125 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg4() as _,
         ) as _,
         Sysno::unlinkat => sys_unlinkat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::rename => sys_renameat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg1() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::renameat => sys_renameat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::renameat2 => sys_renameat2(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::symlinkat => sys_symlinkat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::symlink => sys_symlinkat(
+            tf.arg0() as _,
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg1() as _,
+        ),
+        Sysno::readlinkat => sys_readlinkat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::readlink => sys_readlinkat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+        ),
         Sysno::uname => sys_uname(tf.arg0() as _) as _,
+        Sysno::getrandom => sys_getrandom(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
         Sysno::fstat => sys_fstat(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::newfstatat => sys_fstatat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::stat => sys_fstatat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            0,
+        ) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::lstat => sys_fstatat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            AT_SYMLINK_NOFOLLOW,
+        ) as _,
+        Sysno::fchmodat => sys_fchmodat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, 0),
+        Sysno::fchmod => sys_fchmod(tf.arg0() as _, tf.arg1() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::chmod => sys_fchmodat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            0,
+        ),
+        Sysno::fchownat => sys_fchownat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ),
+        Sysno::fchown => sys_fchown(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::chown => sys_fchownat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            0,
+        ),
+        #[cfg(target_arch = "x86_64")]
+        Sysno::lchown => sys_fchownat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            AT_SYMLINK_NOFOLLOW,
+        ),
+        Sysno::utimensat => sys_utimensat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::faccessat => sys_faccessat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, 0) as _,
+        Sysno::faccessat2 => sys_faccessat(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ) as _,
+        #[cfg(target_arch = "x86_64")]
+        Sysno::access => sys_faccessat(
+            arceos_posix_api::AT_FDCWD as _,
+            tf.arg0() as _,
+            tf.arg1() as _,
+            0,
+        ) as _,
+        Sysno::statfs => sys_statfs(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::fstatfs => sys_fstatfs(tf.arg0() as _, tf.arg1() as _) as _,
         Sysno::statx => sys_statx(
             tf.arg0() as _,
             tf.arg1() as _,
@@ -102,18 +368,55 @@ fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
             tf.arg4() as _,
         ) as _,
         Sysno::munmap => sys_munmap(tf.arg0() as _, tf.arg1() as _) as _,
+        Sysno::mprotect => sys_mprotect(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::madvise => sys_madvise(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::msync => sys_msync(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
+        Sysno::mremap => sys_mremap(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+        ) as _,
         Sysno::times => sys_times(tf.arg0() as _) as _,
         Sysno::brk => sys_brk(tf.arg0() as _) as _,
         #[cfg(target_arch = "x86_64")]
         Sysno::arch_prctl => sys_arch_prctl(tf.arg0() as _, tf.arg1() as _),
         Sysno::set_tid_address => sys_set_tid_address(tf.arg0() as _),
+        Sysno::futex => sys_futex(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+            tf.arg4() as _,
+            tf.arg5() as _,
+        ) as _,
         Sysno::clock_gettime => sys_clock_gettime(tf.arg0() as _, tf.arg1() as _) as _,
         Sysno::exit_group => sys_exit_group(tf.arg0() as _),
+        Sysno::rt_sigaction => sys_rt_sigaction(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::rt_sigprocmask => sys_rt_sigprocmask(
+            tf.arg0() as _,
+            tf.arg1() as _,
+            tf.arg2() as _,
+            tf.arg3() as _,
+        ),
+        Sysno::rt_sigreturn => sys_rt_sigreturn(),
+        Sysno::kill => sys_kill(tf.arg0() as _, tf.arg1() as _),
+        Sysno::tkill => sys_tkill(tf.arg0() as _, tf.arg1() as _),
+        Sysno::tgkill => sys_tgkill(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
         _ => {
             warn!("Unimplemented syscall: {}", syscall_num);
-            axtask::exit(LinuxError::ENOSYS as _)
+            exit_thread(LinuxError::ENOSYS as _)
         }
     };
+    exit_if_group_exiting();
+    // A signal with a handler makes the syscall return into the handler.
+    let ans = crate::signal::handle_signals(ans);
     time_stat_from_kernel_to_user();
     info!("syscall return: {}", ans);
     ans
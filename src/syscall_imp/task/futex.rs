@@ -0,0 +1,236 @@
+//! Fast user-space locking.
+//!
+//! A futex is a 32-bit word in user memory that threads sleep on until another
+//! thread wakes them. A private futex, which is only shared by the threads of a
+//! process, is identified by the address space and its virtual address, and a
+//! shared one by its physical address, so that processes mapping it at different
+//! addresses meet at the same futex.
+
+use core::{
+    ffi::c_int,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+use alloc::{collections::btree_map::BTreeMap, collections::vec_deque::VecDeque, sync::Arc};
+use arceos_posix_api::ctypes::timespec;
+use axerrno::{LinuxError, LinuxResult};
+use axhal::mem::phys_to_virt;
+use axsync::Mutex;
+use axtask::{TaskExtRef, WaitQueue, current};
+use memory_addr::PhysAddr;
+
+use crate::{
+    mm::{user_paddr, write_user_bytes},
+    signal::wait_interruptible,
+    syscall_body,
+    syscall_imp::fs::user_timeout,
+};
+
+const FUTEX_WAIT: c_int = 0;
+const FUTEX_WAKE: c_int = 1;
+const FUTEX_REQUEUE: c_int = 3;
+const FUTEX_CMP_REQUEUE: c_int = 4;
+/// The futex is only shared by the threads of a process.
+const FUTEX_PRIVATE_FLAG: c_int = 128;
+/// The timeout is measured by the realtime clock rather than the monotonic one.
+const FUTEX_CLOCK_REALTIME: c_int = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum FutexKey {
+    /// The address space and the virtual address of a private futex.
+    Private(usize, usize),
+    /// The physical address of a shared futex.
+    Shared(usize),
+}
+
+/// A thread waiting on a futex.
+struct Waiter {
+    /// Set when the waiter is woken, which tells a wakeup from a spurious one.
+    woken: AtomicBool,
+    wait_queue: WaitQueue,
+}
+
+/// The waiters of each futex that has any, in the order they started waiting.
+static FUTEXES: Mutex<BTreeMap<FutexKey, VecDeque<Arc<Waiter>>>> = Mutex::new(BTreeMap::new());
+
+/// Find the futex at `uaddr` and the physical address of its word.
+fn futex_key(uaddr: usize, private: bool) -> LinuxResult<(FutexKey, PhysAddr)> {
+    if uaddr % size_of::<u32>() != 0 {
+        return Err(LinuxError::EINVAL);
+    }
+    let curr = current();
+    let task_ext = curr.task_ext();
+    let mut aspace = task_ext.aspace.lock();
+    let paddr = user_paddr(&task_ext.file_mappings.lock(), &mut aspace, uaddr.into())
+        .map_err(|_| LinuxError::EFAULT)?;
+    let key = if private {
+        FutexKey::Private(Arc::as_ptr(&task_ext.aspace) as usize, uaddr)
+    } else {
+        FutexKey::Shared(paddr.as_usize())
+    };
+    Ok((key, paddr))
+}
+
+/// Read the word of a futex.
+fn futex_value(paddr: PhysAddr) -> u32 {
+    let word = unsafe { &*(phys_to_virt(paddr).as_ptr() as *const AtomicU32) };
+    word.load(Ordering::SeqCst)
+}
+
+/// Sleep on the futex at `uaddr` if its word is still `val`, until it is woken,
+/// `timeout` expires or a signal interrupts it.
+fn futex_wait(uaddr: usize, private: bool, val: u32, timeout: *const timespec) -> LinuxResult {
+    let timeout = user_timeout(timeout)?;
+    let (key, paddr) = futex_key(uaddr, private)?;
+    let waiter = Arc::new(Waiter {
+        woken: AtomicBool::new(false),
+        wait_queue: WaitQueue::new(),
+    });
+    {
+        // The word is checked and the waiter queued at once, so a wakeup after
+        // the word has been changed can't be missed.
+        let mut futexes = FUTEXES.lock();
+        if futex_value(paddr) != val {
+            return Err(LinuxError::EAGAIN);
+        }
+        futexes.entry(key).or_default().push_back(waiter.clone());
+    }
+
+    let woken = || waiter.woken.load(Ordering::Acquire);
+    let err = match wait_interruptible(&waiter.wait_queue, timeout, woken) {
+        Ok(true) => return Ok(()),
+        Ok(false) => LinuxError::ETIMEDOUT,
+        Err(err) => err,
+    };
+    // Timed out or interrupted, unless a wakeup came in the meantime.
+    let mut futexes = FUTEXES.lock();
+    if woken() {
+        return Ok(());
+    }
+    remove_waiter(&mut futexes, &waiter);
+    Err(err)
+}
+
+/// Remove `waiter` from the futex it is waiting on.
+fn remove_waiter(futexes: &mut BTreeMap<FutexKey, VecDeque<Arc<Waiter>>>, waiter: &Arc<Waiter>) {
+    futexes.retain(|_, waiters| {
+        waiters.retain(|other| !Arc::ptr_eq(other, waiter));
+        !waiters.is_empty()
+    });
+}
+
+/// Wake up to `count` of the waiters of `key`, and return how many are woken.
+fn wake_waiters(
+    futexes: &mut BTreeMap<FutexKey, VecDeque<Arc<Waiter>>>,
+    key: FutexKey,
+    count: usize,
+) -> usize {
+    let Some(waiters) = futexes.get_mut(&key) else {
+        return 0;
+    };
+    let mut woken = 0;
+    while woken < count {
+        let Some(waiter) = waiters.pop_front() else {
+            break;
+        };
+        waiter.woken.store(true, Ordering::Release);
+        waiter.wait_queue.notify_one(false);
+        woken += 1;
+    }
+    if waiters.is_empty() {
+        futexes.remove(&key);
+    }
+    woken
+}
+
+/// Wake up to `count` of the threads waiting on the futex at `uaddr`.
+fn futex_wake(uaddr: usize, private: bool, count: usize) -> LinuxResult<usize> {
+    let (key, _) = futex_key(uaddr, private)?;
+    Ok(wake_waiters(&mut FUTEXES.lock(), key, count))
+}
+
+/// Clear the word at `clear_child_tid` of the current thread, which is exiting,
+/// and wake a thread waiting on it, like one joining the exiting thread.
+///
+/// The waiter is woken whether it waits on the futex as a private one or not.
+/// Nothing is done if the word can't be written.
+pub(crate) fn clear_child_tid(clear_child_tid: usize) {
+    let curr = current();
+    if write_user_bytes(curr.task_ext(), clear_child_tid.into(), &0u32.to_ne_bytes()).is_err() {
+        return;
+    }
+    for private in [true, false] {
+        if let Err(err) = futex_wake(clear_child_tid, private, 1) {
+            warn!("Failed to wake the futex at {clear_child_tid:#x}: {err:?}");
+            return;
+        }
+    }
+}
+
+/// Wake up to `count` of the threads waiting on the futex at `uaddr`, and move
+/// up to `requeue` of the rest to wait on the futex at `uaddr2` instead.
+///
+/// If `expected` is given, nothing is done unless the word at `uaddr` is still
+/// `expected`. Returns how many waiters have been woken and moved.
+fn futex_requeue(
+    uaddr: usize,
+    uaddr2: usize,
+    private: bool,
+    count: usize,
+    requeue: usize,
+    expected: Option<u32>,
+) -> LinuxResult<usize> {
+    let (key, paddr) = futex_key(uaddr, private)?;
+    let (key2, _) = futex_key(uaddr2, private)?;
+    let mut futexes = FUTEXES.lock();
+    if expected.is_some_and(|expected| futex_value(paddr) != expected) {
+        return Err(LinuxError::EAGAIN);
+    }
+    let woken = wake_waiters(&mut futexes, key, count);
+    if key == key2 {
+        return Ok(woken);
+    }
+    let Some(waiters) = futexes.get_mut(&key) else {
+        return Ok(woken);
+    };
+    let moved: VecDeque<_> = waiters.drain(..requeue.min(waiters.len())).collect();
+    if waiters.is_empty() {
+        futexes.remove(&key);
+    }
+    let count = moved.len();
+    if count > 0 {
+        futexes.entry(key2).or_default().extend(moved);
+    }
+    Ok(woken + count)
+}
+
+/// Wait on or wake the futex at `uaddr`, as `op` tells.
+///
+/// `FUTEX_WAIT` sleeps until the futex is woken if its word is `val`, for at
+/// most the relative `timeout` if it isn't null, and `FUTEX_WAKE` wakes up to
+/// `val` waiters and returns how many have been woken. `FUTEX_REQUEUE` wakes
+/// `val` waiters as well, and moves up to `timeout`, which is taken as a count,
+/// of the others to the futex at `uaddr2`. `FUTEX_CMP_REQUEUE` does the same
+/// only if the word at `uaddr` is `val3`.
+pub(crate) fn sys_futex(
+    uaddr: usize,
+    op: c_int,
+    val: u32,
+    timeout: *const timespec,
+    uaddr2: usize,
+    val3: u32,
+) -> isize {
+    syscall_body!(sys_futex, {
+        let private = op & FUTEX_PRIVATE_FLAG != 0;
+        let val2 = timeout as usize as u32 as usize;
+        match op & !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME) {
+            FUTEX_WAIT => futex_wait(uaddr, private, val, timeout).map(|_| 0),
+            FUTEX_WAKE => futex_wake(uaddr, private, val as usize),
+            FUTEX_REQUEUE => futex_requeue(uaddr, uaddr2, private, val as usize, val2, None),
+            FUTEX_CMP_REQUEUE => {
+                futex_requeue(uaddr, uaddr2, private, val as usize, val2, Some(val3))
+            }
+            _ => Err(LinuxError::ENOSYS),
+        }
+    })
+}
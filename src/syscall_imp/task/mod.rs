@@ -1,5 +1,9 @@
+mod futex;
 mod schedule;
+mod signal;
 mod thread;
 
+pub(crate) use self::futex::*;
 pub(crate) use self::schedule::*;
-pub(crate) use self::thread::*;
\ No newline at end of file
+pub(crate) use self::signal::*;
+pub(crate) use self::thread::*;
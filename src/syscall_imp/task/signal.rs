@@ -0,0 +1,189 @@
+use core::sync::atomic::Ordering;
+
+use alloc::vec;
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{TaskExtRef, current};
+
+use super::thread::{read_user, write_user};
+use crate::{
+    ctypes::SigAction,
+    signal::{NSIG, SIGKILL, SIGSTOP, set_signal_mask},
+    syscall_body,
+    task::{find_process, find_thread, processes},
+};
+
+const SIG_BLOCK: i32 = 0;
+const SIG_UNBLOCK: i32 = 1;
+const SIG_SETMASK: i32 = 2;
+
+/// Check the signal number `signum`, which is `None` for 0, i.e. no signal but
+/// only a check of the target.
+fn check_signal(signum: i32) -> LinuxResult<Option<u32>> {
+    match u32::try_from(signum) {
+        Ok(0) => Ok(None),
+        Ok(signo) if signo <= NSIG as u32 => Ok(Some(signo)),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Examine and change the action taken on the signal `signum`.
+///
+/// The previous action is written to `oldact` and the new one is read from
+/// `act`, unless they are null. The actions are shared by the threads of the
+/// process, and exec resets those of the caught signals to the default one.
+/// `SIGKILL` and `SIGSTOP` can't be caught or ignored, and `sigsetsize` must be
+/// the size of the signal set of the kernel, i.e. 8.
+pub(crate) fn sys_rt_sigaction(
+    signum: i32,
+    act: *const SigAction,
+    oldact: *mut SigAction,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_rt_sigaction, {
+        if sigsetsize != size_of::<u64>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let signo = u32::try_from(signum)
+            .ok()
+            .filter(|signo| (1..=NSIG as u32).contains(signo))
+            .ok_or(LinuxError::EINVAL)?;
+        let act = if act.is_null() {
+            None
+        } else {
+            Some(read_user(act)?)
+        };
+        if act.is_some() && (signo == SIGKILL || signo == SIGSTOP) {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let mut actions = curr.task_ext().signal_actions.lock();
+        if !oldact.is_null() {
+            write_user(oldact, actions.get(signo))?;
+        }
+        if let Some(act) = act {
+            actions.set(signo, act);
+        }
+        Ok(0)
+    })
+}
+
+/// Examine and change the signals blocked by the calling thread.
+///
+/// The previous mask is written to `oldset` unless it is null. Unless `set` is
+/// null, the signals in it are added to the mask with `SIG_BLOCK`, removed from
+/// it with `SIG_UNBLOCK`, or replace it with `SIG_SETMASK`. `SIGKILL` and
+/// `SIGSTOP` are never blocked.
+pub(crate) fn sys_rt_sigprocmask(
+    how: i32,
+    set: *const u64,
+    oldset: *mut u64,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_rt_sigprocmask, {
+        if sigsetsize != size_of::<u64>() {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let mask = curr.task_ext().signal_mask.load(Ordering::Acquire);
+        let set = if set.is_null() {
+            None
+        } else {
+            Some(read_user(set)?)
+        };
+        let new_mask = match (how, set) {
+            (SIG_BLOCK, Some(set)) => mask | set,
+            (SIG_UNBLOCK, Some(set)) => mask & !set,
+            (SIG_SETMASK, Some(set)) => set,
+            (SIG_BLOCK | SIG_UNBLOCK | SIG_SETMASK, None) => mask,
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if !oldset.is_null() {
+            write_user(oldset, mask)?;
+        }
+        set_signal_mask(new_mask);
+        Ok(0)
+    })
+}
+
+/// Return from a signal handler to the context it has interrupted, which is
+/// saved in the signal frame at the stack pointer.
+///
+/// Returns what the interrupted syscall has returned, so the result is not an
+/// error even if it looks like one.
+pub(crate) fn sys_rt_sigreturn() -> isize {
+    crate::signal::sigreturn()
+}
+
+/// Send the signal `signum` to the process `pid` if it is positive, to the
+/// processes in the process group of the caller if it is 0, to every process
+/// but the caller if it is -1, and to the processes in the group `-pid`
+/// otherwise.
+///
+/// A `signum` of 0 sends nothing, but still checks that there is a process to
+/// send it to. Every process is allowed to send signals to every other.
+pub(crate) fn sys_kill(pid: i32, signum: i32) -> isize {
+    syscall_body!(sys_kill, {
+        let signo = check_signal(signum)?;
+        let curr = current();
+        let targets = match pid {
+            pid if pid > 0 => {
+                vec![find_process(pid as usize).ok_or(LinuxError::ESRCH)?]
+            }
+            -1 => {
+                let mut targets = processes();
+                targets.retain(|task| task.task_ext().proc_id != curr.task_ext().proc_id);
+                targets
+            }
+            pid => {
+                let pgid = match pid {
+                    0 => curr.task_ext().thread_group.pgid(),
+                    pid => pid.unsigned_abs() as usize,
+                };
+                let mut targets = processes();
+                targets.retain(|task| task.task_ext().thread_group.pgid() == pgid);
+                targets
+            }
+        };
+        if targets.is_empty() {
+            return Err(LinuxError::ESRCH);
+        }
+        if let Some(signo) = signo {
+            for task in targets {
+                task.task_ext().thread_group.pending_signals.add(signo);
+            }
+        }
+        Ok(0)
+    })
+}
+
+/// Send the signal `signum` to the thread `tid`, in whichever process it is.
+pub(crate) fn sys_tkill(tid: i32, signum: i32) -> isize {
+    syscall_body!(sys_tkill, {
+        let signo = check_signal(signum)?;
+        if tid <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let thread = find_thread(tid as u64).ok_or(LinuxError::ESRCH)?;
+        if let Some(signo) = signo {
+            thread.task_ext().pending_signals.add(signo);
+        }
+        Ok(0)
+    })
+}
+
+/// Send the signal `signum` to the thread `tid` of the process `tgid`.
+pub(crate) fn sys_tgkill(tgid: i32, tid: i32, signum: i32) -> isize {
+    syscall_body!(sys_tgkill, {
+        let signo = check_signal(signum)?;
+        if tgid <= 0 || tid <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let thread = find_process(tgid as usize)
+            .and_then(|process| process.task_ext().thread_group.find_thread(tid as u64))
+            .ok_or(LinuxError::ESRCH)?;
+        if let Some(signo) = signo {
+            thread.task_ext().pending_signals.add(signo);
+        }
+        Ok(0)
+    })
+}
@@ -1,19 +1,27 @@
 use core::ffi::{c_char, c_int};
 
-use axerrno::LinuxError;
+use alloc::{string::String, vec::Vec};
+use arceos_posix_api::{AT_FDCWD, ctypes::timeval};
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axhal::time::{NANOS_PER_MICROS, NANOS_PER_SEC};
 use axtask::{TaskExtRef, current, yield_now};
+#[cfg(target_arch = "x86_64")]
 use num_enum::TryFromPrimitive;
 
 use crate::{
-    ctypes::{WaitFlags, WaitStatus},
+    ctypes::{RUsage, WaitFlags, WaitStatus},
+    mm::check_user_range,
+    signal::signal_pending,
     syscall_body,
-    task::wait_pid,
+    syscall_imp::fs::{release_record_locks, resolve_at},
+    task::{find_process, processes, wait_pid},
 };
 
 /// ARCH_PRCTL codes
 ///
 /// It is only avaliable on x86_64, and is not convenient
 /// to generate automatically via c_to_rust binding.
+#[cfg(target_arch = "x86_64")]
 #[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
 #[repr(i32)]
 enum ArchPrctlCode {
@@ -31,40 +39,131 @@ enum ArchPrctlCode {
     SetCpuid = 0x1012,
 }
 
+/// Get the process id of the calling thread, which is shared by all the threads
+/// of the process.
 pub(crate) fn sys_getpid() -> i32 {
     syscall_body!(sys_getpid, {
         Ok(axtask::current().task_ext().proc_id as c_int)
     })
 }
 
+/// Get the id of the calling thread, which is the process id for the main thread.
+pub(crate) fn sys_gettid() -> i32 {
+    syscall_body!(sys_gettid, { Ok(axtask::current().id().as_u64() as c_int) })
+}
+
+/// Get the process id of the parent of the calling process.
 pub(crate) fn sys_getppid() -> i32 {
     syscall_body!(sys_getppid, {
         Ok(axtask::current().task_ext().get_parent() as c_int)
     })
 }
 
-pub(crate) fn sys_exit(status: i32) -> ! {
-    let curr = current();
-    let clear_child_tid = curr.task_ext().clear_child_tid() as *mut i32;
-    if !clear_child_tid.is_null() {
-        // TODO: check whether the address is valid
-        unsafe {
-            // TODO: Encapsulate all operations that access user-mode memory into a unified function
-            *(clear_child_tid) = 0;
+/// Get the process group id of the process `pid`, or of the calling process if
+/// `pid` is 0.
+pub(crate) fn sys_getpgid(pid: i32) -> i32 {
+    syscall_body!(sys_getpgid, {
+        let task = match pid {
+            0 => current().clone(),
+            pid if pid > 0 => find_process(pid as usize).ok_or(LinuxError::ESRCH)?,
+            _ => return Err(LinuxError::ESRCH),
+        };
+        Ok(task.task_ext().thread_group.pgid() as c_int)
+    })
+}
+
+/// Move the process `pid` into the process group `pgid`.
+///
+/// `pid` is the calling process or one of its children, and 0 stands for the
+/// calling process. A `pgid` of 0 stands for `pid` itself, which makes the
+/// process the leader of a new group. Otherwise the group must already exist.
+/// Sessions are not supported, so every process counts as in the same session.
+pub(crate) fn sys_setpgid(pid: i32, pgid: i32) -> i32 {
+    syscall_body!(sys_setpgid, {
+        if pid < 0 || pgid < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let task = if pid == 0 || pid as usize == curr.task_ext().proc_id {
+            curr.clone()
+        } else {
+            curr.task_ext()
+                .children
+                .lock()
+                .iter()
+                .find(|child| {
+                    child.task_ext().proc_id == pid as usize
+                        && child.state() != axtask::TaskState::Exited
+                })
+                .cloned()
+                .ok_or(LinuxError::ESRCH)?
+        };
+        let pid = task.task_ext().proc_id;
+        let pgid = if pgid == 0 { pid } else { pgid as usize };
+        if pgid != pid
+            && !processes()
+                .iter()
+                .any(|process| process.task_ext().thread_group.pgid() == pgid)
+        {
+            return Err(LinuxError::EPERM);
         }
-        // TODO: wake up threads, which are blocked by futex, and waiting for the address pointed by clear_child_tid
+        task.task_ext().thread_group.set_pgid(pgid);
+        Ok(0)
+    })
+}
+
+/// Terminate the current thread with `status`, clearing and waking its
+/// `clear_child_tid` first.
+///
+/// This is how every thread exits, so the last thread of a process releases
+/// what the process holds as a whole, i.e. its record locks.
+pub(crate) fn exit_thread(status: i32) -> ! {
+    let curr = current();
+    // Count the thread out before waking up whoever joins it, so that it is no
+    // longer among the threads of the process once joined.
+    if curr.task_ext().thread_group.remove_thread() {
+        release_record_locks(curr.task_ext().proc_id, None);
+    }
+    let clear_child_tid = curr.task_ext().clear_child_tid() as usize;
+    if clear_child_tid != 0 {
+        super::futex::clear_child_tid(clear_child_tid);
     }
     axtask::exit(status);
 }
 
+pub(crate) fn sys_exit(status: i32) -> ! {
+    exit_thread(status);
+}
+
+/// Terminate all the threads of the current process, which exits with `status`.
+///
+/// Each of the other threads exits when it next makes a syscall or faults, or
+/// as it wakes up if it is blocked in a syscall. The process is reaped by
+/// `wait4` once the last of them has exited.
 pub(crate) fn sys_exit_group(status: i32) -> ! {
-    warn!("Temporarily replace sys_exit_group with sys_exit");
-    axtask::exit(status);
+    current().task_ext().thread_group.exit((status & 0xff) << 8);
+    exit_thread(status);
+}
+
+/// Terminate the current thread if its process is being terminated as a whole,
+/// which is checked when it enters and leaves a syscall.
+///
+/// The thread exits with the exit code of the process, or 128 plus the signal
+/// that has terminated it, like a shell reports.
+pub(crate) fn exit_if_group_exiting() {
+    let status = current().task_ext().thread_group.exit_status();
+    if let Some(status) = status {
+        match status & 0x7f {
+            0 => exit_thread(status >> 8),
+            signo => exit_thread(128 + signo),
+        }
+    }
 }
 
-/// To set the clear_child_tid field in the task extended data.
+/// To set the clear_child_tid field in the task extended data, which is cleared
+/// and woken as a futex when the thread exits.
 ///
-/// The set_tid_address() always succeeds
+/// The set_tid_address() always succeeds and returns the tid of the caller
 pub(crate) fn sys_set_tid_address(tid_ptd: *const i32) -> isize {
     syscall_body!(sys_set_tid_address, {
         let curr = current();
@@ -116,8 +215,11 @@ pub(crate) fn sys_clone(
     arg4: usize,
 ) -> isize {
     syscall_body!(sys_clone, {
-        let tls = arg3;
-        let ctid = arg4;
+        // The order of the last two arguments differs between architectures.
+        #[cfg(target_arch = "x86_64")]
+        let (ctid, tls) = (arg3, arg4);
+        #[cfg(not(target_arch = "x86_64"))]
+        let (tls, ctid) = (arg3, arg4);
 
         let stack = if user_stack == 0 {
             None
@@ -127,72 +229,128 @@ pub(crate) fn sys_clone(
 
         let curr_task = current();
 
-        if let Ok(new_task_id) = curr_task
+        match curr_task
             .task_ext()
             .clone_task(flags, stack, ptid, tls, ctid)
         {
-            Ok(new_task_id as isize)
-        } else {
-            Err(LinuxError::ENOMEM)
+            Ok(new_task_id) => Ok(new_task_id as isize),
+            Err(AxError::InvalidInput) => Err(LinuxError::EINVAL),
+            Err(AxError::BadAddress) => Err(LinuxError::EFAULT),
+            Err(_) => Err(LinuxError::ENOMEM),
         }
     })
 }
 
-pub(crate) fn sys_wait4(pid: i32, exit_code_ptr: *mut i32, option: u32) -> isize {
-    let option_flag = WaitFlags::from_bits(option).unwrap();
+/// Read a value from `ptr` in user memory, failing with `EFAULT` if it is not
+/// mapped there.
+pub(super) fn read_user<T>(ptr: *const T) -> LinuxResult<T> {
+    check_user_range(current().task_ext(), (ptr as usize).into(), size_of::<T>())
+        .map_err(|_| LinuxError::EFAULT)?;
+    Ok(unsafe { ptr.read() })
+}
+
+/// Write `value` to `ptr` in user memory, failing with `EFAULT` if it is not
+/// mapped there.
+pub(super) fn write_user<T>(ptr: *mut T, value: T) -> LinuxResult {
+    check_user_range(current().task_ext(), (ptr as usize).into(), size_of::<T>())
+        .map_err(|_| LinuxError::EFAULT)?;
+    unsafe { ptr.write(value) };
+    Ok(())
+}
+
+/// Wait for a child of the current process selected by `pid` to exit, reap it
+/// and return its pid.
+///
+/// `pid` selects the child with that pid if it is positive, any child if it is
+/// -1, and a child in a process group otherwise: that of the current process if
+/// it is 0, and `-pid` if it is less than -1. How the child has exited is
+/// written to `wstatus` and the time it has used to `rusage`, unless they are
+/// null. With `WNOHANG`, 0 is returned at once if no selected child has exited
+/// yet, and otherwise a signal interrupts the wait with `EINTR`.
+pub(crate) fn sys_wait4(pid: i32, wstatus: *mut i32, options: u32, rusage: *mut RUsage) -> isize {
     syscall_body!(sys_wait4, {
-        loop {
-            let answer = wait_pid(pid, exit_code_ptr);
-            match answer {
-                Ok(pid) => {
-                    return Ok(pid as isize);
-                }
-                Err(status) => match status {
-                    WaitStatus::NotExist => {
-                        return Err(LinuxError::ECHILD);
-                    }
-                    WaitStatus::Running => {
-                        if option_flag.contains(WaitFlags::WNOHANG) {
-                            return Ok(0);
-                        } else {
-                            yield_now();
-                        }
-                    }
-                    _ => {
-                        panic!("Shouldn't reach here!");
-                    }
-                },
+        let options = WaitFlags::from_bits(options).ok_or(LinuxError::EINVAL)?;
+        let child = loop {
+            match wait_pid(pid) {
+                Ok(child) => break child,
+                Err(WaitStatus::Running) if options.contains(WaitFlags::WNOHANG) => return Ok(0),
+                Err(WaitStatus::Running) if signal_pending() => return Err(LinuxError::EINTR),
+                Err(WaitStatus::Running) => yield_now(),
+                Err(WaitStatus::NotExist) => return Err(LinuxError::ECHILD),
             }
+        };
+        if !wstatus.is_null() {
+            write_user(wstatus, child.status)?;
+        }
+        if !rusage.is_null() {
+            let to_timeval = |ns: usize| timeval {
+                tv_sec: (ns / NANOS_PER_SEC as usize) as _,
+                tv_usec: (ns % NANOS_PER_SEC as usize / NANOS_PER_MICROS as usize) as _,
+            };
+            write_user(
+                rusage,
+                RUsage {
+                    ru_utime: to_timeval(child.utime_ns),
+                    ru_stime: to_timeval(child.stime_ns),
+                    ru_others: [0; 14],
+                },
+            )?;
         }
+        Ok(child.pid as isize)
     })
 }
 
-pub fn sys_execve(path: *const c_char, argv: *const usize, envp: *const usize) -> isize {
-    syscall_body!(sys_execve, {
-        let path_str = arceos_posix_api::char_ptr_to_str(path)?;
+/// The maximum total size of the arguments and environment variables of exec,
+/// i.e. `ARG_MAX`.
+const ARG_MAX: usize = 128 * 1024;
 
-        info!("execve: {:?}", path_str);
-        if path_str.split('/').filter(|s| !s.is_empty()).count() > 1 {
-            info!("Multi-level directories are not supported");
-            return Err::<isize, _>(LinuxError::EINVAL);
-        }
-
-        let argv_valid = unsafe { argv.is_null() || *argv == 0 };
-        let envp_valid = unsafe { envp.is_null() || *envp == 0 };
-
-        if !argv_valid {
-            info!("argv is not supported");
+/// Read a null-terminated array of strings, like `argv` or `envp`, from user
+/// memory, adding their sizes to `total`.
+fn read_str_array(array: *const usize, total: &mut usize) -> LinuxResult<Vec<String>> {
+    let mut strs = Vec::new();
+    if array.is_null() {
+        return Ok(strs);
+    }
+    loop {
+        let entry = unsafe { array.add(strs.len()) };
+        check_user_range(
+            current().task_ext(),
+            (entry as usize).into(),
+            size_of::<usize>(),
+        )
+        .map_err(|_| LinuxError::EFAULT)?;
+        let ptr = unsafe { entry.read() };
+        if ptr == 0 {
+            return Ok(strs);
         }
-
-        if !envp_valid {
-            info!("envp is not supported");
+        let s = arceos_posix_api::char_ptr_to_str(ptr as *const c_char)?;
+        *total += s.len() + 1 + size_of::<usize>();
+        if *total > ARG_MAX {
+            return Err(LinuxError::E2BIG);
         }
+        strs.push(s.into());
+    }
+}
 
-        if let Err(e) = crate::task::exec(path_str) {
-            error!("Failed to exec: {:?}", e);
-            return Err(LinuxError::ENOSYS);
-        }
+/// Run the program at `path` in place of the current one, with the arguments
+/// `argv` and the environment variables `envp`, which are null-terminated
+/// arrays of strings.
+///
+/// The pid and the fds that are not close-on-exec are kept. It only returns
+/// on failure, which leaves the current program as it was.
+pub fn sys_execve(path: *const c_char, argv: *const usize, envp: *const usize) -> isize {
+    syscall_body!(sys_execve, {
+        let path = resolve_at(AT_FDCWD as i32, path, true)?;
+        info!("execve: {:?}", path);
+        let mut total = 0;
+        let args = read_str_array(argv, &mut total)?;
+        let envs = read_str_array(envp, &mut total)?;
 
+        crate::task::exec(&path, args, envs).map_err(|err| match err {
+            AxError::InvalidData => LinuxError::ENOEXEC,
+            AxError::PermissionDenied => LinuxError::EACCES,
+            err => err.into(),
+        })?;
         unreachable!("execve should never return");
     })
 }
@@ -1,15 +1,24 @@
-use alloc::{string::ToString, sync::Arc, vec, vec::Vec};
+use alloc::{
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
 use arceos_posix_api::FD_TABLE;
 use axerrno::{AxError, AxResult};
 use axfs::{CURRENT_DIR, CURRENT_DIR_PATH};
 use core::{
     alloc::Layout,
     cell::UnsafeCell,
-    sync::atomic::{AtomicU64, Ordering},
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
 };
 use spin::Once;
 
-use crate::ctypes::{CloneFlags, TimeStat, WaitStatus};
+use crate::{
+    ctypes::{CloneFlags, TimeStat, WaitStatus},
+    mm::FileMapping,
+    signal::{PendingSignals, SignalActions},
+};
 use axhal::{
     arch::{TrapFrame, UspaceContext},
     time::{NANOS_PER_MICROS, NANOS_PER_SEC, monotonic_time_nanos},
@@ -17,7 +26,92 @@ use axhal::{
 use axmm::AddrSpace;
 use axns::{AxNamespace, AxNamespaceIf};
 use axsync::Mutex;
-use axtask::{AxTaskRef, TaskExtRef, TaskInner, current};
+use axtask::{AxTaskRef, TaskExtRef, TaskInner, WeakAxTaskRef, current};
+use memory_addr::VirtAddr;
+
+/// The threads of a process, which are terminated together by `exit_group`,
+/// and what else belongs to the process as a whole.
+pub struct ThreadGroup {
+    threads: Mutex<Vec<WeakAxTaskRef>>,
+    /// The number of threads that have not exited.
+    live_threads: AtomicUsize,
+    /// The process group ID.
+    pgid: AtomicUsize,
+    /// The status of the process once it is being terminated as a whole,
+    /// encoded like the status of `wait4`.
+    exit_status: Mutex<Option<i32>>,
+    /// The signals sent to the process, which any of its threads may take.
+    pub pending_signals: PendingSignals,
+}
+
+impl ThreadGroup {
+    fn new(pgid: usize) -> Arc<Self> {
+        Arc::new(Self {
+            threads: Mutex::new(Vec::new()),
+            live_threads: AtomicUsize::new(0),
+            pgid: AtomicUsize::new(pgid),
+            exit_status: Mutex::new(None),
+            pending_signals: PendingSignals::default(),
+        })
+    }
+
+    fn add(&self, task: &AxTaskRef) {
+        let mut threads = self.threads.lock();
+        threads.retain(|thread| thread.strong_count() > 0);
+        threads.push(Arc::downgrade(task));
+        self.live_threads.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Count out a thread of the process as it exits, and return whether it is
+    /// the last one.
+    pub fn remove_thread(&self) -> bool {
+        self.live_threads.fetch_sub(1, Ordering::AcqRel) == 1
+    }
+
+    /// The number of threads of the process that have not exited.
+    pub fn live_threads(&self) -> usize {
+        self.live_threads.load(Ordering::Acquire)
+    }
+
+    /// Whether every thread of the process has exited.
+    pub fn all_exited(&self) -> bool {
+        self.threads.lock().iter().all(|thread| {
+            thread
+                .upgrade()
+                .is_none_or(|thread| thread.state() == axtask::TaskState::Exited)
+        })
+    }
+
+    /// Find the thread of the process with the thread ID `tid` that has not exited.
+    pub fn find_thread(&self, tid: u64) -> Option<AxTaskRef> {
+        self.threads
+            .lock()
+            .iter()
+            .filter_map(|thread| thread.upgrade())
+            .find(|thread| {
+                thread.id().as_u64() == tid && thread.state() != axtask::TaskState::Exited
+            })
+    }
+
+    /// The status of the process if it is being terminated as a whole.
+    pub fn exit_status(&self) -> Option<i32> {
+        *self.exit_status.lock()
+    }
+
+    pub fn pgid(&self) -> usize {
+        self.pgid.load(Ordering::Acquire)
+    }
+
+    pub fn set_pgid(&self, pgid: usize) {
+        self.pgid.store(pgid, Ordering::Release)
+    }
+
+    /// Start terminating the process as a whole with `status`, unless it is
+    /// already being terminated, and return the status it ends up with.
+    pub fn exit(&self, status: i32) -> i32 {
+        *self.exit_status.lock().get_or_insert(status)
+    }
+}
 
 /// Task extended data for the monolithic kernel.
 pub struct TaskExt {
@@ -27,6 +121,10 @@ pub struct TaskExt {
     pub parent_id: AtomicU64,
     /// children process
     pub children: Mutex<Vec<AxTaskRef>>,
+    /// The signal that has terminated the task, or 0 if it has exited by itself.
+    term_signal: AtomicU32,
+    /// The threads of the process, shared by them
+    pub thread_group: Arc<ThreadGroup>,
     /// The clear thread tid field
     ///
     /// See <https://manpages.debian.org/unstable/manpages-dev/set_tid_address.2.en.html#clear_child_tid>
@@ -42,9 +140,29 @@ pub struct TaskExt {
     /// The time statistics
     pub time: UnsafeCell<TimeStat>,
     /// The user heap bottom
-    pub heap_bottom: AtomicU64,
+    pub heap_bottom: Arc<AtomicU64>,
     /// The user heap top
-    pub heap_top: AtomicU64,
+    pub heap_top: Arc<AtomicU64>,
+    /// The fds with the close-on-exec flag (`FD_CLOEXEC`) set, shared along with the fd table
+    pub close_on_exec: Arc<Mutex<BTreeSet<i32>>>,
+    /// The file mode creation mask, shared along with the working directory
+    pub umask: Arc<AtomicU32>,
+    /// The absolute path of the executable, i.e. what `/proc/self/exe` links to
+    pub exe_path: Arc<Mutex<String>>,
+    /// The files mapped into the address space by `mmap`, shared along with the address space
+    pub file_mappings: Arc<Mutex<Vec<FileMapping>>>,
+    /// The actions taken on the signals, shared by the threads of the process
+    pub signal_actions: Arc<Mutex<SignalActions>>,
+    /// The signals blocked by the thread
+    pub signal_mask: AtomicU64,
+    /// The signal mask to restore once the syscall that has replaced it for
+    /// the time being returns
+    pub saved_signal_mask: Mutex<Option<u64>>,
+    /// The signals sent to the thread itself
+    pub pending_signals: PendingSignals,
+    /// Whether the task has replaced its image by exec, which resumes the
+    /// parent suspended by creating it with `CLONE_VFORK`
+    exec_done: AtomicBool,
 }
 
 impl TaskExt {
@@ -58,25 +176,90 @@ impl TaskExt {
             proc_id,
             parent_id: AtomicU64::new(1),
             children: Mutex::new(Vec::new()),
+            term_signal: AtomicU32::new(0),
+            thread_group: ThreadGroup::new(proc_id),
             uctx,
             clear_child_tid: AtomicU64::new(0),
             aspace,
             ns: AxNamespace::new_thread_local(),
             time: TimeStat::new().into(),
-            heap_bottom: AtomicU64::new(heap_bottom),
-            heap_top: AtomicU64::new(heap_bottom),
+            heap_bottom: Arc::new(AtomicU64::new(heap_bottom)),
+            heap_top: Arc::new(AtomicU64::new(heap_bottom)),
+            close_on_exec: Arc::new(Mutex::new(BTreeSet::new())),
+            umask: Arc::new(AtomicU32::new(0o022)),
+            exe_path: Arc::new(Mutex::new(String::new())),
+            file_mappings: Arc::new(Mutex::new(Vec::new())),
+            signal_actions: Arc::new(Mutex::new(SignalActions::default())),
+            signal_mask: AtomicU64::new(0),
+            saved_signal_mask: Mutex::new(None),
+            pending_signals: PendingSignals::default(),
+            exec_done: AtomicBool::new(false),
         }
     }
 
+    /// Create the task extended data of a new thread in the same process,
+    /// which shares everything that belongs to the process.
+    fn new_thread(&self, uctx: UspaceContext) -> Self {
+        Self {
+            proc_id: self.proc_id,
+            parent_id: AtomicU64::new(self.get_parent()),
+            children: Mutex::new(Vec::new()),
+            term_signal: AtomicU32::new(0),
+            thread_group: self.thread_group.clone(),
+            uctx,
+            clear_child_tid: AtomicU64::new(0),
+            aspace: self.aspace.clone(),
+            ns: AxNamespace::new_thread_local(),
+            time: TimeStat::new().into(),
+            heap_bottom: self.heap_bottom.clone(),
+            heap_top: self.heap_top.clone(),
+            close_on_exec: self.close_on_exec.clone(),
+            umask: self.umask.clone(),
+            exe_path: self.exe_path.clone(),
+            file_mappings: self.file_mappings.clone(),
+            signal_actions: self.signal_actions.clone(),
+            signal_mask: AtomicU64::new(0),
+            saved_signal_mask: Mutex::new(None),
+            pending_signals: PendingSignals::default(),
+            exec_done: AtomicBool::new(false),
+        }
+    }
+
+    /// Create a new task running the same code as the current one, which is a
+    /// new process or, with `CLONE_THREAD`, a new thread in the same process.
+    ///
+    /// `flags` tells what is shared with the new task rather than copied: the
+    /// address space with `CLONE_VM`, the fd table with `CLONE_FILES`, the
+    /// working directory with `CLONE_FS` and the signal actions with
+    /// `CLONE_SIGHAND`. The new task starts on `stack` if
+    /// given, and gets the thread pointer `tls` with `CLONE_SETTLS`. Its tid is
+    /// written to `ptid` in the current task with `CLONE_PARENT_SETTID` and to
+    /// `ctid` in the new one with `CLONE_CHILD_SETTID`, and `ctid` is cleared
+    /// when it exits with `CLONE_CHILD_CLEARTID`.
+    ///
+    /// With `CLONE_VFORK`, the current task is suspended until the new one execs
+    /// or exits. The address space is copied even with `CLONE_VM` then, since
+    /// exec can't replace an address space that is shared, which the suspended
+    /// parent can't tell unless the child writes to memory before it execs.
+    ///
+    /// Returns the tid of the new task.
     pub fn clone_task(
         &self,
         flags: usize,
         stack: Option<usize>,
-        _ptid: usize,
-        _tls: usize,
-        _ctid: usize,
+        ptid: usize,
+        tls: usize,
+        ctid: usize,
     ) -> AxResult<u64> {
-        let _clone_flags = CloneFlags::from_bits((flags & !0x3f) as u32).unwrap();
+        let clone_flags = CloneFlags::from_bits_truncate((flags & !0x3f) as u32);
+        let is_thread = clone_flags.contains(CloneFlags::CLONE_THREAD);
+        // Threads share the signal handlers, which requires sharing the address space.
+        if (is_thread && !clone_flags.contains(CloneFlags::CLONE_SIGHAND))
+            || (clone_flags.contains(CloneFlags::CLONE_SIGHAND)
+                && !clone_flags.contains(CloneFlags::CLONE_VM))
+        {
+            return Err(AxError::InvalidInput);
+        }
 
         let mut new_task = TaskInner::new(
             || {
@@ -93,15 +276,45 @@ impl TaskExt {
             current().id_name(),
             axconfig::plat::KERNEL_STACK_SIZE,
         );
+        let tid = new_task.id().as_u64();
+        let is_vfork = clone_flags.contains(CloneFlags::CLONE_VFORK);
+        let shares_vm = clone_flags.contains(CloneFlags::CLONE_VM) && !is_vfork;
 
         let current_task = current();
-        let mut current_aspace = current_task.task_ext().aspace.lock();
-        let new_aspace = current_aspace.clone_or_err()?;
+        let aspace = if shares_vm {
+            self.aspace.clone()
+        } else {
+            let mut current_aspace = self.aspace.lock();
+            let mut new_aspace =
+                axmm::new_user_aspace(current_aspace.base(), current_aspace.size())?;
+            crate::mm::fork_aspace(
+                &self.file_mappings.lock(),
+                &mut current_aspace,
+                &mut new_aspace,
+            )?;
+            Arc::new(Mutex::new(new_aspace))
+        };
         new_task
             .ctx_mut()
-            .set_page_table_root(new_aspace.page_table_root());
-
-        let trap_frame = read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
+            .set_page_table_root(aspace.lock().page_table_root());
+
+        #[allow(unused_mut)]
+        let mut trap_frame =
+            read_trapframe_from_kstack(current_task.get_kernel_stack_top().unwrap());
+        if clone_flags.contains(CloneFlags::CLONE_SETTLS) {
+            #[cfg(target_arch = "x86_64")]
+            {
+                new_task.ctx_mut().fs_base = tls;
+            }
+            #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+            {
+                trap_frame.regs.tp = tls;
+            }
+            #[cfg(target_arch = "aarch64")]
+            {
+                trap_frame.tpidr_el0 = tls as u64;
+            }
+        }
         let mut new_uctx = UspaceContext::from(&trap_frame);
         if let Some(stack) = stack {
             new_uctx.set_sp(stack);
@@ -109,18 +322,77 @@ impl TaskExt {
         // Skip current instruction
         new_uctx.set_ip(new_uctx.get_ip() + 4);
         new_uctx.set_retval(0);
-        let return_id: u64 = new_task.id().as_u64();
-        let new_task_ext = TaskExt::new(
-            return_id as usize,
-            new_uctx,
-            Arc::new(Mutex::new(new_aspace)),
-            0,
-        );
-        new_task_ext.ns_init_new();
+
+        let mut new_task_ext = if is_thread {
+            self.new_thread(new_uctx)
+        } else {
+            // A new process is a child of the current one, in the same process group.
+            let mut new_task_ext =
+                TaskExt::new(tid as usize, new_uctx, aspace, self.get_heap_bottom());
+            new_task_ext.set_parent(self.proc_id as u64);
+            new_task_ext.thread_group.set_pgid(self.thread_group.pgid());
+            new_task_ext.set_heap_top(self.get_heap_top());
+            new_task_ext.set_umask(self.get_umask());
+            *new_task_ext.exe_path.lock() = self.exe_path.lock().clone();
+            if shares_vm {
+                new_task_ext.file_mappings = self.file_mappings.clone();
+            } else {
+                *new_task_ext.file_mappings.lock() = self.file_mappings.lock().clone();
+            }
+            new_task_ext
+        };
+        new_task_ext.ns_init_clone(clone_flags);
+        if clone_flags.contains(CloneFlags::CLONE_FILES) {
+            new_task_ext.close_on_exec = self.close_on_exec.clone();
+        } else {
+            new_task_ext.close_on_exec = Arc::new(Mutex::new(self.close_on_exec.lock().clone()));
+        }
+        if clone_flags.contains(CloneFlags::CLONE_FS) {
+            new_task_ext.umask = self.umask.clone();
+        }
+        if clone_flags.contains(CloneFlags::CLONE_SIGHAND) {
+            new_task_ext.signal_actions = self.signal_actions.clone();
+        } else {
+            new_task_ext.signal_actions = Arc::new(Mutex::new(self.signal_actions.lock().clone()));
+        }
+        // The new task blocks the same signals, but none is pending for it.
+        new_task_ext
+            .signal_mask
+            .store(self.signal_mask.load(Ordering::Acquire), Ordering::Release);
+        if clone_flags.contains(CloneFlags::CLONE_CHILD_SETTID) {
+            crate::mm::write_user_bytes(
+                &new_task_ext,
+                VirtAddr::from(ctid),
+                &(tid as i32).to_ne_bytes(),
+            )?;
+        }
+        if clone_flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) {
+            new_task_ext.set_clear_child_tid(ctid as u64);
+        }
+        if clone_flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+            crate::mm::write_user_bytes(self, VirtAddr::from(ptid), &(tid as i32).to_ne_bytes())?;
+        }
+
         new_task.init_task_ext(new_task_ext);
         let new_task_ref = axtask::spawn_task(new_task);
-        current_task.task_ext().children.lock().push(new_task_ref);
-        Ok(return_id)
+        new_task_ref.task_ext().thread_group.add(&new_task_ref);
+        // Threads are not children to wait for.
+        if !is_thread {
+            register_process(&new_task_ref);
+            current_task
+                .task_ext()
+                .children
+                .lock()
+                .push(new_task_ref.clone());
+        }
+        if is_vfork {
+            while new_task_ref.state() != axtask::TaskState::Exited
+                && !new_task_ref.task_ext().exec_done.load(Ordering::Acquire)
+            {
+                axtask::yield_now();
+            }
+        }
+        Ok(tid)
     }
 
     pub(crate) fn clear_child_tid(&self) -> u64 {
@@ -133,15 +405,73 @@ impl TaskExt {
             .store(clear_child_tid, core::sync::atomic::Ordering::Relaxed);
     }
 
+    /// Encode how the task has exited with `exit_code` as the status reported
+    /// by `wait4`: the low byte of the exit code in the second byte, or the
+    /// signal that has terminated it in the low 7 bits.
+    pub(crate) fn wait_status(&self, exit_code: i32) -> i32 {
+        match self.term_signal.load(Ordering::Acquire) {
+            0 => (exit_code & 0xff) << 8,
+            signo => signo as i32 & 0x7f,
+        }
+    }
+
     pub(crate) fn get_parent(&self) -> u64 {
         self.parent_id.load(Ordering::Acquire)
     }
 
-    #[allow(unused)]
     pub(crate) fn set_parent(&self, parent_id: u64) {
         self.parent_id.store(parent_id, Ordering::Release);
     }
 
+    pub(crate) fn is_close_on_exec(&self, fd: i32) -> bool {
+        self.close_on_exec.lock().contains(&fd)
+    }
+
+    pub(crate) fn set_close_on_exec(&self, fd: i32, close_on_exec: bool) {
+        let mut fds = self.close_on_exec.lock();
+        if close_on_exec {
+            fds.insert(fd);
+        } else {
+            fds.remove(&fd);
+        }
+    }
+
+    pub(crate) fn get_umask(&self) -> u32 {
+        self.umask.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_umask(&self, umask: u32) {
+        self.umask.store(umask, Ordering::Release)
+    }
+
+    /// Initialize the resources from those of the current task, sharing the fd
+    /// table with `CLONE_FILES` and the working directory with `CLONE_FS` in
+    /// `flags` rather than copying them.
+    pub(crate) fn ns_init_clone(&self, flags: CloneFlags) {
+        if flags.contains(CloneFlags::CLONE_FILES) {
+            FD_TABLE.deref_from(&self.ns).init_shared(FD_TABLE.share());
+        } else {
+            FD_TABLE
+                .deref_from(&self.ns)
+                .init_new(FD_TABLE.copy_inner());
+        }
+        if flags.contains(CloneFlags::CLONE_FS) {
+            CURRENT_DIR
+                .deref_from(&self.ns)
+                .init_shared(CURRENT_DIR.share());
+            CURRENT_DIR_PATH
+                .deref_from(&self.ns)
+                .init_shared(CURRENT_DIR_PATH.share());
+        } else {
+            CURRENT_DIR
+                .deref_from(&self.ns)
+                .init_new(CURRENT_DIR.copy_inner());
+            CURRENT_DIR_PATH
+                .deref_from(&self.ns)
+                .init_new(CURRENT_DIR_PATH.copy_inner());
+        }
+    }
+
     pub(crate) fn ns_init_new(&self) {
         FD_TABLE
             .deref_from(&self.ns)
@@ -191,6 +521,18 @@ impl TaskExt {
     }
 }
 
+impl Drop for TaskExt {
+    fn drop(&mut self) {
+        // The frames shared copy-on-write are not freed with the address space.
+        if Arc::strong_count(&self.aspace) == 1 {
+            let mut aspace = self.aspace.lock();
+            if let Err(err) = crate::mm::unmap_user_pages(&mut aspace) {
+                warn!("Failed to unmap the user pages: {err:?}");
+            }
+        }
+    }
+}
+
 struct AxNamespaceImpl;
 #[crate_interface::impl_interface]
 impl AxNamespaceIf for AxNamespaceImpl {
@@ -216,10 +558,14 @@ impl AxNamespaceIf for AxNamespaceImpl {
 
 axtask::def_task_ext!(TaskExt);
 
+/// Spawn the first task of a user app, which enters user space with `uctx`
+/// and the thread pointer `tls`.
 pub fn spawn_user_task(
+    exe_path: &str,
     aspace: Arc<Mutex<AddrSpace>>,
-    uctx: UspaceContext,
+    #[allow(unused_mut)] mut uctx: UspaceContext,
     heap_bottom: u64,
+    tls: usize,
 ) -> AxTaskRef {
     let mut task = TaskInner::new(
         || {
@@ -238,6 +584,18 @@ pub fn spawn_user_task(
     );
     task.ctx_mut()
         .set_page_table_root(aspace.lock().page_table_root());
+    #[cfg(target_arch = "x86_64")]
+    {
+        task.ctx_mut().fs_base = tls;
+    }
+    #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+    {
+        uctx.regs.tp = tls;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        uctx.tpidr_el0 = tls as u64;
+    }
     task.init_task_ext(TaskExt::new(
         task.id().as_u64() as usize,
         uctx,
@@ -245,10 +603,49 @@ pub fn spawn_user_task(
         heap_bottom,
     ));
     task.task_ext().ns_init_new();
-    axtask::spawn_task(task)
+    *task.task_ext().exe_path.lock() =
+        axfs::api::canonicalize(exe_path).unwrap_or_else(|_| exe_path.into());
+    let task = axtask::spawn_task(task);
+    task.task_ext().thread_group.add(&task);
+    register_process(&task);
+    task
+}
+
+/// The processes that have been spawned, keyed by their process IDs.
+static PROCESSES: Mutex<BTreeMap<usize, WeakAxTaskRef>> = Mutex::new(BTreeMap::new());
+
+fn register_process(task: &AxTaskRef) {
+    let mut processes = PROCESSES.lock();
+    processes.retain(|_, task| task.strong_count() > 0);
+    processes.insert(task.task_ext().proc_id, Arc::downgrade(task));
+}
+
+/// Find the process with the process ID `pid` that has not exited.
+pub fn find_process(pid: usize) -> Option<AxTaskRef> {
+    PROCESSES
+        .lock()
+        .get(&pid)
+        .and_then(|task| task.upgrade())
+        .filter(|task| task.state() != axtask::TaskState::Exited)
+}
+
+/// Find the thread with the thread ID `tid` that has not exited, in any process.
+pub fn find_thread(tid: u64) -> Option<AxTaskRef> {
+    processes()
+        .iter()
+        .find_map(|process| process.task_ext().thread_group.find_thread(tid))
+}
+
+/// Get the processes that have not exited, in the order of their process IDs.
+pub fn processes() -> Vec<AxTaskRef> {
+    PROCESSES
+        .lock()
+        .values()
+        .filter_map(|task| task.upgrade())
+        .filter(|task| task.state() != axtask::TaskState::Exited)
+        .collect()
 }
 
-#[allow(unused)]
 pub fn write_trapframe_to_kstack(kstack_top: usize, trap_frame: &TrapFrame) {
     let trap_frame_size = core::mem::size_of::<TrapFrame>();
     let trap_frame_ptr = (kstack_top - trap_frame_size) as *mut TrapFrame;
@@ -263,94 +660,152 @@ pub fn read_trapframe_from_kstack(kstack_top: usize) -> TrapFrame {
     unsafe { *trap_frame_ptr }
 }
 
-pub fn wait_pid(pid: i32, exit_code_ptr: *mut i32) -> Result<u64, WaitStatus> {
-    let curr_task = current();
-    let mut exit_task_id: usize = 0;
-    let mut answer_id: u64 = 0;
-    let mut answer_status = WaitStatus::NotExist;
-
-    for (index, child) in curr_task.task_ext().children.lock().iter().enumerate() {
-        if pid <= 0 {
-            if pid == 0 {
-                warn!("Don't support for process group.");
-            }
-
-            answer_status = WaitStatus::Running;
-            if child.state() == axtask::TaskState::Exited {
-                let exit_code = child.exit_code();
-                answer_status = WaitStatus::Exited;
-                info!(
-                    "wait pid _{}_ with code _{}_",
-                    child.id().as_u64(),
-                    exit_code
-                );
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        *exit_code_ptr = exit_code << 8;
-                    }
-                }
-                answer_id = child.id().as_u64();
-                break;
-            }
-        } else if child.id().as_u64() == pid as u64 {
-            if let Some(exit_code) = child.join() {
-                answer_status = WaitStatus::Exited;
-                info!(
-                    "wait pid _{}_ with code _{:?}_",
-                    child.id().as_u64(),
-                    exit_code
-                );
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        *exit_code_ptr = exit_code << 8;
-                    }
-                }
-                answer_id = child.id().as_u64();
-            } else {
-                answer_status = WaitStatus::Running;
-            }
-            break;
-        }
-    }
+/// A child process reaped by `wait_pid`.
+pub struct ReapedChild {
+    pub pid: u64,
+    /// How the child has exited, encoded like the status of `wait4`.
+    pub status: i32,
+    /// The time the child has spent in user space and in the kernel, in nanoseconds.
+    pub utime_ns: usize,
+    pub stime_ns: usize,
+}
 
-    if answer_status == WaitStatus::Running {
-        axtask::yield_now();
+/// Reap an exited child of the current task that `pid` selects: the child
+/// with that pid if it is positive, any child if it is -1, a child in the
+/// process group of the current task if it is 0, and a child in the process
+/// group `-pid` otherwise.
+///
+/// A child has exited once all its threads have. Its status is the one it has
+/// been terminated with as a whole, if any, and that of its first thread
+/// otherwise.
+///
+/// Fails with `WaitStatus::Running` if the selected children are all still
+/// running, and with `WaitStatus::NotExist` if there are none.
+pub fn wait_pid(pid: i32) -> Result<ReapedChild, WaitStatus> {
+    let curr_task = current();
+    let pgid = match pid {
+        0 => curr_task.task_ext().thread_group.pgid(),
+        pid => pid.unsigned_abs() as usize,
+    };
+    let mut children = curr_task.task_ext().children.lock();
+    let selected = |child: &AxTaskRef| match pid {
+        -1 => true,
+        pid if pid > 0 => child.id().as_u64() == pid as u64,
+        _ => child.task_ext().thread_group.pgid() == pgid,
+    };
+    if !children.iter().any(selected) {
+        return Err(WaitStatus::NotExist);
     }
+    let Some(index) = children.iter().position(|child| {
+        selected(child)
+            && child.state() == axtask::TaskState::Exited
+            && child.task_ext().thread_group.all_exited()
+    }) else {
+        return Err(WaitStatus::Running);
+    };
+    // Dropping the child frees what is left of it.
+    let child = children.remove(index);
+    let status = child
+        .task_ext()
+        .thread_group
+        .exit_status()
+        .unwrap_or_else(|| child.task_ext().wait_status(child.exit_code()));
+    info!(
+        "wait pid _{}_ with status _{:#x}_",
+        child.id().as_u64(),
+        status
+    );
+    let (utime_ns, stime_ns) = child.task_ext().time_stat_output();
+    Ok(ReapedChild {
+        pid: child.id().as_u64(),
+        status,
+        utime_ns,
+        stime_ns,
+    })
+}
 
-    if answer_status == WaitStatus::Exited {
-        curr_task.task_ext().children.lock().remove(exit_task_id);
-        return Ok(answer_id);
-    }
-    Err(answer_status)
+/// Terminate the current task as if it had been killed by the signal `signo`,
+/// e.g. `SIGSEGV` for a bad memory access, which terminates the other threads
+/// of the process as well.
+pub fn exit_on_signal(signo: u32) -> ! {
+    let curr = current();
+    curr.task_ext().term_signal.store(signo, Ordering::Release);
+    curr.task_ext().thread_group.exit(signo as i32 & 0x7f);
+    crate::syscall_imp::exit_thread(128 + signo as i32);
 }
 
-pub fn exec(name: &str) -> AxResult<()> {
+/// Replace the image of the current task with the app at `path`, which is
+/// run with the arguments `args` and the environment variables `envs`.
+///
+/// The app is checked before the old image is torn down, so that exec fails
+/// with the current task intact if the app doesn't exist or isn't an ELF file.
+/// A failure to load it after that leaves nothing to return to, and the task
+/// exits.
+pub fn exec(path: &str, args: Vec<String>, envs: Vec<String>) -> AxResult<()> {
     let current_task = current();
 
-    let program_name = name.to_string();
-
     let mut aspace = current_task.task_ext().aspace.lock();
     if Arc::strong_count(&current_task.task_ext().aspace) != 1 {
         warn!("Address space is shared by multiple tasks, exec is not supported.");
         return Err(AxError::Unsupported);
     }
-
-    aspace.unmap_user_areas()?;
+    crate::mm::check_user_app(path)?;
+
+    crate::mm::unmap_file_mappings(
+        &mut current_task.task_ext().file_mappings.lock(),
+        &aspace,
+        aspace.base(),
+        aspace.end(),
+    )?;
+    crate::mm::unmap_user_pages(&mut aspace)?;
     axhal::arch::flush_tlb(None);
 
-    let args = vec![program_name];
-
-    let (entry_point, user_stack_base) = crate::mm::load_user_app(&mut (args.into()), &mut aspace)
-        .map_err(|_| {
-            error!("Failed to load app {}", name);
-            AxError::NotFound
-        })?;
-    current_task.set_name(name);
+    let (entry_point, user_stack_base, heap_bottom, tls) =
+        match crate::mm::load_user_app(path, &mut args.into(), &envs, &mut aspace) {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                error!("Failed to load app {path}: {err:?}");
+                drop(aspace);
+                crate::syscall_imp::exit_thread(-1);
+            }
+        };
+    current_task
+        .task_ext()
+        .set_heap_bottom(heap_bottom.as_usize() as u64);
+    current_task
+        .task_ext()
+        .set_heap_top(heap_bottom.as_usize() as u64);
+    current_task.set_name(path);
+    // The new image is loaded and exec can no longer fail, so the fds marked
+    // close-on-exec are closed now, while the others are inherited.
+    for fd in core::mem::take(&mut *current_task.task_ext().close_on_exec.lock()) {
+        arceos_posix_api::sys_close(fd);
+    }
+    *current_task.task_ext().exe_path.lock() =
+        axfs::api::canonicalize(path).unwrap_or_else(|_| path.into());
+    current_task
+        .task_ext()
+        .exec_done
+        .store(true, Ordering::Release);
 
     let task_ext = unsafe { &mut *(current_task.task_ext_ptr() as *mut TaskExt) };
+    // The actions are no longer shared with the processes sharing them before.
+    let signal_actions = task_ext.signal_actions.lock().for_exec();
+    task_ext.signal_actions = Arc::new(Mutex::new(signal_actions));
     task_ext.uctx = UspaceContext::new(entry_point.as_usize(), user_stack_base, 0);
+    // The thread pointer of the new image points to its static TLS.
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        axhal::arch::write_thread_pointer(tls);
+    }
+    #[cfg(any(target_arch = "riscv64", target_arch = "loongarch64"))]
+    {
+        task_ext.uctx.regs.tp = tls;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        task_ext.uctx.tpidr_el0 = tls as u64;
+    }
 
     unsafe {
         task_ext.uctx.enter_uspace(